@@ -0,0 +1,168 @@
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+use tauri::Emitter;
+
+use crate::settings;
+
+// Tool milestones closer together than this are folded into the next one
+const TOOL_MILESTONE_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AccessibilitySettings {
+    // Milestones instead of streamed text for every conversation...
+    pub milestone_events: bool,
+    // ...or just these
+    pub milestone_conversations: Vec<String>,
+}
+
+impl AccessibilitySettings {
+    fn wants_milestones(&self, conversation_id: &str) -> bool {
+        self.milestone_events || self.milestone_conversations.iter().any(|id| id == conversation_id)
+    }
+}
+
+// Sent as `claude-milestone-<conversation_id>` in place of the streamed
+// text and thinking events. claude-message-complete, the final is_complete
+// event and the turn's result are the same in both modes.
+#[derive(Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ClaudeMilestone {
+    TurnStarted,
+    ToolsUsed { count: u32, latest: String },
+    ResponseComplete { summary: String, tools_used: u32 },
+}
+
+// Plain description of a response's size and shape, e.g. "Response
+// complete: 3 paragraphs, 412 words, with 2 code blocks and a list."
+fn describe(response: &str, tools_used: u32) -> String {
+    let mut paragraphs = 0;
+    let mut code_blocks = 0;
+    let mut has_list = false;
+    let mut in_code = false;
+    let mut in_paragraph = false;
+    for line in response.lines() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") {
+            if !in_code {
+                code_blocks += 1;
+            }
+            in_code = !in_code;
+            in_paragraph = false;
+            continue;
+        }
+        if in_code {
+            continue;
+        }
+        if trimmed.is_empty() {
+            in_paragraph = false;
+            continue;
+        }
+        let is_item = trimmed.starts_with("- ") || trimmed.starts_with("* ")
+            || trimmed.split_once(". ").is_some_and(|(n, _)| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()));
+        has_list |= is_item;
+        if !in_paragraph && !is_item {
+            paragraphs += 1;
+        }
+        in_paragraph = !is_item;
+    }
+    let words = response.split_whitespace().count();
+    if words == 0 {
+        return "Response complete, with no text.".to_string();
+    }
+
+    let plural = |n: usize, one: &str, many: &str| format!("{} {}", n, if n == 1 { one } else { many });
+    let mut extras = Vec::new();
+    if code_blocks > 0 {
+        extras.push(plural(code_blocks, "code block", "code blocks"));
+    }
+    if has_list {
+        extras.push("a list".to_string());
+    }
+    let mut summary = format!(
+        "Response complete: {}, {}",
+        plural(paragraphs.max(1), "paragraph", "paragraphs"),
+        plural(words, "word", "words"),
+    );
+    if !extras.is_empty() {
+        summary.push_str(&format!(", with {}", extras.join(" and ")));
+    }
+    if tools_used > 0 {
+        summary.push_str(&format!(", after {}", plural(tools_used as usize, "tool use", "tool uses")));
+    }
+    summary.push('.');
+    summary
+}
+
+// Per-turn state for milestone mode; does nothing when the mode is off
+pub(crate) struct Milestones {
+    app: tauri::AppHandle,
+    conversation_id: String,
+    enabled: bool,
+    tools_used: u32,
+    last_tool_event: Option<Instant>,
+}
+
+impl Milestones {
+    pub(crate) async fn new(app: &tauri::AppHandle, conversation_id: &str) -> Self {
+        let enabled = settings::load(app).await
+            .is_ok_and(|s| s.accessibility.wants_milestones(conversation_id));
+        Milestones {
+            app: app.clone(),
+            conversation_id: conversation_id.to_string(),
+            enabled,
+            tools_used: 0,
+            last_tool_event: None,
+        }
+    }
+
+    // Whether streamed text, thinking and tool status events should be left out
+    pub(crate) fn replaces_stream(&self) -> bool {
+        self.enabled
+    }
+
+    fn emit(&self, milestone: ClaudeMilestone) {
+        if self.enabled {
+            let _ = self.app.emit(&format!("claude-milestone-{}", self.conversation_id), milestone);
+        }
+    }
+
+    pub(crate) fn turn_started(&self) {
+        self.emit(ClaudeMilestone::TurnStarted);
+    }
+
+    pub(crate) fn tool_used(&mut self, tool_name: &str) {
+        self.tools_used += 1;
+        if self.last_tool_event.is_some_and(|at| at.elapsed() < TOOL_MILESTONE_INTERVAL) {
+            return;
+        }
+        self.last_tool_event = Some(Instant::now());
+        self.emit(ClaudeMilestone::ToolsUsed { count: self.tools_used, latest: tool_name.to_string() });
+    }
+
+    pub(crate) fn response_complete(&self, response: &str) {
+        self.emit(ClaudeMilestone::ResponseComplete {
+            summary: describe(response, self.tools_used),
+            tools_used: self.tools_used,
+        });
+    }
+}
+
+// Switch milestone mode for one conversation, or for all of them when
+// conversation_id is None
+#[tauri::command]
+pub async fn set_milestone_events(app: tauri::AppHandle, conversation_id: Option<String>, enabled: bool) -> Result<(), String> {
+    settings::update(&app, |settings| {
+        let accessibility = &mut settings.accessibility;
+        match conversation_id {
+            None => accessibility.milestone_events = enabled,
+            Some(ref id) => {
+                accessibility.milestone_conversations.retain(|c| c != id);
+                if enabled {
+                    accessibility.milestone_conversations.push(id.clone());
+                }
+            }
+        }
+    }).await?;
+    Ok(())
+}
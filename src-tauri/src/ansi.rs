@@ -0,0 +1,53 @@
+// Removes ANSI escape sequences from streamed output. State carries over
+// between chunks, so a sequence split across two reads is still removed.
+#[derive(Clone, Copy, Default, PartialEq)]
+enum State {
+    #[default]
+    Ground,
+    // After ESC
+    Escape,
+    // After ESC followed by an intermediate byte, e.g. the `(` of `ESC ( B`
+    EscapeIntermediate,
+    // Inside `ESC [` ... final byte
+    Csi,
+    // Inside `ESC ]` ... BEL or `ESC \`, also used for DCS, APC and PM strings
+    Osc,
+    // ESC seen inside a string, which may be its terminator
+    OscEscape,
+}
+
+#[derive(Default)]
+pub(crate) struct AnsiStripper {
+    state: State,
+}
+
+impl AnsiStripper {
+    pub(crate) fn strip(&mut self, chunk: &str) -> String {
+        let mut out = String::with_capacity(chunk.len());
+        for c in chunk.chars() {
+            self.state = match (self.state, c) {
+                (State::Ground, '\u{1b}') => State::Escape,
+                (State::Ground, '\u{9b}') => State::Csi,
+                (State::Ground, c) => {
+                    out.push(c);
+                    State::Ground
+                }
+                (State::Escape, '[') => State::Csi,
+                (State::Escape, ']' | 'P' | '_' | '^' | 'X') => State::Osc,
+                (State::Escape, '\u{20}'..='\u{2f}') => State::EscapeIntermediate,
+                // Any other byte finishes a two-byte sequence such as `ESC 7`
+                (State::Escape, _) => State::Ground,
+                (State::EscapeIntermediate, '\u{20}'..='\u{2f}') => State::EscapeIntermediate,
+                (State::EscapeIntermediate, _) => State::Ground,
+                (State::Csi, '\u{40}'..='\u{7e}') => State::Ground,
+                (State::Csi, _) => State::Csi,
+                (State::Osc, '\u{7}') => State::Ground,
+                (State::Osc, '\u{1b}') => State::OscEscape,
+                (State::Osc, _) => State::Osc,
+                (State::OscEscape, '\\') => State::Ground,
+                (State::OscEscape, _) => State::Osc,
+            };
+        }
+        out
+    }
+}
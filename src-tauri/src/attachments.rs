@@ -0,0 +1,242 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::PathBuf;
+
+use crate::{storage, unix_millis, write_atomic};
+
+// Total attachment text inlined into a single prompt
+const MAX_INLINE_BYTES: usize = 1024 * 1024;
+// Fresh attachments may not be in any saved message yet, so compaction leaves them alone
+const COMPACTION_GRACE_MS: u64 = 60 * 60 * 1000;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct AttachmentMeta {
+    // Hex sha256 of the content, so identical pastes share one file
+    pub id: String,
+    pub name: String,
+    pub size: u64,
+    pub is_text: bool,
+    pub created_at: u64,
+}
+
+#[derive(Clone, Serialize)]
+pub struct AttachmentContent {
+    #[serde(flatten)]
+    pub meta: AttachmentMeta,
+    // Set for text attachments...
+    pub text: Option<String>,
+    // ...and raw bytes for everything else
+    pub bytes: Option<Vec<u8>>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct CompactionReport {
+    pub attachments_removed: usize,
+    pub bytes_freed: u64,
+}
+
+pub(crate) fn hex_digest(digest: &[u8]) -> String {
+    digest.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn attachments_dir(app: &tauri::AppHandle) -> PathBuf {
+    storage::data_dir(app).join("attachments")
+}
+
+fn valid_id(id: &str) -> Result<(), String> {
+    if id.len() == 64 && id.chars().all(|c| c.is_ascii_hexdigit()) {
+        Ok(())
+    } else {
+        Err(format!("Invalid attachment id: {}", id))
+    }
+}
+
+fn content_path(app: &tauri::AppHandle, id: &str) -> PathBuf {
+    attachments_dir(app).join(id)
+}
+
+fn meta_path(app: &tauri::AppHandle, id: &str) -> PathBuf {
+    attachments_dir(app).join(format!("{}.json", id))
+}
+
+async fn read_meta(app: &tauri::AppHandle, id: &str) -> Result<AttachmentMeta, String> {
+    valid_id(id)?;
+    let data = tokio::fs::read(meta_path(app, id)).await
+        .map_err(|_| format!("Attachment not found: {}", id))?;
+    serde_json::from_slice(&data).map_err(|e| e.to_string())
+}
+
+// Combined size of the given attachments' content
+pub(crate) async fn total_size(app: &tauri::AppHandle, ids: &[String]) -> Result<u64, String> {
+    let mut total = 0;
+    for id in ids {
+        total += read_meta(app, id).await?.size;
+    }
+    Ok(total)
+}
+
+// Prompt text for the given attachments. Text is inlined until the budget
+// runs out; binary content and anything over budget is referenced by path so
+// claude can read it itself.
+pub(crate) async fn inline_attachments(app: &tauri::AppHandle, ids: &[String]) -> Result<String, String> {
+    let mut budget = MAX_INLINE_BYTES;
+    let mut out = String::new();
+    for id in ids {
+        let meta = read_meta(app, id).await?;
+        let path = content_path(app, id);
+        if meta.is_text && (meta.size as usize) <= budget {
+            let text = tokio::fs::read_to_string(&path).await.map_err(|e| e.to_string())?;
+            budget -= text.len();
+            out.push_str(&format!("\n\n<attachment name=\"{}\">\n{}\n</attachment>", meta.name, text));
+        } else {
+            out.push_str(&format!(
+                "\n\n<attachment name=\"{}\" path=\"{}\" size=\"{}\" />",
+                meta.name,
+                path.display(),
+                meta.size
+            ));
+        }
+    }
+    Ok(out)
+}
+
+// Metadata and content path of a stored attachment, for callers already on
+// a blocking thread
+pub(crate) fn locate_blocking(app: &tauri::AppHandle, id: &str) -> Option<(AttachmentMeta, PathBuf)> {
+    valid_id(id).ok()?;
+    let meta = serde_json::from_slice(&std::fs::read(meta_path(app, id)).ok()?).ok()?;
+    Some((meta, content_path(app, id)))
+}
+
+// Text content of an attachment; None for binary ones
+pub(crate) async fn read_text(app: &tauri::AppHandle, id: &str) -> Result<Option<String>, String> {
+    let meta = read_meta(app, id).await?;
+    if !meta.is_text {
+        return Ok(None);
+    }
+    tokio::fs::read_to_string(content_path(app, id)).await.map(Some).map_err(|e| e.to_string())
+}
+
+// Store new text under the name of an existing attachment. The id is the
+// content hash, so the result is a new attachment rather than an edit.
+pub(crate) async fn replace_text(app: &tauri::AppHandle, id: &str, text: String) -> Result<AttachmentMeta, String> {
+    let meta = read_meta(app, id).await?;
+    store(app, text.into_bytes(), Some(meta.name)).await
+}
+
+// Delete an attachment once nothing refers to it any more
+pub(crate) async fn delete_if_unreferenced(app: &tauri::AppHandle, id: &str) -> Result<bool, String> {
+    valid_id(id)?;
+    if referencing_text(app).await?.contains(id) {
+        return Ok(false);
+    }
+    let existed = tokio::fs::remove_file(content_path(app, id)).await.is_ok();
+    let _ = tokio::fs::remove_file(meta_path(app, id)).await;
+    Ok(existed)
+}
+
+#[tauri::command]
+pub async fn store_attachment(
+    app: tauri::AppHandle,
+    text: Option<String>,
+    bytes: Option<Vec<u8>>,
+    suggested_name: Option<String>,
+) -> Result<AttachmentMeta, String> {
+    let content = match (text, bytes) {
+        (Some(text), None) => text.into_bytes(),
+        (None, Some(bytes)) => bytes,
+        _ => return Err("Provide exactly one of text or bytes".to_string()),
+    };
+    store(&app, content, suggested_name).await
+}
+
+pub(crate) async fn store(app: &tauri::AppHandle, content: Vec<u8>, suggested_name: Option<String>) -> Result<AttachmentMeta, String> {
+    let id = hex_digest(&Sha256::digest(&content));
+
+    // Same content already stored: hand back the existing entry
+    if let Ok(existing) = read_meta(app, &id).await {
+        if tokio::fs::metadata(content_path(app, &id)).await.is_ok() {
+            return Ok(existing);
+        }
+    }
+
+    tokio::fs::create_dir_all(attachments_dir(app)).await.map_err(|e| e.to_string())?;
+    let meta = AttachmentMeta {
+        name: suggested_name
+            .filter(|name| !name.trim().is_empty())
+            .unwrap_or_else(|| format!("attachment-{}", &id[..8])),
+        size: content.len() as u64,
+        is_text: std::str::from_utf8(&content).is_ok(),
+        created_at: unix_millis(),
+        id,
+    };
+    write_atomic(&content_path(app, &meta.id), &content).await?;
+    let meta_json = serde_json::to_vec_pretty(&meta).map_err(|e| e.to_string())?;
+    write_atomic(&meta_path(app, &meta.id), &meta_json).await?;
+    Ok(meta)
+}
+
+#[tauri::command]
+pub async fn get_attachment(app: tauri::AppHandle, id: String) -> Result<AttachmentContent, String> {
+    let meta = read_meta(&app, &id).await?;
+    let content = tokio::fs::read(content_path(&app, &id)).await.map_err(|e| e.to_string())?;
+    let (text, bytes) = match String::from_utf8(content) {
+        Ok(text) => (Some(text), None),
+        Err(e) => (None, Some(e.into_bytes())),
+    };
+    Ok(AttachmentContent { meta, text, bytes })
+}
+
+#[tauri::command]
+pub async fn delete_attachment(app: tauri::AppHandle, id: String) -> Result<bool, String> {
+    valid_id(&id)?;
+    let existed = tokio::fs::remove_file(content_path(&app, &id)).await.is_ok();
+    let _ = tokio::fs::remove_file(meta_path(&app, &id)).await;
+    Ok(existed)
+}
+
+// Everything that can reference an attachment: the frontend's data file and
+// the per-conversation transcripts
+async fn referencing_text(app: &tauri::AppHandle) -> Result<String, String> {
+    let mut text = String::new();
+    if let Ok(data) = tokio::fs::read_to_string(crate::get_data_path(app)?).await {
+        text.push_str(&data);
+    }
+    if let Ok(mut entries) = tokio::fs::read_dir(storage::data_dir(app).join("transcripts")).await {
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            if let Ok(data) = tokio::fs::read_to_string(entry.path()).await {
+                text.push_str(&data);
+            }
+        }
+    }
+    Ok(text)
+}
+
+// Remove attachments no transcript refers to any more
+#[tauri::command]
+pub async fn compact_storage(app: tauri::AppHandle) -> Result<CompactionReport, String> {
+    let referenced = referencing_text(&app).await?;
+    let mut report = CompactionReport { attachments_removed: 0, bytes_freed: 0 };
+
+    let Ok(mut entries) = tokio::fs::read_dir(attachments_dir(&app)).await else {
+        return Ok(report);
+    };
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if valid_id(&name).is_err() || referenced.contains(&name) {
+            continue;
+        }
+        let recent = read_meta(&app, &name).await
+            .is_ok_and(|meta| unix_millis().saturating_sub(meta.created_at) < COMPACTION_GRACE_MS);
+        if recent {
+            continue;
+        }
+        let size = entry.metadata().await.map(|m| m.len()).unwrap_or(0);
+        if tokio::fs::remove_file(entry.path()).await.is_ok() {
+            let _ = tokio::fs::remove_file(meta_path(&app, &name)).await;
+            report.attachments_removed += 1;
+            report.bytes_freed += size;
+        }
+    }
+    Ok(report)
+}
@@ -0,0 +1,69 @@
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use tokio::task::{AbortHandle, JoinHandle};
+
+use crate::unix_millis;
+
+#[derive(Clone, Serialize)]
+pub struct BackgroundTask {
+    pub task_id: u64,
+    pub description: String,
+    pub started_at: u64,
+}
+
+struct Entry {
+    info: BackgroundTask,
+    abort: AbortHandle,
+}
+
+// Plain mutex so tasks can be registered from sync code
+static TASKS: Lazy<Mutex<HashMap<u64, Entry>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+static NEXT_TASK_ID: AtomicU64 = AtomicU64::new(1);
+
+// tokio::spawn, but listed by list_background_tasks until it finishes. The
+// handle works as usual; aborting through the registry shows up to whoever
+// awaits it as a cancelled JoinError.
+pub(crate) fn spawn<F>(description: impl Into<String>, future: F) -> JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    let task_id = NEXT_TASK_ID.fetch_add(1, Ordering::Relaxed);
+    let handle = tokio::spawn(async move {
+        let output = future.await;
+        TASKS.lock().unwrap().remove(&task_id);
+        output
+    });
+    let entry = Entry {
+        info: BackgroundTask { task_id, description: description.into(), started_at: unix_millis() },
+        abort: handle.abort_handle(),
+    };
+    // A task that already finished would never remove itself
+    if !entry.abort.is_finished() {
+        TASKS.lock().unwrap().insert(task_id, entry);
+    }
+    handle
+}
+
+#[tauri::command]
+pub async fn list_background_tasks() -> Result<Vec<BackgroundTask>, String> {
+    let mut tasks = TASKS.lock().unwrap();
+    // Aborted tasks never reach their own cleanup
+    tasks.retain(|_, entry| !entry.abort.is_finished());
+    let mut list: Vec<BackgroundTask> = tasks.values().map(|entry| entry.info.clone()).collect();
+    list.sort_by_key(|task| task.task_id);
+    Ok(list)
+}
+
+// Stop a task at its next await point. Returns false if it had already finished.
+#[tauri::command]
+pub async fn abort_background_task(task_id: u64) -> Result<bool, String> {
+    let Some(entry) = TASKS.lock().unwrap().remove(&task_id) else { return Ok(false) };
+    let running = !entry.abort.is_finished();
+    entry.abort.abort();
+    Ok(running)
+}
@@ -0,0 +1,141 @@
+use serde::Serialize;
+use std::process::Stdio;
+use std::time::Instant;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::process::Command;
+
+use crate::{apply_integrations, result_tokens, IntegrationConfig, CLAUDE_SETTINGS_JSON};
+
+#[derive(Clone, Serialize)]
+pub struct BenchmarkRun {
+    // None if no text ever arrived
+    pub first_token_ms: Option<u64>,
+    pub total_ms: u64,
+    pub tokens: u64,
+}
+
+#[derive(Clone, Serialize)]
+pub struct BenchmarkResult {
+    pub avg_first_token_ms: Option<u64>,
+    pub avg_total_ms: u64,
+    pub avg_tokens: u64,
+    pub runs: Vec<BenchmarkRun>,
+}
+
+// Run the prompt once without emitting any events, timing the first text
+// block and the whole call
+async fn run_once(
+    app: &tauri::AppHandle,
+    prompt: &str,
+    working_directory: Option<&str>,
+    model: Option<&str>,
+    integrations: Option<&[IntegrationConfig]>,
+    run: u32,
+) -> Result<BenchmarkRun, String> {
+    let mut cmd = Command::new("claude");
+    if let Some(dir) = working_directory {
+        cmd.current_dir(dir);
+    }
+    if let Some(model) = model {
+        cmd.arg("--model").arg(model);
+    }
+    let temp_config = match integrations {
+        Some(ints) => apply_integrations(app, &mut cmd, &format!("benchmark-{}", run), ints).await?,
+        None => None,
+    };
+
+    cmd.arg("--print")
+       .arg("--output-format").arg("stream-json")
+       .arg("--verbose")
+       .arg("--permission-mode").arg("bypassPermissions")
+       .arg("--settings").arg(CLAUDE_SETTINGS_JSON)
+       .arg(prompt)
+       .stdout(Stdio::piped())
+       .stderr(Stdio::null());
+
+    let started = Instant::now();
+    let result = async {
+        let mut child = cmd.spawn().map_err(|e| format!("Failed to spawn claude: {}", e))?;
+        let stdout = child.stdout.take().ok_or("Failed to capture stdout")?;
+        let mut reader = BufReader::new(stdout).lines();
+
+        let mut first_token_ms = None;
+        let mut tokens = 0;
+        let mut error = None;
+        while let Some(line) = reader.next_line().await.map_err(|e| e.to_string())? {
+            let Ok(json) = serde_json::from_str::<serde_json::Value>(&line) else { continue };
+            match json.get("type").and_then(|t| t.as_str()) {
+                Some("assistant") if first_token_ms.is_none() => {
+                    let has_text = json.get("message")
+                        .and_then(|m| m.get("content"))
+                        .and_then(|c| c.as_array())
+                        .is_some_and(|content| content.iter().any(|item| item.get("type").and_then(|t| t.as_str()) == Some("text")));
+                    if has_text {
+                        first_token_ms = Some(started.elapsed().as_millis() as u64);
+                    }
+                }
+                Some("result") => {
+                    tokens = result_tokens(&json);
+                    if json.get("is_error").and_then(|e| e.as_bool()).unwrap_or(false) {
+                        error = json.get("result").and_then(|r| r.as_str()).map(String::from);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let status = child.wait().await.map_err(|e| e.to_string())?;
+        if let Some(error) = error {
+            return Err(error);
+        }
+        if !status.success() {
+            return Err(format!("Claude exited with status: {}", status));
+        }
+        Ok(BenchmarkRun {
+            first_token_ms,
+            total_ms: started.elapsed().as_millis() as u64,
+            tokens,
+        })
+    }.await;
+
+    drop(temp_config);
+    result
+}
+
+#[tauri::command]
+pub async fn benchmark_claude(
+    app: tauri::AppHandle,
+    prompt: String,
+    working_directory: Option<String>,
+    model: Option<String>,
+    integrations: Option<Vec<IntegrationConfig>>,
+    runs: u32,
+) -> Result<BenchmarkResult, String> {
+    if runs == 0 {
+        return Err("runs must be at least 1".to_string());
+    }
+
+    // Sequential, so runs don't slow each other down
+    let mut results = Vec::new();
+    for run in 0..runs {
+        let result = run_once(
+            &app,
+            &prompt,
+            working_directory.as_deref(),
+            model.as_deref(),
+            integrations.as_deref(),
+            run,
+        ).await.map_err(|e| format!("Run {} failed: {}", run + 1, e))?;
+        results.push(result);
+    }
+
+    let count = results.len() as u64;
+    let first_tokens: Vec<u64> = results.iter().filter_map(|r| r.first_token_ms).collect();
+    Ok(BenchmarkResult {
+        avg_first_token_ms: (!first_tokens.is_empty())
+            .then(|| first_tokens.iter().sum::<u64>() / first_tokens.len() as u64),
+        avg_total_ms: results.iter().map(|r| r.total_ms).sum::<u64>() / count,
+        avg_tokens: results.iter().map(|r| r.tokens).sum::<u64>() / count,
+        runs: results,
+    })
+}
@@ -0,0 +1,140 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// Fetched pages can be huge; only this much of the body is kept
+const MAX_EXCERPT_CHARS: usize = 500;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Citation {
+    pub tool_use_id: String,
+    // "WebFetch" or "WebSearch"
+    pub tool: String,
+    pub url: Option<String>,
+    pub query: Option<String>,
+    pub title: Option<String>,
+    pub excerpt: Option<String>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct ClaudeCitations {
+    pub conversation_id: String,
+    pub citations: Vec<Citation>,
+}
+
+struct PendingCall {
+    tool: String,
+    url: Option<String>,
+    query: Option<String>,
+}
+
+// Collects sources from WebFetch/WebSearch calls as the stream is read
+#[derive(Default)]
+pub(crate) struct CitationCollector {
+    pending: HashMap<String, PendingCall>,
+    citations: Vec<Citation>,
+}
+
+fn truncate_chars(text: &str, max: usize) -> String {
+    match text.char_indices().nth(max) {
+        Some((end, _)) => format!("{}…", &text[..end]),
+        None => text.to_string(),
+    }
+}
+
+// tool_result content is either a plain string or a list of text blocks
+fn result_text(item: &serde_json::Value) -> String {
+    match item.get("content") {
+        Some(serde_json::Value::String(text)) => text.clone(),
+        Some(serde_json::Value::Array(blocks)) => blocks.iter()
+            .filter_map(|block| block.get("text").and_then(|t| t.as_str()))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => String::new(),
+    }
+}
+
+// First markdown heading, or failing that the first non-empty line
+fn guess_title(text: &str) -> Option<String> {
+    let mut lines = text.lines().map(str::trim).filter(|line| !line.is_empty());
+    let heading = lines.clone()
+        .find_map(|line| line.strip_prefix('#').map(|rest| rest.trim_start_matches('#').trim()));
+    heading.or_else(|| lines.next())
+        .filter(|title| !title.is_empty())
+        .map(|title| truncate_chars(title, 200))
+}
+
+// WebSearch results embed a JSON array of {title, url} after "Links:"
+fn search_links(text: &str) -> Vec<(Option<String>, String)> {
+    let mut links = Vec::new();
+    for (pos, _) in text.match_indices("Links:") {
+        let rest = text[pos + "Links:".len()..].trim_start();
+        let Some(end) = rest.find("]\n").map(|e| e + 1).or_else(|| rest.rfind(']').map(|e| e + 1)) else {
+            continue;
+        };
+        let Ok(serde_json::Value::Array(items)) = serde_json::from_str(&rest[..end]) else {
+            continue;
+        };
+        for entry in items {
+            if let Some(url) = entry.get("url").and_then(|u| u.as_str()) {
+                let title = entry.get("title").and_then(|t| t.as_str()).map(String::from);
+                links.push((title, url.to_string()));
+            }
+        }
+    }
+    links
+}
+
+impl CitationCollector {
+    // Handle a `tool_use` content block from an assistant message
+    pub(crate) fn on_tool_use(&mut self, item: &serde_json::Value) {
+        let tool = item.get("name").and_then(|n| n.as_str()).unwrap_or("");
+        if tool != "WebFetch" && tool != "WebSearch" {
+            return;
+        }
+        let Some(id) = item.get("id").and_then(|i| i.as_str()) else { return };
+        let input = item.get("input");
+        let field = |key: &str| input.and_then(|i| i.get(key)).and_then(|v| v.as_str()).map(String::from);
+        self.pending.insert(id.to_string(), PendingCall {
+            tool: tool.to_string(),
+            url: field("url"),
+            query: field("query"),
+        });
+    }
+
+    // Handle a `tool_result` content block from a user message
+    pub(crate) fn on_tool_result(&mut self, item: &serde_json::Value) {
+        let Some(tool_use_id) = item.get("tool_use_id").and_then(|i| i.as_str()).map(String::from) else {
+            return;
+        };
+        let Some(call) = self.pending.remove(&tool_use_id) else { return };
+        let failed = item.get("is_error").and_then(|e| e.as_bool()).unwrap_or(false);
+        let text = if failed { String::new() } else { result_text(item) };
+
+        let links = if call.tool == "WebSearch" { search_links(&text) } else { Vec::new() };
+        if links.is_empty() {
+            self.citations.push(Citation {
+                tool_use_id,
+                tool: call.tool,
+                url: call.url,
+                query: call.query,
+                title: guess_title(&text),
+                excerpt: (!text.trim().is_empty()).then(|| truncate_chars(text.trim(), MAX_EXCERPT_CHARS)),
+            });
+        } else {
+            for (title, url) in links {
+                self.citations.push(Citation {
+                    tool_use_id: tool_use_id.clone(),
+                    tool: call.tool.clone(),
+                    url: Some(url),
+                    query: call.query.clone(),
+                    title,
+                    excerpt: None,
+                });
+            }
+        }
+    }
+
+    pub(crate) fn finish(self) -> Vec<Citation> {
+        self.citations
+    }
+}
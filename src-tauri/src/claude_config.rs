@@ -0,0 +1,127 @@
+use once_cell::sync::Lazy;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use crate::{with_suffix, write_atomic};
+
+#[derive(Clone, Copy)]
+enum ConfigFile {
+    // ~/.claude.json, which `claude config` edits. It also holds account
+    // details, so only allowlisted keys are ever read back out.
+    Global,
+    // ~/.claude/settings.json, the user-level settings file
+    UserSettings,
+}
+
+#[derive(Clone, Copy)]
+enum ValueKind {
+    Bool,
+    String,
+    OneOf(&'static [&'static str]),
+    PositiveInteger,
+}
+
+// The CLI settings the app may show and change
+const CONFIG_KEYS: &[(&str, ConfigFile, ValueKind)] = &[
+    ("theme", ConfigFile::Global, ValueKind::OneOf(&["dark", "light", "dark-daltonized", "light-daltonized", "dark-ansi", "light-ansi"])),
+    ("verbose", ConfigFile::Global, ValueKind::Bool),
+    ("autoUpdates", ConfigFile::Global, ValueKind::Bool),
+    ("preferredNotifChannel", ConfigFile::Global, ValueKind::OneOf(&["auto", "iterm2", "iterm2_with_bell", "terminal_bell", "kitty", "ghostty", "notifications_disabled"])),
+    ("editorMode", ConfigFile::Global, ValueKind::OneOf(&["normal", "vim"])),
+    ("model", ConfigFile::UserSettings, ValueKind::String),
+    ("cleanupPeriodDays", ConfigFile::UserSettings, ValueKind::PositiveInteger),
+    ("includeCoAuthoredBy", ConfigFile::UserSettings, ValueKind::Bool),
+];
+
+static CONFIG_LOCK: Lazy<Arc<Mutex<()>>> = Lazy::new(|| Arc::new(Mutex::new(())));
+
+fn config_path(file: ConfigFile) -> Result<PathBuf, String> {
+    let home = dirs::home_dir().ok_or("Could not determine home directory")?;
+    Ok(match file {
+        ConfigFile::Global => home.join(".claude.json"),
+        ConfigFile::UserSettings => home.join(".claude").join("settings.json"),
+    })
+}
+
+// A missing file is an empty config; one that doesn't parse is an error so
+// it never gets overwritten
+async fn read_config(path: &std::path::Path) -> Result<serde_json::Map<String, serde_json::Value>, String> {
+    let data = match tokio::fs::read_to_string(path).await {
+        Ok(data) => data,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(serde_json::Map::new()),
+        Err(e) => return Err(format!("Failed to read {}: {}", path.display(), e)),
+    };
+    match serde_json::from_str(&data) {
+        Ok(serde_json::Value::Object(map)) => Ok(map),
+        Ok(_) => Err(format!("{} is not a JSON object", path.display())),
+        Err(e) => Err(format!("Failed to parse {}: {}", path.display(), e)),
+    }
+}
+
+fn check_value(key: &str, kind: ValueKind, value: &serde_json::Value) -> Result<(), String> {
+    let ok = match kind {
+        ValueKind::Bool => value.is_boolean(),
+        ValueKind::String => value.as_str().is_some_and(|s| !s.trim().is_empty()),
+        ValueKind::OneOf(allowed) => value.as_str().is_some_and(|s| allowed.contains(&s)),
+        ValueKind::PositiveInteger => value.as_u64().is_some_and(|n| n > 0),
+    };
+    if ok {
+        return Ok(());
+    }
+    let expected = match kind {
+        ValueKind::Bool => "true or false".to_string(),
+        ValueKind::String => "a non-empty string".to_string(),
+        ValueKind::OneOf(allowed) => format!("one of {}", allowed.join(", ")),
+        ValueKind::PositiveInteger => "a positive integer".to_string(),
+    };
+    Err(format!("Invalid value for {}: expected {}", key, expected))
+}
+
+// The allowlisted CLI settings that are currently set, keyed by name
+#[tauri::command]
+pub async fn get_claude_config() -> Result<serde_json::Value, String> {
+    let global = read_config(&config_path(ConfigFile::Global)?).await?;
+    let user = read_config(&config_path(ConfigFile::UserSettings)?).await?;
+    let mut values = serde_json::Map::new();
+    for (key, file, _) in CONFIG_KEYS {
+        let source = match file {
+            ConfigFile::Global => &global,
+            ConfigFile::UserSettings => &user,
+        };
+        if let Some(value) = source.get(*key) {
+            values.insert(key.to_string(), value.clone());
+        }
+    }
+    Ok(serde_json::Value::Object(values))
+}
+
+// Set one allowlisted CLI setting; null removes it. The previous file is
+// kept alongside as `<name>.bak`.
+#[tauri::command]
+pub async fn set_claude_config_value(key: String, value: serde_json::Value) -> Result<(), String> {
+    let (_, file, kind) = CONFIG_KEYS.iter()
+        .find(|(name, _, _)| *name == key)
+        .ok_or_else(|| format!("Unsupported Claude config key: {}", key))?;
+    if !value.is_null() {
+        check_value(&key, *kind, &value)?;
+    }
+
+    let path = config_path(*file)?;
+    let _guard = CONFIG_LOCK.lock().await;
+    let mut config = read_config(&path).await?;
+    if path.exists() {
+        tokio::fs::copy(&path, with_suffix(&path, ".bak")).await
+            .map_err(|e| format!("Failed to back up {}: {}", path.display(), e))?;
+    } else if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await.map_err(|e| e.to_string())?;
+    }
+
+    if value.is_null() {
+        config.remove(&key);
+    } else {
+        config.insert(key, value);
+    }
+    let data = serde_json::to_vec_pretty(&config).map_err(|e| e.to_string())?;
+    write_atomic(&path, &data).await
+}
@@ -0,0 +1,56 @@
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use crate::unix_millis;
+
+const MAX_ERRORS: usize = 200;
+// Only the end of stderr is kept; that's where the useful part usually is
+const STDERR_TAIL_CHARS: usize = 4000;
+
+#[derive(Clone, Serialize)]
+pub struct ClaudeError {
+    pub timestamp: u64,
+    pub conversation_id: String,
+    pub message: String,
+    pub stderr_tail: Option<String>,
+}
+
+// Most recent failures last, capped at MAX_ERRORS
+static RECENT_ERRORS: Lazy<Arc<Mutex<VecDeque<ClaudeError>>>> =
+    Lazy::new(|| Arc::new(Mutex::new(VecDeque::new())));
+
+fn tail(text: &str, max_chars: usize) -> &str {
+    let count = text.chars().count();
+    if count <= max_chars {
+        return text;
+    }
+    let start = text.char_indices().nth(count - max_chars).map(|(i, _)| i).unwrap_or(0);
+    &text[start..]
+}
+
+pub(crate) async fn record(conversation_id: &str, message: &str, stderr: Option<&str>) {
+    let stderr_tail = stderr
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|s| tail(s, STDERR_TAIL_CHARS).to_string());
+    let mut errors = RECENT_ERRORS.lock().await;
+    if errors.len() >= MAX_ERRORS {
+        errors.pop_front();
+    }
+    errors.push_back(ClaudeError {
+        timestamp: unix_millis(),
+        conversation_id: conversation_id.to_string(),
+        message: message.to_string(),
+        stderr_tail,
+    });
+}
+
+// Newest first
+#[tauri::command]
+pub async fn get_recent_claude_errors(limit: usize) -> Result<Vec<ClaudeError>, String> {
+    let errors = RECENT_ERRORS.lock().await;
+    Ok(errors.iter().rev().take(limit).cloned().collect())
+}
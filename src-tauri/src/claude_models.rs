@@ -0,0 +1,86 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::process::Command;
+use tokio::sync::Mutex;
+
+// Aliases every CLI version accepts; always offered, even if the help leaves some out
+const DEFAULT_MODELS: &[&str] = &["opus", "sonnet", "haiku"];
+const CACHE_FOR: Duration = Duration::from_secs(10 * 60);
+const HELP_TIMEOUT: Duration = Duration::from_secs(10);
+
+struct CachedModels {
+    fetched: Instant,
+    models: Vec<String>,
+}
+
+static CACHE: Lazy<Arc<Mutex<Option<CachedModels>>>> =
+    Lazy::new(|| Arc::new(Mutex::new(None)));
+
+// The CLI has no listing command, but the --model entry of its help names
+// the aliases and full model ids it knows about
+fn parse_help(help: &str) -> Vec<String> {
+    static QUOTED: Lazy<Regex> = Lazy::new(|| Regex::new(r#"['"`]([a-z][a-z0-9.\-\[\]]*)['"`]"#).unwrap());
+    static MODEL_ID: Lazy<Regex> = Lazy::new(|| Regex::new(r"\bclaude-[a-z0-9][a-z0-9.\-]*[a-z0-9]").unwrap());
+
+    let mut models: Vec<String> = Vec::new();
+    let mut push = |model: &str| {
+        if !models.iter().any(|m| m == model) {
+            models.push(model.to_string());
+        }
+    };
+    // The --model description can wrap onto the following lines
+    let Some(start) = help.find("--model") else { return Vec::new() };
+    let section: String = help[start..].lines()
+        .enumerate()
+        .take_while(|(i, line)| *i == 0 || !line.trim_start().starts_with('-'))
+        .map(|(_, line)| format!("{}\n", line))
+        .collect();
+    for capture in QUOTED.captures_iter(&section) {
+        let name = &capture[1];
+        if DEFAULT_MODELS.contains(&name) || name.starts_with("claude-") {
+            push(name);
+        }
+    }
+    for found in MODEL_ID.find_iter(&section) {
+        push(found.as_str());
+    }
+    models
+}
+
+async fn query_cli() -> Result<Vec<String>, String> {
+    let child = Command::new("claude")
+        .arg("--help")
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| format!("Failed to run claude: {}", e))?;
+    let output = tokio::time::timeout(HELP_TIMEOUT, child.wait_with_output()).await
+        .map_err(|_| "claude --help timed out".to_string())?
+        .map_err(|e| e.to_string())?;
+    Ok(parse_help(&String::from_utf8_lossy(&output.stdout)))
+}
+
+// Model names for the model picker: whatever the installed CLI mentions,
+// then the usual aliases. Kept for a few minutes.
+#[tauri::command]
+pub async fn list_claude_models() -> Result<Vec<String>, String> {
+    let mut cache = CACHE.lock().await;
+    if let Some(ref cached) = *cache {
+        if cached.fetched.elapsed() < CACHE_FOR {
+            return Ok(cached.models.clone());
+        }
+    }
+    let mut models = query_cli().await.unwrap_or_default();
+    for alias in DEFAULT_MODELS {
+        if !models.iter().any(|m| m == alias) {
+            models.push(alias.to_string());
+        }
+    }
+    *cache = Some(CachedModels { fetched: Instant::now(), models: models.clone() });
+    Ok(models)
+}
@@ -0,0 +1,318 @@
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines};
+use tokio::process::{Child, ChildStdin, ChildStdout};
+use tokio::sync::Mutex;
+
+use crate::stream_errors::{self, Liveness, StreamSource};
+use crate::{ClaudeSpawnParams, IntegrationConfig, McpConfigFile};
+
+// Warm processes are shut down after this long without a turn
+const IDLE_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+const IDLE_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+// A claude process started with `--input-format stream-json` that takes one
+// user message per turn, so its MCP servers stay up between turns
+pub(crate) struct PersistentClaude {
+    child: Child,
+    stdin: ChildStdin,
+    lines: Lines<BufReader<ChildStdout>>,
+    // Everything that went into the spawn; a turn with different settings needs a new process
+    signature: String,
+    session_id: Option<String>,
+    work_dir: Option<String>,
+    temp_mcp_config: Option<McpConfigFile>,
+    stderr: Arc<std::sync::Mutex<String>>,
+    last_used: Instant,
+}
+
+impl PersistentClaude {
+    async fn shut_down(mut self) {
+        let _ = self.child.kill().await;
+        // The config goes with the process
+        drop(self.temp_mcp_config);
+    }
+}
+
+// Idle persistent processes by conversation. A process is taken out for the
+// length of a turn and put back afterwards.
+static IDLE_PROCESSES: Lazy<Arc<Mutex<HashMap<String, PersistentClaude>>>> =
+    Lazy::new(|| Arc::new(Mutex::new(HashMap::new())));
+
+// MCP servers are the expensive part of a spawn, so only conversations that
+// use them get a persistent process
+pub(crate) fn wants_persistent(params: &ClaudeSpawnParams) -> bool {
+    params.integrations.as_deref().is_some_and(|ints: &[IntegrationConfig]| {
+        ints.iter().any(|int| int.enabled && int.integration_type == "mcp" && int.server_command.is_some())
+    })
+}
+
+// An edited project context file means a fresh process
+pub(crate) fn signature(params: &ClaudeSpawnParams, work_dir: Option<&str>, project_context: Option<&str>) -> String {
+    serde_json::json!({
+        "integrations": params.integrations,
+        "system_prompt": params.system_prompt,
+        "project_context": project_context,
+        "profile": params.profile,
+        "model": params.model,
+        "permission_mode": params.permission_mode,
+        "work_dir": work_dir,
+    }).to_string()
+}
+
+// Take the conversation's warm process if it was started with the same
+// settings and is still in the requested session; anything else is shut down
+async fn checkout(conversation_id: &str, signature: &str, session_id: Option<&str>) -> Option<PersistentClaude> {
+    let mut process = IDLE_PROCESSES.lock().await.remove(conversation_id)?;
+    let alive = matches!(process.child.try_wait(), Ok(None));
+    let same_session = match (session_id, process.session_id.as_deref()) {
+        (Some(wanted), Some(current)) => wanted == current,
+        _ => true,
+    };
+    if alive && same_session && process.signature == signature {
+        return Some(process);
+    }
+    process.shut_down().await;
+    None
+}
+
+// The process a single turn reads from
+pub(crate) enum ClaudeProcess {
+    // Spawned for this turn; exits when the turn is over
+    OneShot {
+        child: Child,
+        lines: Lines<BufReader<ChildStdout>>,
+        stderr: Option<tokio::task::JoinHandle<Option<String>>>,
+    },
+    Persistent {
+        conversation_id: String,
+        process: PersistentClaude,
+        turn_done: bool,
+        killed: bool,
+    },
+}
+
+// How a turn's process ended
+pub(crate) struct TurnExit {
+    pub success: bool,
+    pub status: String,
+    pub stderr: String,
+}
+
+fn stderr_source(child: &Child, conversation_id: &str) -> StreamSource {
+    let liveness = child.id().map(Liveness::Pid).unwrap_or(Liveness::Unknown);
+    StreamSource::new(conversation_id, "claude", liveness)
+}
+
+fn collect_stderr(app: &tauri::AppHandle, child: &mut Child, conversation_id: &str) -> Option<tokio::task::JoinHandle<Option<String>>> {
+    let source = stderr_source(child, conversation_id);
+    child.stderr.take().map(|stderr| {
+        let description = format!("claude stderr reader ({})", conversation_id);
+        stream_errors::spawn_reader(app.clone(), source, description, async move {
+            let mut stderr_reader = BufReader::new(stderr).lines();
+            let mut stderr_output = String::new();
+            loop {
+                match stderr_reader.next_line().await {
+                    Ok(Some(line)) => {
+                        stderr_output.push_str(&line);
+                        stderr_output.push('\n');
+                    }
+                    Ok(None) => return (stderr_output, Ok(())),
+                    Err(e) => return (stderr_output, Err(e.to_string())),
+                }
+            }
+        })
+    })
+}
+
+impl ClaudeProcess {
+    pub(crate) fn one_shot(app: &tauri::AppHandle, mut child: Child, conversation_id: &str) -> Result<Self, String> {
+        let stdout = child.stdout.take().ok_or("Failed to capture stdout")?;
+        let stderr = collect_stderr(app, &mut child, conversation_id);
+        Ok(ClaudeProcess::OneShot { child, lines: BufReader::new(stdout).lines(), stderr })
+    }
+
+    // Reuse the conversation's warm process, if it fits
+    pub(crate) async fn reuse(conversation_id: &str, signature: &str, session_id: Option<&str>) -> Option<Self> {
+        let process = checkout(conversation_id, signature, session_id).await?;
+        Some(ClaudeProcess::Persistent {
+            conversation_id: conversation_id.to_string(),
+            process,
+            turn_done: false,
+            killed: false,
+        })
+    }
+
+    pub(crate) fn persistent(
+        app: &tauri::AppHandle,
+        mut child: Child,
+        conversation_id: &str,
+        signature: String,
+        work_dir: Option<String>,
+        temp_mcp_config: Option<McpConfigFile>,
+    ) -> Result<Self, String> {
+        let stdin = child.stdin.take().ok_or("Failed to capture stdin")?;
+        let stdout = child.stdout.take().ok_or("Failed to capture stdout")?;
+
+        // Read for the life of the process; each turn takes what arrived during it
+        let stderr = Arc::new(std::sync::Mutex::new(String::new()));
+        let source = stderr_source(&child, conversation_id);
+        if let Some(pipe) = child.stderr.take() {
+            let stderr = stderr.clone();
+            let description = format!("claude stderr reader ({})", conversation_id);
+            stream_errors::spawn_reader(app.clone(), source, description, async move {
+                let mut reader = BufReader::new(pipe).lines();
+                loop {
+                    match reader.next_line().await {
+                        Ok(Some(line)) => {
+                            let mut buffer = stderr.lock().unwrap();
+                            buffer.push_str(&line);
+                            buffer.push('\n');
+                        }
+                        Ok(None) => return ((), Ok(())),
+                        Err(e) => return ((), Err(e.to_string())),
+                    }
+                }
+            });
+        }
+
+        Ok(ClaudeProcess::Persistent {
+            conversation_id: conversation_id.to_string(),
+            process: PersistentClaude {
+                child,
+                stdin,
+                lines: BufReader::new(stdout).lines(),
+                signature,
+                session_id: None,
+                work_dir,
+                temp_mcp_config,
+                stderr,
+                last_used: Instant::now(),
+            },
+            turn_done: false,
+            killed: false,
+        })
+    }
+
+    // Working directory a reused process was started in
+    pub(crate) fn work_dir(&self) -> Option<String> {
+        match self {
+            ClaudeProcess::Persistent { process, .. } => process.work_dir.clone(),
+            ClaudeProcess::OneShot { .. } => None,
+        }
+    }
+
+    pub(crate) fn pid(&self) -> Option<u32> {
+        match self {
+            ClaudeProcess::OneShot { child, .. } => child.id(),
+            ClaudeProcess::Persistent { process, .. } => process.child.id(),
+        }
+    }
+
+    // Hand the turn's prompt to a persistent process; one-shot processes got it as an argument
+    pub(crate) async fn send_prompt(&mut self, prompt: &str) -> Result<(), String> {
+        let ClaudeProcess::Persistent { process, .. } = self else { return Ok(()) };
+        let message = serde_json::json!({
+            "type": "user",
+            "message": { "role": "user", "content": [{ "type": "text", "text": prompt }] },
+        });
+        let line = format!("{}\n", message);
+        process.stdin.write_all(line.as_bytes()).await.map_err(|e| format!("Failed to send prompt: {}", e))?;
+        process.stdin.flush().await.map_err(|e| format!("Failed to send prompt: {}", e))
+    }
+
+    // Next line of the turn's output. A persistent process never reaches EOF
+    // between turns, so its turn ends after the `result` message.
+    pub(crate) async fn next_line(&mut self) -> std::io::Result<Option<String>> {
+        match self {
+            ClaudeProcess::OneShot { lines, .. } => lines.next_line().await,
+            ClaudeProcess::Persistent { process, turn_done, .. } => {
+                if *turn_done {
+                    return Ok(None);
+                }
+                let line = process.lines.next_line().await?;
+                if let Some(ref text) = line {
+                    if let Ok(json) = serde_json::from_str::<serde_json::Value>(text) {
+                        if let Some(sid) = json.get("session_id").and_then(|s| s.as_str()) {
+                            process.session_id = Some(sid.to_string());
+                        }
+                        *turn_done = json.get("type").and_then(|t| t.as_str()) == Some("result");
+                    }
+                }
+                Ok(line)
+            }
+        }
+    }
+
+    pub(crate) async fn kill(&mut self) {
+        match self {
+            ClaudeProcess::OneShot { child, .. } => {
+                let _ = child.kill().await;
+            }
+            ClaudeProcess::Persistent { process, killed, .. } => {
+                let _ = process.child.kill().await;
+                *killed = true;
+            }
+        }
+    }
+
+    // Wait for a one-shot process to exit. A persistent process that is still
+    // healthy goes back to the idle pool for the next turn instead.
+    pub(crate) async fn finish(self) -> Result<TurnExit, String> {
+        match self {
+            ClaudeProcess::OneShot { mut child, stderr, .. } => {
+                let status = child.wait().await.map_err(|e| e.to_string())?;
+                let stderr = match stderr {
+                    Some(handle) => handle.await.ok().flatten().unwrap_or_default(),
+                    None => String::new(),
+                };
+                Ok(TurnExit { success: status.success(), status: status.to_string(), stderr })
+            }
+            ClaudeProcess::Persistent { conversation_id, mut process, turn_done, killed } => {
+                let stderr = std::mem::take(&mut *process.stderr.lock().unwrap());
+                let exited = process.child.try_wait().map_err(|e| e.to_string())?;
+                if turn_done && !killed && exited.is_none() {
+                    process.last_used = Instant::now();
+                    IDLE_PROCESSES.lock().await.insert(conversation_id, process);
+                    return Ok(TurnExit { success: true, status: "kept alive".to_string(), stderr });
+                }
+                let status = match exited {
+                    Some(status) => status.to_string(),
+                    None if killed => "killed".to_string(),
+                    None => "ended mid-turn".to_string(),
+                };
+                let success = exited.is_some_and(|s| s.success()) && turn_done;
+                process.shut_down().await;
+                Ok(TurnExit { success, status, stderr })
+            }
+        }
+    }
+}
+
+// For settings changes that every warm process would miss, and on exit
+pub(crate) async fn shut_down_idle() {
+    let idle: Vec<PersistentClaude> = IDLE_PROCESSES.lock().await.drain().map(|(_, p)| p).collect();
+    for process in idle {
+        process.shut_down().await;
+    }
+}
+
+// Started from setup; shuts down processes nobody has used in a while
+pub(crate) async fn reap_idle_processes() {
+    loop {
+        tokio::time::sleep(IDLE_CHECK_INTERVAL).await;
+        let expired: Vec<PersistentClaude> = {
+            let mut idle = IDLE_PROCESSES.lock().await;
+            let ids: Vec<String> = idle.iter()
+                .filter(|(_, p)| p.last_used.elapsed() >= IDLE_TIMEOUT)
+                .map(|(id, _)| id.clone())
+                .collect();
+            ids.iter().filter_map(|id| idle.remove(id)).collect()
+        };
+        for process in expired {
+            process.shut_down().await;
+        }
+    }
+}
@@ -0,0 +1,64 @@
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::sync::Arc;
+use tauri::Emitter;
+use tokio::sync::{Mutex, OwnedSemaphorePermit, Semaphore};
+
+use crate::settings;
+
+const DEFAULT_CLAUDE_CONCURRENCY: usize = 3;
+
+#[derive(Clone, Serialize)]
+pub struct ClaudeQueued {
+    pub conversation_id: String,
+    // true while waiting for a slot, false once the request starts
+    pub queued: bool,
+}
+
+// Shared by every send_to_claude call; one permit per running claude process
+static CLAUDE_SLOTS: Lazy<Arc<Semaphore>> =
+    Lazy::new(|| Arc::new(Semaphore::new(DEFAULT_CLAUDE_CONCURRENCY)));
+
+// The permit count CLAUDE_SLOTS is currently sized for
+static CLAUDE_CONCURRENCY: Lazy<Mutex<usize>> =
+    Lazy::new(|| Mutex::new(DEFAULT_CLAUDE_CONCURRENCY));
+
+// Wait for a free slot, telling the UI when the request has to queue
+pub(crate) async fn acquire_slot(app: &tauri::AppHandle, conversation_id: &str) -> Result<OwnedSemaphorePermit, String> {
+    if let Ok(permit) = CLAUDE_SLOTS.clone().try_acquire_owned() {
+        return Ok(permit);
+    }
+
+    let event = format!("claude-queued-{}", conversation_id);
+    let _ = app.emit(&event, ClaudeQueued { conversation_id: conversation_id.to_string(), queued: true });
+    let permit = CLAUDE_SLOTS.clone().acquire_owned().await.map_err(|e| e.to_string())?;
+    let _ = app.emit(&event, ClaudeQueued { conversation_id: conversation_id.to_string(), queued: false });
+    Ok(permit)
+}
+
+pub(crate) async fn apply_concurrency(limit: usize) {
+    let mut current = CLAUDE_CONCURRENCY.lock().await;
+    if limit > *current {
+        CLAUDE_SLOTS.add_permits(limit - *current);
+    } else if limit < *current {
+        // Soak up the surplus as running requests finish, then throw it away
+        let surplus = (*current - limit) as u32;
+        let slots = CLAUDE_SLOTS.clone();
+        crate::background_tasks::spawn("claude slot shrink", async move {
+            if let Ok(permits) = slots.acquire_many_owned(surplus).await {
+                permits.forget();
+            }
+        });
+    }
+    *current = limit;
+}
+
+#[tauri::command]
+pub async fn set_claude_concurrency(app: tauri::AppHandle, limit: usize) -> Result<(), String> {
+    if limit == 0 {
+        return Err("Concurrency limit must be at least 1".to_string());
+    }
+    settings::update(&app, |settings| settings.claude_concurrency = Some(limit)).await?;
+    apply_concurrency(limit).await;
+    Ok(())
+}
@@ -0,0 +1,100 @@
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tauri::{Emitter, EventId, Listener};
+use tokio::sync::Mutex;
+
+use crate::ipc_limits::EventPart;
+use crate::ServiceOutput;
+
+// One service's output as it appears on `combined-logs-<feed_id>`
+#[derive(Clone, Serialize)]
+pub struct CombinedLogChunk {
+    pub feed_id: String,
+    pub service_id: String,
+    // Each line starts with `[service_id] `, like docker compose
+    pub output: String,
+    pub is_stderr: bool,
+    pub is_complete: bool,
+    pub exit_code: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub part: Option<EventPart>,
+}
+
+// Backend listeners on a feed's service-output channels
+struct Feed {
+    listeners: Vec<EventId>,
+}
+
+static FEEDS: Lazy<Arc<Mutex<HashMap<String, Feed>>>> =
+    Lazy::new(|| Arc::new(Mutex::new(HashMap::new())));
+
+fn prefix_lines(service_id: &str, output: &str, continues_line: bool) -> String {
+    let mut prefixed = String::with_capacity(output.len());
+    for (i, line) in output.split_inclusive('\n').enumerate() {
+        // A later part of a split chunk may start mid-line
+        if !(i == 0 && continues_line) {
+            prefixed.push_str(&format!("[{}] ", service_id));
+        }
+        prefixed.push_str(line);
+    }
+    prefixed
+}
+
+fn unlisten_all(app: &tauri::AppHandle, feed: Feed) {
+    for id in feed.listeners {
+        app.unlisten(id);
+    }
+}
+
+// Re-emit the output of several services on one channel. Subscribing again
+// with the same feed_id replaces its service list.
+#[tauri::command]
+pub async fn subscribe_combined_service_logs(
+    app: tauri::AppHandle,
+    feed_id: String,
+    service_ids: Vec<String>,
+) -> Result<(), String> {
+    if service_ids.is_empty() {
+        return Err("At least one service is required".to_string());
+    }
+    let mut feeds = FEEDS.lock().await;
+    if let Some(old) = feeds.remove(&feed_id) {
+        unlisten_all(&app, old);
+    }
+
+    let mut listeners = Vec::new();
+    for service_id in service_ids {
+        let app_clone = app.clone();
+        let feed = feed_id.clone();
+        let id = app.listen(format!("service-output-{}", service_id), move |event| {
+            let Ok(chunk) = serde_json::from_str::<ServiceOutput>(event.payload()) else { return };
+            let continues_line = chunk.part.as_ref().is_some_and(|part| part.seq > 0);
+            let _ = app_clone.emit(&format!("combined-logs-{}", feed), CombinedLogChunk {
+                feed_id: feed.clone(),
+                output: prefix_lines(&chunk.service_id, &chunk.output, continues_line),
+                service_id: chunk.service_id,
+                is_stderr: chunk.is_stderr,
+                is_complete: chunk.is_complete,
+                exit_code: chunk.exit_code,
+                part: chunk.part,
+            });
+        });
+        listeners.push(id);
+    }
+    feeds.insert(feed_id, Feed { listeners });
+    Ok(())
+}
+
+// Returns false if there was no such feed
+#[tauri::command]
+pub async fn unsubscribe_combined_service_logs(app: tauri::AppHandle, feed_id: String) -> Result<bool, String> {
+    match FEEDS.lock().await.remove(&feed_id) {
+        Some(feed) => {
+            unlisten_all(&app, feed);
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
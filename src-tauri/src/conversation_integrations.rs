@@ -0,0 +1,64 @@
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use crate::{storage, write_atomic, IntegrationConfig};
+
+// Guards the overrides file; the value is unused
+static OVERRIDES_LOCK: Lazy<Arc<Mutex<()>>> = Lazy::new(|| Arc::new(Mutex::new(())));
+
+fn get_overrides_path(app: &tauri::AppHandle) -> PathBuf {
+    storage::data_dir(app).join("conversation-integrations.json")
+}
+
+// Enabled integration ids by conversation
+async fn read_overrides(app: &tauri::AppHandle) -> HashMap<String, Vec<String>> {
+    match tokio::fs::read_to_string(get_overrides_path(app)).await {
+        Ok(data) => serde_json::from_str(&data).unwrap_or_default(),
+        Err(_) => HashMap::new(),
+    }
+}
+
+// The integrations a conversation actually uses. A conversation override
+// names exactly which integrations are on, whatever their own `enabled`
+// flag says; without one the flag decides.
+pub(crate) async fn effective(
+    app: &tauri::AppHandle,
+    conversation_id: &str,
+    integrations: Vec<IntegrationConfig>,
+) -> Vec<IntegrationConfig> {
+    let enabled_ids = read_overrides(app).await.remove(conversation_id);
+    integrations.into_iter()
+        .map(|mut int| {
+            if let Some(ref ids) = enabled_ids {
+                int.enabled = ids.contains(&int.id);
+            }
+            int
+        })
+        .filter(|int| int.enabled)
+        .collect()
+}
+
+// Pass None to drop the override and go back to each integration's own flag
+#[tauri::command]
+pub async fn set_conversation_integrations(
+    app: tauri::AppHandle,
+    conversation_id: String,
+    enabled_ids: Option<Vec<String>>,
+) -> Result<(), String> {
+    let _guard = OVERRIDES_LOCK.lock().await;
+    let mut overrides = read_overrides(&app).await;
+    match enabled_ids {
+        Some(ids) => overrides.insert(conversation_id, ids),
+        None => overrides.remove(&conversation_id),
+    };
+    let data = serde_json::to_vec_pretty(&overrides).map_err(|e| e.to_string())?;
+    write_atomic(&get_overrides_path(&app), &data).await
+}
+
+#[tauri::command]
+pub async fn get_conversation_integrations(app: tauri::AppHandle, conversation_id: String) -> Result<Option<Vec<String>>, String> {
+    Ok(read_overrides(&app).await.remove(&conversation_id))
+}
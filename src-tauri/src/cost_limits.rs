@@ -0,0 +1,209 @@
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use crate::{settings, storage, unix_millis, write_atomic};
+
+// USD per million tokens (input, output), matched against the model name in
+// order. Unknown models are priced like the most expensive one so an estimate
+// never undershoots.
+const MODEL_PRICES: &[(&str, f64, f64)] = &[
+    ("opus-4-5", 5.0, 25.0),
+    ("opus", 15.0, 75.0),
+    ("sonnet", 3.0, 15.0),
+    ("haiku-4", 1.0, 5.0),
+    ("haiku", 0.8, 4.0),
+];
+const FALLBACK_PRICE: (f64, f64) = (15.0, 75.0);
+// Cache writes and reads, relative to the input price
+const CACHE_WRITE_FACTOR: f64 = 1.25;
+const CACHE_READ_FACTOR: f64 = 0.1;
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct ConversationSpend {
+    pub spent_usd: f64,
+    pub turns: u64,
+    pub updated_at: u64,
+}
+
+#[derive(Clone, Serialize)]
+pub struct BudgetExceeded {
+    pub conversation_id: String,
+    // "turn" or "conversation"
+    pub scope: String,
+    pub limit_usd: f64,
+    pub spent_usd: f64,
+}
+
+impl BudgetExceeded {
+    pub(crate) fn message(&self) -> String {
+        format!(
+            "Budget exceeded: spent ${:.2} against the ${:.2} per-{} limit",
+            self.spent_usd, self.limit_usd, self.scope
+        )
+    }
+}
+
+static LEDGER_LOCK: Lazy<Arc<Mutex<()>>> = Lazy::new(|| Arc::new(Mutex::new(())));
+
+fn get_ledger_path(app: &tauri::AppHandle) -> PathBuf {
+    storage::data_dir(app).join("cost-ledger.json")
+}
+
+async fn read_ledger(app: &tauri::AppHandle) -> HashMap<String, ConversationSpend> {
+    match tokio::fs::read_to_string(get_ledger_path(app)).await {
+        Ok(data) => serde_json::from_str(&data).unwrap_or_default(),
+        Err(_) => HashMap::new(),
+    }
+}
+
+async fn add_spend(app: &tauri::AppHandle, conversation_id: &str, cost_usd: f64, turns: u64) -> Result<(), String> {
+    let _guard = LEDGER_LOCK.lock().await;
+    let mut ledger = read_ledger(app).await;
+    let spend = ledger.entry(conversation_id.to_string()).or_default();
+    spend.spent_usd += cost_usd;
+    spend.turns += turns;
+    spend.updated_at = unix_millis();
+    let data = serde_json::to_vec_pretty(&ledger).map_err(|e| e.to_string())?;
+    write_atomic(&get_ledger_path(app), &data).await
+}
+
+// Add a finished turn's cost, whether or not the turn succeeded
+pub(crate) async fn record_turn(app: &tauri::AppHandle, conversation_id: &str, cost_usd: f64) -> Result<(), String> {
+    add_spend(app, conversation_id, cost_usd, 1).await
+}
+
+// Charge a side call, such as follow-up suggestions, to the conversation
+// without counting it as a turn
+pub(crate) async fn record_extra(app: &tauri::AppHandle, conversation_id: &str, cost_usd: f64) -> Result<(), String> {
+    add_spend(app, conversation_id, cost_usd, 0).await
+}
+
+// The limit that applies to one turn
+#[derive(Clone)]
+pub(crate) struct Budget {
+    scope: &'static str,
+    limit_usd: f64,
+    // Already spent against the limit before this turn
+    spent_before: f64,
+}
+
+impl Budget {
+    pub(crate) fn remaining(&self) -> f64 {
+        self.limit_usd - self.spent_before
+    }
+
+    fn exceeded(&self, conversation_id: &str, spent_usd: f64) -> BudgetExceeded {
+        BudgetExceeded {
+            conversation_id: conversation_id.to_string(),
+            scope: self.scope.to_string(),
+            limit_usd: self.limit_usd,
+            spent_usd,
+        }
+    }
+}
+
+// The per-turn limit or what's left of the conversation's, whichever is
+// lower. Err when the conversation has nothing left to spend.
+pub(crate) async fn turn_budget(app: &tauri::AppHandle, conversation_id: &str) -> Result<Option<Budget>, String> {
+    let settings = settings::load(app).await?;
+    let turn = settings.max_turn_cost_usd.map(|limit_usd| Budget { scope: "turn", limit_usd, spent_before: 0.0 });
+    let Some(limit_usd) = settings.max_conversation_cost_usd else { return Ok(turn) };
+
+    let spent_before = read_ledger(app).await.remove(conversation_id).unwrap_or_default().spent_usd;
+    let conversation = Budget { scope: "conversation", limit_usd, spent_before };
+    let remaining = limit_usd - spent_before;
+    if remaining <= 0.0 {
+        let exceeded = conversation.exceeded(conversation_id, spent_before);
+        return Err(format!("{} ($0.00 remaining)", exceeded.message()));
+    }
+    Ok(match turn {
+        Some(turn) if turn.limit_usd <= remaining => Some(turn),
+        _ => Some(conversation),
+    })
+}
+
+fn price_for(model: &str) -> (f64, f64) {
+    MODEL_PRICES.iter()
+        .find(|(name, _, _)| model.contains(name))
+        .map(|(_, input, output)| (*input, *output))
+        .unwrap_or(FALLBACK_PRICE)
+}
+
+fn estimate(model: &str, usage: &serde_json::Value) -> f64 {
+    let tokens = |key: &str| usage.get(key).and_then(|t| t.as_u64()).unwrap_or(0) as f64;
+    let (input, output) = price_for(model);
+    let input_cost = tokens("input_tokens") * input
+        + tokens("cache_creation_input_tokens") * input * CACHE_WRITE_FACTOR
+        + tokens("cache_read_input_tokens") * input * CACHE_READ_FACTOR;
+    (input_cost + tokens("output_tokens") * output) / 1_000_000.0
+}
+
+// Running cost of one turn. Assistant messages only carry token usage, so
+// until the result arrives with the real figure the cost is an estimate.
+pub(crate) struct TurnCost {
+    conversation_id: String,
+    budget: Option<Budget>,
+    // Each message's usage is repeated on every line of it; keep the latest
+    by_message: HashMap<String, f64>,
+    reported: Option<f64>,
+}
+
+impl TurnCost {
+    pub(crate) fn new(conversation_id: &str, budget: Option<Budget>) -> Self {
+        TurnCost {
+            conversation_id: conversation_id.to_string(),
+            budget,
+            by_message: HashMap::new(),
+            reported: None,
+        }
+    }
+
+    pub(crate) fn on_assistant(&mut self, message: &serde_json::Value) {
+        let (Some(id), Some(usage)) = (message.get("id").and_then(|i| i.as_str()), message.get("usage")) else { return };
+        let model = message.get("model").and_then(|m| m.as_str()).unwrap_or("");
+        self.by_message.insert(id.to_string(), estimate(model, usage));
+    }
+
+    pub(crate) fn on_result(&mut self, json: &serde_json::Value) {
+        self.reported = json.get("total_cost_usd").and_then(|c| c.as_f64());
+    }
+
+    pub(crate) fn spent(&self) -> f64 {
+        self.reported.unwrap_or_else(|| self.by_message.values().sum())
+    }
+
+    // The limit this turn has gone over, if any. A conversation limit counts
+    // what earlier turns spent too.
+    pub(crate) fn exceeded(&self) -> Option<BudgetExceeded> {
+        let budget = self.budget.as_ref()?;
+        let spent = budget.spent_before + self.spent();
+        (spent > budget.limit_usd).then(|| budget.exceeded(&self.conversation_id, spent))
+    }
+}
+
+#[tauri::command]
+pub async fn set_cost_limits(
+    app: tauri::AppHandle,
+    max_turn_cost_usd: Option<f64>,
+    max_conversation_cost_usd: Option<f64>,
+) -> Result<(), String> {
+    for limit in [max_turn_cost_usd, max_conversation_cost_usd].into_iter().flatten() {
+        if !limit.is_finite() || limit <= 0.0 {
+            return Err(format!("Invalid cost limit: {}", limit));
+        }
+    }
+    settings::update(&app, |settings| {
+        settings.max_turn_cost_usd = max_turn_cost_usd;
+        settings.max_conversation_cost_usd = max_conversation_cost_usd;
+    }).await?;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_conversation_spend(app: tauri::AppHandle, conversation_id: String) -> Result<ConversationSpend, String> {
+    Ok(read_ledger(&app).await.remove(&conversation_id).unwrap_or_default())
+}
@@ -0,0 +1,102 @@
+use notify::{Event, RecursiveMode, Watcher};
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+use std::time::Duration;
+use tauri::Emitter;
+use tokio::sync::{mpsc, Mutex};
+
+use crate::{background_tasks, get_data_path, load_data, subscriptions, unix_millis};
+
+// Sync clients tend to write in several steps; wait for them to settle
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+// Sent as `data-changed` when data.json changes on disk and the new contents
+// aren't what the app itself last read or wrote
+#[derive(Clone, Serialize)]
+pub struct DataChanged {
+    pub path: String,
+    // Unix millis when the change was noticed
+    pub detected_at: u64,
+}
+
+// Hash of the contents the app last loaded or saved, so its own writes
+// don't look like external edits
+static LAST_KNOWN: Lazy<Arc<Mutex<Option<u64>>>> = Lazy::new(|| Arc::new(Mutex::new(None)));
+
+fn content_hash(data: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    data.hash(&mut hasher);
+    hasher.finish()
+}
+
+pub(crate) async fn remember(data: &str) {
+    *LAST_KNOWN.lock().await = Some(content_hash(data));
+}
+
+// Watch the data directory rather than the file, since saves replace the
+// file by renaming over it
+pub(crate) fn start(app: &tauri::AppHandle) -> Result<(), String> {
+    let path = get_data_path(app)?;
+    let dir = path.parent().ok_or("Data file has no parent directory")?.to_path_buf();
+    std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+
+    let (tx, mut rx) = mpsc::unbounded_channel::<Event>();
+    let mut watcher = notify::recommended_watcher(move |res: notify::Result<Event>| {
+        if let Ok(event) = res {
+            let _ = tx.send(event);
+        }
+    }).map_err(|e| format!("Failed to create file watcher: {}", e))?;
+    watcher.watch(&dir, RecursiveMode::NonRecursive)
+        .map_err(|e| format!("Failed to watch {}: {}", dir.display(), e))?;
+
+    let app = app.clone();
+    let touches_data = move |event: &Event| event.paths.iter().any(|p| p.file_name() == path.file_name());
+    background_tasks::spawn("data file watcher", async move {
+        // Owned by the task so notifications last as long as it does
+        let _watcher = watcher;
+        while let Some(event) = rx.recv().await {
+            if !touches_data(&event) {
+                continue;
+            }
+            loop {
+                match tokio::time::timeout(DEBOUNCE, rx.recv()).await {
+                    Ok(Some(_)) => {}
+                    Ok(None) => return,
+                    Err(_) => break,
+                }
+            }
+            // Nobody to tell; check once someone is, dropping what piled up meanwhile
+            if !subscriptions::has_subscribers("data-changed").await {
+                subscriptions::wait_for_subscribers("data-changed").await;
+                while rx.try_recv().is_ok() {}
+            }
+
+            let Ok(path) = get_data_path(&app) else { continue };
+            // A deleted file is left for the next save to recreate
+            let Ok(data) = tokio::fs::read_to_string(&path).await else { continue };
+            let hash = content_hash(&data);
+            let mut last_known = LAST_KNOWN.lock().await;
+            if *last_known == Some(hash) {
+                continue;
+            }
+            // Report each external version once, until it's reloaded or overwritten
+            *last_known = Some(hash);
+            drop(last_known);
+            let _ = app.emit("data-changed", DataChanged {
+                path: path.to_string_lossy().to_string(),
+                detected_at: unix_millis(),
+            });
+        }
+    });
+    Ok(())
+}
+
+// Read data.json again after an external edit. Same result as load_data;
+// the frontend should replace its state with it before saving again.
+#[tauri::command]
+pub async fn reload_data(app: tauri::AppHandle) -> Result<Option<String>, String> {
+    load_data(app).await
+}
@@ -0,0 +1,52 @@
+use std::path::Path;
+use tokio::io::{AsyncWriteExt, BufWriter};
+
+use crate::{settings, unix_millis};
+
+// A raw copy of one turn's stream-json, for attaching to bug reports. It holds
+// everything claude read and wrote, so it has to be switched on in settings
+// first. Write failures are ignored; a debug log never fails the turn.
+pub(crate) struct DebugLog {
+    writer: BufWriter<tokio::fs::File>,
+}
+
+impl DebugLog {
+    // Appends, so several turns can share one file
+    pub(crate) async fn open(app: &tauri::AppHandle, path: &str, conversation_id: &str) -> Result<Self, String> {
+        if !settings::load(app).await?.allow_debug_logs {
+            return Err("Debug logging is turned off in settings".to_string());
+        }
+        let path = Path::new(path);
+        if path.is_dir() {
+            return Err(format!("{} is a directory", path.display()));
+        }
+        let file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .await
+            .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+        let mut log = DebugLog { writer: BufWriter::new(file) };
+        log.write(&format!("# turn started: conversation {} at {}", conversation_id, unix_millis())).await;
+        Ok(log)
+    }
+
+    async fn write(&mut self, line: &str) {
+        let _ = self.writer.write_all(line.as_bytes()).await;
+        let _ = self.writer.write_all(b"\n").await;
+        // Flushed per line so a crash still leaves the trace up to that point
+        let _ = self.writer.flush().await;
+    }
+
+    // One line of stdout exactly as claude sent it
+    pub(crate) async fn stdout(&mut self, line: &str) {
+        self.write(line).await;
+    }
+
+    // The turn's stderr, which is only complete once the process is done with it
+    pub(crate) async fn stderr(&mut self, stderr: &str) {
+        for line in stderr.lines() {
+            self.write(&format!("# stderr: {}", line)).await;
+        }
+    }
+}
@@ -0,0 +1,303 @@
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::error::Error as _;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tauri::Emitter;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::sync::Mutex;
+
+use crate::attachments::hex_digest;
+use crate::{operations, with_suffix};
+
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+
+#[derive(Clone, Serialize)]
+pub struct DownloadProgress {
+    pub download_id: String,
+    pub bytes: u64,
+    // None when the server didn't send a length
+    pub total: Option<u64>,
+    // Average over this call, not counting bytes resumed from an earlier one
+    pub bytes_per_second: f64,
+    pub done: bool,
+}
+
+#[derive(Clone, Serialize)]
+pub struct DownloadResult {
+    pub path: String,
+    pub bytes: u64,
+    // True if an existing partial file was continued with a Range request
+    pub resumed: bool,
+    // Hex sha256 of the finished file, when one was asked to be checked
+    pub sha256: Option<String>,
+}
+
+#[derive(Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct DownloadOptions {
+    // Expected hex sha256; the file is only moved into place if it matches
+    pub sha256: Option<String>,
+    // Continue from an existing `<dest>.part` instead of starting over
+    pub resume: bool,
+}
+
+// Serialized as {"kind": "...", "message": "..."} so the UI can tell failures apart
+#[derive(Clone, Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "snake_case")]
+pub enum DownloadError {
+    InvalidDestination(String),
+    AlreadyRunning(String),
+    TooManyRedirects(String),
+    Tls(String),
+    Network(String),
+    Http(String),
+    DiskFull(String),
+    Io(String),
+    ChecksumMismatch(String),
+    Cancelled(String),
+}
+
+impl DownloadError {
+    pub(crate) fn message(&self) -> &str {
+        match self {
+            DownloadError::InvalidDestination(message)
+            | DownloadError::AlreadyRunning(message)
+            | DownloadError::TooManyRedirects(message)
+            | DownloadError::Tls(message)
+            | DownloadError::Network(message)
+            | DownloadError::Http(message)
+            | DownloadError::DiskFull(message)
+            | DownloadError::Io(message)
+            | DownloadError::ChecksumMismatch(message)
+            | DownloadError::Cancelled(message) => message,
+        }
+    }
+
+    fn request(e: reqwest::Error) -> Self {
+        if e.is_redirect() {
+            return DownloadError::TooManyRedirects(e.to_string());
+        }
+        // reqwest doesn't classify TLS failures; look through the causes
+        let mut causes = String::new();
+        let mut source = e.source();
+        while let Some(cause) = source {
+            causes.push_str(&cause.to_string().to_lowercase());
+            source = cause.source();
+        }
+        let message = format!("{}: {}", e, causes);
+        if ["certificate", "tls", "handshake"].iter().any(|word| causes.contains(word)) {
+            DownloadError::Tls(message)
+        } else {
+            DownloadError::Network(message)
+        }
+    }
+
+    fn io(path: &Path, e: std::io::Error) -> Self {
+        let message = format!("{}: {}", path.display(), e);
+        if e.kind() == std::io::ErrorKind::StorageFull {
+            DownloadError::DiskFull(message)
+        } else {
+            DownloadError::Io(message)
+        }
+    }
+}
+
+// Active download ids, so the same one can't run twice
+static DOWNLOADS: Lazy<Arc<Mutex<HashSet<String>>>> =
+    Lazy::new(|| Arc::new(Mutex::new(HashSet::new())));
+
+struct Progress<'a> {
+    app: &'a tauri::AppHandle,
+    download_id: &'a str,
+    started: Instant,
+    resumed_from: u64,
+    last: Instant,
+}
+
+impl Progress<'_> {
+    fn emit(&mut self, bytes: u64, total: Option<u64>, done: bool) {
+        let elapsed = self.started.elapsed().as_secs_f64();
+        let bytes_per_second = if elapsed > 0.0 { (bytes - self.resumed_from) as f64 / elapsed } else { 0.0 };
+        let _ = self.app.emit(&format!("download-progress-{}", self.download_id), DownloadProgress {
+            download_id: self.download_id.to_string(),
+            bytes,
+            total,
+            bytes_per_second,
+            done,
+        });
+        self.last = Instant::now();
+    }
+}
+
+// The destination must be an absolute, normalized file path in a folder that exists
+fn check_destination(dest_path: &str) -> Result<PathBuf, DownloadError> {
+    let path = PathBuf::from(dest_path);
+    let invalid = |reason: &str| DownloadError::InvalidDestination(format!("{}: {}", dest_path, reason));
+    if !path.is_absolute() {
+        return Err(invalid("must be an absolute path"));
+    }
+    if path.components().any(|c| matches!(c, Component::ParentDir | Component::CurDir)) {
+        return Err(invalid("must not contain . or .. components"));
+    }
+    if path.is_dir() {
+        return Err(invalid("is a directory"));
+    }
+    if !path.parent().is_some_and(|parent| parent.is_dir()) {
+        return Err(invalid("parent folder does not exist"));
+    }
+    Ok(path)
+}
+
+#[cfg(unix)]
+fn link_count(metadata: &std::fs::Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    metadata.nlink()
+}
+
+#[cfg(not(unix))]
+fn link_count(_metadata: &std::fs::Metadata) -> u64 {
+    1
+}
+
+async fn file_sha256(path: &Path) -> Result<String, DownloadError> {
+    let mut file = tokio::fs::File::open(path).await.map_err(|e| DownloadError::io(path, e))?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        let n = file.read(&mut buf).await.map_err(|e| DownloadError::io(path, e))?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(hex_digest(&hasher.finalize()))
+}
+
+async fn run_download(
+    app: &tauri::AppHandle,
+    url: &str,
+    dest: &Path,
+    download_id: &str,
+    options: &DownloadOptions,
+    operation: &mut operations::Operation,
+) -> Result<DownloadResult, DownloadError> {
+    // Data goes to <dest>.part and is renamed over dest at the end, so a
+    // file hard-linked elsewhere is replaced rather than written through
+    let part = with_suffix(dest, ".part");
+    let mut existing = match tokio::fs::metadata(&part).await {
+        Ok(metadata) if options.resume && link_count(&metadata) == 1 => metadata.len(),
+        _ => 0,
+    };
+    if existing == 0 {
+        // Unlinking only drops this name; another link keeps its content
+        let _ = tokio::fs::remove_file(&part).await;
+    }
+
+    let client = reqwest::Client::new();
+    let mut request = client.get(url);
+    if existing > 0 {
+        request = request.header(reqwest::header::RANGE, format!("bytes={}-", existing));
+    }
+    let mut response = request.send().await.map_err(DownloadError::request)?;
+
+    let mut progress = Progress { app, download_id, started: Instant::now(), resumed_from: 0, last: Instant::now() };
+    let status = response.status();
+    // The range starts at the end of the file: nothing left to fetch
+    let complete = status == reqwest::StatusCode::RANGE_NOT_SATISFIABLE && existing > 0;
+    if !complete && !status.is_success() {
+        return Err(DownloadError::Http(format!("Server responded with {}", status)));
+    }
+
+    // A plain 200 means the server ignored the range, so start over
+    let resumed = existing > 0 && (complete || status == reqwest::StatusCode::PARTIAL_CONTENT);
+    if !resumed {
+        existing = 0;
+    }
+    let mut bytes = existing;
+    progress.resumed_from = existing;
+    let total = if complete { Some(existing) } else { response.content_length().map(|len| len + existing) };
+
+    if !complete {
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(resumed)
+            .truncate(!resumed)
+            .open(&part)
+            .await
+            .map_err(|e| DownloadError::io(&part, e))?;
+
+        progress.emit(bytes, total, false);
+        loop {
+            let chunk = tokio::select! {
+                chunk = response.chunk() => chunk.map_err(DownloadError::request)?,
+                _ = operation.cancelled() => {
+                    // The partial file stays so a later call can resume it
+                    let _ = file.flush().await;
+                    return Err(DownloadError::Cancelled("Download cancelled".to_string()));
+                }
+            };
+            let Some(chunk) = chunk else { break };
+            file.write_all(&chunk).await.map_err(|e| DownloadError::io(&part, e))?;
+            bytes += chunk.len() as u64;
+            if let Some(total) = total {
+                operation.set_progress(Some(bytes as f64 / total.max(1) as f64), None);
+            }
+            if progress.last.elapsed() >= PROGRESS_INTERVAL {
+                progress.emit(bytes, total, false);
+            }
+        }
+        file.flush().await.map_err(|e| DownloadError::io(&part, e))?;
+        file.sync_all().await.map_err(|e| DownloadError::io(&part, e))?;
+    }
+
+    let sha256 = match options.sha256 {
+        Some(ref expected) => {
+            let actual = file_sha256(&part).await?;
+            if !actual.eq_ignore_ascii_case(expected.trim()) {
+                // Resuming a corrupt file would only keep it corrupt
+                let _ = tokio::fs::remove_file(&part).await;
+                return Err(DownloadError::ChecksumMismatch(format!("Expected sha256 {}, got {}", expected, actual)));
+            }
+            Some(actual)
+        }
+        None => None,
+    };
+
+    tokio::fs::rename(&part, dest).await.map_err(|e| DownloadError::io(dest, e))?;
+    progress.emit(bytes, total, true);
+    Ok(DownloadResult { path: dest.to_string_lossy().to_string(), bytes, resumed, sha256 })
+}
+
+#[tauri::command]
+pub async fn download_file(
+    app: tauri::AppHandle,
+    url: String,
+    dest_path: String,
+    download_id: String,
+    options: Option<DownloadOptions>,
+) -> Result<DownloadResult, DownloadError> {
+    let options = options.unwrap_or_default();
+    let dest = check_destination(&dest_path)?;
+    if !DOWNLOADS.lock().await.insert(download_id.clone()) {
+        return Err(DownloadError::AlreadyRunning(format!("Download {} is already running", download_id)));
+    }
+
+    let mut operation = operations::register(&download_id, "download", true);
+    operation.set_progress(None, Some(url.clone()));
+    let result = run_download(&app, &url, &dest, &download_id, &options, &mut operation).await;
+    DOWNLOADS.lock().await.remove(&download_id);
+    result
+}
+
+#[tauri::command]
+pub async fn cancel_download(download_id: String) -> Result<bool, String> {
+    if !DOWNLOADS.lock().await.contains(&download_id) {
+        return Ok(false);
+    }
+    operations::cancel_operation(download_id).await
+}
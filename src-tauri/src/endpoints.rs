@@ -0,0 +1,220 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::process::Stdio;
+use std::time::{Duration, Instant};
+use tokio::process::Command;
+
+use crate::{claude_process, secrets, settings};
+
+const TEST_TIMEOUT: Duration = Duration::from_secs(60);
+const TEST_MODEL: &str = "haiku";
+
+// A Claude-compatible API to send requests to instead of Anthropic's, as
+// given by the frontend. The key only passes through on its way to the keychain.
+#[derive(Clone, Deserialize)]
+pub struct EndpointConfig {
+    pub base_url: String,
+    pub api_key: Option<String>,
+    // Send the key as ANTHROPIC_AUTH_TOKEN (bearer, what most gateways want)
+    // rather than ANTHROPIC_API_KEY
+    #[serde(default = "default_bearer")]
+    pub bearer: bool,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+}
+
+fn default_bearer() -> bool {
+    true
+}
+
+// As persisted in settings
+#[derive(Clone, Serialize, Deserialize)]
+pub struct StoredEndpoint {
+    pub base_url: String,
+    pub bearer: bool,
+    pub secret_ref: Option<String>,
+    pub headers: BTreeMap<String, String>,
+}
+
+// Endpoints by workspace directory and by conversation. A conversation's own
+// endpoint wins over its workspace's.
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EndpointSettings {
+    pub workspaces: HashMap<String, StoredEndpoint>,
+    pub conversations: HashMap<String, StoredEndpoint>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct EndpointTest {
+    pub ok: bool,
+    pub duration_ms: u64,
+    // The reply on success; otherwise the error as the gateway or CLI gave it
+    pub message: String,
+}
+
+fn secret_reference(scope: &str, key: &str) -> String {
+    format!("endpoint:{}:{}", scope, key)
+}
+
+fn endpoint_env(endpoint: &StoredEndpoint, key: Option<String>) -> Vec<(String, String)> {
+    let mut env = vec![("ANTHROPIC_BASE_URL".to_string(), endpoint.base_url.clone())];
+    if let Some(key) = key {
+        let name = if endpoint.bearer { "ANTHROPIC_AUTH_TOKEN" } else { "ANTHROPIC_API_KEY" };
+        env.push((name.to_string(), key));
+    }
+    if !endpoint.headers.is_empty() {
+        let headers: Vec<String> = endpoint.headers.iter().map(|(name, value)| format!("{}: {}", name, value)).collect();
+        env.push(("ANTHROPIC_CUSTOM_HEADERS".to_string(), headers.join("\n")));
+    }
+    env
+}
+
+fn validate(config: &EndpointConfig) -> Result<(), String> {
+    let url = reqwest::Url::parse(&config.base_url).map_err(|e| format!("Invalid base URL {}: {}", config.base_url, e))?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(format!("Base URL must be http or https: {}", config.base_url));
+    }
+    for (name, value) in &config.headers {
+        if name.is_empty() || name.contains([':', '\n']) || value.contains('\n') {
+            return Err(format!("Invalid header: {}", name));
+        }
+    }
+    Ok(())
+}
+
+// Variables pointing claude at the endpoint configured for this conversation
+// or, failing that, the closest enclosing workspace
+pub(crate) async fn resolve(
+    app: &tauri::AppHandle,
+    conversation_id: &str,
+    work_dir: Option<&str>,
+) -> Result<Vec<(String, String)>, String> {
+    let endpoints = settings::load(app).await?.endpoints;
+    let by_workspace = work_dir.and_then(|dir| {
+        endpoints.workspaces.iter()
+            .filter(|(root, _)| std::path::Path::new(dir).starts_with(root))
+            .max_by_key(|(root, _)| root.len())
+            .map(|(_, endpoint)| endpoint)
+    });
+    let Some(endpoint) = endpoints.conversations.get(conversation_id).or(by_workspace) else {
+        return Ok(Vec::new());
+    };
+    let key = match endpoint.secret_ref {
+        Some(ref reference) => Some(secrets::read_secret(reference)?),
+        None => None,
+    };
+    Ok(endpoint_env(endpoint, key))
+}
+
+// `scope` is "workspace" (keyed by directory) or "conversation". Pass no
+// config to go back to the default endpoint. Leaving out api_key keeps the
+// one already saved for this scope and key.
+#[tauri::command]
+pub async fn set_endpoint(
+    app: tauri::AppHandle,
+    scope: String,
+    key: String,
+    config: Option<EndpointConfig>,
+) -> Result<(), String> {
+    if !matches!(scope.as_str(), "workspace" | "conversation") {
+        return Err(format!("Unknown endpoint scope: {}", scope));
+    }
+    let reference = secret_reference(&scope, &key);
+    let stored = match config {
+        Some(config) => {
+            validate(&config)?;
+            let previous = settings::load(&app).await?.endpoints;
+            let previous = match scope.as_str() {
+                "workspace" => previous.workspaces.get(&key).cloned(),
+                _ => previous.conversations.get(&key).cloned(),
+            };
+            let secret_ref = match config.api_key {
+                Some(ref api_key) => {
+                    secrets::store_secret(&reference, api_key)?;
+                    Some(reference.clone())
+                }
+                None => previous.and_then(|p| p.secret_ref),
+            };
+            Some(StoredEndpoint {
+                base_url: config.base_url,
+                bearer: config.bearer,
+                secret_ref,
+                headers: config.headers,
+            })
+        }
+        None => None,
+    };
+
+    let clearing = stored.is_none();
+    settings::update(&app, |settings| {
+        let map = match scope.as_str() {
+            "workspace" => &mut settings.endpoints.workspaces,
+            _ => &mut settings.endpoints.conversations,
+        };
+        match stored {
+            Some(ref endpoint) => map.insert(key.clone(), endpoint.clone()),
+            None => map.remove(&key),
+        };
+    }).await?;
+    if clearing {
+        let _ = secrets::delete_secret(&reference);
+    }
+
+    // Warm processes still talk to the old endpoint
+    claude_process::shut_down_idle().await;
+    Ok(())
+}
+
+// Send one tiny prompt through the CLI with the given endpoint settings, so
+// a bad URL or key shows up before anything is saved
+#[tauri::command]
+pub async fn test_endpoint(config: EndpointConfig) -> Result<EndpointTest, String> {
+    validate(&config)?;
+    let endpoint = StoredEndpoint {
+        base_url: config.base_url,
+        bearer: config.bearer,
+        secret_ref: None,
+        headers: config.headers,
+    };
+    let mut cmd = Command::new("claude");
+    cmd.envs(endpoint_env(&endpoint, config.api_key))
+        .arg("--print")
+        .arg("--output-format").arg("json")
+        .arg("--model").arg(TEST_MODEL)
+        .arg("--max-turns").arg("1")
+        .arg("Reply with just the word OK.")
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+
+    let started = Instant::now();
+    let child = cmd.spawn().map_err(|e| format!("Failed to spawn claude: {}", e))?;
+    let output = tokio::time::timeout(TEST_TIMEOUT, child.wait_with_output()).await
+        .map_err(|_| format!("No response from the endpoint within {} seconds", TEST_TIMEOUT.as_secs()))?
+        .map_err(|e| e.to_string())?;
+    let duration_ms = started.elapsed().as_millis() as u64;
+
+    // The CLI puts API failures, with the gateway's own message, in the result
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let result = serde_json::from_str::<serde_json::Value>(stdout.trim()).ok();
+    let is_error = result.as_ref().and_then(|r| r.get("is_error")).and_then(|e| e.as_bool()).unwrap_or(true);
+    let text = result.as_ref().and_then(|r| r.get("result")).and_then(|t| t.as_str()).map(String::from);
+
+    let ok = output.status.success() && !is_error;
+    let message = match text {
+        Some(text) if !text.trim().is_empty() => text,
+        _ => {
+            let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+            if !stderr.is_empty() {
+                stderr
+            } else if !stdout.trim().is_empty() {
+                stdout.trim().to_string()
+            } else {
+                format!("claude exited with status: {}", output.status)
+            }
+        }
+    };
+    Ok(EndpointTest { ok, duration_ms, message })
+}
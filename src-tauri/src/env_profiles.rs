@@ -0,0 +1,373 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::{secrets, settings};
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct EnvVar {
+    pub key: String,
+    // Plain values are stored inline...
+    pub value: Option<String>,
+    // ...while anything that looks like a secret lives in the keychain under this reference
+    pub secret_ref: Option<String>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct EnvProfile {
+    pub source_path: Option<String>,
+    pub vars: Vec<EnvVar>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct EnvProfileSummary {
+    pub name: String,
+    pub source_path: Option<String>,
+    pub keys: Vec<String>,
+    pub secret_keys: Vec<String>,
+}
+
+fn summarize(name: &str, profile: &EnvProfile) -> EnvProfileSummary {
+    EnvProfileSummary {
+        name: name.to_string(),
+        source_path: profile.source_path.clone(),
+        keys: profile.vars.iter().map(|v| v.key.clone()).collect(),
+        secret_keys: profile.vars.iter()
+            .filter(|v| v.secret_ref.is_some())
+            .map(|v| v.key.clone())
+            .collect(),
+    }
+}
+
+pub(crate) fn looks_secret(key: &str, value: &str) -> bool {
+    let key = key.to_uppercase();
+    const KEY_HINTS: [&str; 7] = ["SECRET", "TOKEN", "PASSWORD", "PASSWD", "CREDENTIAL", "PRIVATE", "AUTH"];
+    const VALUE_PREFIXES: [&str; 5] = ["sk-", "ghp_", "github_pat_", "xox", "AKIA"];
+    KEY_HINTS.iter().any(|hint| key.contains(hint))
+        || key == "KEY"
+        || key.ends_with("_KEY")
+        || VALUE_PREFIXES.iter().any(|prefix| value.starts_with(prefix))
+}
+
+// Parse dotenv syntax: comments, `export` prefixes, single quotes (literal),
+// double quotes (escapes, may span lines) and unquoted values with trailing
+// comments. Errors mention line numbers but never the values themselves.
+pub(crate) fn parse_dotenv(contents: &str) -> Result<Vec<(String, String)>, String> {
+    let chars: Vec<char> = contents.chars().collect();
+    let mut vars = Vec::new();
+    let mut i = 0;
+    let mut line = 1;
+
+    let at = |i: usize| chars.get(i).copied();
+
+    loop {
+        // Skip blank space between entries
+        while let Some(c) = at(i) {
+            if c == '\n' {
+                line += 1;
+            } else if !c.is_whitespace() {
+                break;
+            }
+            i += 1;
+        }
+        let Some(c) = at(i) else { break };
+
+        if c == '#' {
+            while at(i).is_some_and(|c| c != '\n') {
+                i += 1;
+            }
+            continue;
+        }
+
+        let entry_line = line;
+        let read_key = |i: &mut usize| {
+            let start = *i;
+            while at(*i).is_some_and(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.') {
+                *i += 1;
+            }
+            chars[start..*i].iter().collect::<String>()
+        };
+
+        let mut key = read_key(&mut i);
+        if key == "export" && at(i).is_some_and(|c| c == ' ' || c == '\t') {
+            while at(i).is_some_and(|c| c == ' ' || c == '\t') {
+                i += 1;
+            }
+            key = read_key(&mut i);
+        }
+        if key.is_empty() || key.starts_with(|c: char| c.is_ascii_digit()) {
+            return Err(format!("Line {}: invalid variable name", entry_line));
+        }
+
+        while at(i).is_some_and(|c| c == ' ' || c == '\t') {
+            i += 1;
+        }
+        if at(i) != Some('=') {
+            return Err(format!("Line {}: expected KEY=VALUE", entry_line));
+        }
+        i += 1;
+        while at(i).is_some_and(|c| c == ' ' || c == '\t') {
+            i += 1;
+        }
+
+        let mut value = String::new();
+        match at(i) {
+            Some(quote @ ('"' | '\'')) => {
+                i += 1;
+                loop {
+                    match at(i) {
+                        None => {
+                            let kind = if quote == '"' { "double" } else { "single" };
+                            return Err(format!("Line {}: unterminated {}-quoted value", entry_line, kind));
+                        }
+                        Some(c) if c == quote => {
+                            i += 1;
+                            break;
+                        }
+                        Some('\\') if quote == '"' => {
+                            let escaped = at(i + 1);
+                            match escaped {
+                                Some('n') => value.push('\n'),
+                                Some('r') => value.push('\r'),
+                                Some('t') => value.push('\t'),
+                                Some(c @ ('"' | '\\' | '$')) => value.push(c),
+                                // Unknown escapes are kept as written
+                                Some(c) => {
+                                    value.push('\\');
+                                    value.push(c);
+                                }
+                                None => value.push('\\'),
+                            }
+                            if escaped == Some('\n') {
+                                line += 1;
+                            }
+                            i += 2;
+                        }
+                        Some(c) => {
+                            if c == '\n' {
+                                line += 1;
+                            }
+                            value.push(c);
+                            i += 1;
+                        }
+                    }
+                }
+                // Only whitespace or a comment may follow the closing quote
+                while at(i).is_some_and(|c| c == ' ' || c == '\t' || c == '\r') {
+                    i += 1;
+                }
+                match at(i) {
+                    None | Some('\n') => {}
+                    Some('#') => {
+                        while at(i).is_some_and(|c| c != '\n') {
+                            i += 1;
+                        }
+                    }
+                    Some(_) => {
+                        return Err(format!("Line {}: unexpected characters after quoted value", line));
+                    }
+                }
+            }
+            _ => {
+                let start = i;
+                while at(i).is_some_and(|c| c != '\n') {
+                    i += 1;
+                }
+                let raw: String = chars[start..i].iter().collect();
+                // ` #` starts a comment in unquoted values
+                let raw = match raw.find(" #").or_else(|| raw.find("\t#")) {
+                    Some(pos) => &raw[..pos],
+                    None => raw.as_str(),
+                };
+                value = raw.trim().to_string();
+            }
+        }
+
+        vars.push((key, value));
+    }
+
+    Ok(vars)
+}
+
+fn secret_reference(profile: &str, key: &str) -> String {
+    format!("env:{}:{}", profile, key)
+}
+
+fn forget_secrets(profile: &EnvProfile) {
+    for var in &profile.vars {
+        if let Some(ref reference) = var.secret_ref {
+            let _ = secrets::delete_secret(reference);
+        }
+    }
+}
+
+// Resolve a profile into the variables to set on a child process
+pub(crate) async fn resolve_profile(app: &tauri::AppHandle, name: &str) -> Result<Vec<(String, String)>, String> {
+    let settings = settings::load(app).await?;
+    let profile = settings.env_profiles.get(name)
+        .ok_or_else(|| format!("Unknown environment profile: {}", name))?;
+
+    profile.vars.iter().map(|var| {
+        let value = match (&var.value, &var.secret_ref) {
+            (_, Some(reference)) => secrets::read_secret(reference)?,
+            (Some(value), None) => value.clone(),
+            (None, None) => String::new(),
+        };
+        Ok((var.key.clone(), value))
+    }).collect()
+}
+
+#[tauri::command]
+pub async fn import_env_file(
+    app: tauri::AppHandle,
+    path: String,
+    name: Option<String>,
+) -> Result<EnvProfileSummary, String> {
+    let contents = tokio::fs::read_to_string(&path).await
+        .map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let parsed = parse_dotenv(&contents)?;
+
+    let name = name.unwrap_or_else(|| {
+        Path::new(&path).file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| path.clone())
+    });
+
+    let mut vars = Vec::new();
+    for (key, value) in parsed {
+        if looks_secret(&key, &value) {
+            let reference = secret_reference(&name, &key);
+            secrets::store_secret(&reference, &value)?;
+            vars.push(EnvVar { key, value: None, secret_ref: Some(reference) });
+        } else {
+            vars.push(EnvVar { key, value: Some(value), secret_ref: None });
+        }
+    }
+
+    let profile = EnvProfile { source_path: Some(path), vars };
+    let summary = summarize(&name, &profile);
+
+    let mut replaced = None;
+    settings::update(&app, |settings| {
+        replaced = settings.env_profiles.insert(name.clone(), profile);
+    }).await?;
+
+    // Drop keychain entries for keys the re-imported file no longer has
+    if let Some(old) = replaced {
+        for var in &old.vars {
+            if var.secret_ref.is_some() && !summary.secret_keys.contains(&var.key) {
+                let _ = secrets::delete_secret(&secret_reference(&name, &var.key));
+            }
+        }
+    }
+
+    Ok(summary)
+}
+
+#[tauri::command]
+pub async fn list_env_profiles(app: tauri::AppHandle) -> Result<Vec<EnvProfileSummary>, String> {
+    let settings = settings::load(&app).await?;
+    let mut profiles: Vec<EnvProfileSummary> = settings.env_profiles.iter()
+        .map(|(name, profile)| summarize(name, profile))
+        .collect();
+    profiles.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(profiles)
+}
+
+#[tauri::command]
+pub async fn delete_env_profile(app: tauri::AppHandle, name: String) -> Result<bool, String> {
+    let mut removed = None;
+    settings::update(&app, |settings| {
+        removed = settings.env_profiles.remove(&name);
+    }).await?;
+
+    match removed {
+        Some(profile) => {
+            forget_secrets(&profile);
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parsed(contents: &str) -> Vec<(String, String)> {
+        parse_dotenv(contents).unwrap()
+    }
+
+    fn pairs(expected: &[(&str, &str)]) -> Vec<(String, String)> {
+        expected.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn plain_values_comments_and_export() {
+        let contents = "# settings\n\nA=1\nexport B=two\n  C = spaced out  \nD=value # note\nURL=http://host/#frag\nexport=kept\nEMPTY=\n";
+        assert_eq!(parsed(contents), pairs(&[
+            ("A", "1"),
+            ("B", "two"),
+            ("C", "spaced out"),
+            ("D", "value"),
+            ("URL", "http://host/#frag"),
+            ("export", "kept"),
+            ("EMPTY", ""),
+        ]));
+        assert_eq!(parsed("A=1\r\nB=2\r\n"), pairs(&[("A", "1"), ("B", "2")]));
+    }
+
+    #[test]
+    fn single_quotes_are_literal() {
+        assert_eq!(parsed(r"A='a\nb $HOME # not a comment'"), pairs(&[("A", r"a\nb $HOME # not a comment")]));
+        assert_eq!(parsed("A='x' # comment"), pairs(&[("A", "x")]));
+    }
+
+    #[test]
+    fn double_quotes_handle_escapes() {
+        assert_eq!(parsed(r#"A="tab\tnew\nquote\" slash\\ dollar\$ other\q""#), pairs(&[("A", "tab\tnew\nquote\" slash\\ dollar$ other\\q")]));
+    }
+
+    #[test]
+    fn quoted_values_may_span_lines() {
+        let contents = "KEY=\"-----BEGIN KEY-----\nabc\n-----END KEY-----\"\nNEXT='one\ntwo'\nLAST=3";
+        assert_eq!(parsed(contents), pairs(&[
+            ("KEY", "-----BEGIN KEY-----\nabc\n-----END KEY-----"),
+            ("NEXT", "one\ntwo"),
+            ("LAST", "3"),
+        ]));
+    }
+
+    #[test]
+    fn errors_give_the_line() {
+        assert_eq!(parse_dotenv("A=1\n1B=2").unwrap_err(), "Line 2: invalid variable name");
+        assert_eq!(parse_dotenv("=x").unwrap_err(), "Line 1: invalid variable name");
+        assert_eq!(parse_dotenv("A=1\n\nJUST_A_KEY").unwrap_err(), "Line 3: expected KEY=VALUE");
+        assert_eq!(parse_dotenv("A=1\nB=\"open").unwrap_err(), "Line 2: unterminated double-quoted value");
+        assert_eq!(parse_dotenv("B='open").unwrap_err(), "Line 1: unterminated single-quoted value");
+        assert_eq!(parse_dotenv("A=\"x\" y").unwrap_err(), "Line 1: unexpected characters after quoted value");
+    }
+
+    #[test]
+    fn lines_are_counted_through_multiline_values() {
+        let contents = "A=\"one\ntwo\nthree\"\nB='x\ny'\nC=\"escaped\\\nbreak\"\nBROKEN";
+        assert_eq!(parse_dotenv(contents).unwrap_err(), "Line 8: expected KEY=VALUE");
+        // Junk after a multi-line value is reported where it is
+        assert_eq!(parse_dotenv("A=\"one\ntwo\" junk").unwrap_err(), "Line 2: unexpected characters after quoted value");
+    }
+
+    #[test]
+    fn errors_never_contain_values() {
+        let secret = "sk-live-do-not-print";
+        for contents in [
+            format!("TOKEN=\"{}", secret),
+            format!("TOKEN='{}", secret),
+            format!("TOKEN=\"{}\" {}", secret, secret),
+            format!("TOKEN={}\n{}", secret, secret),
+            format!("{}=1", secret),
+        ] {
+            let error = parse_dotenv(&contents).unwrap_err();
+            assert!(!error.contains(secret), "{}", error);
+            assert!(!error.contains("sk-"), "{}", error);
+        }
+    }
+}
@@ -0,0 +1,144 @@
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tauri::Emitter;
+use tokio::io::AsyncReadExt;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+use crate::attachments::{self, AttachmentMeta};
+use crate::DirEntry;
+
+const MAX_DROPPED_PATHS: usize = 100;
+const MAX_PARALLEL: usize = 8;
+// Per path, so a stalled network volume only costs its own entries
+const PATH_TIMEOUT: Duration = Duration::from_secs(5);
+// Images up to this are copied into the attachment store
+const MAX_IMAGE_BYTES: u64 = 20 * 1024 * 1024;
+// Text up to this is offered as a context file
+const MAX_CONTEXT_BYTES: u64 = 512 * 1024;
+const SNIFF_BYTES: usize = 8 * 1024;
+const DIRECTORY_PREVIEW_ENTRIES: usize = 200;
+const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "gif", "webp", "bmp", "ico", "tif", "tiff", "heic"];
+
+#[derive(Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum DroppedFile {
+    // Copied into the attachment store
+    Attachment { path: String, attachment: AttachmentMeta },
+    // Small enough text to pin or inline as it is
+    ContextFile { path: String, size: u64 },
+    // The top of the tree, for the frontend to offer a listing
+    Directory { path: String, entries: Vec<DirEntry>, truncated: bool },
+    // Too large or binary; only the path is passed on
+    Reference { path: String, size: u64, reason: String },
+}
+
+#[derive(Clone, Serialize)]
+pub struct RejectedDrop {
+    pub path: String,
+    pub reason: String,
+}
+
+// Sent as `files-dropped` to the window the files landed on
+#[derive(Clone, Serialize)]
+pub struct FilesDropped {
+    pub files: Vec<DroppedFile>,
+    pub rejected: Vec<RejectedDrop>,
+}
+
+fn looks_like_image(path: &Path, head: &[u8]) -> bool {
+    let by_extension = path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| IMAGE_EXTENSIONS.contains(&e.to_lowercase().as_str()));
+    let by_magic = head.starts_with(b"\x89PNG")
+        || head.starts_with(b"\xff\xd8\xff")
+        || head.starts_with(b"GIF8")
+        || (head.starts_with(b"RIFF") && head.get(8..12) == Some(b"WEBP"));
+    by_extension || by_magic
+}
+
+fn looks_like_text(head: &[u8]) -> bool {
+    // A character cut off at the end of the sample is still text
+    !head.contains(&0) && std::str::from_utf8(head).map_or_else(|e| e.error_len().is_none(), |_| true)
+}
+
+async fn classify(app: &tauri::AppHandle, path: PathBuf) -> Result<DroppedFile, String> {
+    let display = path.to_string_lossy().to_string();
+    let metadata = tokio::fs::metadata(&path).await.map_err(|e| e.to_string())?;
+    if metadata.is_dir() {
+        let mut entries = crate::list_directory(app.clone(), display.clone()).await?;
+        let truncated = entries.len() > DIRECTORY_PREVIEW_ENTRIES;
+        entries.truncate(DIRECTORY_PREVIEW_ENTRIES);
+        return Ok(DroppedFile::Directory { path: display, entries, truncated });
+    }
+    if !metadata.is_file() {
+        return Err("Not a regular file".to_string());
+    }
+
+    let size = metadata.len();
+    let mut file = tokio::fs::File::open(&path).await.map_err(|e| e.to_string())?;
+    let mut head = vec![0u8; SNIFF_BYTES.min(size as usize)];
+    file.read_exact(&mut head).await.map_err(|e| e.to_string())?;
+
+    if looks_like_image(&path, &head) {
+        if size > MAX_IMAGE_BYTES {
+            return Ok(DroppedFile::Reference { path: display, size, reason: "Image is too large to attach".to_string() });
+        }
+        let content = tokio::fs::read(&path).await.map_err(|e| e.to_string())?;
+        let name = path.file_name().map(|n| n.to_string_lossy().to_string());
+        let attachment = attachments::store(app, content, name).await?;
+        return Ok(DroppedFile::Attachment { path: display, attachment });
+    }
+    if !looks_like_text(&head) {
+        return Ok(DroppedFile::Reference { path: display, size, reason: "Binary file".to_string() });
+    }
+    if size > MAX_CONTEXT_BYTES {
+        return Ok(DroppedFile::Reference { path: display, size, reason: "Text file is too large for context".to_string() });
+    }
+    Ok(DroppedFile::ContextFile { path: display, size })
+}
+
+// Process files dropped on a window, a few at a time, and tell that window
+// what became of each
+pub(crate) async fn handle_drop(app: tauri::AppHandle, window_label: String, paths: Vec<PathBuf>) {
+    let mut rejected = Vec::new();
+    let mut tasks = JoinSet::new();
+    let permits = Arc::new(Semaphore::new(MAX_PARALLEL));
+    for (index, path) in paths.into_iter().enumerate() {
+        let display = path.to_string_lossy().to_string();
+        if index >= MAX_DROPPED_PATHS {
+            rejected.push(RejectedDrop { path: display, reason: format!("Only {} files can be dropped at once", MAX_DROPPED_PATHS) });
+            continue;
+        }
+        if !path.is_absolute() {
+            rejected.push(RejectedDrop { path: display, reason: "Not an absolute path".to_string() });
+            continue;
+        }
+        let app = app.clone();
+        let permits = permits.clone();
+        tasks.spawn(async move {
+            let _permit = permits.acquire_owned().await;
+            // tokio::fs runs on the blocking pool, so a hung mount only holds this task
+            let result = match tokio::time::timeout(PATH_TIMEOUT, classify(&app, path)).await {
+                Ok(result) => result,
+                Err(_) => Err("Timed out reading the file".to_string()),
+            };
+            (index, display, result)
+        });
+    }
+
+    let mut processed = Vec::new();
+    while let Some(joined) = tasks.join_next().await {
+        match joined {
+            Ok((index, _, Ok(file))) => processed.push((index, file)),
+            Ok((_, path, Err(reason))) => rejected.push(RejectedDrop { path, reason }),
+            Err(_) => {}
+        }
+    }
+    // Keep the order the files were dropped in
+    processed.sort_by_key(|(index, _)| *index);
+    let files = processed.into_iter().map(|(_, file)| file).collect();
+    let _ = app.emit_to(window_label.as_str(), "files-dropped", FilesDropped { files, rejected });
+}
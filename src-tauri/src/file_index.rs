@@ -0,0 +1,317 @@
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tauri::Emitter;
+use tokio::sync::{mpsc, Mutex};
+
+use crate::ignore_rules::{self, RootMatcher};
+use crate::operations::{self, Operation};
+use crate::{background_tasks, git, storage};
+
+// Beyond this many files the index is truncated rather than grown further
+const MAX_INDEXED_FILES: usize = 200_000;
+const PROGRESS_EVERY: usize = 5_000;
+
+#[derive(Clone, Serialize)]
+pub struct FileIndexProgress {
+    pub operation_id: String,
+    pub root: String,
+    pub files_indexed: usize,
+    pub done: bool,
+    pub truncated: bool,
+}
+
+#[derive(Clone, Serialize)]
+pub struct FileIndexSummary {
+    pub root: String,
+    pub file_count: usize,
+    pub truncated: bool,
+    pub from_cache: bool,
+}
+
+#[derive(Clone, Serialize)]
+pub struct FileMatch {
+    pub path: String,
+    pub score: i64,
+}
+
+// On-disk cache, reused while the repository's HEAD commit is unchanged
+#[derive(Serialize, Deserialize)]
+struct CachedIndex {
+    root: String,
+    head: String,
+    // Ignore rules that HEAD doesn't capture: the global list and an uncommitted .claudeignore
+    #[serde(default)]
+    rules: String,
+    truncated: bool,
+    paths: Vec<String>,
+}
+
+struct FileIndex {
+    // Paths relative to the root, using `/` separators
+    paths: BTreeSet<String>,
+    // Dropping the watcher stops incremental updates
+    _watcher: Option<notify::RecommendedWatcher>,
+    task: Option<tokio::task::JoinHandle<()>>,
+}
+
+impl Drop for FileIndex {
+    fn drop(&mut self) {
+        if let Some(ref task) = self.task {
+            task.abort();
+        }
+    }
+}
+
+static FILE_INDEXES: Lazy<Arc<Mutex<HashMap<String, FileIndex>>>> =
+    Lazy::new(|| Arc::new(Mutex::new(HashMap::new())));
+
+fn canonical_root(root: &str) -> Result<PathBuf, String> {
+    let path = std::fs::canonicalize(root).map_err(|e| format!("Invalid workspace root {}: {}", root, e))?;
+    if !path.is_dir() {
+        return Err(format!("Not a directory: {}", root));
+    }
+    Ok(path)
+}
+
+fn relative_path(root: &Path, path: &Path) -> Option<String> {
+    let relative = path.strip_prefix(root).ok()?;
+    let text = relative.to_string_lossy().replace('\\', "/");
+    (!text.is_empty()).then_some(text)
+}
+
+fn cache_path(app: &tauri::AppHandle, root: &str) -> PathBuf {
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    root.hash(&mut hasher);
+    storage::data_dir(app).join("file-index").join(format!("{:016x}.json", hasher.finish()))
+}
+
+async fn read_cache(app: &tauri::AppHandle, root: &str, head: &str, rules: &str) -> Option<CachedIndex> {
+    let data = tokio::fs::read(cache_path(app, root)).await.ok()?;
+    let cached: CachedIndex = serde_json::from_slice(&data).ok()?;
+    (cached.root == root && cached.head == head && cached.rules == rules).then_some(cached)
+}
+
+async fn write_cache(app: &tauri::AppHandle, cached: &CachedIndex) -> Result<(), String> {
+    let path = cache_path(app, &cached.root);
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await.map_err(|e| e.to_string())?;
+    }
+    let data = serde_json::to_vec(cached).map_err(|e| e.to_string())?;
+    crate::write_atomic(&path, &data).await
+}
+
+fn operation_id(root: &str) -> String {
+    format!("file-index:{}", root)
+}
+
+// Walk the tree honouring .gitignore, .claudeignore and the global excludes
+// (and skipping hidden files), reporting progress as we go. None if the
+// operation was cancelled part way.
+fn walk(app: &tauri::AppHandle, root: &Path, global: &[String], operation: &Operation) -> Option<(BTreeSet<String>, bool)> {
+    let root_label = root.to_string_lossy().to_string();
+    let mut paths = BTreeSet::new();
+    let mut truncated = false;
+
+    for entry in ignore_rules::walker(root, global).flatten() {
+        if operation.is_cancelled() {
+            return None;
+        }
+        if !entry.file_type().is_some_and(|t| t.is_file()) {
+            continue;
+        }
+        let Some(relative) = relative_path(root, entry.path()) else { continue };
+        if paths.len() >= MAX_INDEXED_FILES {
+            truncated = true;
+            break;
+        }
+        paths.insert(relative);
+        if paths.len() % PROGRESS_EVERY == 0 {
+            operation.set_progress(None, Some(format!("{} files indexed", paths.len())));
+            let _ = app.emit("file-index-progress", FileIndexProgress {
+                operation_id: operation_id(&root_label),
+                root: root_label.clone(),
+                files_indexed: paths.len(),
+                done: false,
+                truncated: false,
+            });
+        }
+    }
+
+    Some((paths, truncated))
+}
+
+fn is_hidden(relative: &str) -> bool {
+    relative.split('/').any(|part| part.starts_with('.'))
+}
+
+fn apply_event(root: &Path, rules: &RootMatcher, event: &Event, index: &mut FileIndex) {
+    if !matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)) {
+        return;
+    }
+    for path in &event.paths {
+        let Some(relative) = relative_path(root, path) else { continue };
+        if path.is_file() {
+            let ignored = is_hidden(&relative) || rules.is_ignored(path, false);
+            if !ignored && (index.paths.len() < MAX_INDEXED_FILES || index.paths.contains(&relative)) {
+                index.paths.insert(relative);
+            }
+        } else if !path.exists() {
+            // Could have been a file or a whole directory
+            let prefix = format!("{}/", relative);
+            index.paths.remove(&relative);
+            index.paths.retain(|p| !p.starts_with(&prefix));
+        }
+    }
+}
+
+// Files created after the walk are filtered by the root-level rules
+fn start_watcher(root: PathBuf, global: Vec<String>) -> Result<(notify::RecommendedWatcher, tokio::task::JoinHandle<()>), String> {
+    let (tx, mut rx) = mpsc::unbounded_channel::<Event>();
+    let mut watcher = notify::recommended_watcher(move |res: notify::Result<Event>| {
+        if let Ok(event) = res {
+            let _ = tx.send(event);
+        }
+    }).map_err(|e| format!("Failed to create file watcher: {}", e))?;
+    watcher.watch(&root, RecursiveMode::Recursive)
+        .map_err(|e| format!("Failed to watch {}: {}", root.display(), e))?;
+
+    let description = format!("file index watcher ({})", root.display());
+    let task = background_tasks::spawn(description, async move {
+        let key = root.to_string_lossy().to_string();
+        let rules = RootMatcher::new(&root, &global);
+        while let Some(event) = rx.recv().await {
+            let mut indexes = FILE_INDEXES.lock().await;
+            let Some(index) = indexes.get_mut(&key) else { continue };
+            apply_event(&root, &rules, &event, index);
+        }
+    });
+
+    Ok((watcher, task))
+}
+
+#[tauri::command]
+pub async fn build_file_index(app: tauri::AppHandle, workspace_root: String) -> Result<FileIndexSummary, String> {
+    let root = canonical_root(&workspace_root)?;
+    let key = root.to_string_lossy().to_string();
+
+    let head = match git::find_repo_root(&root) {
+        Some(repo_root) => git::head_commit(&repo_root).await,
+        None => None,
+    };
+
+    let global = ignore_rules::global_excludes(&app).await;
+    let rules = ignore_rules::fingerprint(&root, &global);
+    let cached = match head {
+        Some(ref head) => read_cache(&app, &key, head, &rules).await,
+        None => None,
+    };
+    let from_cache = cached.is_some();
+    let (paths, truncated) = match cached {
+        Some(cached) => (cached.paths.into_iter().collect(), cached.truncated),
+        None => {
+            let walk_app = app.clone();
+            let walk_root = root.clone();
+            let walk_global = global.clone();
+            let operation = operations::register(&operation_id(&key), "file_index", true);
+            // Nothing has been written yet, so a cancelled walk leaves no trace
+            tokio::task::spawn_blocking(move || walk(&walk_app, &walk_root, &walk_global, &operation))
+                .await
+                .map_err(|e| e.to_string())?
+                .ok_or("Cancelled")?
+        }
+    };
+
+    if let (Some(head), false) = (head, from_cache) {
+        let _ = write_cache(&app, &CachedIndex {
+            root: key.clone(),
+            head,
+            rules,
+            truncated,
+            paths: paths.iter().cloned().collect(),
+        }).await;
+    }
+
+    let file_count = paths.len();
+    // Without a watcher the index still works, it just won't see new files
+    let (watcher, task) = match start_watcher(root, global) {
+        Ok((watcher, task)) => (Some(watcher), Some(task)),
+        Err(_) => (None, None),
+    };
+    FILE_INDEXES.lock().await.insert(key.clone(), FileIndex {
+        paths,
+        _watcher: watcher,
+        task,
+    });
+
+    let _ = app.emit("file-index-progress", FileIndexProgress {
+        operation_id: operation_id(&key),
+        root: key.clone(),
+        files_indexed: file_count,
+        done: true,
+        truncated,
+    });
+
+    Ok(FileIndexSummary { root: key, file_count, truncated, from_cache })
+}
+
+// Score `query` as a case-insensitive subsequence of `candidate`. Consecutive
+// runs and matches at the start of a word score higher; gaps cost a little.
+fn subsequence_score(query: &[char], candidate: &str) -> Option<i64> {
+    let chars: Vec<char> = candidate.chars().collect();
+    let mut score = 0i64;
+    let mut qi = 0;
+    let mut last_match: Option<usize> = None;
+
+    for (i, c) in chars.iter().enumerate() {
+        if qi == query.len() {
+            break;
+        }
+        if !c.to_lowercase().eq(query[qi].to_lowercase()) {
+            continue;
+        }
+        score += 1;
+        match last_match {
+            Some(last) if last + 1 == i => score += 5,
+            Some(last) => score -= (i - last - 1).min(5) as i64,
+            None => score -= i.min(10) as i64,
+        }
+        let word_start = i == 0 || matches!(chars[i - 1], '/' | '_' | '-' | '.' | ' ');
+        if word_start {
+            score += 8;
+        }
+        last_match = Some(i);
+        qi += 1;
+    }
+
+    (qi == query.len()).then_some(score)
+}
+
+fn fuzzy_score(query: &[char], path: &str) -> Option<i64> {
+    let file_name = path.rsplit('/').next().unwrap_or(path);
+    // A match within the file name always beats one spread across directories
+    if let Some(score) = subsequence_score(query, file_name) {
+        return Some(1000 + score * 2 - file_name.len() as i64);
+    }
+    subsequence_score(query, path).map(|score| score - (path.len() as i64) / 4)
+}
+
+#[tauri::command]
+pub async fn query_file_index(root: String, query: String, limit: usize) -> Result<Vec<FileMatch>, String> {
+    let key = canonical_root(&root)?.to_string_lossy().to_string();
+    let indexes = FILE_INDEXES.lock().await;
+    let index = indexes.get(&key)
+        .ok_or_else(|| format!("No file index for {}; build it first", root))?;
+
+    let query: Vec<char> = query.chars().filter(|c| !c.is_whitespace()).collect();
+    let mut matches: Vec<FileMatch> = index.paths.iter()
+        .filter_map(|path| fuzzy_score(&query, path).map(|score| FileMatch { path: path.clone(), score }))
+        .collect();
+    matches.sort_by(|a, b| b.score.cmp(&a.score).then_with(|| a.path.len().cmp(&b.path.len())).then_with(|| a.path.cmp(&b.path)));
+    matches.truncate(limit);
+    Ok(matches)
+}
@@ -0,0 +1,230 @@
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io::SeekFrom;
+use std::sync::Arc;
+use std::time::Duration;
+use tauri::Emitter;
+use tokio::fs::File;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use tokio::sync::Mutex;
+
+use crate::stream_errors::{self, Liveness, StreamSource};
+use crate::subscriptions;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+// Most data emitted per poll; anything beyond is skipped once we fall too far behind
+const MAX_BYTES_PER_POLL: u64 = 1024 * 1024;
+// How far behind the end of the file we may fall before skipping ahead
+const MAX_BACKLOG_BYTES: u64 = 8 * 1024 * 1024;
+// A "line" this long without a newline is emitted as-is
+const MAX_PARTIAL_LINE: usize = 64 * 1024;
+
+#[derive(Clone, Serialize)]
+pub struct FileTailEvent {
+    pub tail_id: String,
+    pub lines: Vec<String>,
+    // The file was rotated or truncated; following restarted from the top
+    pub rotated: bool,
+    // Total lines skipped so far because the file grew faster than we could emit
+    pub dropped_lines: u64,
+    pub error: Option<String>,
+}
+
+struct FileTail {
+    window_label: String,
+    task: tokio::task::JoinHandle<Option<()>>,
+}
+
+static FILE_TAILS: Lazy<Arc<Mutex<HashMap<String, FileTail>>>> =
+    Lazy::new(|| Arc::new(Mutex::new(HashMap::new())));
+
+#[cfg(unix)]
+fn file_identity(metadata: &std::fs::Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    metadata.ino()
+}
+
+#[cfg(not(unix))]
+fn file_identity(_metadata: &std::fs::Metadata) -> u64 {
+    0
+}
+
+struct TailState {
+    app: tauri::AppHandle,
+    tail_id: String,
+    path: String,
+    file: File,
+    identity: u64,
+    position: u64,
+    partial: Vec<u8>,
+    dropped_lines: u64,
+}
+
+impl TailState {
+    fn emit(&self, lines: Vec<String>, rotated: bool, error: Option<String>) {
+        let _ = self.app.emit(&format!("file-tail-{}", self.tail_id), FileTailEvent {
+            tail_id: self.tail_id.clone(),
+            lines,
+            rotated,
+            dropped_lines: self.dropped_lines,
+            error,
+        });
+    }
+
+    // Reopen the file from the start after rotation or truncation
+    fn restart(&mut self, file: File, identity: u64) {
+        self.file = file;
+        self.identity = identity;
+        self.position = 0;
+        self.partial.clear();
+        self.emit(Vec::new(), true, None);
+    }
+
+    // Skip `count` bytes, counting the lines in them without keeping them around
+    async fn skip(&mut self, count: u64) -> std::io::Result<()> {
+        let mut buf = vec![0u8; 64 * 1024];
+        let mut remaining = count;
+        while remaining > 0 {
+            let want = remaining.min(buf.len() as u64) as usize;
+            let n = self.file.read(&mut buf[..want]).await?;
+            if n == 0 {
+                break;
+            }
+            self.dropped_lines += buf[..n].iter().filter(|&&b| b == b'\n').count() as u64;
+            remaining -= n as u64;
+            self.position += n as u64;
+        }
+        self.partial.clear();
+        Ok(())
+    }
+
+    async fn poll(&mut self) -> std::io::Result<()> {
+        // The path may briefly not exist mid-rotation; try again next poll
+        let Ok(metadata) = tokio::fs::metadata(&self.path).await else {
+            return Ok(());
+        };
+
+        // A new inode means the file was rotated, a shorter one that it was truncated
+        let identity = file_identity(&metadata);
+        if identity != self.identity || metadata.len() < self.position {
+            let file = File::open(&self.path).await?;
+            self.restart(file, identity);
+        }
+
+        let available = metadata.len().saturating_sub(self.position);
+        if available > MAX_BACKLOG_BYTES {
+            self.skip(available - MAX_BYTES_PER_POLL).await?;
+        }
+
+        let mut chunk = Vec::new();
+        (&mut self.file).take(MAX_BYTES_PER_POLL).read_to_end(&mut chunk).await?;
+        if chunk.is_empty() {
+            return Ok(());
+        }
+        self.position += chunk.len() as u64;
+        self.partial.extend_from_slice(&chunk);
+
+        let mut lines = Vec::new();
+        while let Some(pos) = self.partial.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.partial.drain(..=pos).collect();
+            let text = String::from_utf8_lossy(&line[..line.len() - 1]);
+            lines.push(text.trim_end_matches('\r').to_string());
+        }
+        if self.partial.len() > MAX_PARTIAL_LINE {
+            lines.push(String::from_utf8_lossy(&self.partial).to_string());
+            self.partial.clear();
+        }
+
+        if !lines.is_empty() {
+            self.emit(lines, false, None);
+        }
+        Ok(())
+    }
+}
+
+#[tauri::command]
+pub async fn tail_file(
+    app: tauri::AppHandle,
+    window: tauri::Window,
+    tail_id: String,
+    path: String,
+    from_end_bytes: Option<u64>,
+) -> Result<(), String> {
+    let mut tails = FILE_TAILS.lock().await;
+    if tails.contains_key(&tail_id) {
+        return Err("Tail is already running".to_string());
+    }
+
+    let mut file = File::open(&path).await.map_err(|e| format!("Failed to open {}: {}", path, e))?;
+    let metadata = file.metadata().await.map_err(|e| e.to_string())?;
+    let start = metadata.len().saturating_sub(from_end_bytes.unwrap_or(0));
+    file.seek(SeekFrom::Start(start)).await.map_err(|e| e.to_string())?;
+
+    let mut state = TailState {
+        app,
+        tail_id: tail_id.clone(),
+        path,
+        file,
+        identity: file_identity(&metadata),
+        position: start,
+        partial: Vec::new(),
+        dropped_lines: 0,
+    };
+
+    // Starting mid-file almost always lands inside a line; drop that fragment
+    if start > 0 {
+        let mut byte = [0u8; 1];
+        while state.file.read(&mut byte).await.map_err(|e| e.to_string())? == 1 {
+            state.position += 1;
+            if byte[0] == b'\n' {
+                break;
+            }
+        }
+    }
+
+    let description = format!("file tail ({})", state.tail_id);
+    let source = StreamSource::new(&state.tail_id, "file_tail", Liveness::Unknown);
+    let task = stream_errors::spawn_reader(state.app.clone(), source, description, async move {
+        let channel = format!("file-tail-{}", state.tail_id);
+        loop {
+            // Lines written while nobody is watching are picked up on resume
+            subscriptions::wait_for_subscribers(&channel).await;
+            if let Err(e) = state.poll().await {
+                state.emit(Vec::new(), false, Some(e.to_string()));
+                return ((), Err(e.to_string()));
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    });
+
+    tails.insert(tail_id, FileTail {
+        window_label: window.label().to_string(),
+        task,
+    });
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn stop_tail(tail_id: String) -> Result<bool, String> {
+    let mut tails = FILE_TAILS.lock().await;
+    if let Some(tail) = tails.remove(&tail_id) {
+        tail.task.abort();
+        Ok(true)
+    } else {
+        Ok(false)
+    }
+}
+
+// Stop every tail started from a window that has gone away
+pub(crate) async fn stop_tails_for_window(window_label: &str) {
+    let mut tails = FILE_TAILS.lock().await;
+    tails.retain(|_, tail| {
+        if tail.window_label == window_label {
+            tail.task.abort();
+            false
+        } else {
+            true
+        }
+    });
+}
@@ -0,0 +1,310 @@
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, BufReader};
+
+use crate::attachments::hex_digest;
+
+// Matched lines longer than this are cut short in results
+const MAX_LINE_CHARS: usize = 2000;
+
+#[derive(Clone, Serialize)]
+pub struct SearchMatch {
+    pub line_number: usize,
+    pub line: String,
+}
+
+#[derive(Clone, Serialize)]
+pub struct SearchResult {
+    pub matches: Vec<SearchMatch>,
+    // True when the search stopped at max_matches
+    pub truncated: bool,
+}
+
+#[tauri::command]
+pub async fn search_in_file(path: String, pattern: String, max_matches: usize) -> Result<SearchResult, String> {
+    let regex = regex::Regex::new(&pattern).map_err(|e| format!("Invalid pattern: {}", e))?;
+    let file = tokio::fs::File::open(&path).await
+        .map_err(|e| format!("Failed to open {}: {}", path, e))?;
+
+    // Read raw lines so non-UTF-8 content doesn't end the search
+    let mut reader = BufReader::new(file);
+    let mut buf = Vec::new();
+    let mut matches = Vec::new();
+    let mut line_number = 0;
+
+    loop {
+        buf.clear();
+        if reader.read_until(b'\n', &mut buf).await.map_err(|e| e.to_string())? == 0 {
+            break;
+        }
+        line_number += 1;
+        let line = String::from_utf8_lossy(&buf);
+        let line = line.trim_end_matches(['\n', '\r']);
+        if !regex.is_match(line) {
+            continue;
+        }
+        if matches.len() >= max_matches {
+            return Ok(SearchResult { matches, truncated: true });
+        }
+        let line = match line.char_indices().nth(MAX_LINE_CHARS) {
+            Some((end, _)) => line[..end].to_string(),
+            None => line.to_string(),
+        };
+        matches.push(SearchMatch { line_number, line });
+    }
+
+    Ok(SearchResult { matches, truncated: false })
+}
+
+#[derive(Clone, Serialize)]
+pub struct FileContent {
+    pub content: String,
+    // The encoding actually used; a byte order mark overrides the requested one
+    pub encoding: String,
+    pub had_bom: bool,
+    // True if invalid sequences were replaced with U+FFFD
+    pub lossy: bool,
+}
+
+// Read a text file in the given encoding ("utf-8" by default; any WHATWG
+// label such as "latin1" or "utf-16" works), dropping a leading BOM
+#[tauri::command]
+pub async fn read_file(path: String, encoding: Option<String>) -> Result<FileContent, String> {
+    let label = encoding.as_deref().unwrap_or("utf-8");
+    let requested = encoding_rs::Encoding::for_label(label.trim().as_bytes())
+        .ok_or_else(|| format!("Unknown encoding: {}", label))?;
+    let bytes = tokio::fs::read(&path).await
+        .map_err(|e| format!("Failed to read {}: {}", path, e))?;
+
+    let had_bom = encoding_rs::Encoding::for_bom(&bytes).is_some();
+    let (content, used, lossy) = requested.decode(&bytes);
+    Ok(FileContent {
+        content: content.into_owned(),
+        encoding: used.name().to_string(),
+        had_bom,
+        lossy,
+    })
+}
+
+// Check that we can create files in a directory by writing and removing a
+// scratch file. Permission problems and read-only mounts give false; only a
+// path that isn't there is an error.
+#[tauri::command]
+pub async fn is_writable(path: String) -> Result<bool, String> {
+    let metadata = tokio::fs::metadata(&path).await
+        .map_err(|e| format!("Failed to access {}: {}", path, e))?;
+    if !metadata.is_dir() {
+        return Err(format!("Not a directory: {}", path));
+    }
+
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or(0);
+    let probe = std::path::Path::new(&path).join(format!(".write-test-{}-{}", std::process::id(), nanos));
+    let created = tokio::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&probe)
+        .await;
+    match created {
+        Ok(file) => {
+            drop(file);
+            Ok(tokio::fs::remove_file(&probe).await.is_ok())
+        }
+        Err(_) => Ok(false),
+    }
+}
+
+async fn digest_file<D: sha2::Digest>(path: &str) -> Result<String, String> {
+    let mut file = tokio::fs::File::open(path).await
+        .map_err(|e| format!("Failed to open {}: {}", path, e))?;
+    let mut hasher = D::new();
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        let n = file.read(&mut buf).await.map_err(|e| format!("Failed to read {}: {}", path, e))?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(hex_digest(&hasher.finalize()))
+}
+
+// Hex digest of a file, read in chunks so large files are fine
+#[tauri::command]
+pub async fn hash_file(path: String, algorithm: String) -> Result<String, String> {
+    if tokio::fs::metadata(&path).await.map_err(|e| format!("Failed to access {}: {}", path, e))?.is_dir() {
+        return Err(format!("{} is a directory", path));
+    }
+    match algorithm.trim().to_lowercase().replace('-', "").as_str() {
+        "sha256" => digest_file::<sha2::Sha256>(&path).await,
+        "sha1" => digest_file::<sha1::Sha1>(&path).await,
+        "md5" => digest_file::<md5::Md5>(&path).await,
+        _ => Err(format!("Unsupported algorithm {}: use sha256, sha1 or md5", algorithm)),
+    }
+}
+
+// Copy a file, symlink or whole tree, keeping symlinks as links rather than
+// following them
+fn copy_tree(src: &Path, dest: &Path) -> std::io::Result<()> {
+    let metadata = std::fs::symlink_metadata(src)?;
+    if metadata.file_type().is_symlink() {
+        let target = std::fs::read_link(src)?;
+        #[cfg(unix)]
+        return std::os::unix::fs::symlink(target, dest);
+        #[cfg(windows)]
+        return if std::fs::metadata(src).is_ok_and(|m| m.is_dir()) {
+            std::os::windows::fs::symlink_dir(target, dest)
+        } else {
+            std::os::windows::fs::symlink_file(target, dest)
+        };
+    }
+    if metadata.is_dir() {
+        std::fs::create_dir(dest)?;
+        for entry in std::fs::read_dir(src)? {
+            let entry = entry?;
+            copy_tree(&entry.path(), &dest.join(entry.file_name()))?;
+        }
+        std::fs::set_permissions(dest, metadata.permissions())
+    } else {
+        std::fs::copy(src, dest).map(|_| ())
+    }
+}
+
+fn remove_tree(path: &Path) -> std::io::Result<()> {
+    if std::fs::symlink_metadata(path)?.is_dir() {
+        std::fs::remove_dir_all(path)
+    } else {
+        std::fs::remove_file(path)
+    }
+}
+
+// Whether two paths name the same entry, without following a final symlink
+#[cfg(unix)]
+fn same_entry(a: &Path, b: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    match (std::fs::symlink_metadata(a), std::fs::symlink_metadata(b)) {
+        (Ok(a), Ok(b)) => a.dev() == b.dev() && a.ino() == b.ino(),
+        _ => false,
+    }
+}
+
+#[cfg(not(unix))]
+fn same_entry(a: &Path, b: &Path) -> bool {
+    match (std::fs::canonicalize(a), std::fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+fn move_blocking(src: &Path, dest: &Path, overwrite: bool) -> Result<(), String> {
+    let src_metadata = std::fs::symlink_metadata(src)
+        .map_err(|e| format!("Failed to access {}: {}", src.display(), e))?;
+    if !dest.parent().is_some_and(|parent| parent.is_dir()) {
+        return Err(format!("Destination folder for {} does not exist", dest.display()));
+    }
+    if src_metadata.is_dir() {
+        let src_real = std::fs::canonicalize(src).map_err(|e| e.to_string())?;
+        let dest_parent = std::fs::canonicalize(dest.parent().unwrap_or(dest)).map_err(|e| e.to_string())?;
+        if dest_parent.starts_with(&src_real) {
+            return Err(format!("Can't move {} into itself", src.display()));
+        }
+    }
+
+    let existing = std::fs::symlink_metadata(dest).ok();
+    if let Some(ref existing) = existing {
+        // Only a name change, e.g. different case on a case-insensitive disk
+        if same_entry(src, dest) {
+            return std::fs::rename(src, dest).map_err(|e| e.to_string());
+        }
+        if !overwrite {
+            return Err(format!("{} already exists", dest.display()));
+        }
+        if existing.is_dir() != src_metadata.is_dir() {
+            return Err(format!(
+                "Can't replace {} {} with {}",
+                if existing.is_dir() { "the folder" } else { "the file" },
+                dest.display(),
+                if src_metadata.is_dir() { "a folder" } else { "a file" }
+            ));
+        }
+    }
+
+    // rename replaces files in one step but won't replace a non-empty folder
+    if existing.as_ref().is_some_and(|m| m.is_dir()) {
+        remove_tree(dest).map_err(|e| format!("Failed to replace {}: {}", dest.display(), e))?;
+    }
+    match std::fs::rename(src, dest) {
+        Ok(()) => return Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::CrossesDevices => {}
+        Err(e) => return Err(format!("Failed to move {} to {}: {}", src.display(), dest.display(), e)),
+    }
+
+    // Across filesystems: copy beside the destination, swap it in, and only
+    // then remove the source, so a failure never loses both
+    let temp = crate::with_suffix(dest, &format!(".moving-{}", std::process::id()));
+    if let Err(e) = copy_tree(src, &temp) {
+        let _ = remove_tree(&temp);
+        return Err(format!("Failed to copy {} to {}: {}", src.display(), dest.display(), e));
+    }
+    if std::fs::symlink_metadata(dest).is_ok() {
+        if let Err(e) = remove_tree(dest) {
+            let _ = remove_tree(&temp);
+            return Err(format!("Failed to replace {}: {}", dest.display(), e));
+        }
+    }
+    if let Err(e) = std::fs::rename(&temp, dest) {
+        let _ = remove_tree(&temp);
+        return Err(format!("Failed to move the copy into place at {}: {}", dest.display(), e));
+    }
+    remove_tree(src).map_err(|e| format!("Copied to {} but couldn't remove {}: {}", dest.display(), src.display(), e))
+}
+
+// Move or rename a file or folder. An existing destination is only replaced
+// when overwrite is set, and only by the same kind of entry. Moves between
+// filesystems fall back to copying and then deleting the source.
+#[tauri::command]
+pub async fn move_path(src: String, dest: String, overwrite: bool) -> Result<(), String> {
+    let (src, dest) = (PathBuf::from(src), PathBuf::from(dest));
+    if !src.is_absolute() || !dest.is_absolute() {
+        return Err("Both paths must be absolute".to_string());
+    }
+    tokio::task::spawn_blocking(move || move_blocking(&src, &dest, overwrite))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+fn delete_error(path: &Path, e: std::io::Error) -> String {
+    match e.kind() {
+        std::io::ErrorKind::NotFound => format!("{} does not exist", path.display()),
+        std::io::ErrorKind::PermissionDenied => format!("Permission denied deleting {}", path.display()),
+        _ => format!("Failed to delete {}: {}", path.display(), e),
+    }
+}
+
+fn delete_blocking(path: &Path, to_trash: bool) -> Result<(), String> {
+    // Checked up front so both ways of deleting report the same errors
+    std::fs::symlink_metadata(path).map_err(|e| delete_error(path, e))?;
+    if path.parent().is_none() {
+        return Err(format!("Refusing to delete {}", path.display()));
+    }
+    if to_trash {
+        trash::delete(path).map_err(|e| format!("Failed to move {} to the trash: {}", path.display(), e))
+    } else {
+        remove_tree(path).map_err(|e| delete_error(path, e))
+    }
+}
+
+// Delete a file or folder, into the OS trash when to_trash is set and for
+// good otherwise. Symlinks are removed, never what they point to.
+#[tauri::command]
+pub async fn delete_path(path: String, to_trash: bool) -> Result<(), String> {
+    let path = PathBuf::from(path);
+    if !path.is_absolute() {
+        return Err("Path must be absolute".to_string());
+    }
+    tokio::task::spawn_blocking(move || delete_blocking(&path, to_trash))
+        .await
+        .map_err(|e| e.to_string())?
+}
@@ -0,0 +1,155 @@
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tauri::Emitter;
+use tokio::sync::{watch, Mutex};
+
+use crate::{background_tasks, unix_millis};
+
+const MAX_FOCUS_MINUTES: u64 = 24 * 60;
+
+// What a focus session holds back. Anything the user does directly still runs.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FocusOptions {
+    // For the frontend: no desktop notifications while focused
+    pub suppress_notifications: bool,
+    // For the frontend: queued messages wait instead of being sent automatically
+    pub defer_queued_turns: bool,
+    // Restarts triggered by service file watchers wait until the session ends
+    pub defer_service_restarts: bool,
+}
+
+impl Default for FocusOptions {
+    fn default() -> Self {
+        FocusOptions { suppress_notifications: true, defer_queued_turns: true, defer_service_restarts: true }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub(crate) enum Deferral {
+    ServiceRestarts,
+}
+
+#[derive(Clone)]
+struct FocusSession {
+    serial: u64,
+    started_at: u64,
+    ends_at: u64,
+    options: FocusOptions,
+}
+
+impl FocusSession {
+    fn defers(&self, kind: Deferral) -> bool {
+        match kind {
+            Deferral::ServiceRestarts => self.options.defer_service_restarts,
+        }
+    }
+}
+
+// Sent as `focus-changed` whenever a session starts or ends, or when
+// something is held back
+#[derive(Clone, Serialize)]
+pub struct FocusState {
+    pub active: bool,
+    pub started_at: Option<u64>,
+    pub ends_at: Option<u64>,
+    pub options: Option<FocusOptions>,
+    // Services with a watcher-triggered restart waiting for the session to end
+    pub deferred_restarts: Vec<String>,
+}
+
+// A sender so waiters can subscribe; send_replace works with no receivers
+static FOCUS: Lazy<watch::Sender<Option<FocusSession>>> = Lazy::new(|| watch::channel(None).0);
+static NEXT_SERIAL: AtomicU64 = AtomicU64::new(0);
+static DEFERRED_RESTARTS: Lazy<Arc<Mutex<BTreeSet<String>>>> =
+    Lazy::new(|| Arc::new(Mutex::new(BTreeSet::new())));
+
+async fn state() -> FocusState {
+    let session = FOCUS.borrow().clone();
+    FocusState {
+        active: session.is_some(),
+        started_at: session.as_ref().map(|s| s.started_at),
+        ends_at: session.as_ref().map(|s| s.ends_at),
+        options: session.map(|s| s.options),
+        deferred_restarts: DEFERRED_RESTARTS.lock().await.iter().cloned().collect(),
+    }
+}
+
+async fn emit_state(app: &tauri::AppHandle) {
+    let _ = app.emit("focus-changed", state().await);
+}
+
+pub(crate) fn defers(kind: Deferral) -> bool {
+    FOCUS.borrow().as_ref().is_some_and(|s| s.defers(kind))
+}
+
+// Wait out the focus session before restarting a watched service. The
+// restart then runs once, however many changes arrived in the meantime.
+pub(crate) async fn defer_service_restart(app: &tauri::AppHandle, service_id: &str) {
+    if !defers(Deferral::ServiceRestarts) {
+        return;
+    }
+    DEFERRED_RESTARTS.lock().await.insert(service_id.to_string());
+    emit_state(app).await;
+    let mut focus = FOCUS.subscribe();
+    let _ = focus.wait_for(|session| !session.as_ref().is_some_and(|s| s.defers(Deferral::ServiceRestarts))).await;
+    DEFERRED_RESTARTS.lock().await.remove(service_id);
+}
+
+async fn finish(app: &tauri::AppHandle, serial: Option<u64>) -> bool {
+    let ended = FOCUS.send_if_modified(|session| {
+        let matches = session.as_ref().is_some_and(|s| serial.is_none_or(|serial| s.serial == serial));
+        if matches {
+            *session = None;
+        }
+        matches
+    });
+    if ended {
+        emit_state(app).await;
+    }
+    ended
+}
+
+// Start focusing for the given number of minutes, replacing any session
+// already running
+#[tauri::command]
+pub async fn start_focus_session(
+    app: tauri::AppHandle,
+    duration_minutes: u64,
+    options: Option<FocusOptions>,
+) -> Result<FocusState, String> {
+    if duration_minutes == 0 || duration_minutes > MAX_FOCUS_MINUTES {
+        return Err(format!("Focus duration must be between 1 and {} minutes", MAX_FOCUS_MINUTES));
+    }
+    let serial = NEXT_SERIAL.fetch_add(1, Ordering::Relaxed);
+    let started_at = unix_millis();
+    FOCUS.send_replace(Some(FocusSession {
+        serial,
+        started_at,
+        ends_at: started_at + duration_minutes * 60 * 1000,
+        options: options.unwrap_or_default(),
+    }));
+    emit_state(&app).await;
+
+    let app_clone = app.clone();
+    background_tasks::spawn(format!("focus session timer ({})", serial), async move {
+        tokio::time::sleep(Duration::from_secs(duration_minutes * 60)).await;
+        finish(&app_clone, Some(serial)).await;
+    });
+    Ok(state().await)
+}
+
+// End the session early. Returns false if none was running.
+#[tauri::command]
+pub async fn end_focus_session(app: tauri::AppHandle) -> Result<bool, String> {
+    Ok(finish(&app, None).await)
+}
+
+#[tauri::command]
+pub async fn get_focus_state() -> Result<FocusState, String> {
+    Ok(state().await)
+}
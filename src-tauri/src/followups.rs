@@ -0,0 +1,182 @@
+use serde::Serialize;
+use std::process::Stdio;
+use std::time::Duration;
+use tauri::Emitter;
+use tokio::process::Command;
+
+use crate::{background_tasks, conversation_working_directory, cost_limits, endpoints, read_conversation, settings, transcripts};
+
+const MODEL: &str = "haiku";
+const TIMEOUT: Duration = Duration::from_secs(45);
+// Passed to the CLI as the call's own cap; both budgets need this much left
+const MAX_COST_USD: f64 = 0.02;
+// Skipped once the turn has used this share of the per-turn cap
+const TURN_CAP_SHARE: f64 = 0.9;
+const MAX_PROMPT_CHARS: usize = 4_000;
+const MAX_RESPONSE_CHARS: usize = 8_000;
+const SUGGESTION_COUNT: usize = 3;
+const MAX_SUGGESTION_CHARS: usize = 80;
+
+// Sent as `claude-suggestions-<conversation_id>` once ready
+#[derive(Clone, Serialize)]
+pub struct FollowupSuggestions {
+    pub conversation_id: String,
+    pub suggestions: Vec<String>,
+}
+
+fn head(text: &str, max: usize) -> &str {
+    match text.char_indices().nth(max) {
+        Some((index, _)) => &text[..index],
+        None => text,
+    }
+}
+
+// The end of a long response is usually where the next step is
+fn tail(text: &str, max: usize) -> &str {
+    let count = text.chars().count();
+    match text.char_indices().nth(count.saturating_sub(max)) {
+        Some((index, _)) => &text[index..],
+        None => text,
+    }
+}
+
+// Err with the reason when a suggestion call shouldn't spend anything now
+async fn check_budget(app: &tauri::AppHandle, conversation_id: &str, turn_cost_usd: f64) -> Result<(), String> {
+    if let Some(limit) = settings::load(app).await?.max_turn_cost_usd {
+        if turn_cost_usd >= limit * TURN_CAP_SHARE {
+            return Err("The turn used most of its cost cap".to_string());
+        }
+    }
+    if let Some(budget) = cost_limits::turn_budget(app, conversation_id).await? {
+        if budget.remaining() < MAX_COST_USD {
+            return Err("Not enough budget left for suggestions".to_string());
+        }
+    }
+    Ok(())
+}
+
+// A JSON array of strings, or failing that one suggestion per line
+fn parse_suggestions(text: &str) -> Vec<String> {
+    let candidates: Vec<String> = match (text.find('['), text.rfind(']')) {
+        (Some(start), Some(end)) if start < end => serde_json::from_str(&text[start..=end]).unwrap_or_default(),
+        _ => Vec::new(),
+    };
+    let candidates = if candidates.is_empty() { text.lines().map(String::from).collect() } else { candidates };
+
+    let mut suggestions: Vec<String> = Vec::new();
+    for candidate in candidates {
+        let cleaned = candidate
+            .trim()
+            .trim_start_matches(|c: char| c == '-' || c == '*' || c == '•' || c.is_ascii_digit() || c == '.' || c == ')')
+            .trim()
+            .trim_matches('"')
+            .trim();
+        if cleaned.is_empty() || suggestions.iter().any(|s| s.eq_ignore_ascii_case(cleaned)) {
+            continue;
+        }
+        suggestions.push(head(cleaned, MAX_SUGGESTION_CHARS).to_string());
+        if suggestions.len() == SUGGESTION_COUNT {
+            break;
+        }
+    }
+    suggestions
+}
+
+// One small, single-turn call outside the conversation's session: no
+// --resume, and run from the temp folder so no project or session of the
+// conversation is picked up. Returns the suggestions and what they cost.
+async fn generate(app: &tauri::AppHandle, conversation_id: &str, prompt: &str, response: &str) -> Result<(Vec<String>, f64), String> {
+    let request = format!(
+        "Suggest {} short follow-up requests the user might send next to a coding assistant, based on the exchange below. \
+         Reply with only a JSON array of {} strings, each under 60 characters, worded as the user would type them.\n\n\
+         <user_message>\n{}\n</user_message>\n\n<assistant_response>\n{}\n</assistant_response>",
+        SUGGESTION_COUNT,
+        SUGGESTION_COUNT,
+        head(prompt, MAX_PROMPT_CHARS),
+        tail(response, MAX_RESPONSE_CHARS),
+    );
+    // The same gateway as the conversation, found by its id or directory
+    let work_dir = conversation_working_directory(app, conversation_id).await;
+
+    let mut cmd = Command::new("claude");
+    cmd.envs(endpoints::resolve(app, conversation_id, work_dir.as_deref()).await?)
+        .current_dir(std::env::temp_dir())
+        .arg("--print")
+        .arg("--output-format").arg("json")
+        .arg("--model").arg(MODEL)
+        .arg("--max-turns").arg("1")
+        .arg("--max-budget-usd").arg(format!("{:.2}", MAX_COST_USD))
+        .arg(request)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .kill_on_drop(true);
+
+    let child = cmd.spawn().map_err(|e| format!("Failed to spawn claude: {}", e))?;
+    let output = tokio::time::timeout(TIMEOUT, child.wait_with_output()).await
+        .map_err(|_| "Suggestions timed out".to_string())?
+        .map_err(|e| e.to_string())?;
+    let result: serde_json::Value = serde_json::from_slice(&output.stdout)
+        .map_err(|_| format!("Unexpected output from claude (status {})", output.status))?;
+    let cost = result.get("total_cost_usd").and_then(|c| c.as_f64()).unwrap_or(0.0);
+    if result.get("is_error").and_then(|e| e.as_bool()).unwrap_or(false) {
+        return Err(result.get("result").and_then(|r| r.as_str()).unwrap_or("Suggestions failed").to_string());
+    }
+    let text = result.get("result").and_then(|r| r.as_str()).unwrap_or("");
+    Ok((parse_suggestions(text), cost))
+}
+
+async fn suggest(app: &tauri::AppHandle, conversation_id: &str, prompt: &str, response: &str) -> Result<Vec<String>, String> {
+    let generated = generate(app, conversation_id, prompt, response).await;
+    let cost = generated.as_ref().map(|(_, cost)| *cost).unwrap_or(0.0);
+    if cost > 0.0 {
+        let _ = cost_limits::record_extra(app, conversation_id, cost).await;
+    }
+    let (suggestions, _) = generated?;
+    let _ = app.emit(&format!("claude-suggestions-{}", conversation_id), FollowupSuggestions {
+        conversation_id: conversation_id.to_string(),
+        suggestions: suggestions.clone(),
+    });
+    Ok(suggestions)
+}
+
+// Called by send_to_claude once a turn has succeeded. Runs in the
+// background so the result isn't held up, and only when turned on.
+pub(crate) fn after_turn(app: &tauri::AppHandle, conversation_id: &str, prompt: &str, response: &str, turn_cost_usd: f64) {
+    let (app, conversation_id, prompt, response) = (app.clone(), conversation_id.to_string(), prompt.to_string(), response.to_string());
+    background_tasks::spawn(format!("follow-up suggestions ({})", conversation_id), async move {
+        let enabled = settings::load(&app).await.is_ok_and(|s| s.followup_suggestions);
+        if !enabled || response.trim().is_empty() || check_budget(&app, &conversation_id, turn_cost_usd).await.is_err() {
+            return;
+        }
+        let _ = suggest(&app, &conversation_id, &prompt, &response).await;
+    });
+}
+
+// Suggestions for the last exchange as saved, whether or not they're turned
+// on for every turn. Also sent as `claude-suggestions-<conversation_id>`.
+#[tauri::command]
+pub async fn suggest_followups(app: tauri::AppHandle, conversation_id: String) -> Result<Vec<String>, String> {
+    let conversation = match read_conversation(&app, &conversation_id).await {
+        Some(conversation) => conversation,
+        None => transcripts::read_transcript(&app, &conversation_id).await?
+            .ok_or_else(|| format!("Conversation not found: {}", conversation_id))?,
+    };
+    let messages = conversation.get("messages").and_then(|m| m.as_array()).cloned().unwrap_or_default();
+    let last = |role: &str| messages.iter()
+        .rev()
+        .find(|m| m.get("role").and_then(|r| r.as_str()) == Some(role))
+        .and_then(|m| m.get("content").and_then(|c| c.as_str()))
+        .map(String::from);
+    let (Some(prompt), Some(response)) = (last("user"), last("assistant")) else {
+        return Err("The conversation has no exchange to follow up on yet".to_string());
+    };
+    check_budget(&app, &conversation_id, 0.0).await?;
+    suggest(&app, &conversation_id, &prompt, &response).await
+}
+
+#[tauri::command]
+pub async fn set_followup_suggestions(app: tauri::AppHandle, enabled: bool) -> Result<(), String> {
+    settings::update(&app, |settings| settings.followup_suggestions = enabled).await?;
+    Ok(())
+}
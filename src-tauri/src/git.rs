@@ -0,0 +1,89 @@
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use tokio::process::Command;
+
+#[derive(Clone, Serialize)]
+pub struct GitContext {
+    pub repo_root: String,
+    // None when HEAD is detached
+    pub branch: Option<String>,
+    // None when the git binary isn't available to check
+    pub dirty: Option<bool>,
+}
+
+// Walk up from `start` looking for a `.git` directory (or worktree `.git` file)
+pub(crate) fn find_repo_root(start: &Path) -> Option<PathBuf> {
+    start.ancestors()
+        .find(|dir| dir.join(".git").exists())
+        .map(Path::to_path_buf)
+}
+
+// Resolve the real git directory, following the `gitdir:` pointer used by worktrees
+pub(crate) fn git_dir(repo_root: &Path) -> Option<PathBuf> {
+    let dot_git = repo_root.join(".git");
+    if dot_git.is_dir() {
+        return Some(dot_git);
+    }
+    let contents = std::fs::read_to_string(&dot_git).ok()?;
+    let target = contents.trim().strip_prefix("gitdir:")?.trim();
+    Some(repo_root.join(target))
+}
+
+// Contents of HEAD: either `ref: refs/heads/<branch>` or a detached commit id
+pub(crate) async fn read_head(repo_root: &Path) -> Option<String> {
+    let dir = git_dir(repo_root)?;
+    let head = tokio::fs::read_to_string(dir.join("HEAD")).await.ok()?;
+    Some(head.trim().to_string())
+}
+
+// Commit id HEAD points at, following a branch ref through loose refs or
+// packed-refs. Falls back to the raw HEAD contents if the ref can't be read.
+pub(crate) async fn head_commit(repo_root: &Path) -> Option<String> {
+    let head = read_head(repo_root).await?;
+    let Some(reference) = head.strip_prefix("ref: ") else {
+        return Some(head);
+    };
+    let dir = git_dir(repo_root)?;
+    if let Ok(commit) = tokio::fs::read_to_string(dir.join(reference)).await {
+        return Some(commit.trim().to_string());
+    }
+    let packed = tokio::fs::read_to_string(dir.join("packed-refs")).await.unwrap_or_default();
+    let commit = packed.lines()
+        .filter_map(|line| line.split_once(' '))
+        .find(|(_, name)| *name == reference)
+        .map(|(commit, _)| commit.to_string());
+    Some(commit.unwrap_or(head))
+}
+
+fn branch_from_head(head: &str) -> Option<String> {
+    head.strip_prefix("ref: refs/heads/").map(String::from)
+}
+
+#[tauri::command]
+pub async fn git_context(path: String) -> Result<Option<GitContext>, String> {
+    let start = PathBuf::from(&path);
+    if !start.exists() {
+        return Err(format!("Path does not exist: {}", path));
+    }
+    let Some(repo_root) = find_repo_root(&start) else {
+        return Ok(None);
+    };
+
+    let branch = read_head(&repo_root).await.and_then(|head| branch_from_head(&head));
+
+    let dirty = Command::new("git")
+        .arg("status")
+        .arg("--porcelain")
+        .current_dir(&repo_root)
+        .output()
+        .await
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| !output.stdout.is_empty());
+
+    Ok(Some(GitContext {
+        repo_root: repo_root.to_string_lossy().to_string(),
+        branch,
+        dirty,
+    }))
+}
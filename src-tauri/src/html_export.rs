@@ -0,0 +1,492 @@
+use base64::Engine;
+use pulldown_cmark::{CodeBlockKind, CowStr, Event, Options, Parser, Tag, TagEnd};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use syntect::highlighting::ThemeSet;
+use syntect::html::{css_for_theme_with_class_style, ClassStyle, ClassedHTMLGenerator};
+use syntect::parsing::SyntaxSet;
+use syntect::util::LinesWithEndings;
+
+use crate::time_format::TimeFormatter;
+use crate::{attachments, read_conversation, transcripts, turn_recovery, with_suffix};
+
+const DEFAULT_MAX_ATTACHMENT_BYTES: u64 = 5 * 1024 * 1024;
+const DEFAULT_THEME: &str = "InspiredGitHub";
+// Prefixed so highlighting classes can't collide with the page's own
+const CLASS_STYLE: ClassStyle = ClassStyle::SpacedPrefixed { prefix: "hl-" };
+// Tool results can hold whole files; the rest is cut off
+const MAX_TOOL_OUTPUT_CHARS: usize = 20_000;
+// Larger code blocks are shown without highlighting
+const MAX_HIGHLIGHT_BYTES: usize = 256 * 1024;
+const EMBEDDABLE_IMAGES: &[&str] = &["image/png", "image/jpeg", "image/gif", "image/webp"];
+
+// Nothing in the page may load or run anything; styles are inline and
+// images are data URIs
+const CSP: &str = "default-src 'none'; style-src 'unsafe-inline'; img-src data:";
+
+const PAGE_CSS: &str = "
+body { margin: 0; background: #f6f6f4; color: #1f2328; font: 15px/1.55 -apple-system, BlinkMacSystemFont, 'Segoe UI', sans-serif; }
+main { max-width: 860px; margin: 0 auto; padding: 32px 20px 64px; }
+h1 { font-size: 24px; margin: 0 0 4px; }
+.meta, footer { color: #6a737d; font-size: 13px; }
+.message { background: #fff; border: 1px solid #e1e4e8; border-radius: 8px; margin: 16px 0; padding: 12px 16px; }
+.message.user { border-left: 4px solid #0969da; }
+.message.assistant { border-left: 4px solid #8250df; }
+.message.tool { border-left: 4px solid #9a6700; }
+.message header { display: flex; justify-content: space-between; font-size: 13px; color: #6a737d; margin-bottom: 6px; }
+.message header .role { font-weight: 600; color: #1f2328; }
+.body { overflow-wrap: anywhere; }
+.body img { max-width: 100%; }
+pre { background: #f6f8fa; border-radius: 6px; padding: 10px 12px; overflow-x: auto; font: 13px/1.45 ui-monospace, SFMono-Regular, Menlo, monospace; }
+code { font-family: ui-monospace, SFMono-Regular, Menlo, monospace; font-size: 90%; }
+pre code { font-size: inherit; }
+details { margin: 8px 0; border: 1px solid #e1e4e8; border-radius: 6px; padding: 4px 10px; }
+details summary { cursor: pointer; color: #57606a; font-size: 13px; }
+details.error summary { color: #cf222e; }
+.plain { white-space: pre-wrap; }
+.skipped { color: #6a737d; font-style: italic; }
+table { border-collapse: collapse; }
+th, td { border: 1px solid #d0d7de; padding: 4px 8px; }
+";
+
+#[derive(Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct HtmlExportOptions {
+    pub include_thinking: bool,
+    // Tool calls and their results, collapsed; shown unless turned off
+    pub include_tool_calls: Option<bool>,
+    // Attachments larger than this are listed by name instead of embedded
+    pub max_attachment_bytes: Option<u64>,
+    // A syntect theme such as "InspiredGitHub" or "base16-ocean.dark"
+    pub theme: Option<String>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct HtmlExportReport {
+    pub path: String,
+    pub messages: usize,
+    pub bytes_written: u64,
+    pub attachments_embedded: usize,
+    pub attachments_skipped: usize,
+    // "session" when rendered from the CLI's session log, which has tool
+    // calls and thinking; "messages" when only the saved messages were found
+    pub source: String,
+}
+
+enum Part {
+    Text(String),
+    Thinking(String),
+    ToolUse { name: String, input: String },
+    ToolResult { output: String, is_error: bool },
+    Image { media_type: String, data: String },
+}
+
+struct Message {
+    role: String,
+    timestamp: Option<u64>,
+    parts: Vec<Part>,
+    attachment_ids: Vec<String>,
+}
+
+struct Renderer {
+    app: tauri::AppHandle,
+    syntaxes: SyntaxSet,
+    formatter: TimeFormatter,
+    attachment_id: Regex,
+    include_thinking: bool,
+    include_tool_calls: bool,
+    max_attachment_bytes: u64,
+    attachments_embedded: usize,
+    attachments_skipped: usize,
+}
+
+fn escape_html(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+// Links that could run script or point into the user's machine go nowhere
+fn safe_url(url: CowStr) -> CowStr {
+    let lower = url.trim().to_ascii_lowercase();
+    if ["http://", "https://", "mailto:", "#"].iter().any(|prefix| lower.starts_with(prefix)) {
+        url
+    } else {
+        CowStr::Borrowed("#")
+    }
+}
+
+fn image_type(bytes: &[u8]) -> Option<&'static str> {
+    if bytes.starts_with(b"\x89PNG") {
+        Some("image/png")
+    } else if bytes.starts_with(b"\xff\xd8\xff") {
+        Some("image/jpeg")
+    } else if bytes.starts_with(b"GIF8") {
+        Some("image/gif")
+    } else if bytes.starts_with(b"RIFF") && bytes.get(8..12) == Some(b"WEBP") {
+        Some("image/webp")
+    } else {
+        None
+    }
+}
+
+fn truncate_chars(text: &str, max: usize) -> (&str, bool) {
+    match text.char_indices().nth(max) {
+        Some((index, _)) => (&text[..index], true),
+        None => (text, false),
+    }
+}
+
+fn block_text(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(text) => text.clone(),
+        serde_json::Value::Array(items) => items.iter()
+            .filter_map(|item| item.get("text").and_then(|t| t.as_str()))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => String::new(),
+    }
+}
+
+fn part_from_block(block: &serde_json::Value) -> Option<Part> {
+    let field = |name: &str| block.get(name).and_then(|v| v.as_str()).map(String::from);
+    match block.get("type")?.as_str()? {
+        "text" => field("text").map(Part::Text),
+        "thinking" => field("thinking").map(Part::Thinking),
+        "tool_use" => Some(Part::ToolUse {
+            name: field("name").unwrap_or_else(|| "tool".to_string()),
+            input: block.get("input").and_then(|i| serde_json::to_string_pretty(i).ok()).unwrap_or_default(),
+        }),
+        "tool_result" => Some(Part::ToolResult {
+            output: block.get("content").map(block_text).unwrap_or_default(),
+            is_error: block.get("is_error").and_then(|e| e.as_bool()).unwrap_or(false),
+        }),
+        "image" => {
+            let source = block.get("source")?;
+            if source.get("type").and_then(|t| t.as_str()) != Some("base64") {
+                return None;
+            }
+            Some(Part::Image {
+                media_type: source.get("media_type")?.as_str()?.to_string(),
+                data: source.get("data")?.as_str()?.to_string(),
+            })
+        }
+        _ => None,
+    }
+}
+
+impl Renderer {
+    fn attachment_ids(&self, value: &serde_json::Value) -> Vec<String> {
+        let text = value.to_string();
+        let mut ids: Vec<String> = self.attachment_id.find_iter(&text).map(|m| m.as_str().to_string()).collect();
+        ids.sort_unstable();
+        ids.dedup();
+        ids
+    }
+
+    // One line of the CLI's session log, if it's a message worth showing
+    fn session_message(&self, entry: &serde_json::Value) -> Option<Message> {
+        let role = entry.get("type")?.as_str()?;
+        if !matches!(role, "user" | "assistant") || entry.get("isMeta").and_then(|m| m.as_bool()) == Some(true) {
+            return None;
+        }
+        let message = entry.get("message")?;
+        let parts = match message.get("content")? {
+            serde_json::Value::String(text) => vec![Part::Text(text.clone())],
+            serde_json::Value::Array(blocks) => blocks.iter().filter_map(part_from_block).collect(),
+            _ => return None,
+        };
+        Some(Message {
+            role: role.to_string(),
+            timestamp: entry.get("timestamp").and_then(|t| t.as_str()).and_then(turn_recovery::parse_timestamp),
+            parts,
+            attachment_ids: self.attachment_ids(message),
+        })
+    }
+
+    // A message as the frontend saves it: plain markdown content
+    fn saved_message(&self, message: &serde_json::Value) -> Option<Message> {
+        Some(Message {
+            role: message.get("role")?.as_str()?.to_string(),
+            timestamp: match message.get("timestamp")? {
+                serde_json::Value::Number(millis) => millis.as_u64(),
+                serde_json::Value::String(text) => turn_recovery::parse_timestamp(text),
+                _ => None,
+            },
+            parts: vec![Part::Text(message.get("content")?.as_str()?.to_string())],
+            attachment_ids: self.attachment_ids(message),
+        })
+    }
+
+    fn code_block(&self, code: &str, language: &str) -> String {
+        let plain = || format!("<pre class=\"code\"><code>{}</code></pre>", escape_html(code));
+        let Some(syntax) = self.syntaxes.find_syntax_by_token(language).filter(|_| code.len() <= MAX_HIGHLIGHT_BYTES) else {
+            return plain();
+        };
+        let mut generator = ClassedHTMLGenerator::new_with_class_style(syntax, &self.syntaxes, CLASS_STYLE);
+        for line in LinesWithEndings::from(code) {
+            if generator.parse_html_for_line_which_includes_newline(line).is_err() {
+                return plain();
+            }
+        }
+        format!("<pre class=\"code\"><code>{}</code></pre>", generator.finalize())
+    }
+
+    // Markdown to HTML with raw HTML shown as text, unsafe links disabled and
+    // images turned into links, since the page mustn't fetch anything
+    fn markdown(&self, out: &mut String, text: &str) {
+        let mut code: Option<(String, String)> = None;
+        let options = Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS;
+        let events = Parser::new_ext(text, options).filter_map(|event| match event {
+            Event::Start(Tag::CodeBlock(kind)) => {
+                let language = match kind {
+                    CodeBlockKind::Fenced(info) => info.split_whitespace().next().unwrap_or("").to_string(),
+                    CodeBlockKind::Indented => String::new(),
+                };
+                code = Some((language, String::new()));
+                None
+            }
+            Event::Text(text) if code.is_some() => {
+                if let Some((_, body)) = code.as_mut() {
+                    body.push_str(&text);
+                }
+                None
+            }
+            Event::End(TagEnd::CodeBlock) => {
+                let (language, body) = code.take()?;
+                Some(Event::Html(self.code_block(&body, &language).into()))
+            }
+            Event::Html(html) | Event::InlineHtml(html) => Some(Event::Text(html)),
+            Event::Start(Tag::Link { link_type, dest_url, title, id })
+            | Event::Start(Tag::Image { link_type, dest_url, title, id }) => {
+                Some(Event::Start(Tag::Link { link_type, dest_url: safe_url(dest_url), title, id }))
+            }
+            Event::End(TagEnd::Image) => Some(Event::End(TagEnd::Link)),
+            event => Some(event),
+        });
+        pulldown_cmark::html::push_html(out, events);
+    }
+
+    fn image(&mut self, out: &mut String, media_type: &str, data: &str) {
+        let valid = EMBEDDABLE_IMAGES.contains(&media_type)
+            && data.bytes().all(|b| b.is_ascii_alphanumeric() || matches!(b, b'+' | b'/' | b'='));
+        if valid && (data.len() as u64) / 4 * 3 <= self.max_attachment_bytes {
+            out.push_str(&format!("<img alt=\"\" src=\"data:{};base64,{}\">", media_type, data));
+            self.attachments_embedded += 1;
+        } else {
+            out.push_str("<p class=\"skipped\">Image not embedded</p>");
+            self.attachments_skipped += 1;
+        }
+    }
+
+    fn attachment(&mut self, out: &mut String, id: &str) {
+        let Some((meta, path)) = attachments::locate_blocking(&self.app, id) else { return };
+        let name = escape_html(&meta.name);
+        let content = (meta.size <= self.max_attachment_bytes).then(|| std::fs::read(&path).ok()).flatten();
+        let Some(content) = content else {
+            out.push_str(&format!("<p class=\"skipped\">Attachment {} ({} bytes) not embedded</p>", name, meta.size));
+            self.attachments_skipped += 1;
+            return;
+        };
+        self.attachments_embedded += 1;
+        let encoded = || base64::engine::general_purpose::STANDARD.encode(&content);
+        if let Some(media_type) = image_type(&content) {
+            out.push_str(&format!("<figure><img alt=\"{}\" src=\"data:{};base64,{}\"><figcaption>{}</figcaption></figure>", name, media_type, encoded(), name));
+        } else if let Ok(text) = std::str::from_utf8(&content) {
+            out.push_str(&format!("<details><summary>Attachment: {}</summary><pre>{}</pre></details>", name, escape_html(text)));
+        } else {
+            out.push_str(&format!("<p><a download=\"{}\" href=\"data:application/octet-stream;base64,{}\">Attachment: {}</a></p>", name, encoded(), name));
+        }
+    }
+
+    // The HTML for one message, or None when nothing in it is shown
+    fn message(&mut self, message: &Message) -> Option<String> {
+        let mut body = String::new();
+        let mut only_tool_results = true;
+        for part in &message.parts {
+            only_tool_results &= matches!(part, Part::ToolResult { .. });
+            match part {
+                Part::Text(text) => self.markdown(&mut body, text),
+                Part::Thinking(text) if self.include_thinking => {
+                    body.push_str(&format!("<details class=\"thinking\"><summary>Thinking</summary><div class=\"plain\">{}</div></details>", escape_html(text)));
+                }
+                Part::ToolUse { name, input } if self.include_tool_calls => {
+                    body.push_str(&format!("<details class=\"tool\"><summary>Tool: {}</summary>{}</details>", escape_html(name), self.code_block(input, "json")));
+                }
+                Part::ToolResult { output, is_error } if self.include_tool_calls => {
+                    let (shown, cut) = truncate_chars(output, MAX_TOOL_OUTPUT_CHARS);
+                    body.push_str(&format!(
+                        "<details class=\"{}\"><summary>{} ({} lines)</summary><pre>{}{}</pre></details>",
+                        if *is_error { "tool-result error" } else { "tool-result" },
+                        if *is_error { "Error" } else { "Result" },
+                        output.lines().count(),
+                        escape_html(shown),
+                        if cut { "\n…" } else { "" },
+                    ));
+                }
+                Part::Image { media_type, data } => self.image(&mut body, media_type, data),
+                _ => {}
+            }
+        }
+        for id in &message.attachment_ids {
+            self.attachment(&mut body, id);
+        }
+        if body.trim().is_empty() {
+            return None;
+        }
+
+        // Tool results come back from the CLI as user messages
+        let (class, label) = match message.role.as_str() {
+            "user" if only_tool_results && !message.parts.is_empty() => ("tool", "Tool"),
+            "user" => ("user", "User"),
+            _ => ("assistant", "Assistant"),
+        };
+        let time = message.timestamp
+            .map(|millis| format!("<time>{}</time>", escape_html(&self.formatter.format(millis).display)))
+            .unwrap_or_default();
+        Some(format!(
+            "<section class=\"message {}\"><header><span class=\"role\">{}</span>{}</header><div class=\"body\">{}</div></section>\n",
+            class, label, time, body
+        ))
+    }
+}
+
+// What a conversation is rendered from
+enum Source {
+    Session(PathBuf),
+    Messages(Vec<serde_json::Value>),
+}
+
+fn render(renderer: &mut Renderer, title: &str, theme: &str, source: Source, temp: &Path) -> Result<(usize, String), String> {
+    let themes = ThemeSet::load_defaults();
+    let theme = themes.themes.get(theme).ok_or_else(|| format!("Unknown theme: {}", theme))?;
+    let theme_css = css_for_theme_with_class_style(theme, CLASS_STYLE).map_err(|e| e.to_string())?;
+
+    let file = std::fs::File::create(temp).map_err(|e| e.to_string())?;
+    let mut out = BufWriter::new(file);
+    let title = escape_html(title);
+    write!(
+        out,
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<meta http-equiv=\"Content-Security-Policy\" content=\"{}\">\n<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n<title>{}</title>\n<style>{}{}</style>\n</head>\n<body>\n<main>\n<h1>{}</h1>\n<p class=\"meta\">Exported {}</p>\n",
+        CSP, title, PAGE_CSS, theme_css, title, escape_html(&renderer.formatter.format(crate::unix_millis()).display),
+    ).map_err(|e| e.to_string())?;
+
+    // Each message is written as soon as it's rendered, so long
+    // conversations never sit in memory as one string
+    let mut count = 0;
+    let source_name = match source {
+        Source::Session(path) => {
+            let reader = BufReader::new(std::fs::File::open(&path).map_err(|e| e.to_string())?);
+            for line in reader.lines() {
+                let line = line.map_err(|e| e.to_string())?;
+                let Ok(entry) = serde_json::from_str::<serde_json::Value>(&line) else { continue };
+                let Some(message) = renderer.session_message(&entry) else { continue };
+                if let Some(html) = renderer.message(&message) {
+                    out.write_all(html.as_bytes()).map_err(|e| e.to_string())?;
+                    count += 1;
+                }
+            }
+            "session"
+        }
+        Source::Messages(messages) => {
+            for message in &messages {
+                let Some(message) = renderer.saved_message(message) else { continue };
+                if let Some(html) = renderer.message(&message) {
+                    out.write_all(html.as_bytes()).map_err(|e| e.to_string())?;
+                    count += 1;
+                }
+            }
+            "messages"
+        }
+    };
+
+    write!(out, "<footer>{} messages</footer>\n</main>\n</body>\n</html>\n", count).map_err(|e| e.to_string())?;
+    out.flush().map_err(|e| e.to_string())?;
+    Ok((count, source_name.to_string()))
+}
+
+// Write a conversation to a single HTML file that opens anywhere: styles,
+// highlighting and attachments are all inside it. Prefers the CLI's session
+// log, which has tool calls and thinking, over the saved messages.
+#[tauri::command]
+pub async fn export_conversation_html(
+    app: tauri::AppHandle,
+    conversation_id: String,
+    dest_path: String,
+    options: Option<HtmlExportOptions>,
+) -> Result<HtmlExportReport, String> {
+    let options = options.unwrap_or_default();
+    let dest = PathBuf::from(&dest_path);
+    if !dest.is_absolute() {
+        return Err("Destination must be an absolute path".to_string());
+    }
+
+    let conversation = match read_conversation(&app, &conversation_id).await {
+        Some(conversation) => conversation,
+        None => transcripts::read_transcript(&app, &conversation_id).await?
+            .ok_or_else(|| format!("Conversation not found: {}", conversation_id))?,
+    };
+    let title = conversation.get("title").and_then(|t| t.as_str()).unwrap_or("Conversation").to_string();
+    let session_file = match conversation.get("claudeSessionId").and_then(|s| s.as_str()) {
+        Some(session_id) => turn_recovery::find_session_file(session_id).await,
+        None => None,
+    };
+    let source = match session_file {
+        Some(path) => Source::Session(path),
+        None => Source::Messages(conversation.get("messages").and_then(|m| m.as_array()).cloned().unwrap_or_default()),
+    };
+
+    let renderer = Renderer {
+        app: app.clone(),
+        syntaxes: SyntaxSet::load_defaults_newlines(),
+        formatter: TimeFormatter::load(&app).await,
+        attachment_id: Regex::new(r"\b[0-9a-f]{64}\b").expect("valid regex"),
+        include_thinking: options.include_thinking,
+        include_tool_calls: options.include_tool_calls.unwrap_or(true),
+        max_attachment_bytes: options.max_attachment_bytes.unwrap_or(DEFAULT_MAX_ATTACHMENT_BYTES),
+        attachments_embedded: 0,
+        attachments_skipped: 0,
+    };
+    let theme = options.theme.unwrap_or_else(|| DEFAULT_THEME.to_string());
+
+    // Written beside the destination and renamed into place when complete
+    let temp = with_suffix(&dest, ".tmp");
+    let task_temp = temp.clone();
+    let rendered = tokio::task::spawn_blocking(move || {
+        let mut renderer = renderer;
+        render(&mut renderer, &title, &theme, source, &task_temp)
+            .map(|(count, source)| (count, source, renderer.attachments_embedded, renderer.attachments_skipped))
+    }).await.map_err(|e| e.to_string())?;
+
+    let (messages, source, attachments_embedded, attachments_skipped) = match rendered {
+        Ok(rendered) => rendered,
+        Err(e) => {
+            let _ = tokio::fs::remove_file(&temp).await;
+            return Err(e);
+        }
+    };
+    tokio::fs::rename(&temp, &dest).await.map_err(|e| {
+        let _ = std::fs::remove_file(&temp);
+        e.to_string()
+    })?;
+    let bytes_written = tokio::fs::metadata(&dest).await.map(|m| m.len()).unwrap_or(0);
+    Ok(HtmlExportReport {
+        path: dest.to_string_lossy().to_string(),
+        messages,
+        bytes_written,
+        attachments_embedded,
+        attachments_skipped,
+        source,
+    })
+}
@@ -0,0 +1,293 @@
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Mutex;
+
+use crate::env_profiles::looks_secret;
+use crate::{janitor, secrets, storage, unix_millis, write_atomic};
+
+const DEFAULT_TIMEOUT_MS: u64 = 30_000;
+// Response bytes returned inline; the whole body goes to a temp file past this
+const MAX_INLINE_BODY: usize = 1024 * 1024;
+// How much of the body is looked at to decide whether it's binary
+const BINARY_SNIFF_BYTES: usize = 8 * 1024;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct HttpHeader {
+    pub name: String,
+    // Plain values are stored inline...
+    pub value: Option<String>,
+    // ...while anything that looks like a secret lives in the keychain under this reference
+    pub secret_ref: Option<String>,
+}
+
+#[derive(Clone, Deserialize)]
+pub struct HttpRequest {
+    pub method: String,
+    pub url: String,
+    #[serde(default)]
+    pub headers: Vec<HttpHeader>,
+    pub body: Option<String>,
+    pub timeout_ms: Option<u64>,
+    // PEM file with extra root certificates, e.g. for a local dev CA
+    pub ca_bundle_path: Option<String>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct HttpTiming {
+    // Until the status line and headers arrived
+    pub headers_ms: u64,
+    // Reading the body after that
+    pub body_ms: u64,
+    pub total_ms: u64,
+}
+
+#[derive(Clone, Serialize)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub status_text: String,
+    pub headers: Vec<(String, String)>,
+    pub timing: HttpTiming,
+    pub size: u64,
+    pub is_binary: bool,
+    // The body, or its first MAX_INLINE_BODY bytes, as text...
+    pub body: Option<String>,
+    // ...or as raw bytes when it's binary
+    pub body_bytes: Option<Vec<u8>>,
+    pub truncated: bool,
+    // Where the complete body was written when it was too large to return;
+    // the janitor removes it a day later
+    pub spill_path: Option<String>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SavedHttpRequest {
+    pub request_id: String,
+    // Directory of the workspace the request belongs to
+    pub workspace: String,
+    pub name: String,
+    pub method: String,
+    pub url: String,
+    pub headers: Vec<HttpHeader>,
+    pub body: Option<String>,
+    pub timeout_ms: Option<u64>,
+    pub ca_bundle_path: Option<String>,
+    pub updated_at: u64,
+}
+
+// What save_http_request takes; an existing request_id updates that request
+#[derive(Clone, Deserialize)]
+pub struct HttpRequestInput {
+    pub request_id: Option<String>,
+    pub name: String,
+    #[serde(flatten)]
+    pub request: HttpRequest,
+}
+
+static HTTP_REQUESTS_LOCK: Lazy<Arc<Mutex<()>>> = Lazy::new(|| Arc::new(Mutex::new(())));
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+fn get_store_path(app: &tauri::AppHandle) -> PathBuf {
+    storage::data_dir(app).join("http-requests.json")
+}
+
+async fn read_store(app: &tauri::AppHandle) -> Vec<SavedHttpRequest> {
+    match tokio::fs::read_to_string(get_store_path(app)).await {
+        Ok(data) => serde_json::from_str(&data).unwrap_or_default(),
+        Err(_) => Vec::new(),
+    }
+}
+
+async fn write_store(app: &tauri::AppHandle, requests: &[SavedHttpRequest]) -> Result<(), String> {
+    let data = serde_json::to_vec_pretty(requests).map_err(|e| e.to_string())?;
+    write_atomic(&get_store_path(app), &data).await
+}
+
+fn secret_reference(request_id: &str, header: &str) -> String {
+    format!("http:{}:{}", request_id, header.to_lowercase())
+}
+
+fn resolve_headers(headers: &[HttpHeader]) -> Result<Vec<(String, String)>, String> {
+    headers.iter().map(|header| {
+        let value = match (&header.value, &header.secret_ref) {
+            // Only references saved requests made; other keychain entries stay out of reach
+            (_, Some(reference)) if reference.starts_with("http:") => secrets::read_secret(reference)?,
+            (_, Some(reference)) => return Err(format!("Not an HTTP request secret: {}", reference)),
+            (Some(value), None) => value.clone(),
+            (None, None) => String::new(),
+        };
+        Ok((header.name.clone(), value))
+    }).collect()
+}
+
+fn looks_binary(content_type: &str, sample: &[u8]) -> bool {
+    let textual = content_type.starts_with("text/")
+        || ["json", "xml", "javascript", "x-www-form-urlencoded"].iter().any(|t| content_type.contains(t));
+    if textual {
+        return false;
+    }
+    let sample = &sample[..sample.len().min(BINARY_SNIFF_BYTES)];
+    // A multi-byte character cut off at the end of the sample is still text
+    sample.contains(&0) || std::str::from_utf8(sample).is_err_and(|e| e.error_len().is_some())
+}
+
+// Send a request from the backend, so localhost services and private CAs
+// work the way they do from a terminal
+#[tauri::command]
+pub async fn send_http_request(app: tauri::AppHandle, request: HttpRequest) -> Result<HttpResponse, String> {
+    let method = reqwest::Method::from_bytes(request.method.trim().to_uppercase().as_bytes())
+        .map_err(|_| format!("Invalid HTTP method: {}", request.method))?;
+    let timeout = Duration::from_millis(request.timeout_ms.unwrap_or(DEFAULT_TIMEOUT_MS));
+    let mut client = reqwest::Client::builder().timeout(timeout);
+    if let Some(ref path) = request.ca_bundle_path {
+        let pem = tokio::fs::read(path).await.map_err(|e| format!("Failed to read {}: {}", path, e))?;
+        for cert in reqwest::Certificate::from_pem_bundle(&pem).map_err(|e| format!("Invalid CA bundle {}: {}", path, e))? {
+            client = client.add_root_certificate(cert);
+        }
+    }
+    let client = client.build().map_err(|e| e.to_string())?;
+
+    let mut builder = client.request(method, &request.url);
+    for (name, value) in resolve_headers(&request.headers)? {
+        builder = builder.header(name, value);
+    }
+    if let Some(body) = request.body {
+        builder = builder.body(body);
+    }
+
+    let started = Instant::now();
+    let mut response = builder.send().await.map_err(|e| format!("Request failed: {}", e))?;
+    let headers_ms = started.elapsed().as_millis() as u64;
+
+    let status = response.status();
+    let headers: Vec<(String, String)> = response.headers().iter()
+        .map(|(name, value)| (name.to_string(), String::from_utf8_lossy(value.as_bytes()).to_string()))
+        .collect();
+    let content_type = response.headers().get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("")
+        .to_lowercase();
+
+    let mut inline = Vec::new();
+    let mut size = 0u64;
+    let mut spill: Option<(PathBuf, tokio::fs::File)> = None;
+    while let Some(chunk) = response.chunk().await.map_err(|e| format!("Failed to read response: {}", e))? {
+        size += chunk.len() as u64;
+        if spill.is_none() && inline.len() + chunk.len() > MAX_INLINE_BODY {
+            let name = format!("http-response-{}-{}", unix_millis(), NEXT_ID.fetch_add(1, Ordering::Relaxed));
+            let path = storage::temp_dir(&app)?.join(name);
+            let mut file = tokio::fs::File::create(&path).await.map_err(|e| e.to_string())?;
+            janitor::register_temp_file(&app, &path).await;
+            file.write_all(&inline).await.map_err(|e| e.to_string())?;
+            spill = Some((path, file));
+        }
+        match spill {
+            Some((_, ref mut file)) => {
+                file.write_all(&chunk).await.map_err(|e| e.to_string())?;
+                let room = MAX_INLINE_BODY.saturating_sub(inline.len());
+                inline.extend_from_slice(&chunk[..room.min(chunk.len())]);
+            }
+            None => inline.extend_from_slice(&chunk),
+        }
+    }
+    let spill_path = match spill {
+        Some((path, mut file)) => {
+            file.flush().await.map_err(|e| e.to_string())?;
+            Some(path.to_string_lossy().to_string())
+        }
+        None => None,
+    };
+    let total_ms = started.elapsed().as_millis() as u64;
+
+    let is_binary = looks_binary(&content_type, &inline);
+    let (body, body_bytes) = if is_binary {
+        (None, Some(inline))
+    } else {
+        (Some(String::from_utf8_lossy(&inline).to_string()), None)
+    };
+    Ok(HttpResponse {
+        status: status.as_u16(),
+        status_text: status.canonical_reason().unwrap_or("").to_string(),
+        headers,
+        timing: HttpTiming { headers_ms, body_ms: total_ms - headers_ms, total_ms },
+        size,
+        is_binary,
+        body,
+        body_bytes,
+        truncated: spill_path.is_some(),
+        spill_path,
+    })
+}
+
+// Save a request for a workspace. Header values that look like secrets go
+// to the keychain and are kept as references.
+#[tauri::command]
+pub async fn save_http_request(
+    app: tauri::AppHandle,
+    workspace: String,
+    request: HttpRequestInput,
+) -> Result<SavedHttpRequest, String> {
+    if request.name.trim().is_empty() {
+        return Err("Request name is required".to_string());
+    }
+    reqwest::Method::from_bytes(request.request.method.trim().to_uppercase().as_bytes())
+        .map_err(|_| format!("Invalid HTTP method: {}", request.request.method))?;
+
+    let _guard = HTTP_REQUESTS_LOCK.lock().await;
+    let mut saved = read_store(&app).await;
+    let request_id = request.request_id.clone()
+        .unwrap_or_else(|| format!("{}-{}", unix_millis(), NEXT_ID.fetch_add(1, Ordering::Relaxed)));
+
+    let mut headers = Vec::new();
+    for header in request.request.headers {
+        let normalized = header.name.to_uppercase().replace('-', "_");
+        match header.value {
+            Some(value) if looks_secret(&normalized, &value) => {
+                let reference = secret_reference(&request_id, &header.name);
+                secrets::store_secret(&reference, &value)?;
+                headers.push(HttpHeader { name: header.name, value: None, secret_ref: Some(reference) });
+            }
+            value => headers.push(HttpHeader { name: header.name, value, secret_ref: header.secret_ref }),
+        }
+    }
+
+    // Secrets of headers that were dropped or are no longer secret
+    let existing = saved.iter().position(|r| r.request_id == request_id);
+    if let Some(index) = existing {
+        for old in &saved[index].headers {
+            if let Some(ref reference) = old.secret_ref {
+                if !headers.iter().any(|h| h.secret_ref.as_ref() == Some(reference)) {
+                    let _ = secrets::delete_secret(reference);
+                }
+            }
+        }
+    }
+
+    let entry = SavedHttpRequest {
+        request_id,
+        workspace,
+        name: request.name,
+        method: request.request.method.trim().to_uppercase(),
+        url: request.request.url,
+        headers,
+        body: request.request.body,
+        timeout_ms: request.request.timeout_ms,
+        ca_bundle_path: request.request.ca_bundle_path,
+        updated_at: unix_millis(),
+    };
+    match existing {
+        Some(index) => saved[index] = entry.clone(),
+        None => saved.push(entry.clone()),
+    }
+    write_store(&app, &saved).await?;
+    Ok(entry)
+}
+
+#[tauri::command]
+pub async fn list_http_requests(app: tauri::AppHandle, workspace: String) -> Result<Vec<SavedHttpRequest>, String> {
+    Ok(read_store(&app).await.into_iter().filter(|r| r.workspace == workspace).collect())
+}
@@ -0,0 +1,211 @@
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use serde::Serialize;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+
+use crate::{git, settings};
+
+// Per-directory exclude file, read like .gitignore but only by us
+pub(crate) const IGNORE_FILE_NAME: &str = ".claudeignore";
+
+// Where a list of rules came from, in the order they take precedence
+#[derive(Clone, Serialize)]
+pub struct IgnoreSource {
+    // A file path, or "settings" for the global exclude list
+    pub source: String,
+    pub rules: Vec<String>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct IgnoreMatch {
+    pub path: String,
+    pub ignored: bool,
+    // The source and rule that decided it; None when nothing matched
+    pub source: Option<String>,
+    pub rule: Option<String>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct EffectiveIgnores {
+    pub sources: Vec<IgnoreSource>,
+    pub matched: Option<IgnoreMatch>,
+}
+
+pub(crate) async fn global_excludes(app: &tauri::AppHandle) -> Vec<String> {
+    settings::load(app).await.map(|s| s.global_excludes).unwrap_or_default()
+}
+
+fn build_global(root: &Path, patterns: &[String]) -> Gitignore {
+    let mut builder = GitignoreBuilder::new(root);
+    for pattern in patterns {
+        let _ = builder.add_line(None, pattern);
+    }
+    builder.build().unwrap_or_else(|_| Gitignore::empty())
+}
+
+fn build_file(dir: &Path, name: &str) -> Option<Gitignore> {
+    let path = dir.join(name);
+    if !path.is_file() {
+        return None;
+    }
+    let mut builder = GitignoreBuilder::new(dir);
+    builder.add(path);
+    builder.build().ok()
+}
+
+// Walk `root` honouring .gitignore, .claudeignore at any depth and the
+// global exclude list
+pub(crate) fn walker(root: &Path, global: &[String]) -> ignore::Walk {
+    let global = build_global(root, global);
+    let mut builder = ignore::WalkBuilder::new(root);
+    builder.add_custom_ignore_filename(IGNORE_FILE_NAME);
+    builder.filter_entry(move |entry| {
+        let is_dir = entry.file_type().is_some_and(|t| t.is_dir());
+        !global.matched(entry.path(), is_dir).is_ignore()
+    });
+    builder.build()
+}
+
+// Rules at the root of the workspace, for paths that turn up after a walk.
+// Earlier layers win, the same way a .claudeignore overrides .gitignore.
+pub(crate) struct RootMatcher {
+    layers: Vec<Gitignore>,
+}
+
+impl RootMatcher {
+    pub(crate) fn new(root: &Path, global: &[String]) -> Self {
+        let mut layers: Vec<Gitignore> = [IGNORE_FILE_NAME, ".gitignore"].iter()
+            .filter_map(|name| build_file(root, name))
+            .collect();
+        layers.push(build_global(root, global));
+        RootMatcher { layers }
+    }
+
+    pub(crate) fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        for layer in &self.layers {
+            let matched = layer.matched_path_or_any_parents(path, is_dir);
+            if !matched.is_none() {
+                return matched.is_ignore();
+            }
+        }
+        false
+    }
+}
+
+// Changes whenever rules that a cached index wouldn't otherwise notice change
+pub(crate) fn fingerprint(root: &Path, global: &[String]) -> String {
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    global.hash(&mut hasher);
+    std::fs::read(root.join(IGNORE_FILE_NAME)).unwrap_or_default().hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
+
+fn read_rules(path: &Path) -> Vec<String> {
+    std::fs::read_to_string(path)
+        .unwrap_or_default()
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(String::from)
+        .collect()
+}
+
+// Every rule that can apply to `path` (or to the root when there's no path),
+// deepest directory first and the global list (file None) last. .gitignore
+// only counts inside a git repository, as in the walk.
+fn layers(root: &Path, path: Option<&Path>, global: &[String]) -> Vec<(Option<PathBuf>, Gitignore)> {
+    let in_repo = git::find_repo_root(root).is_some();
+    let names: &[&str] = if in_repo { &[IGNORE_FILE_NAME, ".gitignore"] } else { &[IGNORE_FILE_NAME] };
+    let start = path.and_then(Path::parent).unwrap_or(root);
+
+    let mut layers = Vec::new();
+    for dir in start.ancestors().take_while(|dir| dir.starts_with(root)) {
+        for name in names {
+            if let Some(matcher) = build_file(dir, name) {
+                layers.push((Some(dir.join(name)), matcher));
+            }
+        }
+    }
+    layers.push((None, build_global(root, global)));
+    layers
+}
+
+fn source_label(file: &Option<PathBuf>) -> String {
+    match file {
+        Some(file) => file.to_string_lossy().to_string(),
+        None => "settings".to_string(),
+    }
+}
+
+fn explain(root: &Path, path: &Path, global: &[String]) -> IgnoreMatch {
+    let label = path.to_string_lossy().to_string();
+    let is_dir = path.is_dir();
+    for (file, matcher) in layers(root, Some(path), global) {
+        let matched = matcher.matched_path_or_any_parents(path, is_dir);
+        if let Some(glob) = matched.inner() {
+            return IgnoreMatch {
+                path: label,
+                ignored: matched.is_ignore(),
+                source: Some(source_label(&file)),
+                rule: Some(glob.original().to_string()),
+            };
+        }
+    }
+    // The walk skips hidden files and directories without any rule saying so
+    let relative = path.strip_prefix(root).unwrap_or(path);
+    let hidden = relative.components().any(|c| c.as_os_str().to_string_lossy().starts_with('.'));
+    IgnoreMatch {
+        path: label,
+        ignored: hidden,
+        source: hidden.then(|| "hidden".to_string()),
+        rule: None,
+    }
+}
+
+// List the ignore rules in effect for a workspace. With `path`, also say
+// whether that file is excluded and which rule from which file decided it.
+#[tauri::command]
+pub async fn get_effective_ignores(
+    app: tauri::AppHandle,
+    workspace_root: String,
+    path: Option<String>,
+) -> Result<EffectiveIgnores, String> {
+    let root = std::fs::canonicalize(&workspace_root)
+        .map_err(|e| format!("Invalid workspace root {}: {}", workspace_root, e))?;
+    let path = match path {
+        Some(path) => {
+            let full = root.join(&path);
+            let full = std::fs::canonicalize(&full).unwrap_or(full);
+            if !full.starts_with(&root) {
+                return Err(format!("{} is outside {}", path, workspace_root));
+            }
+            Some(full)
+        }
+        None => None,
+    };
+    let global = global_excludes(&app).await;
+
+    let sources = layers(&root, path.as_deref(), &global).into_iter()
+        .map(|(file, _)| IgnoreSource {
+            source: source_label(&file),
+            rules: match file {
+                Some(ref file) => read_rules(file),
+                None => global.clone(),
+            },
+        })
+        .collect();
+    let matched = path.map(|path| explain(&root, &path, &global));
+    Ok(EffectiveIgnores { sources, matched })
+}
+
+// Gitignore-style patterns excluded from every workspace, on top of each
+// workspace's own .gitignore and .claudeignore
+#[tauri::command]
+pub async fn set_global_excludes(app: tauri::AppHandle, patterns: Vec<String>) -> Result<Vec<String>, String> {
+    let mut builder = GitignoreBuilder::new("/");
+    for pattern in &patterns {
+        builder.add_line(None, pattern).map_err(|e| format!("Invalid pattern {}: {}", pattern, e))?;
+    }
+    settings::update(&app, |settings| settings.global_excludes = patterns.clone()).await?;
+    Ok(patterns)
+}
@@ -0,0 +1,259 @@
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::sync::RwLock;
+use tauri::Emitter;
+
+use crate::settings;
+
+// Above this a single event can stall the webview while it's parsed
+const DEFAULT_MAX_EVENT_BYTES: usize = 256 * 1024;
+// Smaller limits would turn ordinary output into a flood of events
+const MIN_EVENT_BYTES: usize = 1024;
+
+// What happens to text that doesn't fit in one event
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Oversize {
+    // Sent as several events carrying a `part`
+    Split,
+    // Cut short with a marker saying how much was dropped
+    Truncate,
+}
+
+// The limit for events whose name starts with `prefix`
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ChannelLimit {
+    pub prefix: String,
+    pub max_bytes: usize,
+    pub oversize: Oversize,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IpcLimitSettings {
+    // For events no channel matches; those are always split
+    pub default_max_event_bytes: usize,
+    // Checked in order, first match wins
+    pub channels: Vec<ChannelLimit>,
+}
+
+impl Default for IpcLimitSettings {
+    fn default() -> Self {
+        let channel = |prefix: &str, oversize| ChannelLimit {
+            prefix: prefix.to_string(),
+            max_bytes: DEFAULT_MAX_EVENT_BYTES,
+            oversize,
+        };
+        IpcLimitSettings {
+            default_max_event_bytes: DEFAULT_MAX_EVENT_BYTES,
+            channels: vec![
+                channel("claude-response-", Oversize::Split),
+                channel("shell-output-", Oversize::Split),
+                channel("service-output-", Oversize::Split),
+                channel("tracked-output-", Oversize::Split),
+                // The text already streamed in claude-response; this is just the notice
+                channel("claude-message-complete-", Oversize::Truncate),
+                // A websocket message is shown as one entry
+                channel("ws-message-", Oversize::Truncate),
+            ],
+        }
+    }
+}
+
+impl IpcLimitSettings {
+    fn limit_for(&self, event: &str) -> (usize, Oversize) {
+        self.channels.iter()
+            .find(|channel| event.starts_with(&channel.prefix))
+            .map(|channel| (channel.max_bytes, channel.oversize))
+            .unwrap_or((self.default_max_event_bytes, Oversize::Split))
+    }
+}
+
+// A copy of the saved limits, readable from the synchronous output readers
+static ACTIVE: Lazy<RwLock<IpcLimitSettings>> = Lazy::new(|| RwLock::new(IpcLimitSettings::default()));
+
+// Called whenever settings are loaded or changed
+pub(crate) fn apply(limits: &IpcLimitSettings) {
+    *ACTIVE.write().unwrap() = limits.clone();
+}
+
+fn limit_for(event: &str) -> (usize, Oversize) {
+    ACTIVE.read().unwrap().limit_for(event)
+}
+
+// Present only on events that are pieces of one larger chunk. Pieces arrive
+// in order with seq counting up from 0; the UI joins them until is_final.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct EventPart {
+    pub seq: u32,
+    pub is_final: bool,
+}
+
+// Largest char boundary at or below `index`
+fn floor_boundary(text: &str, index: usize) -> usize {
+    let mut index = index.min(text.len());
+    while !text.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
+}
+
+// Pieces of at most max_bytes each, never splitting a character
+pub(crate) fn split_text(text: &str, max_bytes: usize) -> Vec<&str> {
+    let mut pieces = Vec::new();
+    let mut rest = text;
+    while rest.len() > max_bytes {
+        // A max below one character's width still has to make progress
+        let mut end = floor_boundary(rest, max_bytes);
+        if end == 0 {
+            end = rest.chars().next().map(char::len_utf8).unwrap_or(rest.len());
+        }
+        let (piece, tail) = rest.split_at(end);
+        pieces.push(piece);
+        rest = tail;
+    }
+    // The loop can consume everything when the last character was wider than max_bytes
+    if !rest.is_empty() || pieces.is_empty() {
+        pieces.push(rest);
+    }
+    pieces
+}
+
+// The text cut to fit max_bytes, marker included
+pub(crate) fn truncate_text(text: &str, max_bytes: usize) -> String {
+    if text.len() <= max_bytes {
+        return text.to_string();
+    }
+    // Sized for the largest count the marker could show
+    let marker_room = format!("\n[... {} more bytes truncated]", text.len()).len();
+    let end = floor_boundary(text, max_bytes.saturating_sub(marker_room));
+    format!("{}\n[... {} more bytes truncated]", &text[..end], text.len() - end)
+}
+
+// The text of each event to send, and its part when there is more than one
+fn plan(text: String, max_bytes: usize, oversize: Oversize) -> Vec<(String, Option<EventPart>)> {
+    if text.len() <= max_bytes {
+        return vec![(text, None)];
+    }
+    match oversize {
+        Oversize::Truncate => vec![(truncate_text(&text, max_bytes), None)],
+        Oversize::Split => {
+            let pieces = split_text(&text, max_bytes);
+            let last = pieces.len() - 1;
+            pieces.into_iter().enumerate()
+                .map(|(seq, piece)| (piece.to_string(), Some(EventPart { seq: seq as u32, is_final: seq == last })))
+                .collect()
+        }
+    }
+}
+
+// Emit a text-carrying event, splitting or truncating the text according to
+// the channel's limit. `make` builds the payload for each piece; it gets
+// None as the part when everything fit in one event.
+pub(crate) fn emit_text<S, F>(app: &tauri::AppHandle, event: &str, text: String, make: F)
+where
+    S: Serialize + Clone,
+    F: Fn(String, Option<EventPart>) -> S,
+{
+    let (max_bytes, oversize) = limit_for(event);
+    for (piece, part) in plan(text, max_bytes, oversize) {
+        let _ = app.emit(event, make(piece, part));
+    }
+}
+
+// Limit for text that only makes sense whole, like a status line
+pub(crate) fn limit_status_text(text: &str) -> String {
+    truncate_text(text, ACTIVE.read().unwrap().default_max_event_bytes)
+}
+
+#[tauri::command]
+pub async fn set_ipc_limits(app: tauri::AppHandle, limits: IpcLimitSettings) -> Result<(), String> {
+    let too_small = std::iter::once(limits.default_max_event_bytes)
+        .chain(limits.channels.iter().map(|channel| channel.max_bytes))
+        .any(|max| max < MIN_EVENT_BYTES);
+    if too_small {
+        return Err(format!("Event limits must be at least {} bytes", MIN_EVENT_BYTES));
+    }
+    settings::update(&app, |settings| settings.ipc_limits = limits).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TWENTY_MB: usize = 20 * 1024 * 1024;
+
+    fn reassemble(events: &[(String, Option<EventPart>)]) -> String {
+        let mut text = String::new();
+        for (index, (piece, part)) in events.iter().enumerate() {
+            let part = part.as_ref().expect("every piece of a split payload has a part");
+            assert_eq!(part.seq as usize, index);
+            assert_eq!(part.is_final, index == events.len() - 1);
+            text.push_str(piece);
+        }
+        text
+    }
+
+    #[test]
+    fn splits_a_20mb_payload_into_ordered_pieces() {
+        let text: String = (0..TWENTY_MB).map(|i| (b'a' + (i % 26) as u8) as char).collect();
+        let events = plan(text.clone(), DEFAULT_MAX_EVENT_BYTES, Oversize::Split);
+        assert_eq!(events.len(), TWENTY_MB.div_ceil(DEFAULT_MAX_EVENT_BYTES));
+        assert!(events.iter().all(|(piece, _)| piece.len() <= DEFAULT_MAX_EVENT_BYTES));
+        assert_eq!(reassemble(&events), text);
+    }
+
+    #[test]
+    fn small_payloads_are_sent_whole() {
+        let events = plan("hello".to_string(), DEFAULT_MAX_EVENT_BYTES, Oversize::Split);
+        assert_eq!(events, vec![("hello".to_string(), None)]);
+    }
+
+    #[test]
+    fn never_splits_a_character() {
+        // "€" is three bytes and straddles the 4-byte boundary
+        assert_eq!(split_text("ab€cd", 4), vec!["ab", "€c", "d"]);
+
+        let text = "日本語のテキスト🦀".repeat(TWENTY_MB / 40);
+        let events = plan(text.clone(), DEFAULT_MAX_EVENT_BYTES, Oversize::Split);
+        assert!(events.iter().all(|(piece, _)| piece.len() <= DEFAULT_MAX_EVENT_BYTES));
+        assert_eq!(reassemble(&events), text);
+    }
+
+    #[test]
+    fn limits_below_a_character_still_make_progress() {
+        assert_eq!(split_text("🦀🦀", 1), vec!["🦀", "🦀"]);
+    }
+
+    #[test]
+    fn truncates_a_20mb_payload_with_a_marker() {
+        let text = "x".repeat(TWENTY_MB);
+        let events = plan(text, DEFAULT_MAX_EVENT_BYTES, Oversize::Truncate);
+        assert_eq!(events.len(), 1);
+        let (truncated, part) = &events[0];
+        assert!(part.is_none());
+        assert!(truncated.len() <= DEFAULT_MAX_EVENT_BYTES);
+
+        let (kept, marker) = truncated.rsplit_once('\n').unwrap();
+        assert!(kept.bytes().all(|b| b == b'x'));
+        assert_eq!(marker, format!("[... {} more bytes truncated]", TWENTY_MB - kept.len()));
+    }
+
+    #[test]
+    fn truncation_keeps_whole_characters() {
+        let text = "é".repeat(1000);
+        let truncated = truncate_text(&text, 101);
+        let (kept, _) = truncated.rsplit_once('\n').unwrap();
+        assert!(kept.chars().all(|c| c == 'é'));
+        assert!(truncated.len() <= 101);
+    }
+
+    #[test]
+    fn channel_limits_match_by_prefix() {
+        let limits = IpcLimitSettings::default();
+        assert_eq!(limits.limit_for("claude-message-complete-abc").1, Oversize::Truncate);
+        assert_eq!(limits.limit_for("shell-output-1").1, Oversize::Split);
+        assert_eq!(limits.limit_for("something-else"), (DEFAULT_MAX_EVENT_BYTES, Oversize::Split));
+    }
+}
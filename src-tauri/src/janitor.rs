@@ -0,0 +1,133 @@
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+use crate::{storage, unix_millis, write_atomic, KILL_SIGNALS, RUNNING_PROCESSES};
+
+const SWEEP_INTERVAL: Duration = Duration::from_secs(10 * 60);
+// Temp files can back a long claude turn, so only clearly abandoned ones go
+const TEMP_FILE_MAX_AGE_MS: u64 = 24 * 60 * 60 * 1000;
+
+#[derive(Clone, Default, Serialize)]
+pub struct CleanupStats {
+    pub ran_at: u64,
+    pub temp_files_removed: usize,
+    pub kill_signals_cleared: usize,
+}
+
+// Temp files we created, with their creation time. Nothing outside this
+// manifest is ever deleted.
+#[derive(Default, Serialize, Deserialize)]
+struct TempManifest {
+    files: HashMap<String, u64>,
+}
+
+// Kill signals with no matching process at the last sweep. A signal is only
+// dropped once it has been stale for two sweeps in a row, so one sent just
+// before its process registers isn't lost.
+static STALE_KILL_SIGNALS: Lazy<Arc<Mutex<HashSet<String>>>> =
+    Lazy::new(|| Arc::new(Mutex::new(HashSet::new())));
+
+static MANIFEST_LOCK: Lazy<Arc<Mutex<()>>> = Lazy::new(|| Arc::new(Mutex::new(())));
+
+static LAST_SWEEP: Lazy<Arc<Mutex<Option<CleanupStats>>>> =
+    Lazy::new(|| Arc::new(Mutex::new(None)));
+
+fn get_manifest_path(app: &tauri::AppHandle) -> PathBuf {
+    storage::data_dir(app).join("temp-manifest.json")
+}
+
+async fn read_manifest(app: &tauri::AppHandle) -> TempManifest {
+    match tokio::fs::read_to_string(get_manifest_path(app)).await {
+        Ok(data) => serde_json::from_str(&data).unwrap_or_default(),
+        Err(_) => TempManifest::default(),
+    }
+}
+
+async fn write_manifest(app: &tauri::AppHandle, manifest: &TempManifest) -> Result<(), String> {
+    let data = serde_json::to_vec_pretty(manifest).map_err(|e| e.to_string())?;
+    write_atomic(&get_manifest_path(app), &data).await
+}
+
+// Record a temp file as ours so the sweeper may remove it later
+pub(crate) async fn register_temp_file(app: &tauri::AppHandle, path: &Path) {
+    let _guard = MANIFEST_LOCK.lock().await;
+    let mut manifest = read_manifest(app).await;
+    manifest.files.insert(path.to_string_lossy().to_string(), unix_millis());
+    let _ = write_manifest(app, &manifest).await;
+}
+
+async fn sweep_temp_files(app: &tauri::AppHandle) -> usize {
+    let _guard = MANIFEST_LOCK.lock().await;
+    let mut manifest = read_manifest(app).await;
+    let now = unix_millis();
+    let mut removed = 0;
+    let mut kept = HashMap::new();
+    for (path, created_at) in manifest.files.drain() {
+        if tokio::fs::metadata(&path).await.is_err() {
+            // Already cleaned up by whoever made it
+            continue;
+        }
+        if now.saturating_sub(created_at) >= TEMP_FILE_MAX_AGE_MS {
+            if tokio::fs::remove_file(&path).await.is_ok() {
+                removed += 1;
+            }
+            continue;
+        }
+        kept.insert(path, created_at);
+    }
+    manifest.files = kept;
+    let _ = write_manifest(app, &manifest).await;
+    removed
+}
+
+async fn clear_stale_kill_signals() -> usize {
+    let running: HashSet<String> = RUNNING_PROCESSES.lock().await.keys().cloned().collect();
+    let mut signals = KILL_SIGNALS.lock().await;
+    let mut stale = STALE_KILL_SIGNALS.lock().await;
+
+    let before = signals.len();
+    signals.retain(|id| running.contains(id) || !stale.contains(id));
+    let cleared = before - signals.len();
+
+    *stale = signals.iter().filter(|id| !running.contains(*id)).cloned().collect();
+    cleared
+}
+
+async fn sweep(app: &tauri::AppHandle) -> CleanupStats {
+    let stats = CleanupStats {
+        ran_at: unix_millis(),
+        temp_files_removed: sweep_temp_files(app).await,
+        kill_signals_cleared: clear_stale_kill_signals().await,
+    };
+    if stats.temp_files_removed + stats.kill_signals_cleared > 0 {
+        eprintln!(
+            "cleanup: removed {} temp file(s), cleared {} stale kill signal(s)",
+            stats.temp_files_removed, stats.kill_signals_cleared
+        );
+    }
+    *LAST_SWEEP.lock().await = Some(stats.clone());
+    stats
+}
+
+// Started from setup; runs for the lifetime of the app
+pub(crate) async fn run_janitor(app: tauri::AppHandle) {
+    loop {
+        sweep(&app).await;
+        tokio::time::sleep(SWEEP_INTERVAL).await;
+    }
+}
+
+#[tauri::command]
+pub async fn run_cleanup_now(app: tauri::AppHandle) -> Result<CleanupStats, String> {
+    Ok(sweep(&app).await)
+}
+
+#[tauri::command]
+pub async fn get_cleanup_stats() -> Result<Option<CleanupStats>, String> {
+    Ok(LAST_SWEEP.lock().await.clone())
+}
@@ -2,30 +2,215 @@ use serde::{Deserialize, Serialize};
 use std::process::Stdio;
 use std::collections::HashMap;
 use std::sync::Arc;
-use tauri::Emitter;
-use tauri::Manager;
-use tokio::io::{AsyncBufReadExt, BufReader};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use tauri::{Emitter, Manager};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWriteExt, BufReader};
 use tokio::process::{Command, Child};
 use tokio::sync::Mutex;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use once_cell::sync::Lazy;
 
+mod accessibility;
+mod ansi;
+mod attachments;
+mod background_tasks;
+mod benchmark;
+mod citations;
+mod claude_config;
+mod claude_errors;
+mod claude_models;
+mod claude_process;
+mod claude_queue;
+mod combined_logs;
+mod conversation_integrations;
+mod cost_limits;
+mod data_watch;
+mod debug_log;
+mod downloads;
+mod endpoints;
+mod env_profiles;
+mod file_drop;
+mod file_index;
+mod file_tail;
+mod files;
+mod followups;
+mod focus;
+mod git;
+mod html_export;
+mod http_requests;
+mod ignore_rules;
+mod ipc_limits;
+mod janitor;
+mod markdown_assets;
+mod merge;
+mod migration;
+mod operations;
+mod orphans;
+mod pinned_files;
+mod port_forward;
+mod port_scan;
+mod postprocess;
+mod redaction;
+mod response_file;
+mod safe_mode;
+mod process_limits;
+mod project_context;
+mod secrets;
+mod service_groups;
+mod service_watch;
+mod session_binding;
+mod settings;
+mod share;
+mod shell_batch;
+mod shell_complete;
+mod shell_config;
+mod shell_diagnosis;
+mod sleep;
+mod speech;
+mod spawn_preview;
+mod storage;
+mod stream_errors;
+mod subscriptions;
+mod templates;
+mod timeline;
+mod time_format;
+mod tool_stats;
+mod tracked_commands;
+mod transcripts;
+mod turn_recovery;
+mod websocket;
+mod workspace_changes;
+
+
+// A running shell command along with what it was started as
+pub struct RunningProcess {
+    child: Child,
+    command: String,
+    working_directory: Option<String>,
+    started_at: u64,
+}
 
 // Global map to track running shell processes
-static RUNNING_PROCESSES: Lazy<Arc<Mutex<HashMap<String, Child>>>> =
+static RUNNING_PROCESSES: Lazy<Arc<Mutex<HashMap<String, RunningProcess>>>> =
     Lazy::new(|| Arc::new(Mutex::new(HashMap::new())));
 
 // Global map to track running services (long-running processes)
-static RUNNING_SERVICES: Lazy<Arc<Mutex<HashMap<String, Child>>>> =
+static RUNNING_SERVICES: Lazy<Arc<Mutex<HashMap<String, RunningService>>>> =
     Lazy::new(|| Arc::new(Mutex::new(HashMap::new())));
 
-#[derive(Clone, Serialize)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct ServiceOutput {
     pub service_id: String,
     pub output: String,
     pub is_stderr: bool,
     pub is_complete: bool,
     pub exit_code: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub part: Option<ipc_limits::EventPart>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct ShellOutputChunk {
+    pub process_id: String,
+    // A complete line in line mode, otherwise whatever was read
+    pub line: String,
+    pub is_stderr: bool,
+    // Counts up across stdout and stderr together, in the order chunks were
+    // read; the pieces of a split chunk share one
+    pub seq: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub part: Option<ipc_limits::EventPart>,
+}
+
+// How piped child output is split into emitted chunks
+#[derive(Clone, Copy, PartialEq)]
+enum OutputMode {
+    // One chunk per complete line (default)
+    Line,
+    // Whatever bytes are available, without waiting for a newline
+    Byte,
+}
+
+impl OutputMode {
+    fn parse(mode: Option<&str>) -> Result<Self, String> {
+        match mode.unwrap_or("line") {
+            "line" => Ok(OutputMode::Line),
+            "byte" => Ok(OutputMode::Byte),
+            other => Err(format!("Unknown output mode: {}", other)),
+        }
+    }
+}
+
+// Read child output until EOF, handing each line or byte chunk to `on_chunk`.
+// In byte mode a UTF-8 sequence split across reads is held back until complete,
+// so progress bars and prompts show up without waiting for a newline. Err if
+// reading failed before EOF (in line mode that includes invalid UTF-8).
+async fn read_output<R, F>(reader: R, mode: OutputMode, mut on_chunk: F) -> std::io::Result<()>
+where
+    R: AsyncRead + Unpin,
+    F: FnMut(String),
+{
+    match mode {
+        OutputMode::Line => {
+            let mut lines = BufReader::new(reader).lines();
+            while let Some(line) = lines.next_line().await? {
+                on_chunk(line);
+            }
+        }
+        OutputMode::Byte => {
+            let mut reader = reader;
+            let mut buf = [0u8; 4096];
+            let mut pending: Vec<u8> = Vec::new();
+            loop {
+                let n = match reader.read(&mut buf).await {
+                    Ok(0) => break,
+                    Ok(n) => n,
+                    Err(e) => {
+                        if !pending.is_empty() {
+                            on_chunk(String::from_utf8_lossy(&pending).to_string());
+                        }
+                        return Err(e);
+                    }
+                };
+                pending.extend_from_slice(&buf[..n]);
+                let complete = match std::str::from_utf8(&pending) {
+                    Ok(_) => pending.len(),
+                    // Invalid bytes won't become valid later, flush them lossily
+                    Err(e) if e.error_len().is_some() => pending.len(),
+                    Err(e) => e.valid_up_to(),
+                };
+                if complete > 0 {
+                    let chunk: Vec<u8> = pending.drain(..complete).collect();
+                    on_chunk(String::from_utf8_lossy(&chunk).to_string());
+                }
+            }
+            if !pending.is_empty() {
+                on_chunk(String::from_utf8_lossy(&pending).to_string());
+            }
+        }
+    }
+    Ok(())
+}
+
+// Sent each time one assistant message within a turn is finished
+#[derive(Clone, Serialize)]
+pub struct ClaudeMessageComplete {
+    pub conversation_id: String,
+    pub message_id: Option<String>,
+    // Text of just this message
+    pub content: String,
+    pub stop_reason: Option<String>,
+}
+
+fn emit_message_complete(app: &tauri::AppHandle, message: Option<ClaudeMessageComplete>) {
+    if let Some(message) = message {
+        let event = format!("claude-message-complete-{}", message.conversation_id);
+        ipc_limits::emit_text(app, &event, message.content.clone(), |content, _| ClaudeMessageComplete {
+            content,
+            ..message.clone()
+        });
+    }
 }
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -36,12 +221,111 @@ pub struct ClaudeResponse {
     pub thinking: Option<String>,
     #[serde(default)]
     pub tokens_used: Option<u64>,
+    // Set on the final event, with cached tokens counted separately
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<TokenUsage>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub part: Option<ipc_limits::EventPart>,
+    // Set on the final event: whether claude sent its `result` message
+    // before the stream ended, false if it crashed or was stopped
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub had_result: Option<bool>,
+}
+
+// Sends a turn's final `is_complete` event exactly once, however the turn
+// ends, so the UI never waits on a completion that won't come
+struct TurnCompletion {
+    app: tauri::AppHandle,
+    conversation_id: String,
+    tokens_used: u64,
+    usage: Option<TokenUsage>,
+    had_result: bool,
+    sent: bool,
+}
+
+impl TurnCompletion {
+    fn new(app: &tauri::AppHandle, conversation_id: &str) -> Self {
+        TurnCompletion {
+            app: app.clone(),
+            conversation_id: conversation_id.to_string(),
+            tokens_used: 0,
+            usage: None,
+            had_result: false,
+            sent: false,
+        }
+    }
+
+    fn send(&mut self) {
+        if self.sent {
+            return;
+        }
+        self.sent = true;
+        let event = format!("claude-response-{}", self.conversation_id);
+        ipc_limits::emit_text(&self.app, &event, String::new(), |content, part| ClaudeResponse {
+            content,
+            is_complete: true,
+            thinking: None,
+            tokens_used: (self.tokens_used > 0).then_some(self.tokens_used),
+            usage: self.usage.clone(),
+            part,
+            had_result: Some(self.had_result),
+        });
+    }
+}
+
+impl Drop for TurnCompletion {
+    fn drop(&mut self) {
+        self.send();
+    }
+}
+
+#[derive(Clone, Serialize)]
+pub struct ClaudeImage {
+    pub media_type: Option<String>,
+    // Base64 image data for inline images...
+    pub data: Option<String>,
+    // ...or a URL for images referenced by location
+    pub url: Option<String>,
+}
+
+// Read an `image` content block: {"type":"image","source":{"type":"base64"|"url",...}}
+fn parse_image_block(item: &serde_json::Value) -> Option<ClaudeImage> {
+    let source = item.get("source")?;
+    let field = |name: &str| source.get(name).and_then(|v| v.as_str()).map(String::from);
+    let image = ClaudeImage {
+        media_type: field("media_type"),
+        data: field("data"),
+        url: field("url"),
+    };
+    (image.data.is_some() || image.url.is_some()).then_some(image)
 }
 
 #[derive(Clone, Serialize, Deserialize)]
 pub struct ClaudeResult {
     pub response: String,
     pub session_id: Option<String>,
+    // The response before post-processing, when any transform changed it
+    pub raw_response: Option<String>,
+    // Sources from WebFetch/WebSearch calls, for display under the response
+    pub citations: Vec<citations::Citation>,
+    // Set when the response was streamed to a file rather than returned inline
+    pub output_file: Option<response_file::StreamedFile>,
+    // Ids of the integrations the turn ran with, after toggles and overrides
+    pub active_integrations: Vec<String>,
+    // What happened during the turn, in order
+    pub timeline: Vec<timeline::TimelineEntry>,
+    pub timing: ClaudeTiming,
+    // From the CLI's result when it reports one, otherwise estimated from token usage
+    pub cost_usd: f64,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ClaudeTiming {
+    // Until the first line of output, which is where a fresh process pays for MCP server startup
+    pub startup_ms: Option<u64>,
+    pub total_ms: u64,
+    // True when the turn ran on a process kept warm from an earlier turn
+    pub reused_process: bool,
 }
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -50,16 +334,37 @@ pub struct IntegrationConfig {
     pub name: String,
     #[serde(rename = "type")]
     pub integration_type: String,
+    // Aliases read the frontend's own shape, as saved in integrations.json
+    #[serde(alias = "serverCommand")]
     pub server_command: Option<String>,
+    #[serde(alias = "serverArgs")]
     pub server_args: Option<Vec<String>>,
+    // Directory and environment for the MCP server process itself
+    #[serde(alias = "serverCwd")]
+    pub server_cwd: Option<String>,
+    #[serde(alias = "serverEnv")]
+    pub server_env: Option<HashMap<String, String>>,
+    #[serde(alias = "envVariable")]
     pub env_variable: Option<String>,
+    #[serde(alias = "apiKey")]
     pub api_key: Option<String>,
+    // Disabled integrations stay configured but are left out of every spawn
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+fn default_enabled() -> bool {
+    true
 }
 
 #[derive(Serialize)]
 struct McpServerConfig {
     command: String,
     args: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cwd: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    env: Option<HashMap<String, String>>,
 }
 
 #[derive(Serialize)]
@@ -68,20 +373,166 @@ struct McpConfig {
     mcp_servers: HashMap<String, McpServerConfig>,
 }
 
+#[derive(Clone, Serialize)]
+pub struct ToolInfo {
+    pub name: String,
+    // MCP server the tool comes from, or "builtin" for the CLI's own tools
+    pub server: String,
+    pub description: Option<String>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct McpServerStatus {
+    pub name: String,
+    pub status: String,
+}
+
+#[derive(Clone, Serialize)]
+pub struct ClaudeSessionInit {
+    pub session_id: Option<String>,
+    pub model: Option<String>,
+    pub tools: Vec<ToolInfo>,
+    pub mcp_servers: Vec<McpServerStatus>,
+}
+
+type ToolsByConversation = HashMap<String, Vec<ToolInfo>>;
+
+// Tools reported by the most recent session init, per conversation
+static AVAILABLE_TOOLS: Lazy<Arc<Mutex<ToolsByConversation>>> =
+    Lazy::new(|| Arc::new(Mutex::new(HashMap::new())));
+
+// Parse the `system`/`init` stream message the CLI sends when a session starts
+fn parse_session_init(json: &serde_json::Value) -> ClaudeSessionInit {
+    let tools = json.get("tools")
+        .and_then(|t| t.as_array())
+        .map(|tools| {
+            tools.iter().filter_map(|tool| {
+                // Tools are usually bare names, but accept objects too
+                let name = tool.as_str()
+                    .or_else(|| tool.get("name").and_then(|n| n.as_str()))?;
+                // MCP tools are named mcp__<server>__<tool>
+                let server = name.strip_prefix("mcp__")
+                    .and_then(|rest| rest.split_once("__"))
+                    .map(|(server, _)| server.to_string())
+                    .unwrap_or_else(|| "builtin".to_string());
+                Some(ToolInfo {
+                    name: name.to_string(),
+                    server,
+                    description: tool.get("description").and_then(|d| d.as_str()).map(String::from),
+                })
+            }).collect()
+        })
+        .unwrap_or_default();
+
+    let mcp_servers = json.get("mcp_servers")
+        .and_then(|s| s.as_array())
+        .map(|servers| {
+            servers.iter().filter_map(|server| {
+                Some(McpServerStatus {
+                    name: server.get("name")?.as_str()?.to_string(),
+                    status: server.get("status").and_then(|s| s.as_str()).unwrap_or("unknown").to_string(),
+                })
+            }).collect()
+        })
+        .unwrap_or_default();
+
+    ClaudeSessionInit {
+        session_id: json.get("session_id").and_then(|s| s.as_str()).map(String::from),
+        model: json.get("model").and_then(|m| m.as_str()).map(String::from),
+        tools,
+        mcp_servers,
+    }
+}
+
 fn get_data_path(app: &tauri::AppHandle) -> Result<PathBuf, String> {
-    let app_data = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    let app_data = storage::data_dir(app);
     Ok(app_data.join("data.json"))
 }
 
+// A conversation as the frontend last saved it in data.json
+pub(crate) async fn read_conversation(app: &tauri::AppHandle, conversation_id: &str) -> Option<serde_json::Value> {
+    let data = tokio::fs::read_to_string(get_data_path(app).ok()?).await.ok()?;
+    let mut data: serde_json::Value = serde_json::from_str(&data).ok()?;
+    // zustand's persist middleware wraps everything in {"state": ..., "version": n}
+    let state = if data.get("state").is_some() { data["state"].take() } else { data };
+    state.get("conversations")?.as_array()?
+        .iter()
+        .find(|c| c.get("id").and_then(|i| i.as_str()) == Some(conversation_id))
+        .cloned()
+}
+
+// A conversation's working directory, as the frontend last saved it
+pub(crate) async fn conversation_working_directory(app: &tauri::AppHandle, conversation_id: &str) -> Option<String> {
+    read_conversation(app, conversation_id).await?
+        .get("workingDirectory")?
+        .as_str()
+        .filter(|dir| !dir.is_empty())
+        .map(String::from)
+}
+
+// Where the frontend's data file lives on this machine, whether or not it
+// has been written yet
+#[tauri::command]
+async fn get_data_file_path(app: tauri::AppHandle) -> Result<String, String> {
+    let path = get_data_path(&app)?;
+    let path = std::path::absolute(&path).map_err(|e| e.to_string())?;
+    Ok(path.to_string_lossy().to_string())
+}
+
+// Show a file or folder in the system file manager, selected in its parent.
+// A path that doesn't exist yet falls back to the nearest folder that does.
+#[tauri::command]
+async fn reveal_in_file_manager(path: String) -> Result<(), String> {
+    let mut target = PathBuf::from(&path);
+    while !target.exists() {
+        target = target.parent()
+            .map(|parent| parent.to_path_buf())
+            .ok_or_else(|| format!("Path not found: {}", path))?;
+    }
+    tauri_plugin_opener::reveal_item_in_dir(&target).map_err(|e| e.to_string())
+}
+
+// Milliseconds since the unix epoch
+pub(crate) fn unix_millis() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+// `data.json` + `.bak` -> `data.json.bak`
+fn with_suffix(path: &std::path::Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+// Write to a sibling temp file and rename it into place, so readers never
+// see a half-written file
+async fn write_atomic(path: &std::path::Path, data: &[u8]) -> Result<(), String> {
+    let temp = with_suffix(path, ".tmp");
+    tokio::fs::write(&temp, data).await.map_err(|e| e.to_string())?;
+    tokio::fs::rename(&temp, path).await.map_err(|e| {
+        let _ = std::fs::remove_file(&temp);
+        e.to_string()
+    })
+}
+
 #[derive(Clone, Serialize, Deserialize)]
 pub struct DirEntry {
     pub name: String,
     pub path: String,
     pub is_dir: bool,
+    // Unix millis; None where the platform doesn't report it
+    #[serde(default)]
+    pub modified: Option<u64>,
+    #[serde(default)]
+    pub modified_display: Option<time_format::FormattedTime>,
 }
 
 #[tauri::command]
-async fn list_directory(path: String) -> Result<Vec<DirEntry>, String> {
+async fn list_directory(app: tauri::AppHandle, path: String) -> Result<Vec<DirEntry>, String> {
+    let formatter = time_format::TimeFormatter::load(&app).await;
     let mut entries = Vec::new();
     let mut read_dir = tokio::fs::read_dir(&path).await.map_err(|e| e.to_string())?;
 
@@ -92,10 +543,15 @@ async fn list_directory(path: String) -> Result<Vec<DirEntry>, String> {
             continue;
         }
         let metadata = entry.metadata().await.map_err(|e| e.to_string())?;
+        let modified = metadata.modified().ok()
+            .and_then(|time| time.duration_since(std::time::UNIX_EPOCH).ok())
+            .map(|d| d.as_millis() as u64);
         entries.push(DirEntry {
             name,
             path: entry.path().to_string_lossy().to_string(),
             is_dir: metadata.is_dir(),
+            modified,
+            modified_display: modified.map(|millis| formatter.format(millis)),
         });
     }
 
@@ -127,10 +583,63 @@ async fn save_data(app: tauri::AppHandle, data: String) -> Result<(), String> {
         tokio::fs::create_dir_all(parent).await.map_err(|e| e.to_string())?;
     }
 
-    tokio::fs::write(&path, data).await.map_err(|e| e.to_string())?;
+    // Keep the last good copy around as a backup before replacing it
+    if let Ok(existing) = tokio::fs::read_to_string(&path).await {
+        if serde_json::from_str::<serde_json::Value>(&existing).is_ok() {
+            let _ = tokio::fs::copy(&path, with_suffix(&path, ".bak")).await;
+        }
+    }
+
+    write_atomic(&path, data.as_bytes()).await?;
+    data_watch::remember(&data).await;
     Ok(())
 }
 
+#[derive(Clone, Serialize)]
+pub struct RepairResult {
+    pub was_corrupt: bool,
+    pub recovered: bool,
+    pub from_backup: bool,
+    // Where the unreadable file was moved, if it was
+    pub quarantined_path: Option<String>,
+}
+
+#[tauri::command]
+async fn repair_data(app: tauri::AppHandle) -> Result<RepairResult, String> {
+    let path = get_data_path(&app)?;
+    let parses = |text: &str| serde_json::from_str::<serde_json::Value>(text).is_ok();
+
+    let current = match tokio::fs::read(&path).await {
+        Ok(bytes) => Some(String::from_utf8_lossy(&bytes).to_string()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+        Err(e) => return Err(e.to_string()),
+    };
+    if current.as_deref().is_some_and(parses) {
+        return Ok(RepairResult { was_corrupt: false, recovered: true, from_backup: false, quarantined_path: None });
+    }
+
+    // Never delete what's there; move it aside so it can still be inspected
+    let mut quarantined_path = None;
+    if current.is_some() {
+        let secs = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let quarantine = with_suffix(&path, &format!(".corrupt-{}", secs));
+        tokio::fs::rename(&path, &quarantine).await.map_err(|e| e.to_string())?;
+        quarantined_path = Some(quarantine.to_string_lossy().to_string());
+    }
+
+    let backup = with_suffix(&path, ".bak");
+    match tokio::fs::read_to_string(&backup).await {
+        Ok(text) if parses(&text) => {
+            write_atomic(&path, text.as_bytes()).await?;
+            Ok(RepairResult { was_corrupt: current.is_some(), recovered: true, from_backup: true, quarantined_path })
+        }
+        _ => Ok(RepairResult { was_corrupt: current.is_some(), recovered: false, from_backup: false, quarantined_path }),
+    }
+}
+
 #[tauri::command]
 async fn load_data(app: tauri::AppHandle) -> Result<Option<String>, String> {
     let path = get_data_path(&app)?;
@@ -140,6 +649,7 @@ async fn load_data(app: tauri::AppHandle) -> Result<Option<String>, String> {
     }
 
     let data = tokio::fs::read_to_string(&path).await.map_err(|e| e.to_string())?;
+    data_watch::remember(&data).await;
     Ok(Some(data))
 }
 
@@ -148,129 +658,456 @@ fn greet(name: &str) -> String {
     format!("Hello, {}! You've been greeted from Rust!", name)
 }
 
-#[tauri::command]
-async fn send_to_claude(
-    app: tauri::AppHandle,
-    conversation_id: String,
-    message: String,
-    system_prompt: Option<String>,
-    working_directory: Option<String>,
-    integrations: Option<Vec<IntegrationConfig>>,
-    session_id: Option<String>,
-) -> Result<ClaudeResult, String> {
-    let mut cmd = Command::new("claude");
+// Create inline settings JSON to allow all tools
+const CLAUDE_SETTINGS_JSON: &str = r#"{"permissions":{"allow":["Bash(*)","Read(*)","Write(*)","Edit(*)","WebFetch(*)"],"deny":[]}}"#;
 
-    // Resume specific session if provided (for conversation continuity)
-    if let Some(ref sid) = session_id {
-        cmd.arg("--resume").arg(sid);
+// Keeps MCP config paths unique even for spawns in the same conversation
+static NEXT_MCP_CONFIG: AtomicU64 = AtomicU64::new(0);
+
+// A spawn's own MCP config file, removed when dropped so no way out of a
+// turn leaves one behind
+pub struct McpConfigFile(PathBuf);
+
+impl McpConfigFile {
+    // Every spawn gets its own file, so one cleaning up can't pull it from under another
+    async fn create(dir: &Path, config_name: &str, contents: &str) -> Result<Self, String> {
+        let serial = NEXT_MCP_CONFIG.fetch_add(1, Ordering::Relaxed);
+        let file = McpConfigFile(dir.join(format!("mcp-{}-{}-{}.json", config_name, std::process::id(), serial)));
+        tokio::fs::write(&file.0, contents).await
+            .map_err(|e| format!("Failed to write MCP config: {}", e))?;
+        Ok(file)
     }
 
-    if let Some(prompt) = system_prompt {
-        cmd.arg("--system-prompt").arg(prompt);
+    pub(crate) fn path(&self) -> &Path {
+        &self.0
     }
+}
 
-    // Set working directory
-    let work_dir = working_directory.clone();
-    if let Some(ref dir) = work_dir {
-        cmd.current_dir(dir);
+impl Drop for McpConfigFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
     }
+}
 
-    // Handle integrations
-    let mut temp_mcp_config_path: Option<PathBuf> = None;
+// Apply integrations to a claude command: API keys go into the environment and
+// MCP servers into a temp config file, which lasts as long as the returned guard
+async fn apply_integrations(
+    app: &tauri::AppHandle,
+    cmd: &mut Command,
+    config_name: &str,
+    ints: &[IntegrationConfig],
+) -> Result<Option<McpConfigFile>, String> {
     let mut has_api_key_integrations = false;
 
-    if let Some(ref ints) = integrations {
-        // Collect MCP integrations for config file
-        let mut mcp_servers: HashMap<String, McpServerConfig> = HashMap::new();
-
-        for int in ints {
-            match int.integration_type.as_str() {
-                "mcp" => {
-                    if let (Some(cmd_str), Some(args)) = (&int.server_command, &int.server_args) {
-                        mcp_servers.insert(int.id.clone(), McpServerConfig {
-                            command: cmd_str.clone(),
-                            args: args.clone(),
-                        });
-                    }
+    // Collect MCP integrations for config file
+    let mut mcp_servers: HashMap<String, McpServerConfig> = HashMap::new();
+
+    for int in ints.iter().filter(|int| int.enabled) {
+        match int.integration_type.as_str() {
+            "mcp" => {
+                if let (Some(cmd_str), Some(args)) = (&int.server_command, &int.server_args) {
+                    mcp_servers.insert(int.id.clone(), McpServerConfig {
+                        command: cmd_str.clone(),
+                        args: args.clone(),
+                        cwd: int.server_cwd.clone(),
+                        env: int.server_env.clone().filter(|env| !env.is_empty()),
+                    });
                 }
-                "api-key" => {
-                    // Set environment variable for API key integrations
-                    if let (Some(env_var), Some(api_key)) = (&int.env_variable, &int.api_key) {
-                        if !api_key.is_empty() {
-                            cmd.env(env_var, api_key);
-                            has_api_key_integrations = true;
-                        }
+            }
+            "api-key" => {
+                // Set environment variable for API key integrations
+                if let (Some(env_var), Some(api_key)) = (&int.env_variable, &int.api_key) {
+                    if !api_key.is_empty() {
+                        cmd.env(env_var, api_key);
+                        has_api_key_integrations = true;
                     }
                 }
-                _ => {}
             }
+            _ => {}
         }
+    }
+
+    // Write MCP config to temp file
+    // If we have MCP integrations, include them
+    // If we only have API key integrations, pass empty config to override global MCP settings
+    if !mcp_servers.is_empty() || has_api_key_integrations {
+        let mcp_config = McpConfig { mcp_servers };
+        let config_json = serde_json::to_string_pretty(&mcp_config)
+            .map_err(|e| format!("Failed to serialize MCP config: {}", e))?;
+
+        let config = McpConfigFile::create(&storage::temp_dir(app)?, config_name, &config_json).await?;
+        janitor::register_temp_file(app, config.path()).await;
+
+        cmd.arg("--mcp-config").arg(config.path());
+        return Ok(Some(config));
+    }
+
+    Ok(None)
+}
 
-        // Write MCP config to temp file
-        // If we have MCP integrations, include them
-        // If we only have API key integrations, pass empty config to override global MCP settings
-        if !mcp_servers.is_empty() || has_api_key_integrations {
-            let mcp_config = McpConfig { mcp_servers };
-            let config_json = serde_json::to_string_pretty(&mcp_config)
-                .map_err(|e| format!("Failed to serialize MCP config: {}", e))?;
+// Token counts from a `result` message. Cache reads are billed at a
+// fraction of the input price and cache writes at a premium, so they're kept
+// apart from plain input.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct TokenUsage {
+    pub input: u64,
+    pub output: u64,
+    pub cache_read: u64,
+    pub cache_write: u64,
+    // All of the above together
+    pub total: u64,
+}
+
+impl TokenUsage {
+    fn from_result(json: &serde_json::Value) -> Option<Self> {
+        let usage = json.get("usage")?;
+        let count = |name: &str| usage.get(name).and_then(|t| t.as_u64()).unwrap_or(0);
+        let mut tokens = TokenUsage {
+            input: count("input_tokens"),
+            output: count("output_tokens"),
+            cache_read: count("cache_read_input_tokens"),
+            cache_write: count("cache_creation_input_tokens"),
+            total: 0,
+        };
+        tokens.total = tokens.input + tokens.output + tokens.cache_read + tokens.cache_write;
+        (tokens.total > 0).then_some(tokens)
+    }
+}
 
-            // Create temp file in working directory or temp dir
-            let temp_dir = work_dir.as_ref()
-                .map(PathBuf::from)
-                .unwrap_or_else(std::env::temp_dir);
+// Token count from a `result` message - try different possible locations
+fn result_tokens(json: &serde_json::Value) -> u64 {
+    if let Some(usage) = json.get("usage") {
+        if let Some(total) = usage.get("total_tokens").and_then(|t| t.as_u64()) {
+            return total;
+        }
+        // Sum input and output tokens if total not available
+        let input = usage.get("input_tokens").and_then(|t| t.as_u64()).unwrap_or(0);
+        let output = usage.get("output_tokens").and_then(|t| t.as_u64()).unwrap_or(0);
+        if input + output > 0 {
+            return input + output;
+        }
+    }
+    // Also check the stats path for token info
+    json.get("stats").map(|stats| {
+        let input = stats.get("input_tokens").and_then(|t| t.as_u64()).unwrap_or(0);
+        let output = stats.get("output_tokens").and_then(|t| t.as_u64()).unwrap_or(0);
+        input + output
+    }).unwrap_or(0)
+}
 
-            let config_path = temp_dir.join(format!(".claude-quest-mcp-{}.json", conversation_id));
-            tokio::fs::write(&config_path, &config_json).await
-                .map_err(|e| format!("Failed to write MCP config: {}", e))?;
+// Everything that shapes a claude invocation, shared by send_to_claude and preview_spawn
+#[derive(Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ClaudeSpawnParams {
+    pub conversation_id: String,
+    pub message: String,
+    pub system_prompt: Option<String>,
+    pub working_directory: Option<String>,
+    pub integrations: Option<Vec<IntegrationConfig>>,
+    pub session_id: Option<String>,
+    pub profile: Option<String>,
+    pub continue_latest: Option<bool>,
+    pub attachments: Option<Vec<String>>,
+    pub model: Option<String>,
+    // Defaults to bypassPermissions
+    pub permission_mode: Option<String>,
+    // Append the working directory's CLAUDE.md (or similar) to the system prompt
+    pub load_project_context: Option<bool>,
+}
 
-            cmd.arg("--mcp-config").arg(&config_path);
-            temp_mcp_config_path = Some(config_path);
+// The prompt for a turn: the message, any attachments, then `extra_prompt`
+async fn build_prompt(app: &tauri::AppHandle, params: &ClaudeSpawnParams, extra_prompt: &str) -> Result<String, String> {
+    // Attachments are stored separately and only expanded into the prompt here
+    let mut prompt = match params.attachments {
+        Some(ref ids) if !ids.is_empty() => {
+            format!("{}{}", params.message, attachments::inline_attachments(app, ids).await?)
         }
+        _ => params.message.clone(),
+    };
+    prompt.push_str(extra_prompt);
+    Ok(prompt)
+}
+
+pub struct ClaudeCommand {
+    pub cmd: Command,
+    pub work_dir: Option<String>,
+    // Written for this spawn; removed once whoever owns the process drops it
+    pub temp_mcp_config: Option<McpConfigFile>,
+    // Takes prompts as stream-json on stdin rather than one as an argument
+    pub persistent: bool,
+}
+
+// Assemble the claude command without running it
+async fn build_claude_command(
+    app: &tauri::AppHandle,
+    params: &ClaudeSpawnParams,
+    prompt: &str,
+) -> Result<ClaudeCommand, String> {
+    let mut cmd = Command::new("claude");
+
+    if let Some(ref name) = params.profile {
+        cmd.envs(env_profiles::resolve_profile(app, name).await?);
+    }
+
+    // Resume specific session if provided (for conversation continuity),
+    // otherwise optionally pick up the directory's most recent session
+    if let Some(ref sid) = params.session_id {
+        cmd.arg("--resume").arg(sid);
+    } else if params.continue_latest.unwrap_or(false) {
+        cmd.arg("--continue");
+    }
+
+    if let Some(ref prompt) = params.system_prompt {
+        cmd.arg("--system-prompt").arg(prompt);
     }
 
-    // Create inline settings JSON to allow all tools
-    let settings_json = r#"{"permissions":{"allow":["Bash(*)","Read(*)","Write(*)","Edit(*)","WebFetch(*)"],"deny":[]}}"#;
+    if let Some(ref model) = params.model {
+        cmd.arg("--model").arg(model);
+    }
+
+    // Set working directory
+    let work_dir = settings::working_dir_or_default(app, params.working_directory.clone()).await;
+    if let Some(ref dir) = work_dir {
+        cmd.current_dir(dir);
+    }
+    cmd.envs(endpoints::resolve(app, &params.conversation_id, work_dir.as_deref()).await?);
+    if let Some(context) = project_context::for_spawn(params, work_dir.as_deref()).await {
+        cmd.arg("--append-system-prompt").arg(context);
+    }
+
+    // Handle integrations
+    let temp_mcp_config = match params.integrations {
+        Some(ref ints) => apply_integrations(app, &mut cmd, &params.conversation_id, ints).await?,
+        None => None,
+    };
 
     cmd.arg("--print")
        .arg("--output-format").arg("stream-json")
        .arg("--verbose")
-       .arg("--permission-mode").arg("bypassPermissions")
-       .arg("--settings").arg(settings_json)
-       .arg(&message)
-       .stdout(Stdio::piped())
-       .stderr(Stdio::piped());
+       .arg("--permission-mode").arg(params.permission_mode.as_deref().unwrap_or("bypassPermissions"))
+       .arg("--settings").arg(CLAUDE_SETTINGS_JSON);
 
-    let mut child = cmd.spawn().map_err(|e| format!("Failed to spawn claude: {}", e))?;
+    let persistent = claude_process::wants_persistent(params);
+    if persistent {
+        cmd.arg("--input-format").arg("stream-json");
+    } else {
+        // The CLI's own cap covers the whole process, so only one-shot turns get it
+        if let Ok(Some(budget)) = cost_limits::turn_budget(app, &params.conversation_id).await {
+            cmd.arg("--max-budget-usd").arg(format!("{:.2}", budget.remaining()));
+        }
+        cmd.arg(prompt);
+    }
 
-    let stdout = child.stdout.take().ok_or("Failed to capture stdout")?;
-    let stderr = child.stderr.take();
-    let mut reader = BufReader::new(stdout).lines();
-
-    // Spawn a task to read stderr for debugging
-    let stderr_handle = if let Some(stderr) = stderr {
-        Some(tokio::spawn(async move {
-            let mut stderr_reader = BufReader::new(stderr).lines();
-            let mut stderr_output = String::new();
-            while let Ok(Some(line)) = stderr_reader.next_line().await {
-                stderr_output.push_str(&line);
-                stderr_output.push('\n');
-            }
-            stderr_output
-        }))
+    Ok(ClaudeCommand { cmd, work_dir, temp_mcp_config, persistent })
+}
+
+// Never resolves for None
+async fn sleep_until_some(deadline: Option<tokio::time::Instant>) {
+    match deadline {
+        Some(deadline) => tokio::time::sleep_until(deadline).await,
+        None => std::future::pending().await,
+    }
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn send_to_claude(
+    app: tauri::AppHandle,
+    conversation_id: String,
+    message: String,
+    system_prompt: Option<String>,
+    working_directory: Option<String>,
+    integrations: Option<Vec<IntegrationConfig>>,
+    session_id: Option<String>,
+    profile: Option<String>,
+    continue_latest: Option<bool>,
+    attachments: Option<Vec<String>>,
+    stream_to_file: Option<String>,
+    save_response_to: Option<String>,
+    model: Option<String>,
+    permission_mode: Option<String>,
+    debug_log_path: Option<String>,
+    load_project_context: Option<bool>,
+    startup_timeout_ms: Option<u64>,
+) -> Result<ClaudeResult, String> {
+    settings::check_prompt_size(&app, &message, attachments.as_deref()).await?;
+    let dir = settings::working_dir_or_default(&app, working_directory.clone()).await;
+    settings::check_allowed_dir(&app, dir.as_deref()).await?;
+    if let (Some(ref sid), Some(ref wd)) = (&session_id, &working_directory) {
+        session_binding::check_session_dir(&app, &conversation_id, sid, wd).await?;
+    }
+
+    // Held until this function returns, capping how many claude processes run at once
+    let _slot = claude_queue::acquire_slot(&app, &conversation_id).await?;
+    // Fails straight away once the conversation's budget is used up
+    let budget = cost_limits::turn_budget(&app, &conversation_id).await?;
+
+    // Very large responses go straight to disk instead of through events and memory
+    let mut response_file = match stream_to_file {
+        Some(ref path) => Some(response_file::ResponseFile::create(path).await?),
+        None => None,
+    };
+    // A copy of the text appended to disk as it arrives, events still go out
+    let mut saved_response = match save_response_to {
+        Some(ref path) => Some(response_file::SavedResponse::open(path).await?),
+        None => None,
+    };
+    let mut debug_log = match debug_log_path {
+        Some(ref path) => Some(debug_log::DebugLog::open(&app, path, &conversation_id).await?),
+        None => None,
+    };
+
+    let integrations = match integrations {
+        Some(ints) => Some(conversation_integrations::effective(&app, &conversation_id, ints).await),
+        None => None,
+    };
+    let active_integrations = integrations.iter().flatten().map(|int| int.id.clone()).collect();
+
+    let params = ClaudeSpawnParams {
+        conversation_id: conversation_id.clone(),
+        message: message.clone(),
+        system_prompt,
+        working_directory,
+        integrations,
+        session_id: session_id.clone(),
+        profile,
+        continue_latest,
+        attachments,
+        model,
+        permission_mode,
+        load_project_context,
+    };
+    let pinned = pinned_files::inline_pinned(&app, &conversation_id).await?;
+    let prompt = build_prompt(&app, &params, &pinned).await?;
+    let started = std::time::Instant::now();
+
+    // Conversations with MCP servers keep one claude process across turns
+    let reused = if claude_process::wants_persistent(&params) {
+        let dir = settings::working_dir_or_default(&app, params.working_directory.clone()).await;
+        let context = project_context::for_spawn(&params, dir.as_deref()).await;
+        let signature = claude_process::signature(&params, dir.as_deref(), context.as_deref());
+        claude_process::ClaudeProcess::reuse(&conversation_id, &signature, session_id.as_deref()).await
     } else {
         None
     };
+    let reused_process = reused.is_some();
+    let (mut process, work_dir, temp_mcp_config) = match reused {
+        Some(process) => {
+            let dir = process.work_dir();
+            (process, dir, None)
+        }
+        None => {
+            let mut built = build_claude_command(&app, &params, &prompt).await?;
+            built.cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
+            if built.persistent {
+                built.cmd.stdin(Stdio::piped());
+            }
+            let child = match built.cmd.spawn() {
+                Ok(child) => child,
+                Err(e) => {
+                    let err_msg = format!("Failed to spawn claude: {}", e);
+                    claude_errors::record(&conversation_id, &err_msg, None).await;
+                    return Err(err_msg);
+                }
+            };
+            if built.persistent {
+                let context = project_context::for_spawn(&params, built.work_dir.as_deref()).await;
+                let signature = claude_process::signature(&params, built.work_dir.as_deref(), context.as_deref());
+                let process = claude_process::ClaudeProcess::persistent(
+                    &app,
+                    child,
+                    &conversation_id,
+                    signature,
+                    built.work_dir.clone(),
+                    built.temp_mcp_config,
+                )?;
+                (process, built.work_dir, None)
+            } else {
+                (claude_process::ClaudeProcess::one_shot(&app, child, &conversation_id)?, built.work_dir, built.temp_mcp_config)
+            }
+        }
+    };
+    if let Err(e) = process.send_prompt(&prompt).await {
+        process.kill().await;
+        let _ = process.finish().await;
+        return Err(e);
+    }
+
+    let mut operation = operations::register(&conversation_id, "claude", true);
+    let mut cancelled = false;
+
+    turn_recovery::begin_turn(&app, turn_recovery::InFlightTurn {
+        conversation_id: conversation_id.clone(),
+        session_id: session_id.clone(),
+        message: message.clone(),
+        started_at: unix_millis(),
+    }).await;
 
     let mut full_response = String::new();
-    let mut total_tokens: u64 = 0;
+    let mut completion = TurnCompletion::new(&app, &conversation_id);
+    let mut milestones = accessibility::Milestones::new(&app, &conversation_id).await;
+    milestones.turn_started();
     let mut result_session_id: Option<String> = None;
     let mut error_message: Option<String> = None;
+    let mcp_integrations = params.integrations.iter().flatten()
+        .filter(|int| int.integration_type == "mcp")
+        .map(|int| int.id.clone())
+        .collect();
+    let mut tools = tool_stats::ToolTracker::new(&conversation_id, mcp_integrations);
+    let mut cost = cost_limits::TurnCost::new(&conversation_id, budget);
+    let mut budget_exceeded = None;
+    let mut timeline = timeline::TimelineRecorder::new();
+    let mut citations = citations::CitationCollector::default();
+    // The assistant message being streamed; its content can span several lines
+    let mut current_message: Option<ClaudeMessageComplete> = None;
+    // Time until claude produced anything; mostly MCP server startup on a fresh process
+    let mut startup_ms = None;
+    // Separate from any limit on the whole turn: only the wait for the first line
+    let startup_deadline = startup_timeout_ms
+        .map(|ms| tokio::time::Instant::from_std(started + std::time::Duration::from_millis(ms)));
+    let mut startup_timed_out = false;
+    let mut read_error = None;
+
+    loop {
+        let line = tokio::select! {
+            line = process.next_line() => match line {
+                Ok(line) => line,
+                Err(e) => {
+                    let liveness = process.pid().map(stream_errors::Liveness::Pid).unwrap_or(stream_errors::Liveness::Unknown);
+                    stream_errors::StreamSource::new(&conversation_id, "claude", liveness)
+                        .report(&app, "read_failed", e.to_string(), true).await;
+                    // Cleaned up like any other ended turn before the error goes back
+                    process.kill().await;
+                    read_error = Some(e.to_string());
+                    break;
+                }
+            },
+            _ = operation.cancelled() => {
+                process.kill().await;
+                cancelled = true;
+                break;
+            }
+            _ = sleep_until_some(startup_deadline), if startup_ms.is_none() => {
+                process.kill().await;
+                startup_timed_out = true;
+                break;
+            }
+        };
+        let Some(line) = line else { break };
+        startup_ms.get_or_insert(started.elapsed().as_millis() as u64);
+        if let Some(ref mut log) = debug_log {
+            log.stdout(&line).await;
+        }
 
-    while let Some(line) = reader.next_line().await.map_err(|e| e.to_string())? {
         // Parse JSON line
         if let Ok(json) = serde_json::from_str::<serde_json::Value>(&line) {
             let msg_type = json.get("type").and_then(|t| t.as_str()).unwrap_or("");
 
+            // Anything other than more assistant output ends the current message
+            if msg_type != "assistant" {
+                emit_message_complete(&app, current_message.take());
+            }
+
             match msg_type {
                 "error" => {
                     // Capture error messages from the JSON stream
@@ -284,6 +1121,18 @@ async fn send_to_claude(
                     }
                 }
                 "system" => {
+                    if json.get("subtype").and_then(|s| s.as_str()) == Some("init") {
+                        let init = parse_session_init(&json);
+                        // With --continue this is the first place the resolved id shows up
+                        if result_session_id.is_none() {
+                            result_session_id = init.session_id.clone();
+                        }
+                        if let (None, Some(ref sid)) = (&session_id, &init.session_id) {
+                            turn_recovery::set_turn_session(&app, &conversation_id, sid).await;
+                        }
+                        AVAILABLE_TOOLS.lock().await.insert(conversation_id.clone(), init.tools.clone());
+                        let _ = app.emit(&format!("claude-session-init-{}", conversation_id), init);
+                    }
                     // System messages might contain errors too
                     if let Some(msg) = json.get("message").and_then(|m| m.as_str()) {
                         if msg.to_lowercase().contains("error") {
@@ -294,40 +1143,92 @@ async fn send_to_claude(
                 "assistant" => {
                     // Extract text content from assistant message
                     if let Some(message) = json.get("message") {
+                        cost.on_assistant(message);
+                        let message_id = message.get("id").and_then(|i| i.as_str()).map(String::from);
+                        if current_message.as_ref().is_some_and(|m| m.message_id != message_id) {
+                            emit_message_complete(&app, current_message.take());
+                        }
+                        let current = current_message.get_or_insert_with(|| ClaudeMessageComplete {
+                            conversation_id: conversation_id.clone(),
+                            message_id,
+                            content: String::new(),
+                            stop_reason: None,
+                        });
+
                         if let Some(content) = message.get("content").and_then(|c| c.as_array()) {
                             for item in content {
                                 if let Some(item_type) = item.get("type").and_then(|t| t.as_str()) {
                                     match item_type {
                                         "text" => {
                                             if let Some(text) = item.get("text").and_then(|t| t.as_str()) {
+                                                timeline.on_text();
+                                                if let Some(ref mut saved) = saved_response {
+                                                    if let Err(e) = saved.append(text).await {
+                                                        error_message.get_or_insert(format!("Failed to save response: {}", e));
+                                                    }
+                                                }
+                                                if let Some(ref mut file) = response_file {
+                                                    if let Err(e) = file.write(&app, &conversation_id, text).await {
+                                                        error_message.get_or_insert(format!("Failed to write response file: {}", e));
+                                                    }
+                                                    continue;
+                                                }
                                                 full_response.push_str(text);
-                                                let _ = app.emit(&format!("claude-response-{}", conversation_id), ClaudeResponse {
-                                                    content: text.to_string(),
+                                                current.content.push_str(text);
+                                                if milestones.replaces_stream() {
+                                                    continue;
+                                                }
+                                                ipc_limits::emit_text(&app, &format!("claude-response-{}", conversation_id), text.to_string(), |content, part| ClaudeResponse {
+                                                    content,
                                                     is_complete: false,
                                                     thinking: None,
                                                     tokens_used: None,
+                                                    usage: None,
+                                                    part,
+                                                    had_result: None,
                                                 });
                                             }
                                         }
                                         "thinking" => {
+                                            if milestones.replaces_stream() {
+                                                continue;
+                                            }
                                             if let Some(thinking) = item.get("thinking").and_then(|t| t.as_str()) {
                                                 let _ = app.emit(&format!("claude-response-{}", conversation_id), ClaudeResponse {
                                                     content: String::new(),
                                                     is_complete: false,
-                                                    thinking: Some(thinking.to_string()),
+                                                    thinking: Some(ipc_limits::limit_status_text(thinking)),
                                                     tokens_used: None,
+                                                    usage: None,
+                                                    part: None,
+                                                    had_result: None,
                                                 });
                                             }
                                         }
+                                        "image" => {
+                                            if let Some(image) = parse_image_block(item) {
+                                                let _ = app.emit(&format!("claude-image-{}", conversation_id), image);
+                                            }
+                                        }
                                         "tool_use" => {
+                                            tools.on_tool_use(item);
+                                            timeline.on_tool_use(item);
+                                            citations.on_tool_use(item);
                                             // Show tool usage as thinking
                                             let tool_name = item.get("name").and_then(|n| n.as_str()).unwrap_or("tool");
+                                            milestones.tool_used(tool_name);
+                                            if milestones.replaces_stream() {
+                                                continue;
+                                            }
                                             let thinking_msg = format!("Using {}...", tool_name);
                                             let _ = app.emit(&format!("claude-response-{}", conversation_id), ClaudeResponse {
                                                 content: String::new(),
                                                 is_complete: false,
-                                                thinking: Some(thinking_msg),
+                                                thinking: Some(ipc_limits::limit_status_text(&thinking_msg)),
                                                 tokens_used: None,
+                                                usage: None,
+                                                part: None,
+                                                had_result: None,
                                             });
                                         }
                                         _ => {}
@@ -335,6 +1236,23 @@ async fn send_to_claude(
                                 }
                             }
                         }
+
+                        if let Some(stop_reason) = message.get("stop_reason").and_then(|r| r.as_str()) {
+                            current.stop_reason = Some(stop_reason.to_string());
+                            emit_message_complete(&app, current_message.take());
+                        }
+                    }
+                }
+                "user" => {
+                    // Tool results come back to claude as user messages
+                    if let Some(content) = json.get("message").and_then(|m| m.get("content")).and_then(|c| c.as_array()) {
+                        for item in content {
+                            if item.get("type").and_then(|t| t.as_str()) == Some("tool_result") {
+                                tools.on_tool_result(item);
+                                timeline.on_tool_result(item);
+                                citations.on_tool_result(item);
+                            }
+                        }
                     }
                 }
                 "result" => {
@@ -345,79 +1263,166 @@ async fn send_to_claude(
                     if let Some(result) = json.get("result").and_then(|r| r.as_str()) {
                         if is_error {
                             error_message = Some(result.to_string());
-                        } else if full_response.is_empty() {
-                            full_response = result.to_string();
-                        }
-                    }
-                    // Extract session ID for conversation continuity
-                    if let Some(sid) = json.get("session_id").and_then(|s| s.as_str()) {
-                        result_session_id = Some(sid.to_string());
-                    }
-                    // Extract token usage - try different possible locations
-                    if let Some(usage) = json.get("usage") {
-                        if let Some(total) = usage.get("total_tokens").and_then(|t| t.as_u64()) {
-                            total_tokens = total;
                         } else {
-                            // Sum input and output tokens if total not available
-                            let input = usage.get("input_tokens").and_then(|t| t.as_u64()).unwrap_or(0);
-                            let output = usage.get("output_tokens").and_then(|t| t.as_u64()).unwrap_or(0);
-                            total_tokens = input + output;
+                            if let Some(saved) = saved_response.as_mut().filter(|s| s.is_empty()) {
+                                if let Err(e) = saved.append(result).await {
+                                    error_message.get_or_insert(format!("Failed to save response: {}", e));
+                                }
+                            }
+                            if let Some(ref mut file) = response_file {
+                                if file.is_empty() {
+                                    if let Err(e) = file.write(&app, &conversation_id, result).await {
+                                        error_message.get_or_insert(format!("Failed to write response file: {}", e));
+                                    }
+                                }
+                            } else if full_response.is_empty() {
+                                full_response = result.to_string();
+                            }
                         }
                     }
-                    // Also check total_cost_usd path for token info
-                    if total_tokens == 0 {
-                        if let Some(stats) = json.get("stats") {
-                            let input = stats.get("input_tokens").and_then(|t| t.as_u64()).unwrap_or(0);
-                            let output = stats.get("output_tokens").and_then(|t| t.as_u64()).unwrap_or(0);
-                            total_tokens = input + output;
-                        }
+                    // Extract session ID for conversation continuity
+                    if let Some(sid) = json.get("session_id").and_then(|s| s.as_str()) {
+                        result_session_id = Some(sid.to_string());
                     }
+                    completion.tokens_used = result_tokens(&json);
+                    completion.usage = TokenUsage::from_result(&json);
+                    completion.had_result = true;
+                    cost.on_result(&json);
                 }
                 _ => {}
             }
+
+            // Over budget: stop the turn the same way a cancel would
+            if msg_type == "assistant" {
+                if let Some(exceeded) = cost.exceeded() {
+                    process.kill().await;
+                    cancelled = true;
+                    budget_exceeded = Some(exceeded);
+                    break;
+                }
+            }
         }
     }
 
-    let status = child.wait().await.map_err(|e| e.to_string())?;
+    emit_message_complete(&app, current_message.take());
+
+    let exit = process.finish().await;
+    turn_recovery::finish_turn(&app, &conversation_id).await;
+    let exit = exit?;
+
+    let _ = tool_stats::record(&app, &tools.finish()).await;
+    let cost_usd = cost.spent();
+    let _ = cost_limits::record_turn(&app, &conversation_id, cost_usd).await;
 
     // Get stderr output for debugging
-    let stderr_output = if let Some(handle) = stderr_handle {
-        handle.await.unwrap_or_default()
-    } else {
-        String::new()
-    };
+    let stderr_output = exit.stderr;
+    if let Some(ref mut log) = debug_log {
+        log.stderr(&stderr_output).await;
+    }
 
     // Cleanup temp MCP config file
-    if let Some(path) = temp_mcp_config_path {
-        let _ = tokio::fs::remove_file(path).await;
+    drop(temp_mcp_config);
+
+    // Whatever was saved stays, finished or not
+    if let Some(saved) = saved_response.take() {
+        if let Err(e) = saved.close().await {
+            error_message.get_or_insert(format!("Failed to save response: {}", e));
+        }
+    }
+
+    // A turn that didn't finish cleanly leaves its output under the .partial name
+    let failed = cancelled || !exit.success || error_message.is_some();
+    let mut partial_file = None;
+    let output_file = match response_file.take() {
+        Some(file) if failed => {
+            partial_file = Some(file.abandon().await);
+            None
+        }
+        Some(file) => Some(file.finish(&app, &conversation_id).await?),
+        None => None,
+    };
+    let with_partial = |err: String| match partial_file {
+        Some(ref path) => format!("{} (partial output kept at {})", err, path),
+        None => err,
+    };
+
+    if let Some(err_msg) = read_error {
+        claude_errors::record(&conversation_id, &err_msg, Some(&stderr_output)).await;
+        return Err(with_partial(err_msg));
+    }
+
+    if let Some(exceeded) = budget_exceeded {
+        let err_msg = exceeded.message();
+        let _ = app.emit(&format!("claude-budget-exceeded-{}", conversation_id), exceeded);
+        claude_errors::record(&conversation_id, &err_msg, None).await;
+        return Err(with_partial(err_msg));
     }
 
-    if !status.success() {
+    if startup_timed_out {
+        let err_msg = format!(
+            "claude did not respond within {} ms (possible auth/TTY issue)",
+            startup_timeout_ms.unwrap_or_default()
+        );
+        claude_errors::record(&conversation_id, &err_msg, Some(&stderr_output)).await;
+        return Err(err_msg);
+    }
+
+    if cancelled {
+        return Err(with_partial("Cancelled".to_string()));
+    }
+
+    if !exit.success {
         let err_msg = if let Some(err) = error_message {
             err
         } else if !stderr_output.is_empty() {
             format!("Claude error: {}", stderr_output)
         } else {
-            format!("Claude exited with status: {}", status)
+            format!("Claude exited with status: {}", exit.status)
         };
-        return Err(err_msg);
+        claude_errors::record(&conversation_id, &err_msg, Some(&stderr_output)).await;
+        return Err(with_partial(err_msg));
     }
 
     // Also return error if we got one in the stream even if status was success
     if let Some(err) = error_message {
-        return Err(err);
+        claude_errors::record(&conversation_id, &err, Some(&stderr_output)).await;
+        return Err(with_partial(err));
     }
 
-    let _ = app.emit(&format!("claude-response-{}", conversation_id), ClaudeResponse {
-        content: String::new(),
-        is_complete: true,
-        thinking: None,
-        tokens_used: if total_tokens > 0 { Some(total_tokens) } else { None },
-    });
+    let citations = citations.finish();
+    if !citations.is_empty() {
+        let _ = app.emit(&format!("claude-citations-{}", conversation_id), citations::ClaudeCitations {
+            conversation_id: conversation_id.clone(),
+            citations: citations.clone(),
+        });
+    }
+
+    completion.send();
+
+    if let Some(ref file) = output_file {
+        full_response = format!("Response written to {} ({} bytes)", file.path, file.bytes);
+    }
+
+    let raw_response = full_response.trim().to_string();
+    let transforms = settings::load(&app).await.map(|s| s.response_transforms).unwrap_or_default();
+    let response = postprocess::apply(&raw_response, &transforms, work_dir.as_deref());
+    milestones.response_complete(&response);
+    followups::after_turn(&app, &conversation_id, &message, &response, cost_usd);
 
     Ok(ClaudeResult {
-        response: full_response.trim().to_string(),
+        raw_response: (response != raw_response).then_some(raw_response),
+        response,
         session_id: result_session_id,
+        citations,
+        output_file,
+        active_integrations,
+        timeline: timeline.finish(),
+        timing: ClaudeTiming {
+            startup_ms,
+            total_ms: started.elapsed().as_millis() as u64,
+            reused_process,
+        },
+        cost_usd,
     })
 }
 
@@ -426,22 +1431,120 @@ pub struct ShellOutput {
     pub stdout: String,
     pub stderr: String,
     pub exit_code: i32,
+    // The shell and leading arguments the command actually ran with
+    pub shell: String,
+    pub shell_args: Vec<String>,
+    // Why a failed command probably failed, when that could be worked out
+    pub diagnosis: Option<shell_diagnosis::ExitDiagnosis>,
+}
+
+// Emit a shell process's output as `shell-output-<process_id>` events and
+// return everything read once the stream closes. `seq` is shared by the
+// process's stdout and stderr readers.
+#[allow(clippy::too_many_arguments)]
+fn spawn_shell_reader<R>(
+    app: tauri::AppHandle,
+    process_id: String,
+    pid: Option<u32>,
+    reader: Option<R>,
+    mode: OutputMode,
+    is_stderr: bool,
+    strip_ansi: bool,
+    seq: Arc<AtomicU64>,
+) -> tokio::task::JoinHandle<Option<String>>
+where
+    R: AsyncRead + Unpin + Send + 'static,
+{
+    let description = format!("shell {} reader ({})", if is_stderr { "stderr" } else { "stdout" }, process_id);
+    let liveness = pid.map(stream_errors::Liveness::Pid).unwrap_or(stream_errors::Liveness::Unknown);
+    let source = stream_errors::StreamSource::new(&process_id, "shell", liveness);
+    stream_errors::spawn_reader(app.clone(), source, description, async move {
+        let mut collected = String::new();
+        let mut result = Ok(());
+        let mut stripper = strip_ansi.then(ansi::AnsiStripper::default);
+        if let Some(reader) = reader {
+            result = read_output(reader, mode, |chunk| {
+                // The returned output keeps the escape codes; only events are stripped
+                collected.push_str(&chunk);
+                if mode == OutputMode::Line {
+                    collected.push('\n');
+                }
+                let chunk = match stripper.as_mut() {
+                    Some(stripper) => stripper.strip(&chunk),
+                    None => chunk,
+                };
+                let seq = seq.fetch_add(1, Ordering::SeqCst);
+                ipc_limits::emit_text(&app, &format!("shell-output-{}", process_id), chunk, |line, part| ShellOutputChunk {
+                    process_id: process_id.clone(),
+                    line,
+                    is_stderr,
+                    seq,
+                    part,
+                });
+            }).await.map_err(|e| e.to_string());
+        }
+        (collected, result)
+    })
+}
+
+// PATH given to processes started with a clean environment
+const CLEAN_ENV_PATH: &str = "/usr/local/bin:/usr/bin:/bin:/usr/sbin:/sbin";
+
+// Set up a child's environment: optionally drop everything inherited from the
+// app, then layer the profile's variables and finally the explicit ones
+async fn apply_env(
+    app: &tauri::AppHandle,
+    cmd: &mut Command,
+    clean_env: bool,
+    profile: Option<&str>,
+    env: Option<&HashMap<String, String>>,
+) -> Result<(), String> {
+    if clean_env {
+        cmd.env_clear();
+        cmd.env("PATH", CLEAN_ENV_PATH);
+    }
+    if let Some(name) = profile {
+        cmd.envs(env_profiles::resolve_profile(app, name).await?);
+    }
+    if let Some(env) = env {
+        cmd.envs(env);
+    }
+    Ok(())
 }
 
 // Track process IDs that should be killed
 static KILL_SIGNALS: Lazy<Arc<Mutex<std::collections::HashSet<String>>>> =
     Lazy::new(|| Arc::new(Mutex::new(std::collections::HashSet::new())));
 
-#[tauri::command]
-async fn run_shell_command(
-    process_id: String,
-    command: String,
-    working_directory: Option<String>,
-) -> Result<ShellOutput, String> {
-    let mut cmd = Command::new("sh");
-    cmd.arg("-c").arg(&command);
+// How a shell command is launched, shared by run_shell_command and preview_spawn
+#[derive(Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ShellSpawnParams {
+    pub command: String,
+    pub working_directory: Option<String>,
+    pub profile: Option<String>,
+    pub env: Option<HashMap<String, String>>,
+    pub clean_env: Option<bool>,
+    pub shell: Option<String>,
+    pub shell_args: Option<Vec<String>>,
+    pub interactive: Option<bool>,
+}
 
-    if let Some(dir) = working_directory {
+// Assemble a shell command without running it, returning the resolved shell
+// and its leading arguments alongside
+async fn build_shell_command(
+    app: &tauri::AppHandle,
+    params: ShellSpawnParams,
+) -> Result<(Command, String, Vec<String>), String> {
+    let (shell, shell_args) = shell_config::resolve(app, params.shell, params.shell_args, params.interactive).await;
+    let mut cmd = Command::new(&shell);
+    cmd.args(&shell_args).arg(&params.command);
+
+    apply_env(app, &mut cmd, params.clean_env.unwrap_or(false), params.profile.as_deref(), params.env.as_ref()).await?;
+
+    let dir = settings::working_dir_or_default(app, params.working_directory).await;
+    settings::check_allowed_dir(app, dir.as_deref()).await?;
+    if let Some(dir) = dir {
         cmd.current_dir(dir);
     }
 
@@ -454,15 +1557,69 @@ async fn run_shell_command(
         });
     }
 
+    Ok((cmd, shell, shell_args))
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn run_shell_command(
+    app: tauri::AppHandle,
+    process_id: String,
+    command: String,
+    working_directory: Option<String>,
+    output_mode: Option<String>,
+    profile: Option<String>,
+    env: Option<HashMap<String, String>>,
+    clean_env: Option<bool>,
+    shell: Option<String>,
+    shell_args: Option<Vec<String>>,
+    interactive: Option<bool>,
+    priority: Option<String>,
+    max_cpu_percent: Option<u8>,
+    strip_ansi: Option<bool>,
+) -> Result<ShellOutput, String> {
+    let mode = OutputMode::parse(output_mode.as_deref())?;
+    let limits = process_limits::ProcessLimits::new(priority, max_cpu_percent)?;
+    let command_line = command.clone();
+    let directory = settings::working_dir_or_default(&app, working_directory.clone()).await;
+
+    let (mut cmd, shell, shell_args) = build_shell_command(&app, ShellSpawnParams {
+        command,
+        working_directory,
+        profile,
+        env,
+        clean_env,
+        shell,
+        shell_args,
+        interactive,
+    }).await?;
+
     cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
 
-    let child = cmd.spawn().map_err(|e| format!("Failed to spawn command: {}", e))?;
+    process_limits::apply_priority(&mut cmd, &limits);
+
+    let mut child = cmd.spawn().map_err(|e| format!("Failed to spawn {}: {}", shell, e))?;
+    if let Some(pid) = child.id() {
+        process_limits::track(pid, limits).await;
+    }
+
+    // Stream output as it arrives while also collecting it for the final result
+    let seq = Arc::new(AtomicU64::new(0));
+    let stdout_handle = spawn_shell_reader(app.clone(), process_id.clone(), child.id(), child.stdout.take(), mode, false, strip_ansi.unwrap_or(false), seq.clone());
+    let stderr_handle = spawn_shell_reader(app.clone(), process_id.clone(), child.id(), child.stderr.take(), mode, true, strip_ansi.unwrap_or(false), seq);
+
+    let operation = operations::register(&process_id, "shell", true);
 
     // Store process ID mapping
     let child_pid = child.id();
     {
         let mut processes = RUNNING_PROCESSES.lock().await;
-        processes.insert(process_id.clone(), child);
+        processes.insert(process_id.clone(), RunningProcess {
+            child,
+            command: command_line,
+            working_directory: directory,
+            started_at: unix_millis(),
+        });
     }
 
     // Wait for the process in a loop, checking for kill signal
@@ -470,13 +1627,14 @@ async fn run_shell_command(
         // Check if we should kill
         {
             let mut signals = KILL_SIGNALS.lock().await;
-            if signals.remove(&process_id) {
+            if signals.remove(&process_id) || operation.is_cancelled() {
                 // Kill signal received
                 let mut processes = RUNNING_PROCESSES.lock().await;
-                if let Some(mut child) = processes.remove(&process_id) {
+                if let Some(RunningProcess { mut child, .. }) = processes.remove(&process_id) {
                     // Kill the process group on Unix
                     #[cfg(unix)]
                     if let Some(pid) = child_pid {
+                        process_limits::release(pid).await;
                         unsafe {
                             libc::killpg(pid as i32, libc::SIGTERM);
                         }
@@ -487,6 +1645,9 @@ async fn run_shell_command(
                     stdout: String::new(),
                     stderr: "^C".to_string(),
                     exit_code: 130, // Standard exit code for SIGINT
+                    shell,
+                    shell_args,
+                    diagnosis: None,
                 });
             }
         }
@@ -494,19 +1655,35 @@ async fn run_shell_command(
         // Check if process finished
         {
             let mut processes = RUNNING_PROCESSES.lock().await;
-            if let Some(child) = processes.get_mut(&process_id) {
-                match child.try_wait() {
-                    Ok(Some(_)) => {
-                        // Process finished, get output
-                        if let Some(child) = processes.remove(&process_id) {
-                            let output = child.wait_with_output().await
-                                .map_err(|e| format!("Failed to get output: {}", e))?;
-                            return Ok(ShellOutput {
-                                stdout: String::from_utf8_lossy(&output.stdout).to_string(),
-                                stderr: String::from_utf8_lossy(&output.stderr).to_string(),
-                                exit_code: output.status.code().unwrap_or(-1),
-                            });
-                        }
+            if let Some(process) = processes.get_mut(&process_id) {
+                match process.child.try_wait() {
+                    Ok(Some(status)) => {
+                        // Process finished, collect what the readers gathered
+                        let finished = processes.remove(&process_id);
+                        drop(processes);
+                        let stdout = stdout_handle.await.ok().flatten().unwrap_or_default();
+                        let stderr = stderr_handle.await.ok().flatten().unwrap_or_default();
+                        let diagnosis = match finished {
+                            Some(process) if !status.success() => {
+                                let stderr = stderr.clone();
+                                // Looks at PATH, file modes and the kernel log, so off the async threads
+                                tokio::task::spawn_blocking(move || shell_diagnosis::diagnose(&shell_diagnosis::FailedCommand {
+                                    command: &process.command,
+                                    stderr: &stderr,
+                                    status,
+                                    pid: child_pid,
+                                })).await.ok().flatten()
+                            }
+                            _ => None,
+                        };
+                        return Ok(ShellOutput {
+                            stdout,
+                            stderr,
+                            exit_code: status.code().unwrap_or(-1),
+                            shell,
+                            shell_args,
+                            diagnosis,
+                        });
                     }
                     Ok(None) => {
                         // Still running, continue loop
@@ -522,6 +1699,9 @@ async fn run_shell_command(
                     stdout: String::new(),
                     stderr: "Process terminated".to_string(),
                     exit_code: -1,
+                    shell,
+                    shell_args,
+                    diagnosis: None,
                 });
             }
         }
@@ -532,150 +1712,653 @@ async fn run_shell_command(
 }
 
 #[tauri::command]
-async fn kill_shell_process(process_id: String) -> Result<bool, String> {
-    // Signal the process to be killed
-    let mut signals = KILL_SIGNALS.lock().await;
-    signals.insert(process_id);
-    Ok(true)
+async fn validate_shell_command(command: String, shell: Option<String>) -> Result<(), String> {
+    let shell = shell.unwrap_or_else(|| "sh".to_string());
+    // Only shells known to support a parse-only `-n` flag
+    if !matches!(shell.as_str(), "sh" | "bash" | "zsh" | "dash" | "ksh") {
+        return Err(format!("Syntax checking is not supported for shell: {}", shell));
+    }
+
+    let output = Command::new(&shell)
+        .arg("-n")
+        .arg("-c")
+        .arg(&command)
+        .stdin(Stdio::null())
+        .output()
+        .await
+        .map_err(|e| format!("Failed to run {}: {}", shell, e))?;
+
+    if output.status.success() {
+        Ok(())
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        Err(if stderr.is_empty() { "Syntax error".to_string() } else { stderr })
+    }
 }
 
+// By default the wait loop notices the request and sends SIGTERM. With
+// `force` the whole process group gets SIGKILL right here, for processes that
+// ignore SIGTERM; the wait loop then sees it exit. A forced kill returns false
+// if there was no such process.
 #[tauri::command]
-async fn start_service(
-    app: tauri::AppHandle,
-    service_id: String,
-    command: String,
-    working_directory: Option<String>,
-) -> Result<(), String> {
-    // Check if service is already running
-    {
-        let services = RUNNING_SERVICES.lock().await;
-        if services.contains_key(&service_id) {
-            return Err("Service is already running".to_string());
+async fn kill_shell_process(process_id: String, force: Option<bool>) -> Result<bool, String> {
+    if !force.unwrap_or(false) {
+        // Signal the process to be killed
+        let mut signals = KILL_SIGNALS.lock().await;
+        signals.insert(process_id);
+        return Ok(true);
+    }
+
+    let mut processes = RUNNING_PROCESSES.lock().await;
+    let Some(RunningProcess { child, .. }) = processes.get_mut(&process_id) else {
+        return Ok(false);
+    };
+    #[cfg(unix)]
+    if let Some(pid) = child.id() {
+        unsafe {
+            libc::killpg(pid as i32, libc::SIGKILL);
         }
     }
+    // Reaches the shell itself even if it left its process group
+    child.start_kill().map_err(|e| format!("Failed to kill {}: {}", process_id, e))?;
+    #[cfg(unix)]
+    if let Some(pid) = child.id() {
+        drop(processes);
+        process_limits::release(pid).await;
+    }
+    Ok(true)
+}
+
+// Everything needed to (re)spawn a service
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct ServiceDefinition {
+    pub service_id: String,
+    pub command: String,
+    pub working_directory: Option<String>,
+    pub output_mode: Option<String>,
+    // Services that must be up before this one starts as part of a group
+    #[serde(default)]
+    pub depends_on: Vec<String>,
+    #[serde(default)]
+    pub health_check: Option<service_groups::HealthCheck>,
+    // Used instead of a health check to decide when dependents may start
+    #[serde(default)]
+    pub startup_delay_ms: Option<u64>,
+    // Restart the service when matching files change
+    #[serde(default)]
+    pub watch: Option<service_watch::WatchConfig>,
+    // Environment profile merged into the service's environment
+    #[serde(default)]
+    pub env_profile: Option<String>,
+    // Extra variables, applied after the profile
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    // Start from an empty environment instead of inheriting the app's
+    #[serde(default)]
+    pub clean_env: bool,
+    // Priority and CPU cap, applied at spawn
+    #[serde(flatten, default)]
+    pub limits: process_limits::ProcessLimits,
+    // Stopped by prepare_for_sleep and started again on wake
+    #[serde(default)]
+    pub stop_before_sleep: bool,
+    // Remove ANSI escape codes from emitted output
+    #[serde(default)]
+    pub strip_ansi: bool,
+    // Line written to the service's stdin to ask it to exit, before any signal
+    #[serde(default)]
+    pub shutdown_command: Option<String>,
+}
 
+// A spawned service along with the definition it was started from
+pub struct RunningService {
+    child: Child,
+    definition: ServiceDefinition,
+    // Increases with every spawn so a monitor task never adopts a restarted child
+    instance: u64,
+    started_at: u64,
+}
+
+static NEXT_SERVICE_INSTANCE: AtomicU64 = AtomicU64::new(0);
+
+// How long a service gets to exit after SIGTERM before being killed
+const SERVICE_STOP_TIMEOUT: Duration = Duration::from_secs(5);
+// How long a service gets to exit after its shutdown command before being signalled
+const SERVICE_SHUTDOWN_COMMAND_TIMEOUT: Duration = Duration::from_secs(3);
+// How long an exited service's remaining output may take to arrive
+const SERVICE_OUTPUT_DRAIN_TIMEOUT: Duration = Duration::from_secs(1);
+
+// Assemble a service's command without running it
+async fn build_service_command(app: &tauri::AppHandle, definition: &ServiceDefinition) -> Result<Command, String> {
     let mut cmd = Command::new("sh");
-    cmd.arg("-c").arg(&command);
+    cmd.arg("-c").arg(&definition.command);
+
+    apply_env(app, &mut cmd, definition.clean_env, definition.env_profile.as_deref(), Some(&definition.env)).await?;
 
-    if let Some(dir) = working_directory {
+    if let Some(ref dir) = definition.working_directory {
         cmd.current_dir(dir);
     }
 
-    cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
+    // Create process group so stopping the service also stops its children
+    #[cfg(unix)]
+    unsafe {
+        cmd.pre_exec(|| {
+            libc::setpgid(0, 0);
+            Ok(())
+        });
+    }
 
-    let mut child = cmd.spawn().map_err(|e| format!("Failed to start service: {}", e))?;
+    process_limits::apply_priority(&mut cmd, &definition.limits);
+    Ok(cmd)
+}
 
-    let stdout = child.stdout.take();
-    let stderr = child.stderr.take();
+async fn spawn_service(app: tauri::AppHandle, definition: ServiceDefinition) -> Result<(), String> {
+    OutputMode::parse(definition.output_mode.as_deref())?;
+    settings::check_allowed_dir(&app, definition.working_directory.as_deref()).await?;
 
-    // Store the child process
-    {
-        let mut services = RUNNING_SERVICES.lock().await;
-        services.insert(service_id.clone(), child);
+    let mut cmd = build_service_command(&app, &definition).await?;
+    cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
+    // Kept open so stopping can write the shutdown command
+    if definition.shutdown_command.is_some() {
+        cmd.stdin(Stdio::piped());
     }
 
-    let app_clone = app.clone();
-    let service_id_clone = service_id.clone();
+    let child = cmd.spawn().map_err(|e| format!("Failed to start service: {}", e))?;
+    run_service(child, definition, service_sink(&app), Some(app)).await;
+    Ok(())
+}
 
-    // Spawn task to read stdout
-    if let Some(stdout) = stdout {
-        let app = app_clone.clone();
-        let sid = service_id_clone.clone();
-        tokio::spawn(async move {
-            let mut reader = BufReader::new(stdout).lines();
-            while let Ok(Some(line)) = reader.next_line().await {
-                let _ = app.emit(&format!("service-output-{}", sid), ServiceOutput {
-                    service_id: sid.clone(),
-                    output: line,
-                    is_stderr: false,
-                    is_complete: false,
-                    exit_code: None,
-                });
-            }
+// Receives a service's output and completion events
+type ServiceSink = Arc<dyn Fn(ServiceOutput) + Send + Sync>;
+
+// Sends service events to the webview as service-output-<id>
+fn service_sink(app: &tauri::AppHandle) -> ServiceSink {
+    let app = app.clone();
+    Arc::new(move |event: ServiceOutput| {
+        let name = format!("service-output-{}", event.service_id);
+        ipc_limits::emit_text(&app, &name, event.output.clone(), |output, part| ServiceOutput {
+            output,
+            part,
+            ..event.clone()
         });
+    })
+}
+
+// Read one of a service's output streams into `sink`. Read failures are
+// reported through stream-error when there's an app to report them to.
+fn spawn_service_reader<R>(
+    app: Option<&tauri::AppHandle>,
+    service_id: &str,
+    reader: R,
+    is_stderr: bool,
+    mode: OutputMode,
+    strip_ansi: bool,
+    sink: ServiceSink,
+) -> tokio::task::JoinHandle<Option<()>>
+where
+    R: AsyncRead + Unpin + Send + 'static,
+{
+    let sid = service_id.to_string();
+    let mut stripper = strip_ansi.then(ansi::AnsiStripper::default);
+    let read = async move {
+        let result = read_output(reader, mode, |output| {
+            let output = match stripper.as_mut() {
+                Some(stripper) => stripper.strip(&output),
+                None => output,
+            };
+            sink(ServiceOutput {
+                service_id: sid.clone(),
+                output,
+                is_stderr,
+                is_complete: false,
+                exit_code: None,
+                part: None,
+            });
+        }).await;
+        ((), result.map_err(|e| e.to_string()))
+    };
+    let description = format!("service {} reader ({})", if is_stderr { "stderr" } else { "stdout" }, service_id);
+    match app {
+        Some(app) => {
+            let source = stream_errors::StreamSource::new(service_id, "service", stream_errors::Liveness::Service(service_id.to_string()));
+            stream_errors::spawn_reader(app.clone(), source, description, read)
+        }
+        None => background_tasks::spawn(description, async move {
+            let _ = read.await;
+            Some(())
+        }),
+    }
+}
+
+// Register a spawned service and start its output readers and exit monitor.
+// The child is stored before this returns, so the monitor always finds it
+// however quickly the process exits.
+async fn run_service(mut child: Child, definition: ServiceDefinition, sink: ServiceSink, app: Option<tauri::AppHandle>) {
+    let mode = OutputMode::parse(definition.output_mode.as_deref()).unwrap_or(OutputMode::Line);
+    let stdout = child.stdout.take();
+    let stderr = child.stderr.take();
+    let sid = definition.service_id.clone();
+    let instance = NEXT_SERVICE_INSTANCE.fetch_add(1, Ordering::SeqCst);
+    let strip_ansi = definition.strip_ansi;
+    let pid = child.id();
+    let command = definition.command.clone();
+    let working_directory = definition.working_directory.clone();
+    let limits = definition.limits.clone();
+
+    RUNNING_SERVICES.lock().await.insert(sid.clone(), RunningService { child, definition, instance, started_at: unix_millis() });
+
+    if let Some(pid) = pid {
+        // Remember the process on disk so a later run can find it if we crash
+        if let Some(ref app) = app {
+            orphans::record_service(app, &sid, pid, &command, working_directory.as_deref()).await;
+        }
+        process_limits::track(pid, limits).await;
     }
 
-    // Spawn task to read stderr
+    let mut readers = Vec::new();
+    if let Some(stdout) = stdout {
+        readers.push(spawn_service_reader(app.as_ref(), &sid, stdout, false, mode, strip_ansi, sink.clone()));
+    }
     if let Some(stderr) = stderr {
-        let app = app_clone.clone();
-        let sid = service_id_clone.clone();
-        tokio::spawn(async move {
-            let mut reader = BufReader::new(stderr).lines();
-            while let Ok(Some(line)) = reader.next_line().await {
-                let _ = app.emit(&format!("service-output-{}", sid), ServiceOutput {
-                    service_id: sid.clone(),
-                    output: line,
-                    is_stderr: true,
-                    is_complete: false,
-                    exit_code: None,
-                });
-            }
-        });
+        readers.push(spawn_service_reader(app.as_ref(), &sid, stderr, true, mode, strip_ansi, sink.clone()));
     }
 
     // Spawn task to wait for process completion
-    let app = app_clone;
-    let sid = service_id_clone;
-    tokio::spawn(async move {
-        loop {
+    background_tasks::spawn(format!("service monitor ({})", sid), async move {
+        let exit_code = loop {
             tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
 
             let mut services = RUNNING_SERVICES.lock().await;
-            if let Some(child) = services.get_mut(&sid) {
-                match child.try_wait() {
-                    Ok(Some(status)) => {
-                        services.remove(&sid);
-                        let _ = app.emit(&format!("service-output-{}", sid), ServiceOutput {
-                            service_id: sid.clone(),
-                            output: String::new(),
-                            is_stderr: false,
-                            is_complete: true,
-                            exit_code: status.code(),
-                        });
-                        break;
-                    }
-                    Ok(None) => {
-                        // Still running
-                    }
-                    Err(_) => {
-                        services.remove(&sid);
-                        break;
+            match services.get_mut(&sid) {
+                Some(service) if service.instance == instance => {
+                    match service.child.try_wait() {
+                        Ok(Some(status)) => {
+                            services.remove(&sid);
+                            break status.code();
+                        }
+                        Ok(None) => {
+                            // Still running
+                        }
+                        Err(_) => {
+                            services.remove(&sid);
+                            break None;
+                        }
                     }
                 }
-            } else {
-                // Service was stopped externally
-                break;
+                _ => {
+                    // Service was stopped (and possibly restarted) externally
+                    return;
+                }
             }
+        };
+        if let Some(ref app) = app {
+            orphans::forget_service(app, &sid).await;
+        }
+
+        // Let the readers drain so the completion event comes after the last
+        // output. Children that inherited the pipes can keep them open, so
+        // don't wait on them forever.
+        for reader in readers {
+            let _ = tokio::time::timeout(SERVICE_OUTPUT_DRAIN_TIMEOUT, reader).await;
         }
+        sink(ServiceOutput {
+            service_id: sid.clone(),
+            output: String::new(),
+            is_stderr: false,
+            is_complete: true,
+            exit_code,
+            part: None,
+        });
     });
+}
+
+// Write the service's shutdown command to its stdin, if it has one, and
+// give it a moment to exit. Returns whether it did.
+async fn request_shutdown(service: &mut RunningService) -> bool {
+    let Some(ref command) = service.definition.shutdown_command else { return false };
+    let Some(mut stdin) = service.child.stdin.take() else { return false };
+    let line = format!("{}\n", command.trim_end_matches('\n'));
+    if stdin.write_all(line.as_bytes()).await.is_err() || stdin.flush().await.is_err() {
+        return false;
+    }
+    drop(stdin);
+    tokio::time::timeout(SERVICE_SHUTDOWN_COMMAND_TIMEOUT, service.child.wait()).await.is_ok()
+}
 
+// Send SIGTERM to the service's process group and wait for it to exit,
+// killing it outright if it doesn't within `timeout`
+async fn terminate_service(mut service: RunningService, timeout: Duration) -> Result<(), String> {
+    #[cfg(unix)]
+    if let Some(pid) = service.child.id() {
+        process_limits::release(pid).await;
+    }
+    if request_shutdown(&mut service).await {
+        return Ok(());
+    }
+    #[cfg(unix)]
+    if let Some(pid) = service.child.id() {
+        unsafe {
+            libc::killpg(pid as i32, libc::SIGTERM);
+        }
+    }
+    if tokio::time::timeout(timeout, service.child.wait()).await.is_err() {
+        service.child.kill().await.map_err(|e| format!("Failed to stop service: {}", e))?;
+    }
     Ok(())
 }
 
 #[tauri::command]
-async fn stop_service(service_id: String) -> Result<bool, String> {
-    let mut services = RUNNING_SERVICES.lock().await;
-    if let Some(mut child) = services.remove(&service_id) {
-        // Try to get the process group and kill it
-        #[cfg(unix)]
-        if let Some(pid) = child.id() {
-            unsafe {
-                libc::killpg(pid as i32, libc::SIGTERM);
-            }
+#[allow(clippy::too_many_arguments)]
+async fn start_service(
+    app: tauri::AppHandle,
+    service_id: String,
+    command: String,
+    working_directory: Option<String>,
+    output_mode: Option<String>,
+    profile: Option<String>,
+    on_conflict: Option<String>,
+    env: Option<HashMap<String, String>>,
+    clean_env: Option<bool>,
+    priority: Option<String>,
+    max_cpu_percent: Option<u8>,
+    strip_ansi: Option<bool>,
+    shutdown_command: Option<String>,
+) -> Result<(), String> {
+    OutputMode::parse(output_mode.as_deref())?;
+    let limits = process_limits::ProcessLimits::new(priority, max_cpu_percent)?;
+
+    // What to do if the service is already running: "error" (default), "restart" or "ignore"
+    let on_conflict = on_conflict.as_deref().unwrap_or("error");
+    if !matches!(on_conflict, "error" | "restart" | "ignore") {
+        return Err(format!("Unknown on_conflict value: {}", on_conflict));
+    }
+
+    let existing = {
+        let mut services = RUNNING_SERVICES.lock().await;
+        match on_conflict {
+            _ if !services.contains_key(&service_id) => None,
+            "ignore" => return Ok(()),
+            "restart" => services.remove(&service_id),
+            _ => return Err("Service is already running".to_string()),
+        }
+    };
+    if let Some(service) = existing {
+        service_watch::unwatch_service(&service_id).await;
+        orphans::forget_service(&app, &service_id).await;
+        terminate_service(service, SERVICE_STOP_TIMEOUT).await?;
+    }
+
+    // A copy left over from a previous run would usually hold the same port
+    if let Some(pid) = orphans::orphan_pid(&service_id).await {
+        return Err(format!(
+            "Service is still running from a previous session (pid {}); adopt or kill it first",
+            pid
+        ));
+    }
+
+    // Start from the saved definition (if any) so dependency and watch settings
+    // apply, with the command and directory given here taking precedence
+    let mut definition = service_groups::load_definitions(&app).await
+        .unwrap_or_default()
+        .remove(&service_id)
+        .unwrap_or_default();
+    definition.service_id = service_id;
+    definition.command = command;
+    definition.working_directory = settings::working_dir_or_default(&app, working_directory).await;
+    definition.output_mode = output_mode;
+    if profile.is_some() {
+        definition.env_profile = profile;
+    }
+    if let Some(env) = env {
+        definition.env = env;
+    }
+    if let Some(clean_env) = clean_env {
+        definition.clean_env = clean_env;
+    }
+    if let Some(strip_ansi) = strip_ansi {
+        definition.strip_ansi = strip_ansi;
+    }
+    if shutdown_command.is_some() {
+        definition.shutdown_command = shutdown_command;
+    }
+    if limits != process_limits::ProcessLimits::default() {
+        definition.limits = limits;
+    }
+
+    spawn_service(app.clone(), definition.clone()).await?;
+    service_watch::watch_service(app, &definition).await
+}
+
+// Stop a service if it's running and start it again. The running instance's
+// definition wins over `definition`, which is only used if it isn't running.
+async fn restart_service_instance(app: tauri::AppHandle, definition: &ServiceDefinition) -> Result<(), String> {
+    let running = RUNNING_SERVICES.lock().await.remove(&definition.service_id);
+    let definition = match running {
+        Some(service) => {
+            let definition = service.definition.clone();
+            terminate_service(service, SERVICE_STOP_TIMEOUT).await?;
+            definition
         }
-        child.kill().await.map_err(|e| format!("Failed to stop service: {}", e))?;
+        None => definition.clone(),
+    };
+    spawn_service(app, definition).await
+}
+
+#[tauri::command]
+async fn restart_service(app: tauri::AppHandle, service_id: String) -> Result<bool, String> {
+    let definition = match RUNNING_SERVICES.lock().await.get(&service_id) {
+        Some(service) => service.definition.clone(),
+        None => return Ok(false),
+    };
+    restart_service_instance(app, &definition).await?;
+    Ok(true)
+}
+
+#[tauri::command]
+async fn stop_service(app: tauri::AppHandle, service_id: String) -> Result<bool, String> {
+    service_watch::unwatch_service(&service_id).await;
+    orphans::forget_service(&app, &service_id).await;
+
+    let service = RUNNING_SERVICES.lock().await.remove(&service_id);
+    if let Some(service) = service {
+        terminate_service(service, SERVICE_STOP_TIMEOUT).await?;
         Ok(true)
     } else {
         Ok(false)
     }
 }
 
+// Map a signal name like "HUP" or "SIGUSR1" to its libc number
+#[cfg(unix)]
+fn parse_signal(name: &str) -> Option<i32> {
+    let name = name.trim().to_uppercase();
+    let name = name.strip_prefix("SIG").unwrap_or(&name);
+    Some(match name {
+        "HUP" => libc::SIGHUP,
+        "INT" => libc::SIGINT,
+        "QUIT" => libc::SIGQUIT,
+        "TERM" => libc::SIGTERM,
+        "KILL" => libc::SIGKILL,
+        "USR1" => libc::SIGUSR1,
+        "USR2" => libc::SIGUSR2,
+        "STOP" => libc::SIGSTOP,
+        "CONT" => libc::SIGCONT,
+        "WINCH" => libc::SIGWINCH,
+        _ => return None,
+    })
+}
+
+#[tauri::command]
+async fn signal_service(service_id: String, signal: String) -> Result<bool, String> {
+    #[cfg(unix)]
+    {
+        let signum = parse_signal(&signal).ok_or_else(|| format!("Unknown signal: {}", signal))?;
+        let services = RUNNING_SERVICES.lock().await;
+        let Some(service) = services.get(&service_id) else {
+            return Ok(false);
+        };
+        let pid = service.child.id().ok_or("Service has already exited")?;
+        // Deliver to the whole process group so e.g. `npm run dev` children see it too
+        let result = unsafe { libc::killpg(pid as i32, signum) };
+        if result != 0 {
+            return Err(format!("Failed to send {}: {}", signal, std::io::Error::last_os_error()));
+        }
+        Ok(true)
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = service_id;
+        Err(format!("Sending {} is not supported on this platform", signal))
+    }
+}
+
+#[derive(Clone, Serialize)]
+pub struct ServiceFailure {
+    pub service_id: String,
+    pub error: String,
+}
+
+#[derive(Clone, Serialize)]
+pub struct ServicesRestarted {
+    pub restarted: Vec<String>,
+    // Services that couldn't be stopped or started again; the rest still go ahead
+    pub failed: Vec<ServiceFailure>,
+}
+
+#[tauri::command]
+async fn restart_all_services(app: tauri::AppHandle) -> Result<ServicesRestarted, String> {
+    let mut running: Vec<(u64, String)> = RUNNING_SERVICES.lock().await.values()
+        .map(|service| (service.instance, service.definition.service_id.clone()))
+        .collect();
+    running.sort();
+
+    // Stop everything first (last started goes first) so ports are free again.
+    // Each one only leaves the map as it is stopped.
+    let mut stopped = Vec::new();
+    let mut failed = Vec::new();
+    for (_, service_id) in running.into_iter().rev() {
+        // Stopped by hand in the meantime
+        let Some(service) = RUNNING_SERVICES.lock().await.remove(&service_id) else { continue };
+        service_watch::unwatch_service(&service_id).await;
+        let definition = service.definition.clone();
+        match terminate_service(service, SERVICE_STOP_TIMEOUT).await {
+            Ok(()) => stopped.insert(0, definition),
+            Err(error) => failed.push(ServiceFailure { service_id, error }),
+        }
+    }
+
+    // Then bring them back one at a time in their original start order
+    let mut restarted = Vec::new();
+    for definition in stopped {
+        let service_id = definition.service_id.clone();
+        // Started again by hand in the meantime
+        if RUNNING_SERVICES.lock().await.contains_key(&service_id) {
+            continue;
+        }
+        match spawn_service(app.clone(), definition.clone()).await {
+            Ok(()) => {
+                let _ = service_watch::watch_service(app.clone(), &definition).await;
+                restarted.push(service_id);
+            }
+            Err(error) => failed.push(ServiceFailure { service_id, error }),
+        }
+    }
+
+    Ok(ServicesRestarted { restarted, failed })
+}
+
 #[tauri::command]
 async fn get_running_services() -> Result<Vec<String>, String> {
     let services = RUNNING_SERVICES.lock().await;
     Ok(services.keys().cloned().collect())
 }
 
+#[derive(Clone, Serialize)]
+pub struct RunningProcessInfo {
+    pub id: String,
+    // "service" or "shell"
+    pub kind: String,
+    pub pid: Option<u32>,
+    #[serde(flatten)]
+    pub limits: process_limits::ProcessLimits,
+    // When this process was spawned, and how long ago
+    pub started_at: Option<u64>,
+    pub started: Option<time_format::FormattedTime>,
+}
+
+// Pids of running services, including orphans from an earlier run
+pub(crate) async fn service_pids() -> Vec<(String, u32)> {
+    let mut pids: Vec<(String, u32)> = RUNNING_SERVICES.lock().await.iter()
+        .filter_map(|(id, s)| s.child.id().map(|pid| (id.clone(), pid)))
+        .collect();
+    pids.extend(orphans::orphan_pids().await);
+    pids
+}
+
+#[tauri::command]
+async fn get_running_processes(app: tauri::AppHandle) -> Result<Vec<RunningProcessInfo>, String> {
+    let formatter = time_format::TimeFormatter::load(&app).await;
+    let mut list = Vec::new();
+    let pids: Vec<(String, &str, Option<u32>, Option<u64>)> = {
+        let services = RUNNING_SERVICES.lock().await;
+        let processes = RUNNING_PROCESSES.lock().await;
+        services.iter().map(|(id, s)| (id.clone(), "service", s.child.id(), Some(s.started_at)))
+            .chain(processes.iter().map(|(id, p)| (id.clone(), "shell", p.child.id(), Some(p.started_at))))
+            .collect()
+    };
+    for (id, kind, pid, started_at) in pids {
+        let limits = match pid {
+            Some(pid) => process_limits::limits_for(pid).await,
+            None => process_limits::ProcessLimits::default(),
+        };
+        list.push(RunningProcessInfo {
+            id,
+            kind: kind.to_string(),
+            pid,
+            limits,
+            started_at,
+            started: started_at.map(|millis| formatter.format(millis)),
+        });
+    }
+    list.sort_by(|a, b| a.kind.cmp(&b.kind).then_with(|| a.id.cmp(&b.id)));
+    Ok(list)
+}
+
+#[derive(Clone, Serialize)]
+pub struct ShellProcessInfo {
+    pub process_id: String,
+    pub pid: Option<u32>,
+    pub command: String,
+    pub working_directory: Option<String>,
+    pub started_at: u64,
+    pub started: time_format::FormattedTime,
+}
+
+// Shell commands still running, oldest first, so a reloaded UI can pick
+// them up again or offer to kill them
+#[tauri::command]
+async fn list_running_processes(app: tauri::AppHandle) -> Result<Vec<ShellProcessInfo>, String> {
+    let formatter = time_format::TimeFormatter::load(&app).await;
+    let processes = RUNNING_PROCESSES.lock().await;
+    let mut list: Vec<ShellProcessInfo> = processes.iter().map(|(id, process)| ShellProcessInfo {
+        process_id: id.clone(),
+        pid: process.child.id(),
+        command: process.command.clone(),
+        working_directory: process.working_directory.clone(),
+        started_at: process.started_at,
+        started: formatter.format(process.started_at),
+    }).collect();
+    list.sort_by(|a, b| a.started_at.cmp(&b.started_at).then_with(|| a.process_id.cmp(&b.process_id)));
+    Ok(list)
+}
+
+#[tauri::command]
+async fn get_available_tools(conversation_id: String) -> Result<Vec<ToolInfo>, String> {
+    let tools = AVAILABLE_TOOLS.lock().await;
+    Ok(tools.get(&conversation_id).cloned().unwrap_or_default())
+}
+
 #[tauri::command]
 async fn check_claude_installed() -> Result<bool, String> {
     let output = Command::new("which")
@@ -698,16 +2381,277 @@ pub fn run() {
             greet,
             send_to_claude,
             check_claude_installed,
+            get_available_tools,
             run_shell_command,
+            validate_shell_command,
+            shell_complete::complete_shell_input,
             kill_shell_process,
             start_service,
             stop_service,
             get_running_services,
+            restart_service,
+            signal_service,
+            restart_all_services,
+            service_groups::save_service_definition,
+            service_groups::list_service_definitions,
+            service_groups::delete_service_definition,
+            service_groups::start_service_group,
+            service_groups::stop_service_group,
+            port_forward::start_port_forward,
+            port_forward::stop_port_forward,
+            port_forward::get_port_forward_stats,
+            file_tail::tail_file,
+            file_tail::stop_tail,
+            git::git_context,
+            settings::get_settings,
+            tool_stats::get_tool_stats,
+            tool_stats::get_integration_usage,
+            env_profiles::import_env_file,
+            env_profiles::list_env_profiles,
+            env_profiles::delete_env_profile,
+            orphans::get_orphaned_processes,
+            orphans::adopt_orphan,
+            orphans::kill_orphan,
+            claude_queue::set_claude_concurrency,
             save_data,
             load_data,
+            repair_data,
             list_directory,
-            get_home_dir
+            get_home_dir,
+            files::search_in_file,
+            files::read_file,
+            files::is_writable,
+            migration::get_migration_report,
+            claude_errors::get_recent_claude_errors,
+            postprocess::set_response_transforms,
+            file_index::build_file_index,
+            file_index::query_file_index,
+            ignore_rules::get_effective_ignores,
+            ignore_rules::set_global_excludes,
+            benchmark::benchmark_claude,
+            turn_recovery::get_recovered_turns,
+            attachments::store_attachment,
+            attachments::get_attachment,
+            attachments::delete_attachment,
+            attachments::compact_storage,
+            downloads::download_file,
+            downloads::cancel_download,
+            shell_config::set_shell_settings,
+            get_running_processes,
+            settings::set_default_working_dir,
+            settings::get_default_working_dir,
+            settings::set_max_prompt_bytes,
+            janitor::run_cleanup_now,
+            janitor::get_cleanup_stats,
+            pinned_files::pin_context_file,
+            pinned_files::unpin_context_file,
+            pinned_files::get_pinned_files,
+            tracked_commands::spawn_tracked_command,
+            tracked_commands::get_tracked_output,
+            tracked_commands::kill_tracked_command,
+            tracked_commands::discard_tracked_command,
+            operations::list_operations,
+            operations::cancel_operation,
+            sleep::prepare_for_sleep,
+            sleep::resume_after_sleep,
+            spawn_preview::preview_spawn,
+            conversation_integrations::set_conversation_integrations,
+            conversation_integrations::get_conversation_integrations,
+            background_tasks::list_background_tasks,
+            background_tasks::abort_background_task,
+            cost_limits::set_cost_limits,
+            cost_limits::get_conversation_spend,
+            endpoints::set_endpoint,
+            endpoints::test_endpoint,
+            templates::save_conversation_template,
+            templates::list_conversation_templates,
+            templates::delete_conversation_template,
+            templates::instantiate_template,
+            claude_config::get_claude_config,
+            claude_config::set_claude_config_value,
+            redaction::redact_transcript,
+            shell_batch::run_shell_commands,
+            shell_batch::kill_shell_batch,
+            get_data_file_path,
+            reveal_in_file_manager,
+            focus::start_focus_session,
+            focus::end_focus_session,
+            focus::get_focus_state,
+            settings::set_allowed_roots,
+            http_requests::send_http_request,
+            http_requests::save_http_request,
+            http_requests::list_http_requests,
+            settings::set_allow_debug_logs,
+            websocket::ws_connect,
+            websocket::ws_send,
+            websocket::ws_close,
+            files::hash_file,
+            port_scan::scan_local_ports,
+            markdown_assets::resolve_markdown_assets,
+            session_binding::set_session_dir_check,
+            combined_logs::subscribe_combined_service_logs,
+            combined_logs::unsubscribe_combined_service_logs,
+            time_format::set_time_format_settings,
+            list_running_processes,
+            safe_mode::get_safe_mode_state,
+            safe_mode::exit_safe_mode,
+            data_watch::reload_data,
+            html_export::export_conversation_html,
+            speech::transcribe_audio,
+            speech::start_audio_capture,
+            speech::stop_audio_capture,
+            speech::set_speech_settings,
+            files::move_path,
+            followups::suggest_followups,
+            followups::set_followup_suggestions,
+            subscriptions::subscribe,
+            subscriptions::unsubscribe,
+            files::delete_path,
+            merge::merge_conversations,
+            accessibility::set_milestone_events,
+            claude_models::list_claude_models,
+            workspace_changes::get_workspace_changes,
+            share::share_content,
+            ipc_limits::set_ipc_limits,
+            port_forward::get_port_forward_log
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .setup(|app| {
+            storage::probe_at_startup(app.handle());
+            // Migration, recovery and background work; falls back to safe mode on failure
+            safe_mode::start(app.handle());
+            Ok(())
+        })
+        .on_window_event(|window, event| {
+            if let tauri::WindowEvent::DragDrop(tauri::DragDropEvent::Drop { paths, .. }) = event {
+                let app = window.app_handle().clone();
+                let label = window.label().to_string();
+                let paths = paths.clone();
+                tauri::async_runtime::spawn(file_drop::handle_drop(app, label, paths));
+            }
+            if let tauri::WindowEvent::Destroyed = event {
+                let label = window.label().to_string();
+                tauri::async_runtime::spawn(async move {
+                    file_tail::stop_tails_for_window(&label).await;
+                    websocket::close_for_window(&label).await;
+                    subscriptions::remove_window(&label).await;
+                });
+            }
+        })
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|_app, event| {
+            if let tauri::RunEvent::Exit = event {
+                tauri::async_runtime::block_on(async {
+                    port_forward::stop_all_port_forwards().await;
+                    websocket::close_all().await;
+                    claude_process::shut_down_idle().await;
+                });
+            }
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver};
+
+    fn collecting_sink() -> (ServiceSink, UnboundedReceiver<ServiceOutput>) {
+        let (tx, rx) = unbounded_channel();
+        (Arc::new(move |event| { let _ = tx.send(event); }), rx)
+    }
+
+    fn spawn_sh(script: &str) -> Child {
+        Command::new("sh")
+            .arg("-c")
+            .arg(script)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap()
+    }
+
+    // Every event for one run, ending when the monitor and readers drop the sink
+    async fn run_to_end(service_id: &str, script: &str) -> Vec<ServiceOutput> {
+        let (sink, mut rx) = collecting_sink();
+        let definition = ServiceDefinition {
+            service_id: service_id.to_string(),
+            command: script.to_string(),
+            ..Default::default()
+        };
+        run_service(spawn_sh(script), definition, sink, None).await;
+        let mut events = Vec::new();
+        while let Some(event) = tokio::time::timeout(Duration::from_secs(10), rx.recv()).await
+            .expect("service events stopped without a completion")
+        {
+            events.push(event);
+        }
+        events
+    }
+
+    // Only the config files: two turns in one conversation and directory
+    // still get a file each
+    #[tokio::test]
+    async fn overlapping_turns_get_their_own_mcp_config_file() {
+        let dir = std::env::temp_dir().join(format!("mcp-config-test-{}", std::process::id()));
+        tokio::fs::create_dir_all(&dir).await.unwrap();
+
+        let (a, b) = tokio::join!(
+            McpConfigFile::create(&dir, "conversation", r#"{"mcpServers":{"a":{}}}"#),
+            McpConfigFile::create(&dir, "conversation", r#"{"mcpServers":{"b":{}}}"#),
+        );
+        let (a, b) = (a.unwrap(), b.unwrap());
+        assert_ne!(a.path(), b.path());
+
+        // One turn ending mustn't take the other's config with it
+        let a_path = a.path().to_path_buf();
+        drop(a);
+        assert!(!a_path.exists());
+        assert_eq!(tokio::fs::read_to_string(b.path()).await.unwrap(), r#"{"mcpServers":{"b":{}}}"#);
+
+        let b_path = b.path().to_path_buf();
+        drop(b);
+        assert!(!b_path.exists());
+        tokio::fs::remove_dir(&dir).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn failed_turns_remove_their_mcp_config() {
+        let dir = std::env::temp_dir().join(format!("mcp-config-fail-test-{}", std::process::id()));
+        tokio::fs::create_dir_all(&dir).await.unwrap();
+
+        let mut path = None;
+        let turn = async {
+            let config = McpConfigFile::create(&dir, "conversation", "{}").await?;
+            path = Some(config.path().to_path_buf());
+            Err::<(), String>("read failed".to_string())
+        };
+        assert!(turn.await.is_err());
+        assert!(!path.unwrap().exists());
+        tokio::fs::remove_dir(&dir).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn instant_exits_never_lose_completion() {
+        for round in 0..50 {
+            let service_id = format!("instant-exit-{}", round);
+            let events = run_to_end(&service_id, "exit 0").await;
+            assert_eq!(events.len(), 1, "round {}", round);
+            assert!(events[0].is_complete);
+            assert_eq!(events[0].exit_code, Some(0));
+            assert!(!RUNNING_SERVICES.lock().await.contains_key(&service_id));
+        }
+    }
+
+    #[tokio::test]
+    async fn completion_follows_all_output() {
+        for round in 0..20 {
+            let events = run_to_end(&format!("quick-output-{}", round), "echo out; echo err >&2; exit 3").await;
+            let (last, output) = events.split_last().unwrap();
+            assert!(last.is_complete);
+            assert_eq!(last.exit_code, Some(3));
+            assert!(output.iter().all(|event| !event.is_complete));
+            assert!(output.iter().any(|event| !event.is_stderr && event.output == "out"));
+            assert!(output.iter().any(|event| event.is_stderr && event.output == "err"));
+        }
+    }
 }
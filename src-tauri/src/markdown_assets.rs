@@ -0,0 +1,226 @@
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use tauri::Manager;
+
+// Where images that can't be shown point instead, so the webview never
+// requests a broken URL
+const MISSING_ASSET: &str = "#missing-asset";
+// Link targets that tried to leave the working directory
+const BLOCKED_LINK: &str = "#blocked-link";
+// Prefix of rewritten file links; the number indexes `links` in the result
+const FILE_LINK_PREFIX: &str = "#file-link-";
+
+// `![alt](target "title")` and `[text](target)`, with `<...>` targets for paths with spaces
+static LINK_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(!?)\[([^\]]*)\]\((?:<([^>]*)>|([^)\s]+))(\s+"[^"]*")?\)"#).unwrap()
+});
+static LINE_SUFFIX_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^(.*?)(?:#L(\d+)(?:-L?\d+)?|:(\d+))$").unwrap());
+
+#[derive(Clone, Serialize)]
+pub struct ResolvedLink {
+    // The target as written in the markdown
+    pub target: String,
+    // Absolute path, or None when the target pointed outside the working directory
+    pub path: Option<String>,
+    pub exists: bool,
+    pub is_dir: bool,
+    // From `#L12` or `:12`, for opening the editor at that line
+    pub line: Option<u32>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct ResolvedImage {
+    pub target: String,
+    // Asset-protocol URL, or None when it was replaced with the placeholder
+    pub url: Option<String>,
+    // "missing", "outside_working_directory" or "not_a_file" when url is None
+    pub problem: Option<String>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct ResolvedMarkdown {
+    pub markdown: String,
+    pub links: Vec<ResolvedLink>,
+    pub images: Vec<ResolvedImage>,
+}
+
+// Anything with a scheme, plus in-page anchors, is left alone
+fn is_external(target: &str) -> bool {
+    if target.starts_with('#') || target.starts_with("//") {
+        return true;
+    }
+    match target.split_once(':') {
+        // A single letter is a Windows drive, not a scheme
+        Some((scheme, _)) => scheme.len() > 1
+            && scheme.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.')),
+        None => false,
+    }
+}
+
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes.get(i + 1..i + 3).and_then(|h| std::str::from_utf8(h).ok()).and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                out.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                out.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).to_string()
+}
+
+// Same as the frontend's convertFileSrc
+fn asset_url(path: &Path) -> String {
+    let encoded: String = path.to_string_lossy().bytes().map(|b| match b {
+        b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'!' | b'~' | b'*' | b'\'' | b'(' | b')' => (b as char).to_string(),
+        _ => format!("%{:02X}", b),
+    }).collect();
+    if cfg!(any(windows, target_os = "android")) {
+        format!("http://asset.localhost/{}", encoded)
+    } else {
+        format!("asset://localhost/{}", encoded)
+    }
+}
+
+// Resolve a target against the working directory. Err(()) means it points
+// outside it, through `..` or a symlink.
+fn resolve(root: &Path, target: &str) -> Result<PathBuf, ()> {
+    let joined = root.join(percent_decode(target));
+    // Normalize lexically first so a missing file can still be judged
+    let mut normalized = PathBuf::new();
+    for component in joined.components() {
+        match component {
+            std::path::Component::ParentDir => {
+                if !normalized.pop() {
+                    return Err(());
+                }
+            }
+            std::path::Component::CurDir => {}
+            other => normalized.push(other),
+        }
+    }
+    if !normalized.starts_with(root) {
+        return Err(());
+    }
+    match std::fs::canonicalize(&normalized) {
+        Ok(real) if real.starts_with(root) => Ok(real),
+        Ok(_) => Err(()),
+        Err(_) => Ok(normalized),
+    }
+}
+
+fn resolve_image(app: &tauri::AppHandle, root: &Path, target: &str) -> ResolvedImage {
+    let problem = match resolve(root, target) {
+        Err(()) => "outside_working_directory",
+        Ok(path) if !path.exists() => "missing",
+        Ok(path) if !path.is_file() => "not_a_file",
+        Ok(path) => {
+            // Only this one file becomes readable by the webview
+            return match app.asset_protocol_scope().allow_file(&path) {
+                Ok(()) => ResolvedImage { target: target.to_string(), url: Some(asset_url(&path)), problem: None },
+                Err(e) => ResolvedImage { target: target.to_string(), url: None, problem: Some(e.to_string()) },
+            };
+        }
+    };
+    ResolvedImage { target: target.to_string(), url: None, problem: Some(problem.to_string()) }
+}
+
+fn resolve_link(root: &Path, target: &str) -> ResolvedLink {
+    let (mut file, mut line) = (target.to_string(), None);
+    if let Some(caps) = LINE_SUFFIX_RE.captures(target) {
+        let number = caps.get(2).or(caps.get(3)).and_then(|n| n.as_str().parse::<u32>().ok());
+        // `name:12` might be a real file name; only split it off when the file exists without it
+        let base = &caps[1];
+        let is_colon = caps.get(3).is_some();
+        if !is_colon || resolve(root, base).is_ok_and(|p| p.exists()) {
+            file = base.to_string();
+            line = number;
+        }
+    }
+    match resolve(root, &file) {
+        Ok(path) => ResolvedLink {
+            target: target.to_string(),
+            exists: path.exists(),
+            is_dir: path.is_dir(),
+            path: Some(path.to_string_lossy().to_string()),
+            line,
+        },
+        Err(()) => ResolvedLink { target: target.to_string(), path: None, exists: false, is_dir: false, line: None },
+    }
+}
+
+// Rewrite a response's relative image references to asset URLs the webview
+// can load, and number its relative file links so the frontend can open them
+// in the editor. Nothing outside the conversation's working directory is
+// exposed; such targets become placeholders. Fenced code is left untouched.
+#[tauri::command]
+pub async fn resolve_markdown_assets(
+    app: tauri::AppHandle,
+    conversation_id: String,
+    markdown: String,
+) -> Result<ResolvedMarkdown, String> {
+    let directory = crate::conversation_working_directory(&app, &conversation_id).await
+        .ok_or_else(|| format!("Conversation {} has no working directory", conversation_id))?;
+    let root = std::fs::canonicalize(&directory).map_err(|e| format!("Failed to access {}: {}", directory, e))?;
+
+    let mut links = Vec::new();
+    let mut images = Vec::new();
+    let mut output = String::with_capacity(markdown.len());
+    let mut fence: Option<&str> = None;
+    for line in markdown.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+        match fence {
+            Some(marker) => {
+                if trimmed.starts_with(marker) {
+                    fence = None;
+                }
+                output.push_str(line);
+                continue;
+            }
+            None if trimmed.starts_with("```") || trimmed.starts_with("~~~") => {
+                fence = Some(&trimmed[..3]);
+                output.push_str(line);
+                continue;
+            }
+            None => {}
+        }
+
+        let rewritten = LINK_RE.replace_all(line, |caps: &Captures| {
+            let target = caps.get(3).or(caps.get(4)).map(|m| m.as_str()).unwrap_or("");
+            if target.is_empty() || is_external(target) {
+                return caps[0].to_string();
+            }
+            let is_image = &caps[1] == "!";
+            let label = &caps[2];
+            let title = caps.get(5).map(|m| m.as_str()).unwrap_or("");
+            let new_target = if is_image {
+                let image = resolve_image(&app, &root, target);
+                let url = image.url.clone().unwrap_or_else(|| MISSING_ASSET.to_string());
+                images.push(image);
+                url
+            } else {
+                let link = resolve_link(&root, target);
+                let new_target = match link.path {
+                    Some(_) => format!("{}{}", FILE_LINK_PREFIX, links.len()),
+                    None => BLOCKED_LINK.to_string(),
+                };
+                links.push(link);
+                new_target
+            };
+            format!("{}[{}](<{}>{})", &caps[1], label, new_target, title)
+        });
+        output.push_str(&rewritten);
+    }
+
+    Ok(ResolvedMarkdown { markdown: output, links, images })
+}
@@ -0,0 +1,288 @@
+use serde::{Deserialize, Serialize};
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::process::Command;
+
+use crate::operations::{self, Operation};
+use crate::templates::new_id;
+use crate::{cost_limits, endpoints, read_conversation, transcripts, unix_millis};
+
+const SUMMARY_MODEL: &str = "haiku";
+const SUMMARY_TIMEOUT: Duration = Duration::from_secs(120);
+// Cap passed to the CLI for each source's summary
+const MAX_SUMMARY_COST_USD: f64 = 0.10;
+// The newest part of a long transcript is what gets summarized
+const MAX_SUMMARY_INPUT_CHARS: usize = 100_000;
+const DEFAULT_TOKEN_BUDGET: usize = 30_000;
+// Rough size of a token when budgeting raw messages
+const CHARS_PER_TOKEN: usize = 4;
+
+#[derive(Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum MergeStrategy {
+    // One bounded claude call per source, then the summaries in order
+    Summarize,
+    // Messages from every source by time, newest kept up to the budget
+    Interleave { token_budget: Option<usize> },
+}
+
+#[derive(Serialize)]
+pub struct MergedConversation {
+    pub conversation_id: String,
+    pub session_id: Option<String>,
+    pub parent_ids: Vec<String>,
+    // Summary calls plus the seeding turn
+    pub cost_usd: f64,
+}
+
+struct Source {
+    id: String,
+    title: String,
+    working_directory: Option<String>,
+    // (role, text, timestamp) in the order they were said
+    messages: Vec<(String, String, Option<u64>)>,
+}
+
+fn message_timestamp(message: &serde_json::Value) -> Option<u64> {
+    match message.get("timestamp")? {
+        serde_json::Value::Number(n) => n.as_u64(),
+        serde_json::Value::String(text) => crate::turn_recovery::parse_timestamp(text),
+        _ => None,
+    }
+}
+
+async fn load_source(app: &tauri::AppHandle, conversation_id: &str) -> Result<Source, String> {
+    let conversation = match read_conversation(app, conversation_id).await {
+        Some(conversation) => conversation,
+        None => transcripts::read_transcript(app, conversation_id).await?
+            .ok_or_else(|| format!("Conversation not found: {}", conversation_id))?,
+    };
+    let messages = conversation.get("messages").and_then(|m| m.as_array()).into_iter().flatten()
+        .filter_map(|m| Some((
+            m.get("role")?.as_str()?.to_string(),
+            m.get("content")?.as_str()?.to_string(),
+            message_timestamp(m),
+        )))
+        .collect();
+    Ok(Source {
+        id: conversation_id.to_string(),
+        title: conversation.get("title").and_then(|t| t.as_str()).unwrap_or(conversation_id).to_string(),
+        working_directory: conversation.get("workingDirectory").and_then(|d| d.as_str())
+            .filter(|d| !d.is_empty())
+            .map(String::from),
+        messages,
+    })
+}
+
+fn transcript_text(source: &Source) -> String {
+    let text: String = source.messages.iter()
+        .map(|(role, content, _)| format!("{}: {}\n\n", role, content))
+        .collect();
+    let count = text.chars().count();
+    match text.char_indices().nth(count.saturating_sub(MAX_SUMMARY_INPUT_CHARS)) {
+        Some((index, _)) if index > 0 => format!("[earlier messages left out]\n\n{}", &text[index..]),
+        _ => text,
+    }
+}
+
+// Summarize one source in a single-turn call outside its session. Returns
+// the summary and its cost; stops the call if the merge is cancelled.
+async fn summarize(app: &tauri::AppHandle, source: &Source, operation: &mut Operation) -> Result<(String, f64), String> {
+    let request = format!(
+        "Summarize this conversation between a user and a coding assistant so that it can be continued elsewhere. \
+         Keep the goal, decisions made, current state, file names and open questions; leave out pleasantries. \
+         Reply with only the summary.\n\n<conversation title=\"{}\">\n{}</conversation>",
+        source.title,
+        transcript_text(source),
+    );
+    let mut cmd = Command::new("claude");
+    cmd.envs(endpoints::resolve(app, &source.id, source.working_directory.as_deref()).await?)
+        .current_dir(std::env::temp_dir())
+        .arg("--print")
+        .arg("--output-format").arg("json")
+        .arg("--model").arg(SUMMARY_MODEL)
+        .arg("--max-turns").arg("1")
+        .arg("--max-budget-usd").arg(format!("{:.2}", MAX_SUMMARY_COST_USD))
+        .arg(request)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .kill_on_drop(true);
+
+    let child = cmd.spawn().map_err(|e| format!("Failed to spawn claude: {}", e))?;
+    let output = tokio::select! {
+        output = tokio::time::timeout(SUMMARY_TIMEOUT, child.wait_with_output()) => output
+            .map_err(|_| format!("Summarizing {} timed out", source.title))?
+            .map_err(|e| e.to_string())?,
+        _ = operation.cancelled() => return Err("Merge cancelled".to_string()),
+    };
+    let result: serde_json::Value = serde_json::from_slice(&output.stdout)
+        .map_err(|_| format!("Unexpected output from claude (status {})", output.status))?;
+    let cost = result.get("total_cost_usd").and_then(|c| c.as_f64()).unwrap_or(0.0);
+    let text = result.get("result").and_then(|r| r.as_str()).unwrap_or("").trim().to_string();
+    if result.get("is_error").and_then(|e| e.as_bool()).unwrap_or(false) || text.is_empty() {
+        return Err(format!("Failed to summarize {}: {}", source.title, if text.is_empty() { "empty summary" } else { &text }));
+    }
+    Ok((text, cost))
+}
+
+// Every source's messages ordered by time, keeping the newest that fit.
+// Messages without a timestamp stay next to the one before them.
+fn interleave(sources: &[Source], token_budget: usize) -> String {
+    let mut entries = Vec::new();
+    for source in sources {
+        let mut last = 0;
+        for (role, content, timestamp) in &source.messages {
+            last = timestamp.unwrap_or(last);
+            entries.push((last, &source.title, role, content));
+        }
+    }
+    entries.sort_by_key(|(timestamp, ..)| *timestamp);
+
+    let mut remaining = token_budget * CHARS_PER_TOKEN;
+    let mut kept = Vec::new();
+    for (_, title, role, content) in entries.iter().rev() {
+        let entry = format!("[{}] {}: {}\n\n", title, role, content);
+        if entry.len() > remaining {
+            break;
+        }
+        remaining -= entry.len();
+        kept.push(entry);
+    }
+    let mut text = String::new();
+    if kept.len() < entries.len() {
+        text.push_str(&format!("[{} earlier messages left out]\n\n", entries.len() - kept.len()));
+    }
+    text.extend(kept.into_iter().rev());
+    text
+}
+
+// Start a new conversation carrying the context of several others. The
+// sources are only read. `merge_id` names the operation, so the merge can be
+// stopped with cancel_operation until the new conversation has been saved.
+#[tauri::command]
+pub async fn merge_conversations(
+    app: tauri::AppHandle,
+    merge_id: String,
+    source_ids: Vec<String>,
+    strategy: MergeStrategy,
+) -> Result<MergedConversation, String> {
+    let mut parent_ids: Vec<String> = Vec::new();
+    for id in source_ids {
+        if !parent_ids.contains(&id) {
+            parent_ids.push(id);
+        }
+    }
+    if parent_ids.len() < 2 {
+        return Err("Pick at least two conversations to merge".to_string());
+    }
+    let mut sources = Vec::new();
+    for id in &parent_ids {
+        sources.push(load_source(&app, id).await?);
+    }
+
+    let mut operation = operations::register(&merge_id, "merge", true);
+    let total_steps = match strategy {
+        MergeStrategy::Summarize => sources.len() + 1,
+        MergeStrategy::Interleave { .. } => 1,
+    };
+    let mut cost_usd = 0.0;
+    let context = match strategy {
+        MergeStrategy::Summarize => {
+            let mut sections = Vec::new();
+            for (index, source) in sources.iter().enumerate() {
+                operation.set_progress(Some(index as f64 / total_steps as f64), Some(format!("Summarizing {}", source.title)));
+                let (summary, cost) = summarize(&app, source, &mut operation).await?;
+                cost_usd += cost;
+                sections.push(format!("## {}\n\n{}", source.title, summary));
+            }
+            sections.join("\n\n")
+        }
+        MergeStrategy::Interleave { token_budget } => interleave(&sources, token_budget.unwrap_or(DEFAULT_TOKEN_BUDGET)),
+    };
+    if operation.is_cancelled() {
+        return Err("Merge cancelled".to_string());
+    }
+
+    let titles: Vec<&str> = sources.iter().map(|s| s.title.as_str()).collect();
+    let seed = format!(
+        "This conversation continues {} earlier conversations ({}) about the same work. \
+         Their combined context follows. Read it, then reply with a short note of where things stand.\n\n{}",
+        sources.len(),
+        titles.join(", "),
+        context,
+    );
+    let conversation_id = new_id();
+    let working_directory = sources.iter().find_map(|s| s.working_directory.clone());
+    operation.set_progress(Some((total_steps - 1) as f64 / total_steps as f64), Some("Starting the merged session".to_string()));
+
+    // The seeding turn runs as its own operation under the new conversation's
+    // id; cancelling the merge cancels that too
+    let send = crate::send_to_claude(
+        app.clone(),
+        conversation_id.clone(),
+        seed.clone(),
+        None,
+        working_directory.clone(),
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+    );
+    tokio::pin!(send);
+    let result = tokio::select! {
+        result = &mut send => result,
+        _ = operation.cancelled() => {
+            let _ = operations::cancel_operation(conversation_id.clone()).await;
+            let _ = (&mut send).await;
+            return Err("Merge cancelled".to_string());
+        }
+    }?;
+    // The seeding turn's own cost was recorded against the new conversation
+    if cost_usd > 0.0 {
+        let _ = cost_limits::record_extra(&app, &conversation_id, cost_usd).await;
+    }
+    cost_usd += result.cost_usd;
+
+    let now = unix_millis();
+    let title = format!("Merged: {}", titles.join(" + "));
+    // Shaped like the frontend's own conversations; the extra fields are kept as-is
+    let record = serde_json::json!({
+        "id": conversation_id,
+        "title": title,
+        "messages": [
+            { "id": new_id(), "role": "user", "content": seed, "timestamp": now },
+            { "id": new_id(), "role": "assistant", "content": result.response, "timestamp": unix_millis() },
+        ],
+        "status": "idle",
+        "createdAt": now,
+        "updatedAt": unix_millis(),
+        "equippedSkills": [],
+        "equippedIntegrations": [],
+        "workingDirectory": working_directory.unwrap_or_default(),
+        "lastSeenMessageCount": 0,
+        "tokensUsed": 0,
+        "claudeSessionId": result.session_id,
+        "parentConversationIds": parent_ids,
+        "mergeStrategy": match strategy {
+            MergeStrategy::Summarize => "summarize",
+            MergeStrategy::Interleave { .. } => "interleave",
+        },
+    });
+    transcripts::write_transcript(&app, &conversation_id, &record).await?;
+
+    Ok(MergedConversation {
+        conversation_id,
+        session_id: result.session_id,
+        parent_ids,
+        cost_usd,
+    })
+}
@@ -0,0 +1,119 @@
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use crate::{storage, transcripts, unix_millis, with_suffix, write_atomic};
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct MigrationReport {
+    // Only set once every step succeeded; an unfinished migration reruns on next start
+    pub completed: bool,
+    pub finished_at: Option<u64>,
+    pub source_found: bool,
+    pub conversations_migrated: usize,
+    // Conversations without an id can't be given a transcript file
+    pub conversations_skipped: usize,
+    pub integrations_migrated: usize,
+    pub preserved_copy: Option<String>,
+    pub errors: Vec<String>,
+}
+
+fn get_report_path(app: &tauri::AppHandle) -> PathBuf {
+    storage::data_dir(app).join("migration-report.json")
+}
+
+pub(crate) async fn read_report(app: &tauri::AppHandle) -> Option<MigrationReport> {
+    let data = tokio::fs::read_to_string(get_report_path(app)).await.ok()?;
+    serde_json::from_str(&data).ok()
+}
+
+async fn write_report(app: &tauri::AppHandle, report: &MigrationReport) -> Result<(), String> {
+    let data = serde_json::to_vec_pretty(report).map_err(|e| e.to_string())?;
+    write_atomic(&get_report_path(app), &data).await
+}
+
+// Split the frontend's single data.json into per-conversation transcripts and
+// an integrations store. Every step overwrites its own output, so a run that
+// was interrupted can simply be repeated. data.json itself is never modified:
+// it stays the frontend's working copy, and an untouched copy is kept as
+// data.json.pre-migration.
+async fn migrate(app: &tauri::AppHandle) -> MigrationReport {
+    let mut report = MigrationReport::default();
+    let Ok(path) = crate::get_data_path(app) else {
+        return report;
+    };
+    let contents = match tokio::fs::read_to_string(&path).await {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            report.completed = true;
+            report.finished_at = Some(unix_millis());
+            return report;
+        }
+        Err(e) => {
+            report.errors.push(format!("Failed to read {}: {}", path.display(), e));
+            return report;
+        }
+    };
+    report.source_found = true;
+
+    let legacy: serde_json::Value = match serde_json::from_str(&contents) {
+        Ok(legacy) => legacy,
+        Err(e) => {
+            report.errors.push(format!("data.json is not valid JSON: {}", e));
+            return report;
+        }
+    };
+
+    let preserved = with_suffix(&path, ".pre-migration");
+    if !preserved.exists() {
+        if let Err(e) = write_atomic(&preserved, contents.as_bytes()).await {
+            report.errors.push(format!("Failed to preserve original data: {}", e));
+            return report;
+        }
+    }
+    report.preserved_copy = Some(preserved.to_string_lossy().to_string());
+
+    // zustand's persist middleware wraps everything in {"state": ..., "version": n}
+    let state = legacy.get("state").unwrap_or(&legacy);
+
+    let conversations = state.get("conversations").and_then(|c| c.as_array()).cloned().unwrap_or_default();
+    for conversation in &conversations {
+        let Some(id) = conversation.get("id").and_then(|i| i.as_str()) else {
+            report.conversations_skipped += 1;
+            continue;
+        };
+        match transcripts::write_transcript(app, id, conversation).await {
+            Ok(()) => report.conversations_migrated += 1,
+            Err(e) => report.errors.push(format!("Conversation {}: {}", id, e)),
+        }
+    }
+
+    if let Some(integrations) = state.get("integrations").and_then(|i| i.as_array()) {
+        let store = storage::data_dir(app).join("integrations.json");
+        let written = match serde_json::to_vec_pretty(integrations) {
+            Ok(data) => write_atomic(&store, &data).await,
+            Err(e) => Err(e.to_string()),
+        };
+        match written {
+            Ok(()) => report.integrations_migrated = integrations.len(),
+            Err(e) => report.errors.push(format!("Integrations: {}", e)),
+        }
+    }
+
+    report.completed = report.errors.is_empty();
+    report.finished_at = Some(unix_millis());
+    report
+}
+
+// Run once during setup; does nothing if a previous run already completed
+pub(crate) async fn run_migration(app: tauri::AppHandle) {
+    if read_report(&app).await.is_some_and(|report| report.completed) {
+        return;
+    }
+    let report = migrate(&app).await;
+    let _ = write_report(&app, &report).await;
+}
+
+#[tauri::command]
+pub async fn get_migration_report(app: tauri::AppHandle) -> Result<Option<MigrationReport>, String> {
+    Ok(read_report(&app).await)
+}
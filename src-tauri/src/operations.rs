@@ -0,0 +1,112 @@
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use tokio::sync::watch;
+
+use crate::unix_millis;
+
+#[derive(Clone, Serialize)]
+pub struct OperationInfo {
+    pub operation_id: String,
+    // "shell", "claude", "file_index", ...
+    pub kind: String,
+    pub started_at: u64,
+    // Fraction complete, when the operation knows its total
+    pub progress: Option<f64>,
+    pub detail: Option<String>,
+    pub cancellable: bool,
+    pub cancel_requested: bool,
+}
+
+struct Entry {
+    info: OperationInfo,
+    // Distinguishes a re-registered id from the registration being dropped
+    instance: u64,
+    cancel: watch::Sender<bool>,
+}
+
+// Plain mutex so entries can be removed from Drop
+static OPERATIONS: Lazy<Mutex<HashMap<String, Entry>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+static NEXT_INSTANCE: AtomicU64 = AtomicU64::new(0);
+
+// A registered long-running operation. Cancellation is cooperative: the
+// owner checks `is_cancelled` or awaits `cancelled` and does its own cleanup.
+// Dropping it removes the operation from the registry.
+pub(crate) struct Operation {
+    id: String,
+    instance: u64,
+    cancel: watch::Receiver<bool>,
+}
+
+impl Operation {
+    pub(crate) fn is_cancelled(&self) -> bool {
+        *self.cancel.borrow()
+    }
+
+    // Resolves once cancellation is requested; never, if it isn't
+    pub(crate) async fn cancelled(&mut self) {
+        if self.cancel.wait_for(|cancelled| *cancelled).await.is_err() {
+            std::future::pending::<()>().await;
+        }
+    }
+
+    pub(crate) fn set_progress(&self, progress: Option<f64>, detail: Option<String>) {
+        let mut operations = OPERATIONS.lock().unwrap();
+        if let Some(entry) = operations.get_mut(&self.id).filter(|e| e.instance == self.instance) {
+            entry.info.progress = progress;
+            entry.info.detail = detail;
+        }
+    }
+}
+
+impl Drop for Operation {
+    fn drop(&mut self) {
+        let mut operations = OPERATIONS.lock().unwrap();
+        if operations.get(&self.id).is_some_and(|e| e.instance == self.instance) {
+            operations.remove(&self.id);
+        }
+    }
+}
+
+pub(crate) fn register(operation_id: &str, kind: &str, cancellable: bool) -> Operation {
+    let (sender, receiver) = watch::channel(false);
+    let instance = NEXT_INSTANCE.fetch_add(1, Ordering::SeqCst);
+    OPERATIONS.lock().unwrap().insert(operation_id.to_string(), Entry {
+        info: OperationInfo {
+            operation_id: operation_id.to_string(),
+            kind: kind.to_string(),
+            started_at: unix_millis(),
+            progress: None,
+            detail: None,
+            cancellable,
+            cancel_requested: false,
+        },
+        instance,
+        cancel: sender,
+    });
+    Operation { id: operation_id.to_string(), instance, cancel: receiver }
+}
+
+#[tauri::command]
+pub async fn list_operations() -> Result<Vec<OperationInfo>, String> {
+    let mut list: Vec<OperationInfo> = OPERATIONS.lock().unwrap().values().map(|e| e.info.clone()).collect();
+    list.sort_by_key(|info| info.started_at);
+    Ok(list)
+}
+
+// Ask an operation to stop. Returns false if there is no such operation.
+#[tauri::command]
+pub async fn cancel_operation(operation_id: String) -> Result<bool, String> {
+    let mut operations = OPERATIONS.lock().unwrap();
+    let Some(entry) = operations.get_mut(&operation_id) else {
+        return Ok(false);
+    };
+    if !entry.info.cancellable {
+        return Err(format!("Operation {} can't be cancelled", operation_id));
+    }
+    entry.info.cancel_requested = true;
+    let _ = entry.cancel.send(true);
+    Ok(true)
+}
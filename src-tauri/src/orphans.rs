@@ -0,0 +1,211 @@
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::process::Command;
+use tokio::sync::Mutex;
+
+// Written for every spawned service so a later run can find it again
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ProcessRecord {
+    pub service_id: String,
+    pub pid: u32,
+    // Process start time as reported by `ps`, so a reused pid isn't mistaken for ours
+    pub started: String,
+    pub command: String,
+    pub working_directory: Option<String>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct OrphanedProcess {
+    #[serde(flatten)]
+    pub record: ProcessRecord,
+    pub adopted: bool,
+}
+
+// Records for services spawned by this run, mirrored to disk
+static RECORDS: Lazy<Arc<Mutex<HashMap<String, ProcessRecord>>>> =
+    Lazy::new(|| Arc::new(Mutex::new(HashMap::new())));
+
+// Still-alive processes left behind by a previous run
+static ORPHANS: Lazy<Arc<Mutex<HashMap<String, OrphanedProcess>>>> =
+    Lazy::new(|| Arc::new(Mutex::new(HashMap::new())));
+
+fn get_records_path(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    let app_data = crate::storage::data_dir(app);
+    Ok(app_data.join("service-processes.json"))
+}
+
+// Persist both live services and orphans we still know about
+async fn persist(app: &tauri::AppHandle) -> Result<(), String> {
+    let mut all: HashMap<String, ProcessRecord> = ORPHANS.lock().await.iter()
+        .map(|(id, orphan)| (id.clone(), orphan.record.clone()))
+        .collect();
+    all.extend(RECORDS.lock().await.clone());
+
+    let path = get_records_path(app)?;
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await.map_err(|e| e.to_string())?;
+    }
+    let data = serde_json::to_string_pretty(&all).map_err(|e| e.to_string())?;
+    tokio::fs::write(&path, data).await.map_err(|e| e.to_string())
+}
+
+#[cfg(unix)]
+async fn process_start_marker(pid: u32) -> Option<String> {
+    let output = Command::new("ps")
+        .arg("-o").arg("lstart=")
+        .arg("-p").arg(pid.to_string())
+        .output()
+        .await
+        .ok()?;
+    let marker = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (output.status.success() && !marker.is_empty()).then_some(marker)
+}
+
+#[cfg(not(unix))]
+async fn process_start_marker(_pid: u32) -> Option<String> {
+    None
+}
+
+async fn is_alive(record: &ProcessRecord) -> bool {
+    process_start_marker(record.pid).await.as_deref() == Some(record.started.as_str())
+}
+
+pub(crate) async fn record_service(
+    app: &tauri::AppHandle,
+    service_id: &str,
+    pid: u32,
+    command: &str,
+    working_directory: Option<&str>,
+) {
+    let Some(started) = process_start_marker(pid).await else {
+        return;
+    };
+    RECORDS.lock().await.insert(service_id.to_string(), ProcessRecord {
+        service_id: service_id.to_string(),
+        pid,
+        started,
+        command: command.to_string(),
+        working_directory: working_directory.map(String::from),
+    });
+    let _ = persist(app).await;
+}
+
+pub(crate) async fn forget_service(app: &tauri::AppHandle, service_id: &str) {
+    if RECORDS.lock().await.remove(service_id).is_some() {
+        let _ = persist(app).await;
+    }
+}
+
+// Pid of a still-running orphan using this service id, if any
+pub(crate) async fn orphan_pid(service_id: &str) -> Option<u32> {
+    ORPHANS.lock().await.get(service_id).map(|orphan| orphan.record.pid)
+}
+
+// Service ids and pids of orphans that are still around
+pub(crate) async fn orphan_pids() -> Vec<(String, u32)> {
+    ORPHANS.lock().await.iter().map(|(id, orphan)| (id.clone(), orphan.record.pid)).collect()
+}
+
+// Load the previous run's records at startup, keeping the ones whose process
+// is still alive and dropping the rest
+pub(crate) async fn scan_for_orphans(app: tauri::AppHandle) {
+    let Ok(path) = get_records_path(&app) else { return };
+    let Ok(data) = tokio::fs::read_to_string(&path).await else { return };
+    let records: HashMap<String, ProcessRecord> = serde_json::from_str(&data).unwrap_or_default();
+
+    {
+        let mut orphans = ORPHANS.lock().await;
+        for (service_id, record) in records {
+            if is_alive(&record).await {
+                orphans.insert(service_id, OrphanedProcess { record, adopted: false });
+            }
+        }
+    }
+    let _ = persist(&app).await;
+}
+
+#[cfg(unix)]
+fn signal_orphan(pid: u32, signal: i32) {
+    // Services run in their own process group; fall back to the pid alone
+    unsafe {
+        if libc::killpg(pid as i32, signal) != 0 {
+            libc::kill(pid as i32, signal);
+        }
+    }
+}
+
+#[tauri::command]
+pub async fn get_orphaned_processes() -> Result<Vec<OrphanedProcess>, String> {
+    let orphans = ORPHANS.lock().await;
+    let mut list: Vec<OrphanedProcess> = orphans.values().cloned().collect();
+    list.sort_by(|a, b| a.record.service_id.cmp(&b.record.service_id));
+    Ok(list)
+}
+
+#[tauri::command]
+pub async fn adopt_orphan(app: tauri::AppHandle, service_id: String) -> Result<bool, String> {
+    {
+        let mut orphans = ORPHANS.lock().await;
+        match orphans.get_mut(&service_id) {
+            Some(orphan) if orphan.adopted => return Ok(true),
+            Some(orphan) => orphan.adopted = true,
+            None => return Ok(false),
+        }
+    }
+
+    // Output can't be reattached, but we can notice when it exits
+    crate::background_tasks::spawn(format!("orphan monitor ({})", service_id), async move {
+        loop {
+            tokio::time::sleep(Duration::from_secs(2)).await;
+            let record = match ORPHANS.lock().await.get(&service_id) {
+                Some(orphan) => orphan.record.clone(),
+                None => break,
+            };
+            if !is_alive(&record).await {
+                ORPHANS.lock().await.remove(&service_id);
+                let _ = persist(&app).await;
+                let event = format!("service-output-{}", service_id);
+                crate::ipc_limits::emit_text(&app, &event, String::new(), |output, part| crate::ServiceOutput {
+                    service_id: service_id.clone(),
+                    output,
+                    is_stderr: false,
+                    is_complete: true,
+                    exit_code: None,
+                    part,
+                });
+                break;
+            }
+        }
+    });
+
+    Ok(true)
+}
+
+#[tauri::command]
+pub async fn kill_orphan(app: tauri::AppHandle, service_id: String) -> Result<bool, String> {
+    let Some(orphan) = ORPHANS.lock().await.remove(&service_id) else {
+        return Ok(false);
+    };
+    let record = orphan.record;
+
+    #[cfg(unix)]
+    if is_alive(&record).await {
+        signal_orphan(record.pid, libc::SIGTERM);
+        for _ in 0..50 {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            if !is_alive(&record).await {
+                break;
+            }
+        }
+        if is_alive(&record).await {
+            signal_orphan(record.pid, libc::SIGKILL);
+        }
+    }
+
+    persist(&app).await?;
+    Ok(true)
+}
@@ -0,0 +1,185 @@
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use crate::attachments::hex_digest;
+use crate::{storage, unix_millis, write_atomic};
+
+// Total pinned file text inlined into a single prompt
+const MAX_PINNED_BYTES: usize = 512 * 1024;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PinnedFile {
+    pub path: String,
+    pub pinned_at: u64,
+    // What the file looked like the last time it went into a prompt
+    pub included_at: Option<u64>,
+    pub included_mtime: Option<u64>,
+    pub included_hash: Option<String>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct PinnedFileStatus {
+    #[serde(flatten)]
+    pub pin: PinnedFile,
+    // "fresh", "changed", "deleted" or "not_included"
+    pub status: String,
+}
+
+// Guards the pins file; the value is unused
+static PINS_LOCK: Lazy<Arc<Mutex<()>>> = Lazy::new(|| Arc::new(Mutex::new(())));
+
+fn get_pins_path(app: &tauri::AppHandle) -> PathBuf {
+    storage::data_dir(app).join("pinned-files.json")
+}
+
+async fn read_pins(app: &tauri::AppHandle) -> HashMap<String, Vec<PinnedFile>> {
+    match tokio::fs::read_to_string(get_pins_path(app)).await {
+        Ok(data) => serde_json::from_str(&data).unwrap_or_default(),
+        Err(_) => HashMap::new(),
+    }
+}
+
+async fn write_pins(app: &tauri::AppHandle, pins: &HashMap<String, Vec<PinnedFile>>) -> Result<(), String> {
+    let data = serde_json::to_vec_pretty(pins).map_err(|e| e.to_string())?;
+    write_atomic(&get_pins_path(app), &data).await
+}
+
+async fn modify_pins<F, T>(app: &tauri::AppHandle, change: F) -> Result<T, String>
+where
+    F: FnOnce(&mut HashMap<String, Vec<PinnedFile>>) -> T,
+{
+    let _guard = PINS_LOCK.lock().await;
+    let mut pins = read_pins(app).await;
+    let result = change(&mut pins);
+    pins.retain(|_, files| !files.is_empty());
+    write_pins(app, &pins).await?;
+    Ok(result)
+}
+
+fn mtime_millis(metadata: &std::fs::Metadata) -> Option<u64> {
+    let modified = metadata.modified().ok()?;
+    Some(modified.duration_since(std::time::UNIX_EPOCH).ok()?.as_millis() as u64)
+}
+
+async fn file_hash(path: &str) -> Option<String> {
+    let data = tokio::fs::read(path).await.ok()?;
+    Some(hex_digest(&Sha256::digest(&data)))
+}
+
+async fn status_of(pin: &PinnedFile) -> &'static str {
+    let Ok(metadata) = tokio::fs::metadata(&pin.path).await else {
+        return "deleted";
+    };
+    let Some(ref included_hash) = pin.included_hash else {
+        return "not_included";
+    };
+    // Only hash when the mtime moved; touching a file without editing it isn't a change
+    if pin.included_mtime.is_some() && mtime_millis(&metadata) == pin.included_mtime {
+        return "fresh";
+    }
+    match file_hash(&pin.path).await {
+        Some(hash) if &hash == included_hash => "fresh",
+        _ => "changed",
+    }
+}
+
+// Prompt text for the conversation's pinned files. Most recently modified
+// files go first so the budget is spent on what's likely being worked on;
+// anything over budget is referenced by path, and deleted pins are called out.
+pub(crate) async fn inline_pinned(app: &tauri::AppHandle, conversation_id: &str) -> Result<String, String> {
+    let pins = read_pins(app).await.remove(conversation_id).unwrap_or_default();
+    if pins.is_empty() {
+        return Ok(String::new());
+    }
+
+    let mut present = Vec::new();
+    let mut out = String::new();
+    for pin in pins {
+        match tokio::fs::metadata(&pin.path).await {
+            Ok(metadata) => present.push((mtime_millis(&metadata), pin.path)),
+            Err(_) => out.push_str(&format!("\n\n<pinned-file path=\"{}\" deleted=\"true\" />", pin.path)),
+        }
+    }
+    present.sort_by_key(|(mtime, _)| std::cmp::Reverse(*mtime));
+
+    let mut budget = MAX_PINNED_BYTES;
+    let mut included = HashMap::new();
+    for (mtime, path) in present {
+        let Ok(data) = tokio::fs::read(&path).await else { continue };
+        match String::from_utf8(data) {
+            Ok(text) if text.len() <= budget => {
+                budget -= text.len();
+                included.insert(path.clone(), (mtime, hex_digest(&Sha256::digest(text.as_bytes()))));
+                out.push_str(&format!("\n\n<pinned-file path=\"{}\">\n{}\n</pinned-file>", path, text));
+            }
+            _ => out.push_str(&format!("\n\n<pinned-file path=\"{}\" />", path)),
+        }
+    }
+
+    let now = unix_millis();
+    modify_pins(app, |pins| {
+        for pin in pins.get_mut(conversation_id).into_iter().flatten() {
+            if let Some((mtime, hash)) = included.remove(&pin.path) {
+                pin.included_at = Some(now);
+                pin.included_mtime = mtime;
+                pin.included_hash = Some(hash);
+            }
+        }
+    }).await?;
+    Ok(out)
+}
+
+#[tauri::command]
+pub async fn pin_context_file(app: tauri::AppHandle, conversation_id: String, path: String) -> Result<PinnedFile, String> {
+    let resolved = std::fs::canonicalize(&path).map_err(|e| format!("Cannot pin {}: {}", path, e))?;
+    if !resolved.is_file() {
+        return Err(format!("Not a file: {}", path));
+    }
+    let path = resolved.to_string_lossy().to_string();
+
+    modify_pins(&app, |pins| {
+        let files = pins.entry(conversation_id).or_default();
+        if let Some(existing) = files.iter().find(|pin| pin.path == path) {
+            return existing.clone();
+        }
+        let pin = PinnedFile {
+            path,
+            pinned_at: unix_millis(),
+            included_at: None,
+            included_mtime: None,
+            included_hash: None,
+        };
+        files.push(pin.clone());
+        pin
+    }).await
+}
+
+#[tauri::command]
+pub async fn unpin_context_file(app: tauri::AppHandle, conversation_id: String, path: String) -> Result<bool, String> {
+    // Deleted files can't be canonicalized, so match the stored path as given too
+    let resolved = std::fs::canonicalize(&path)
+        .map(|p| p.to_string_lossy().to_string())
+        .unwrap_or_else(|_| path.clone());
+    modify_pins(&app, |pins| {
+        let Some(files) = pins.get_mut(&conversation_id) else { return false };
+        let before = files.len();
+        files.retain(|pin| pin.path != resolved && pin.path != path);
+        files.len() != before
+    }).await
+}
+
+#[tauri::command]
+pub async fn get_pinned_files(app: tauri::AppHandle, conversation_id: String) -> Result<Vec<PinnedFileStatus>, String> {
+    let pins = read_pins(&app).await.remove(&conversation_id).unwrap_or_default();
+    let mut statuses = Vec::new();
+    for pin in pins {
+        let status = status_of(&pin).await.to_string();
+        statuses.push(PinnedFileStatus { pin, status });
+    }
+    Ok(statuses)
+}
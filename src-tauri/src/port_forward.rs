@@ -0,0 +1,318 @@
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::Mutex;
+use tokio::task::JoinSet;
+
+use crate::unix_millis;
+
+// Connections with no traffic in either direction for this long are closed
+const DEFAULT_IDLE_TIMEOUT_SECS: u64 = 300;
+// Pause after a failed accept, so running out of file descriptors doesn't
+// turn the accept loop into a busy loop
+const ACCEPT_ERROR_BACKOFF: Duration = Duration::from_millis(100);
+// Finished connections kept per forward for get_port_forward_log
+const MAX_LOG_ENTRIES: usize = 200;
+// Longest HTTP request line we look for at the start of a connection
+const MAX_REQUEST_LINE_BYTES: usize = 2048;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PortForwardConfig {
+    pub id: String,
+    pub listen_port: u16,
+    pub target_host: String,
+    pub target_port: u16,
+    pub idle_timeout_secs: Option<u64>,
+}
+
+#[derive(Default)]
+struct ForwardCounters {
+    active_connections: AtomicU64,
+    total_connections: AtomicU64,
+    failed_connections: AtomicU64,
+    accept_errors: AtomicU64,
+    bytes_to_target: AtomicU64,
+    bytes_from_target: AtomicU64,
+}
+
+#[derive(Clone, Serialize)]
+pub struct PortForwardStats {
+    pub id: String,
+    pub listen_port: u16,
+    pub target: String,
+    pub active_connections: u64,
+    pub total_connections: u64,
+    pub failed_connections: u64,
+    pub accept_errors: u64,
+    pub bytes_to_target: u64,
+    pub bytes_from_target: u64,
+    pub last_accept_error: Option<String>,
+}
+
+// One finished connection through a forward
+#[derive(Clone, Serialize)]
+pub struct ConnectionLogEntry {
+    pub peer: String,
+    pub opened_at: u64,
+    pub duration_ms: u64,
+    // Like "GET /api/items", when the client opened with an HTTP request.
+    // Only the connection's first request is seen.
+    pub request: Option<String>,
+    pub bytes_to_target: u64,
+    pub bytes_from_target: u64,
+    // Set when the target couldn't be reached
+    pub error: Option<String>,
+}
+
+// Shared between a forward's accept loop and its connections
+#[derive(Default)]
+struct ForwardLog {
+    connections: VecDeque<ConnectionLogEntry>,
+    last_accept_error: Option<String>,
+}
+
+impl ForwardLog {
+    fn push(&mut self, entry: ConnectionLogEntry) {
+        if self.connections.len() == MAX_LOG_ENTRIES {
+            self.connections.pop_front();
+        }
+        self.connections.push_back(entry);
+    }
+}
+
+struct PortForward {
+    config: PortForwardConfig,
+    counters: Arc<ForwardCounters>,
+    log: Arc<std::sync::Mutex<ForwardLog>>,
+    // Owns the accept loop, which in turn owns every connection task
+    task: tokio::task::JoinHandle<()>,
+}
+
+static PORT_FORWARDS: Lazy<Arc<Mutex<HashMap<String, PortForward>>>> =
+    Lazy::new(|| Arc::new(Mutex::new(HashMap::new())));
+
+// "METHOD /path" from the start of an HTTP request, if that's what it is
+fn request_line(bytes: &[u8]) -> Option<String> {
+    let head = &bytes[..bytes.len().min(MAX_REQUEST_LINE_BYTES)];
+    let end = head.windows(2).position(|w| w == b"\r\n")?;
+    let line = std::str::from_utf8(&head[..end]).ok()?;
+    let mut parts = line.split(' ');
+    let (method, target, version) = (parts.next()?, parts.next()?, parts.next()?);
+    let is_method = !method.is_empty() && method.bytes().all(|b| b.is_ascii_uppercase());
+    (is_method && parts.next().is_none() && version.starts_with("HTTP/")).then(|| format!("{} {}", method, target))
+}
+
+// Copy one direction of a connection until EOF, an error, or the whole
+// connection has been idle for `idle`. Returns the bytes copied and, if
+// asked to look, the HTTP request line the data started with.
+async fn pipe(
+    mut from: OwnedReadHalf,
+    mut to: OwnedWriteHalf,
+    counter: &AtomicU64,
+    last_activity: &std::sync::Mutex<Instant>,
+    idle: Duration,
+    find_request: bool,
+) -> (u64, Option<String>) {
+    let mut buf = vec![0u8; 16 * 1024];
+    let mut copied = 0;
+    let mut request = None;
+    loop {
+        match tokio::time::timeout(idle, from.read(&mut buf)).await {
+            Ok(Ok(0)) | Ok(Err(_)) => break,
+            Ok(Ok(n)) => {
+                if find_request && copied == 0 {
+                    request = request_line(&buf[..n]);
+                }
+                if to.write_all(&buf[..n]).await.is_err() {
+                    break;
+                }
+                copied += n as u64;
+                counter.fetch_add(n as u64, Ordering::Relaxed);
+                *last_activity.lock().unwrap() = Instant::now();
+            }
+            Err(_) => {
+                // Quiet on this side, but the other direction may still be busy
+                if last_activity.lock().unwrap().elapsed() >= idle {
+                    break;
+                }
+            }
+        }
+    }
+    let _ = to.shutdown().await;
+    (copied, request)
+}
+
+async fn handle_connection(
+    client: TcpStream,
+    peer: String,
+    target: String,
+    counters: Arc<ForwardCounters>,
+    log: Arc<std::sync::Mutex<ForwardLog>>,
+    idle: Duration,
+) {
+    let opened_at = unix_millis();
+    let started = Instant::now();
+    let mut entry = ConnectionLogEntry {
+        peer,
+        opened_at,
+        duration_ms: 0,
+        request: None,
+        bytes_to_target: 0,
+        bytes_from_target: 0,
+        error: None,
+    };
+    let upstream = match TcpStream::connect(&target).await {
+        Ok(stream) => stream,
+        Err(e) => {
+            counters.failed_connections.fetch_add(1, Ordering::Relaxed);
+            entry.duration_ms = started.elapsed().as_millis() as u64;
+            entry.error = Some(format!("Cannot reach {}: {}", target, e));
+            log.lock().unwrap().push(entry);
+            return;
+        }
+    };
+
+    counters.active_connections.fetch_add(1, Ordering::Relaxed);
+    let (client_read, client_write) = client.into_split();
+    let (upstream_read, upstream_write) = upstream.into_split();
+    let last_activity = std::sync::Mutex::new(Instant::now());
+
+    let ((to_target, request), (from_target, _)) = tokio::join!(
+        pipe(client_read, upstream_write, &counters.bytes_to_target, &last_activity, idle, true),
+        pipe(upstream_read, client_write, &counters.bytes_from_target, &last_activity, idle, false),
+    );
+    counters.active_connections.fetch_sub(1, Ordering::Relaxed);
+
+    entry.duration_ms = started.elapsed().as_millis() as u64;
+    entry.request = request;
+    entry.bytes_to_target = to_target;
+    entry.bytes_from_target = from_target;
+    log.lock().unwrap().push(entry);
+}
+
+#[tauri::command]
+pub async fn start_port_forward(config: PortForwardConfig) -> Result<(), String> {
+    let mut forwards = PORT_FORWARDS.lock().await;
+    if forwards.contains_key(&config.id) {
+        return Err("Port forward is already running".to_string());
+    }
+
+    // Binding up front surfaces port conflicts to the caller
+    let listener = TcpListener::bind(("127.0.0.1", config.listen_port)).await
+        .map_err(|e| format!("Cannot listen on port {}: {}", config.listen_port, e))?;
+
+    let counters = Arc::new(ForwardCounters::default());
+    let target = format!("{}:{}", config.target_host, config.target_port);
+    let idle = Duration::from_secs(config.idle_timeout_secs.unwrap_or(DEFAULT_IDLE_TIMEOUT_SECS));
+
+    let log = Arc::new(std::sync::Mutex::new(ForwardLog::default()));
+    let task_counters = counters.clone();
+    let task_log = log.clone();
+    let description = format!("port forward {} -> {}", config.listen_port, target);
+    let task = crate::background_tasks::spawn(description, async move {
+        // Dropping the set (when this task is aborted) aborts every open connection
+        let mut connections = JoinSet::new();
+        loop {
+            tokio::select! {
+                accepted = listener.accept() => match accepted {
+                    Ok((client, peer)) => {
+                        task_counters.total_connections.fetch_add(1, Ordering::Relaxed);
+                        connections.spawn(handle_connection(
+                            client,
+                            peer.to_string(),
+                            target.clone(),
+                            task_counters.clone(),
+                            task_log.clone(),
+                            idle,
+                        ));
+                    }
+                    Err(e) => {
+                        task_counters.accept_errors.fetch_add(1, Ordering::Relaxed);
+                        task_log.lock().unwrap().last_accept_error = Some(e.to_string());
+                        tokio::time::sleep(ACCEPT_ERROR_BACKOFF).await;
+                    }
+                },
+                // Reap finished connections so the set doesn't grow forever
+                Some(_) = connections.join_next(), if !connections.is_empty() => {}
+            }
+        }
+    });
+
+    forwards.insert(config.id.clone(), PortForward { config, counters, log, task });
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn stop_port_forward(id: String) -> Result<bool, String> {
+    let mut forwards = PORT_FORWARDS.lock().await;
+    if let Some(forward) = forwards.remove(&id) {
+        forward.task.abort();
+        Ok(true)
+    } else {
+        Ok(false)
+    }
+}
+
+#[tauri::command]
+pub async fn get_port_forward_stats(id: String) -> Result<PortForwardStats, String> {
+    let forwards = PORT_FORWARDS.lock().await;
+    let forward = forwards.get(&id).ok_or_else(|| format!("No port forward with id {}", id))?;
+    let counters = &forward.counters;
+    let last_accept_error = forward.log.lock().unwrap().last_accept_error.clone();
+    Ok(PortForwardStats {
+        id: forward.config.id.clone(),
+        listen_port: forward.config.listen_port,
+        target: format!("{}:{}", forward.config.target_host, forward.config.target_port),
+        active_connections: counters.active_connections.load(Ordering::Relaxed),
+        total_connections: counters.total_connections.load(Ordering::Relaxed),
+        failed_connections: counters.failed_connections.load(Ordering::Relaxed),
+        accept_errors: counters.accept_errors.load(Ordering::Relaxed),
+        bytes_to_target: counters.bytes_to_target.load(Ordering::Relaxed),
+        bytes_from_target: counters.bytes_from_target.load(Ordering::Relaxed),
+        last_accept_error,
+    })
+}
+
+// The most recent finished connections, oldest first
+#[tauri::command]
+pub async fn get_port_forward_log(id: String) -> Result<Vec<ConnectionLogEntry>, String> {
+    let forwards = PORT_FORWARDS.lock().await;
+    let forward = forwards.get(&id).ok_or_else(|| format!("No port forward with id {}", id))?;
+    let log = forward.log.lock().unwrap();
+    Ok(log.connections.iter().cloned().collect())
+}
+
+// Tear down every forward, used when the app exits
+pub(crate) async fn stop_all_port_forwards() {
+    let mut forwards = PORT_FORWARDS.lock().await;
+    for (_, forward) in forwards.drain() {
+        forward.task.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn request_lines_are_read_from_http_requests_only() {
+        assert_eq!(request_line(b"GET /api/items?page=2 HTTP/1.1\r\nHost: x\r\n\r\n").as_deref(), Some("GET /api/items?page=2"));
+        assert_eq!(request_line(b"OPTIONS * HTTP/1.1\r\n").as_deref(), Some("OPTIONS *"));
+        for bytes in [
+            &b"\x16\x03\x01\x02\x00"[..],
+            b"GET /no-line-end HTTP/1.1",
+            b"get / HTTP/1.1\r\n",
+            b"GET / SMTP\r\n",
+            b"GET /a b HTTP/1.1\r\n",
+            b"",
+        ] {
+            assert_eq!(request_line(bytes), None);
+        }
+    }
+}
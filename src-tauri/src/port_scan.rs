@@ -0,0 +1,240 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::net::TcpStream;
+use tokio::task::JoinSet;
+
+// Ports dev servers and databases usually sit on
+const DEFAULT_PORTS: &[u16] = &[
+    3000, 3001, 3306, 4000, 4200, 5000, 5173, 5432, 5500, 6379, 8000, 8080, 8081, 8443, 8888, 9000, 9200, 27017,
+];
+const MAX_PORTS: usize = 1024;
+const CONNECT_TIMEOUT: Duration = Duration::from_millis(400);
+// The whole scan, owner lookup included, gives up after this
+const SCAN_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Clone, Serialize)]
+pub struct PortStatus {
+    pub port: u16,
+    pub listening: bool,
+    // Filled in when the owning process could be found and read
+    pub pid: Option<u32>,
+    pub process_name: Option<String>,
+    pub command_line: Option<String>,
+    // Set when the listener is one of this app's services or a child of one
+    pub service_id: Option<String>,
+}
+
+#[derive(Clone, Default)]
+struct Owner {
+    pid: u32,
+    name: Option<String>,
+    command_line: Option<String>,
+}
+
+async fn is_listening(port: u16) -> bool {
+    for addr in ["127.0.0.1", "::1"] {
+        if let Ok(Ok(_)) = tokio::time::timeout(CONNECT_TIMEOUT, TcpStream::connect((addr, port))).await {
+            return true;
+        }
+    }
+    false
+}
+
+// Listening sockets from /proc/net/tcp{,6}, then the process holding each
+// socket inode from /proc/<pid>/fd. Other users' processes can't be read
+// without privileges and are left unresolved.
+#[cfg(target_os = "linux")]
+async fn find_owners(ports: &[u16]) -> HashMap<u16, Owner> {
+    let mut inodes: HashMap<u64, u16> = HashMap::new();
+    for table in ["/proc/net/tcp", "/proc/net/tcp6"] {
+        let Ok(data) = tokio::fs::read_to_string(table).await else { continue };
+        for line in data.lines().skip(1) {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            // 0A is TCP_LISTEN
+            if fields.len() < 10 || fields[3] != "0A" {
+                continue;
+            }
+            let port = fields[1].rsplit(':').next().and_then(|p| u16::from_str_radix(p, 16).ok());
+            let inode = fields[9].parse::<u64>().ok();
+            if let (Some(port), Some(inode)) = (port, inode) {
+                if ports.contains(&port) && inode != 0 {
+                    inodes.insert(inode, port);
+                }
+            }
+        }
+    }
+    if inodes.is_empty() {
+        return HashMap::new();
+    }
+
+    let mut owners = HashMap::new();
+    let Ok(mut procs) = tokio::fs::read_dir("/proc").await else { return owners };
+    while let Ok(Some(entry)) = procs.next_entry().await {
+        let Some(pid) = entry.file_name().to_str().and_then(|name| name.parse::<u32>().ok()) else { continue };
+        let Ok(mut fds) = tokio::fs::read_dir(entry.path().join("fd")).await else { continue };
+        while let Ok(Some(fd)) = fds.next_entry().await {
+            let Ok(target) = tokio::fs::read_link(fd.path()).await else { continue };
+            let target = target.to_string_lossy();
+            let inode = target.strip_prefix("socket:[").and_then(|t| t.strip_suffix(']')).and_then(|t| t.parse::<u64>().ok());
+            if let Some(port) = inode.and_then(|inode| inodes.get(&inode)) {
+                owners.entry(*port).or_insert(Owner { pid, ..Default::default() });
+            }
+        }
+        if owners.len() == inodes.len() {
+            break;
+        }
+    }
+    for owner in owners.values_mut() {
+        let dir = std::path::PathBuf::from(format!("/proc/{}", owner.pid));
+        owner.name = tokio::fs::read_to_string(dir.join("comm")).await.ok().map(|s| s.trim().to_string());
+        owner.command_line = tokio::fs::read(dir.join("cmdline")).await.ok()
+            .map(|raw| raw.split(|b| *b == 0).filter(|arg| !arg.is_empty())
+                .map(|arg| String::from_utf8_lossy(arg).to_string())
+                .collect::<Vec<_>>()
+                .join(" "))
+            .filter(|s| !s.is_empty());
+    }
+    owners
+}
+
+// lsof lists every listening TCP socket with its pid and command name
+#[cfg(target_os = "macos")]
+async fn find_owners(ports: &[u16]) -> HashMap<u16, Owner> {
+    let mut owners = HashMap::new();
+    let Ok(output) = tokio::process::Command::new("lsof")
+        .args(["-nP", "-iTCP", "-sTCP:LISTEN", "-Fpcn"])
+        .output()
+        .await
+    else {
+        return owners;
+    };
+    let mut current = Owner::default();
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        let (tag, value) = line.split_at(line.len().min(1));
+        match tag {
+            "p" => current = Owner { pid: value.parse().unwrap_or(0), ..Default::default() },
+            "c" => current.name = Some(value.to_string()),
+            "n" => {
+                let port = value.rsplit(':').next().and_then(|p| p.parse::<u16>().ok());
+                if let Some(port) = port.filter(|port| ports.contains(port)) {
+                    owners.entry(port).or_insert_with(|| current.clone());
+                }
+            }
+            _ => {}
+        }
+    }
+    for owner in owners.values_mut() {
+        let output = tokio::process::Command::new("ps")
+            .args(["-o", "command=", "-p", &owner.pid.to_string()])
+            .output()
+            .await;
+        owner.command_line = output.ok()
+            .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string())
+            .filter(|s| !s.is_empty());
+    }
+    owners
+}
+
+// netstat's listening rows carry the pid; tasklist names it
+#[cfg(windows)]
+async fn find_owners(ports: &[u16]) -> HashMap<u16, Owner> {
+    let mut owners = HashMap::new();
+    let Ok(output) = tokio::process::Command::new("netstat").args(["-ano", "-p", "TCP"]).output().await else {
+        return owners;
+    };
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() < 5 || fields[3] != "LISTENING" {
+            continue;
+        }
+        let port = fields[1].rsplit(':').next().and_then(|p| p.parse::<u16>().ok());
+        let pid = fields[4].parse::<u32>().ok();
+        if let (Some(port), Some(pid)) = (port, pid) {
+            if ports.contains(&port) {
+                owners.entry(port).or_insert(Owner { pid, ..Default::default() });
+            }
+        }
+    }
+    for owner in owners.values_mut() {
+        let output = tokio::process::Command::new("tasklist")
+            .args(["/FI", &format!("PID eq {}", owner.pid), "/FO", "CSV", "/NH"])
+            .output()
+            .await;
+        owner.name = output.ok().and_then(|o| {
+            let stdout = String::from_utf8_lossy(&o.stdout).to_string();
+            stdout.split(',').next().map(|name| name.trim().trim_matches('"').to_string())
+        }).filter(|name| !name.is_empty() && !name.starts_with("INFO:"));
+    }
+    owners
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+async fn find_owners(_ports: &[u16]) -> HashMap<u16, Owner> {
+    HashMap::new()
+}
+
+// Services run in their own process group, so a server started by `npm run
+// dev` still belongs to the service even though npm isn't the listener
+fn owning_service(pid: u32, services: &[(String, u32)]) -> Option<String> {
+    if let Some((id, _)) = services.iter().find(|(_, service_pid)| *service_pid == pid) {
+        return Some(id.clone());
+    }
+    #[cfg(unix)]
+    {
+        let group = unsafe { libc::getpgid(pid as i32) };
+        if group > 0 {
+            return services.iter().find(|(_, service_pid)| *service_pid as i32 == group).map(|(id, _)| id.clone());
+        }
+    }
+    None
+}
+
+// Which of the given ports (or the usual dev ports) have something listening
+// on localhost, and what it is where that can be found out
+#[tauri::command]
+pub async fn scan_local_ports(ports: Option<Vec<u16>>) -> Result<Vec<PortStatus>, String> {
+    let mut ports = ports.unwrap_or_else(|| DEFAULT_PORTS.to_vec());
+    ports.sort_unstable();
+    ports.dedup();
+    ports.retain(|port| *port != 0);
+    if ports.len() > MAX_PORTS {
+        return Err(format!("At most {} ports can be scanned at once", MAX_PORTS));
+    }
+
+    let mut probes = JoinSet::new();
+    for port in ports.iter().copied() {
+        probes.spawn(async move { (port, is_listening(port).await) });
+    }
+    let lookup_ports = ports.clone();
+    let lookup = tokio::spawn(async move { find_owners(&lookup_ports).await });
+
+    let mut listening: HashMap<u16, bool> = HashMap::new();
+    let deadline = tokio::time::Instant::now() + SCAN_TIMEOUT;
+    while let Ok(Some(Ok((port, open)))) = tokio::time::timeout_at(deadline, probes.join_next()).await {
+        listening.insert(port, open);
+    }
+    probes.abort_all();
+    let abort = lookup.abort_handle();
+    let owners = match tokio::time::timeout_at(deadline, lookup).await {
+        Ok(Ok(owners)) => owners,
+        _ => {
+            abort.abort();
+            HashMap::new()
+        }
+    };
+
+    let services = crate::service_pids().await;
+    Ok(ports.into_iter().map(|port| {
+        let owner = owners.get(&port).cloned();
+        PortStatus {
+            port,
+            // A socket found in the OS tables counts even if the probe didn't finish
+            listening: listening.get(&port).copied().unwrap_or(false) || owner.is_some(),
+            pid: owner.as_ref().map(|o| o.pid),
+            service_id: owner.as_ref().and_then(|o| owning_service(o.pid, &services)),
+            process_name: owner.as_ref().and_then(|o| o.name.clone()),
+            command_line: owner.and_then(|o| o.command_line),
+        }
+    }).collect())
+}
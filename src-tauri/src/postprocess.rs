@@ -0,0 +1,223 @@
+use serde::{Deserialize, Serialize};
+
+use crate::settings;
+
+// Shallowest heading in a response is moved to this level; a top-level
+// heading is too loud inside a chat message
+const TOP_HEADING_LEVEL: usize = 2;
+
+// Which built-in transforms run on claude's final response
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ResponseTransforms {
+    pub normalize_headings: bool,
+    pub relative_paths: bool,
+    pub trim_trailing_whitespace: bool,
+}
+
+impl ResponseTransforms {
+    fn any(&self) -> bool {
+        self.normalize_headings || self.relative_paths || self.trim_trailing_whitespace
+    }
+}
+
+fn is_fence(line: &str) -> bool {
+    let trimmed = line.trim_start_matches(' ');
+    line.len() - trimmed.len() <= 3 && (trimmed.starts_with("```") || trimmed.starts_with("~~~"))
+}
+
+// Split into lines tagged with whether they sit inside a fenced code block.
+// Fence lines themselves count as code.
+fn lines_with_code_state(text: &str) -> Vec<(&str, bool)> {
+    let mut in_code = false;
+    text.split('\n').map(|line| {
+        if is_fence(line) {
+            in_code = !in_code;
+            (line, true)
+        } else {
+            (line, in_code)
+        }
+    }).collect()
+}
+
+fn heading_level(line: &str) -> Option<usize> {
+    let hashes = line.chars().take_while(|c| *c == '#').count();
+    let rest = &line[hashes..];
+    ((1..=6).contains(&hashes) && (rest.is_empty() || rest.starts_with(' '))).then_some(hashes)
+}
+
+// Shift ATX headings so the shallowest one ends up at TOP_HEADING_LEVEL,
+// keeping their relative depth. Code blocks are left alone.
+pub(crate) fn normalize_headings(text: &str) -> String {
+    let lines = lines_with_code_state(text);
+    let Some(min_level) = lines.iter()
+        .filter(|(_, in_code)| !in_code)
+        .filter_map(|(line, _)| heading_level(line))
+        .min()
+    else {
+        return text.to_string();
+    };
+
+    lines.iter().map(|(line, in_code)| {
+        match heading_level(line) {
+            Some(level) if !in_code => {
+                let new_level = (level + TOP_HEADING_LEVEL).saturating_sub(min_level).clamp(1, 6);
+                format!("{}{}", "#".repeat(new_level), &line[level..])
+            }
+            _ => line.to_string(),
+        }
+    }).collect::<Vec<_>>().join("\n")
+}
+
+fn is_path_char(c: char) -> bool {
+    c.is_alphanumeric() || "._-~/".contains(c)
+}
+
+// Rewrite absolute paths under `workspace` to workspace-relative ones.
+// Paths in fenced code blocks are left as written, since those are often
+// commands meant to be copied and run from anywhere.
+pub(crate) fn relativize_paths(text: &str, workspace: &str) -> String {
+    let workspace = workspace.trim_end_matches('/');
+    if workspace.is_empty() {
+        return text.to_string();
+    }
+
+    lines_with_code_state(text).iter().map(|(line, in_code)| {
+        if *in_code {
+            return line.to_string();
+        }
+        let mut out = String::with_capacity(line.len());
+        let mut rest = *line;
+        while let Some(pos) = rest.find(workspace) {
+            let before = rest[..pos].chars().next_back();
+            let after_start = pos + workspace.len();
+            let after = rest[after_start..].chars().next();
+            // Only whole paths: not part of a longer word before, and the
+            // workspace name must end at a separator or the end of the path
+            let starts_path = before.is_none_or(|c| !is_path_char(c));
+            // A full stop straight after is the end of a sentence, not of a longer name
+            let ends_path = match after {
+                Some('.') => rest[after_start + 1..].chars().next().is_none_or(|c| !is_path_char(c)),
+                after => after.is_none_or(|c| !is_path_char(c)),
+            };
+            if starts_path && after == Some('/') && rest[after_start + 1..].starts_with(|c: char| is_path_char(c) && c != '/') {
+                out.push_str(&rest[..pos]);
+                rest = &rest[after_start + 1..];
+            } else if starts_path && ends_path {
+                out.push_str(&rest[..pos]);
+                out.push('.');
+                rest = &rest[after_start..];
+            } else {
+                out.push_str(&rest[..after_start]);
+                rest = &rest[after_start..];
+            }
+        }
+        out.push_str(rest);
+        out
+    }).collect::<Vec<_>>().join("\n")
+}
+
+// Strip whitespace at the end of each line and trailing blank lines
+pub(crate) fn trim_trailing_whitespace(text: &str) -> String {
+    text.split('\n')
+        .map(str::trim_end)
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim_end()
+        .to_string()
+}
+
+// Run the enabled transforms over a complete response
+pub(crate) fn apply(text: &str, transforms: &ResponseTransforms, workspace: Option<&str>) -> String {
+    if !transforms.any() {
+        return text.to_string();
+    }
+    let mut text = text.to_string();
+    if transforms.normalize_headings {
+        text = normalize_headings(&text);
+    }
+    if let (true, Some(workspace)) = (transforms.relative_paths, workspace) {
+        text = relativize_paths(&text, workspace);
+    }
+    if transforms.trim_trailing_whitespace {
+        text = trim_trailing_whitespace(&text);
+    }
+    text
+}
+
+#[tauri::command]
+pub async fn set_response_transforms(app: tauri::AppHandle, transforms: ResponseTransforms) -> Result<(), String> {
+    settings::update(&app, |settings| settings.response_transforms = transforms).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WORKSPACE: &str = "/home/dev/project";
+
+    #[test]
+    fn headings_shift_to_the_top_level_keeping_depth() {
+        let text = "# Title\ntext\n### Detail\n###### Deepest";
+        assert_eq!(normalize_headings(text), "## Title\ntext\n#### Detail\n###### Deepest");
+        // Already at the top level, or no headings at all
+        assert_eq!(normalize_headings("## A\n### B"), "## A\n### B");
+        assert_eq!(normalize_headings("#hashtag and #1"), "#hashtag and #1");
+    }
+
+    #[test]
+    fn headings_in_code_blocks_are_not_touched() {
+        let text = "#### Setup\n```sh\n# install\nnpm i\n```\n#### Run";
+        assert_eq!(normalize_headings(text), "## Setup\n```sh\n# install\nnpm i\n```\n## Run");
+    }
+
+    #[test]
+    fn paths_under_the_workspace_become_relative() {
+        let text = "Edited /home/dev/project/src/main.rs and `/home/dev/project/Cargo.toml`.";
+        assert_eq!(relativize_paths(text, WORKSPACE), "Edited src/main.rs and `Cargo.toml`.");
+        assert_eq!(relativize_paths("Ran in /home/dev/project.", WORKSPACE), "Ran in ..");
+        assert_eq!(relativize_paths("In /home/dev/project/src", "/home/dev/project/"), "In src");
+    }
+
+    #[test]
+    fn other_paths_are_left_alone() {
+        for text in [
+            "/home/dev/project2/src/main.rs",
+            "/mnt/home/dev/project/src/main.rs",
+            "/home/dev/projects/readme",
+            "/home/dev/project.old/notes",
+            "see ~/home/dev/project/x",
+        ] {
+            assert_eq!(relativize_paths(text, WORKSPACE), text);
+        }
+        assert_eq!(relativize_paths("/home/dev/project/src", ""), "/home/dev/project/src");
+    }
+
+    #[test]
+    fn paths_in_code_fences_keep_their_absolute_form() {
+        let text = "Open /home/dev/project/a.rs\n```sh\ncd /home/dev/project/app && make\n```\n~~~\n/home/dev/project/b.rs\n~~~\nThen /home/dev/project/c.rs";
+        let expected = "Open a.rs\n```sh\ncd /home/dev/project/app && make\n```\n~~~\n/home/dev/project/b.rs\n~~~\nThen c.rs";
+        assert_eq!(relativize_paths(text, WORKSPACE), expected);
+    }
+
+    #[test]
+    fn trailing_whitespace_and_blank_lines_are_removed() {
+        assert_eq!(trim_trailing_whitespace("a  \n    b\t\n\n\n"), "a\n    b");
+        assert_eq!(trim_trailing_whitespace("  \n"), "");
+    }
+
+    #[test]
+    fn only_enabled_transforms_run() {
+        let text = "# Title  \nSee /home/dev/project/src/lib.rs\n";
+        assert_eq!(apply(text, &ResponseTransforms::default(), Some(WORKSPACE)), text);
+
+        let all = ResponseTransforms { normalize_headings: true, relative_paths: true, trim_trailing_whitespace: true };
+        assert_eq!(apply(text, &all, Some(WORKSPACE)), "## Title\nSee src/lib.rs");
+        // No workspace, nothing to be relative to
+        assert_eq!(apply(text, &all, None), "## Title\nSee /home/dev/project/src/lib.rs");
+
+        let paths_only = ResponseTransforms { relative_paths: true, ..Default::default() };
+        assert_eq!(apply(text, &paths_only, Some(WORKSPACE)), "# Title  \nSee src/lib.rs\n");
+    }
+}
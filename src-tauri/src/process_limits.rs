@@ -0,0 +1,123 @@
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::process::Command;
+use tokio::sync::Mutex;
+
+// Length of one run/pause cycle when limiting CPU
+const THROTTLE_PERIOD_MS: u64 = 100;
+// Niceness used for "low" priority
+#[cfg(unix)]
+const LOW_PRIORITY_NICE: i32 = 10;
+#[cfg(windows)]
+const BELOW_NORMAL_PRIORITY_CLASS: u32 = 0x0000_4000;
+
+#[derive(Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct ProcessLimits {
+    // "low" or "normal"
+    pub priority: Option<String>,
+    // Duty-cycled with SIGSTOP/SIGCONT on unix
+    pub max_cpu_percent: Option<u8>,
+}
+
+impl ProcessLimits {
+    pub(crate) fn new(priority: Option<String>, max_cpu_percent: Option<u8>) -> Result<Self, String> {
+        if let Some(ref p) = priority {
+            if p != "low" && p != "normal" {
+                return Err(format!("Unknown priority: {}", p));
+            }
+        }
+        if let Some(pct) = max_cpu_percent {
+            if !(1..=100).contains(&pct) {
+                return Err("max_cpu_percent must be between 1 and 100".to_string());
+            }
+        }
+        Ok(ProcessLimits { priority, max_cpu_percent })
+    }
+
+    fn is_low(&self) -> bool {
+        self.priority.as_deref() == Some("low")
+    }
+}
+
+// Limits applied to live processes, keyed by pid (which is also the process
+// group id, since everything we spawn leads its own group)
+static APPLIED: Lazy<Arc<Mutex<HashMap<u32, ProcessLimits>>>> =
+    Lazy::new(|| Arc::new(Mutex::new(HashMap::new())));
+
+// Lower the priority at spawn time; children inherit it
+pub(crate) fn apply_priority(cmd: &mut Command, limits: &ProcessLimits) {
+    if !limits.is_low() {
+        return;
+    }
+    #[cfg(unix)]
+    unsafe {
+        cmd.pre_exec(|| {
+            libc::setpriority(libc::PRIO_PROCESS as _, 0, LOW_PRIORITY_NICE);
+            Ok(())
+        });
+    }
+    #[cfg(windows)]
+    cmd.creation_flags(BELOW_NORMAL_PRIORITY_CLASS);
+}
+
+#[cfg(unix)]
+fn group_alive(pgid: u32) -> bool {
+    unsafe { libc::killpg(pgid as i32, 0) == 0 }
+}
+
+#[cfg(unix)]
+fn signal_group(pgid: u32, signal: i32) {
+    unsafe {
+        libc::killpg(pgid as i32, signal);
+    }
+}
+
+// Remember the limits for reporting and, if a CPU cap was asked for, keep
+// pausing and resuming the process group until it exits or is released
+pub(crate) async fn track(pid: u32, limits: ProcessLimits) {
+    if limits == ProcessLimits::default() {
+        return;
+    }
+    let max_cpu = limits.max_cpu_percent.filter(|pct| *pct < 100);
+    APPLIED.lock().await.insert(pid, limits);
+
+    #[cfg(unix)]
+    crate::background_tasks::spawn(format!("process limits ({})", pid), async move {
+        loop {
+            if !APPLIED.lock().await.contains_key(&pid) || !group_alive(pid) {
+                break;
+            }
+            match max_cpu {
+                Some(pct) => {
+                    let run = THROTTLE_PERIOD_MS * pct as u64 / 100;
+                    signal_group(pid, libc::SIGCONT);
+                    tokio::time::sleep(Duration::from_millis(run)).await;
+                    signal_group(pid, libc::SIGSTOP);
+                    tokio::time::sleep(Duration::from_millis(THROTTLE_PERIOD_MS - run)).await;
+                }
+                None => tokio::time::sleep(Duration::from_secs(1)).await,
+            }
+        }
+        // Never leave the group paused
+        if max_cpu.is_some() {
+            signal_group(pid, libc::SIGCONT);
+        }
+        APPLIED.lock().await.remove(&pid);
+    });
+}
+
+// Stop throttling before a process is signalled to exit, so it isn't sitting
+// stopped when the signal arrives
+pub(crate) async fn release(pid: u32) {
+    if APPLIED.lock().await.remove(&pid).is_some() {
+        #[cfg(unix)]
+        signal_group(pid, libc::SIGCONT);
+    }
+}
+
+pub(crate) async fn limits_for(pid: u32) -> ProcessLimits {
+    APPLIED.lock().await.get(&pid).cloned().unwrap_or_default()
+}
@@ -0,0 +1,67 @@
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+use tokio::io::AsyncReadExt;
+use tokio::sync::Mutex;
+
+use crate::ClaudeSpawnParams;
+
+// Looked for in the working directory, first match wins
+const CONTEXT_FILES: &[&str] = &["CLAUDE.md", "AGENTS.md", ".claude/CONTEXT.md"];
+// Anything past this is left out of the system prompt
+const MAX_CONTEXT_BYTES: u64 = 64 * 1024;
+
+struct CachedContext {
+    modified: SystemTime,
+    len: u64,
+    text: String,
+}
+
+// By file path; re-read only when the file's mtime or size changes
+static CACHE: Lazy<Arc<Mutex<HashMap<PathBuf, CachedContext>>>> =
+    Lazy::new(|| Arc::new(Mutex::new(HashMap::new())));
+
+async fn read_limited(path: &Path) -> std::io::Result<String> {
+    let file = tokio::fs::File::open(path).await?;
+    let mut bytes = Vec::new();
+    file.take(MAX_CONTEXT_BYTES).read_to_end(&mut bytes).await?;
+    Ok(String::from_utf8_lossy(&bytes).to_string())
+}
+
+async fn load(dir: &Path) -> Option<String> {
+    for name in CONTEXT_FILES {
+        let path = dir.join(name);
+        let Ok(metadata) = tokio::fs::metadata(&path).await else { continue };
+        if !metadata.is_file() {
+            continue;
+        }
+        let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+
+        let mut cache = CACHE.lock().await;
+        if let Some(cached) = cache.get(&path) {
+            if cached.modified == modified && cached.len == metadata.len() {
+                return Some(cached.text.clone());
+            }
+        }
+        // Unreadable like missing: try the next name
+        let Ok(contents) = read_limited(&path).await else { continue };
+        if contents.trim().is_empty() {
+            continue;
+        }
+        let text = format!("Project context from {} in the working directory:\n\n{}", name, contents.trim_end());
+        cache.insert(path, CachedContext { modified, len: metadata.len(), text: text.clone() });
+        return Some(text);
+    }
+    None
+}
+
+// Text for --append-system-prompt when the spawn asked for its project's
+// context file and the working directory has one
+pub(crate) async fn for_spawn(params: &ClaudeSpawnParams, work_dir: Option<&str>) -> Option<String> {
+    if !params.load_project_context.unwrap_or(false) {
+        return None;
+    }
+    load(Path::new(work_dir?)).await
+}
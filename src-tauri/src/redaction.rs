@@ -0,0 +1,299 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use tauri::Emitter;
+
+use crate::{attachments, get_data_path, transcripts, unix_millis, with_suffix, write_atomic};
+
+const DEFAULT_REPLACEMENT: &str = "[REDACTED]";
+// Characters of surrounding text shown on each side of a previewed match
+const CONTEXT_CHARS: usize = 40;
+// Matches listed in a report; all of them are still counted
+const MAX_LISTED_MATCHES: usize = 500;
+
+// Exactly one of regex or literal
+#[derive(Clone, Deserialize)]
+pub struct RedactionPattern {
+    pub regex: Option<String>,
+    pub literal: Option<String>,
+    // Defaults to [REDACTED]
+    pub replacement: Option<String>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct RedactionMatch {
+    // "transcript" or "attachment:<id>"
+    pub source: String,
+    // JSON pointer to the string within the transcript; empty for attachments
+    pub pointer: String,
+    pub matched: String,
+    pub context: String,
+    pub replacement: String,
+}
+
+#[derive(Clone, Serialize)]
+pub struct RedactionReport {
+    pub conversation_id: String,
+    pub dry_run: bool,
+    pub total_matches: usize,
+    pub matches: Vec<RedactionMatch>,
+    // Files rewritten; always empty in a dry run
+    pub rewritten: Vec<String>,
+    // Redacted attachments are stored anew, so their ids change: old id -> new id
+    pub replaced_attachments: BTreeMap<String, String>,
+}
+
+#[derive(Clone, Serialize)]
+struct ConversationRedacted {
+    conversation_id: String,
+}
+
+struct Rule {
+    regex: Regex,
+    replacement: String,
+}
+
+#[derive(Default)]
+struct Found {
+    total: usize,
+    matches: Vec<RedactionMatch>,
+}
+
+fn compile(patterns: &[RedactionPattern]) -> Result<Vec<Rule>, String> {
+    if patterns.is_empty() {
+        return Err("No redaction patterns given".to_string());
+    }
+    patterns.iter().map(|pattern| {
+        let regex = match (&pattern.regex, &pattern.literal) {
+            (Some(regex), None) if !regex.is_empty() => {
+                Regex::new(regex).map_err(|e| format!("Invalid pattern {}: {}", regex, e))?
+            }
+            (None, Some(literal)) if !literal.is_empty() => Regex::new(&regex::escape(literal)).map_err(|e| e.to_string())?,
+            _ => return Err("Each pattern needs exactly one non-empty regex or literal".to_string()),
+        };
+        let replacement = pattern.replacement.clone().unwrap_or_else(|| DEFAULT_REPLACEMENT.to_string());
+        Ok(Rule { regex, replacement })
+    }).collect()
+}
+
+// Up to CONTEXT_CHARS characters either side of the match
+fn context(text: &str, start: usize, end: usize) -> String {
+    let before: String = text[..start].chars().rev().take(CONTEXT_CHARS).collect::<Vec<_>>().into_iter().rev().collect();
+    let after: String = text[end..].chars().take(CONTEXT_CHARS).collect();
+    format!("{}{}{}", before, &text[start..end], after)
+}
+
+// Apply every rule in turn. Text already covered by a replacement marker is
+// left alone, so running the same redaction twice changes nothing.
+fn redact_text(rules: &[Rule], text: &str, source: &str, pointer: &str, found: &mut Found) -> Option<String> {
+    let mut current: Option<String> = None;
+    for rule in rules {
+        let text = current.as_deref().unwrap_or(text);
+        let marked: Vec<(usize, usize)> = rules.iter()
+            .filter(|r| !r.replacement.is_empty())
+            .flat_map(|r| text.match_indices(r.replacement.as_str()).map(|(i, m)| (i, i + m.len())).collect::<Vec<_>>())
+            .collect();
+        let hits: Vec<_> = rule.regex.find_iter(text)
+            .filter(|m| !m.is_empty() && !marked.iter().any(|&(start, end)| m.start() < end && start < m.end()))
+            .collect();
+        if hits.is_empty() {
+            continue;
+        }
+
+        let mut out = String::with_capacity(text.len());
+        let mut last = 0;
+        for m in hits {
+            found.total += 1;
+            if found.matches.len() < MAX_LISTED_MATCHES {
+                found.matches.push(RedactionMatch {
+                    source: source.to_string(),
+                    pointer: pointer.to_string(),
+                    matched: m.as_str().to_string(),
+                    context: context(text, m.start(), m.end()),
+                    replacement: rule.replacement.clone(),
+                });
+            }
+            out.push_str(&text[last..m.start()]);
+            out.push_str(&rule.replacement);
+            last = m.end();
+        }
+        out.push_str(&text[last..]);
+        current = Some(out);
+    }
+    current
+}
+
+fn redact_value(rules: &[Rule], value: &mut serde_json::Value, pointer: &str, found: &mut Found) -> bool {
+    match value {
+        serde_json::Value::String(text) => match redact_text(rules, text, "transcript", pointer, found) {
+            Some(redacted) => {
+                *text = redacted;
+                true
+            }
+            None => false,
+        },
+        serde_json::Value::Array(items) => {
+            let mut changed = false;
+            for (i, item) in items.iter_mut().enumerate() {
+                changed |= redact_value(rules, item, &format!("{}/{}", pointer, i), found);
+            }
+            changed
+        }
+        serde_json::Value::Object(map) => {
+            let mut changed = false;
+            for (key, item) in map.iter_mut() {
+                let key = key.replace('~', "~0").replace('/', "~1");
+                changed |= redact_value(rules, item, &format!("{}/{}", pointer, key), found);
+            }
+            changed
+        }
+        _ => false,
+    }
+}
+
+// Point references at the redacted copies of attachments
+fn replace_ids(value: &mut serde_json::Value, replaced: &BTreeMap<String, String>) {
+    match value {
+        serde_json::Value::String(text) => {
+            for (old, new) in replaced {
+                if text.contains(old.as_str()) {
+                    *text = text.replace(old.as_str(), new);
+                }
+            }
+        }
+        serde_json::Value::Array(items) => items.iter_mut().for_each(|item| replace_ids(item, replaced)),
+        serde_json::Value::Object(map) => map.values_mut().for_each(|item| replace_ids(item, replaced)),
+        _ => {}
+    }
+}
+
+// The conversation's entry in the frontend's data file
+fn find_conversation<'a>(data: &'a mut serde_json::Value, conversation_id: &str) -> Option<&'a mut serde_json::Value> {
+    // zustand's persist middleware wraps everything in {"state": ..., "version": n}
+    let state = if data.get("state").is_some() { &mut data["state"] } else { data };
+    state.get_mut("conversations")?.as_array_mut()?
+        .iter_mut()
+        .find(|c| c.get("id").and_then(|i| i.as_str()) == Some(conversation_id))
+}
+
+fn add_note(conversation: &mut serde_json::Value, note: &serde_json::Value) {
+    let Some(record) = conversation.as_object_mut() else { return };
+    match record.get_mut("redactions").and_then(|r| r.as_array_mut()) {
+        Some(notes) => notes.push(note.clone()),
+        None => {
+            record.insert("redactions".to_string(), serde_json::json!([note]));
+        }
+    }
+}
+
+// Scrub matches from everything stored for a conversation: its transcript,
+// its entry in the frontend's data file and that file's backups, and the
+// text attachments it refers to. A dry run only reports what would change.
+#[tauri::command]
+pub async fn redact_transcript(
+    app: tauri::AppHandle,
+    conversation_id: String,
+    patterns: Vec<RedactionPattern>,
+    dry_run: bool,
+) -> Result<RedactionReport, String> {
+    let rules = compile(&patterns)?;
+    let mut found = Found::default();
+    // Copies after the first hold the same messages; their matches aren't listed again
+    let mut duplicates = Found::default();
+
+    let mut transcript = transcripts::read_transcript(&app, &conversation_id).await?;
+    let data_path = get_data_path(&app)?;
+    let mut copies: Vec<(PathBuf, serde_json::Value)> = Vec::new();
+    for path in [data_path.clone(), with_suffix(&data_path, ".bak"), with_suffix(&data_path, ".pre-migration")] {
+        let Ok(text) = tokio::fs::read_to_string(&path).await else { continue };
+        let Ok(mut data) = serde_json::from_str::<serde_json::Value>(&text) else { continue };
+        if find_conversation(&mut data, &conversation_id).is_some() {
+            copies.push((path, data));
+        }
+    }
+    if transcript.is_none() && copies.is_empty() {
+        return Err(format!("Conversation not found: {}", conversation_id));
+    }
+
+    let mut transcript_changed = false;
+    if let Some(ref mut record) = transcript {
+        transcript_changed = redact_value(&rules, record, "", &mut found);
+    }
+    let mut copies_changed = Vec::new();
+    for (i, (_, data)) in copies.iter_mut().enumerate() {
+        let conversation = find_conversation(data, &conversation_id).expect("checked above");
+        let target = if transcript.is_none() && i == 0 { &mut found } else { &mut duplicates };
+        copies_changed.push(redact_value(&rules, conversation, "", target));
+    }
+
+    // Attachments are referenced by their 64-character hex id
+    let primary = match transcript {
+        Some(ref record) => record.to_string(),
+        None => copies.first().map(|(_, data)| data.to_string()).unwrap_or_default(),
+    };
+    let id_pattern = Regex::new(r"\b[0-9a-f]{64}\b").expect("valid regex");
+    let mut ids: Vec<&str> = id_pattern.find_iter(&primary).map(|m| m.as_str()).collect();
+    ids.sort_unstable();
+    ids.dedup();
+    let mut redacted_attachments = Vec::new();
+    for id in ids {
+        let Ok(Some(text)) = attachments::read_text(&app, id).await else { continue };
+        if let Some(redacted) = redact_text(&rules, &text, &format!("attachment:{}", id), "", &mut found) {
+            redacted_attachments.push((id.to_string(), redacted));
+        }
+    }
+
+    let mut report = RedactionReport {
+        conversation_id: conversation_id.clone(),
+        dry_run,
+        total_matches: found.total,
+        matches: found.matches,
+        rewritten: Vec::new(),
+        replaced_attachments: BTreeMap::new(),
+    };
+    if dry_run || (report.total_matches == 0 && duplicates.total == 0) {
+        return Ok(report);
+    }
+
+    for (id, text) in redacted_attachments {
+        let meta = attachments::replace_text(&app, &id, text).await?;
+        report.replaced_attachments.insert(id, meta.id);
+    }
+
+    // Only counts go in the note; the patterns themselves may be secrets
+    let note = serde_json::json!({
+        "redacted_at": unix_millis(),
+        "patterns": rules.len(),
+        "matches": report.total_matches + duplicates.total,
+    });
+    let any_attachments = !report.replaced_attachments.is_empty();
+    if let Some(ref mut record) = transcript {
+        if transcript_changed || any_attachments {
+            replace_ids(record, &report.replaced_attachments);
+            add_note(record, &note);
+            transcripts::write_transcript(&app, &conversation_id, record).await?;
+            report.rewritten.push(format!("transcripts/{}.json", conversation_id));
+        }
+    }
+    for ((path, mut data), changed) in copies.into_iter().zip(copies_changed) {
+        if !changed && !any_attachments {
+            continue;
+        }
+        let conversation = find_conversation(&mut data, &conversation_id).expect("checked above");
+        replace_ids(conversation, &report.replaced_attachments);
+        add_note(conversation, &note);
+        let bytes = serde_json::to_vec(&data).map_err(|e| e.to_string())?;
+        write_atomic(&path, &bytes).await?;
+        report.rewritten.push(path.to_string_lossy().to_string());
+    }
+
+    for old in report.replaced_attachments.keys() {
+        let _ = attachments::delete_if_unreferenced(&app, old).await;
+    }
+
+    // The frontend holds the conversation in memory and would save the
+    // unredacted messages straight back; it reloads on this event
+    let _ = app.emit("conversation-redacted", ConversationRedacted { conversation_id });
+    Ok(report)
+}
@@ -0,0 +1,169 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tauri::Emitter;
+use tokio::io::{AsyncWriteExt, BufWriter};
+
+use crate::with_suffix;
+
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(500);
+// Characters kept from each end of the response for the result excerpt
+const EXCERPT_CHARS: usize = 500;
+
+#[derive(Clone, Serialize)]
+pub struct ResponseFileProgress {
+    pub conversation_id: String,
+    pub path: String,
+    pub bytes_written: u64,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct StreamedFile {
+    pub path: String,
+    pub bytes: u64,
+    pub head: String,
+    pub tail: String,
+}
+
+// Streams response text to `<path>.partial`, renamed into place only once the
+// turn succeeds
+pub(crate) struct ResponseFile {
+    path: PathBuf,
+    partial: PathBuf,
+    writer: BufWriter<tokio::fs::File>,
+    bytes: u64,
+    head: String,
+    tail: String,
+    last_progress: Instant,
+}
+
+fn last_chars(text: &str, count: usize) -> &str {
+    match text.char_indices().rev().nth(count.saturating_sub(1)) {
+        Some((start, _)) => &text[start..],
+        None => text,
+    }
+}
+
+impl ResponseFile {
+    pub(crate) async fn create(path: &str) -> Result<Self, String> {
+        let path = PathBuf::from(path);
+        let parent = path.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+        if !parent.is_dir() {
+            return Err(format!("Directory does not exist: {}", parent.display()));
+        }
+        let partial = with_suffix(&path, ".partial");
+        let file = tokio::fs::File::create(&partial).await
+            .map_err(|e| format!("Failed to create {}: {}", partial.display(), e))?;
+        Ok(ResponseFile {
+            path,
+            partial,
+            writer: BufWriter::new(file),
+            bytes: 0,
+            head: String::new(),
+            tail: String::new(),
+            last_progress: Instant::now(),
+        })
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.bytes == 0
+    }
+
+    pub(crate) async fn write(&mut self, app: &tauri::AppHandle, conversation_id: &str, text: &str) -> Result<(), String> {
+        self.writer.write_all(text.as_bytes()).await.map_err(|e| e.to_string())?;
+        self.bytes += text.len() as u64;
+
+        let head_chars = self.head.chars().count();
+        if head_chars < EXCERPT_CHARS {
+            self.head.extend(text.chars().take(EXCERPT_CHARS - head_chars));
+        }
+        self.tail.push_str(text);
+        if self.tail.len() > EXCERPT_CHARS * 8 {
+            self.tail = last_chars(&self.tail, EXCERPT_CHARS).to_string();
+        }
+
+        if self.last_progress.elapsed() >= PROGRESS_INTERVAL {
+            self.emit_progress(app, conversation_id);
+            self.last_progress = Instant::now();
+        }
+        Ok(())
+    }
+
+    fn emit_progress(&self, app: &tauri::AppHandle, conversation_id: &str) {
+        let _ = app.emit(&format!("claude-file-progress-{}", conversation_id), ResponseFileProgress {
+            conversation_id: conversation_id.to_string(),
+            path: self.path.to_string_lossy().to_string(),
+            bytes_written: self.bytes,
+        });
+    }
+
+    pub(crate) async fn finish(mut self, app: &tauri::AppHandle, conversation_id: &str) -> Result<StreamedFile, String> {
+        self.writer.flush().await.map_err(|e| e.to_string())?;
+        tokio::fs::rename(&self.partial, &self.path).await
+            .map_err(|e| format!("Failed to move response into {}: {}", self.path.display(), e))?;
+        self.emit_progress(app, conversation_id);
+        Ok(StreamedFile {
+            path: self.path.to_string_lossy().to_string(),
+            bytes: self.bytes,
+            head: self.head,
+            tail: last_chars(&self.tail, EXCERPT_CHARS).to_string(),
+        })
+    }
+
+    // Keep what was written under the .partial name and return its path
+    pub(crate) async fn abandon(mut self) -> String {
+        let _ = self.writer.flush().await;
+        self.partial.to_string_lossy().to_string()
+    }
+}
+
+// How often appended response text is flushed to disk
+const SAVE_FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+// Appends response text to a file as it streams, alongside the usual events.
+// Nothing is renamed or removed afterwards, so a failed turn leaves whatever
+// arrived before it.
+pub(crate) struct SavedResponse {
+    writer: BufWriter<tokio::fs::File>,
+    bytes: u64,
+    last_flush: Instant,
+}
+
+impl SavedResponse {
+    // Opened before the turn starts so a bad path fails fast
+    pub(crate) async fn open(path: &str) -> Result<Self, String> {
+        let path = Path::new(path);
+        if path.is_dir() {
+            return Err(format!("{} is a directory", path.display()));
+        }
+        let parent = path.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+        if !parent.is_dir() {
+            return Err(format!("Directory does not exist: {}", parent.display()));
+        }
+        let file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .await
+            .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+        Ok(SavedResponse { writer: BufWriter::new(file), bytes: 0, last_flush: Instant::now() })
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.bytes == 0
+    }
+
+    pub(crate) async fn append(&mut self, text: &str) -> Result<(), String> {
+        self.writer.write_all(text.as_bytes()).await.map_err(|e| e.to_string())?;
+        self.bytes += text.len() as u64;
+        if self.last_flush.elapsed() >= SAVE_FLUSH_INTERVAL {
+            self.writer.flush().await.map_err(|e| e.to_string())?;
+            self.last_flush = Instant::now();
+        }
+        Ok(())
+    }
+
+    pub(crate) async fn close(mut self) -> Result<(), String> {
+        self.writer.flush().await.map_err(|e| e.to_string())
+    }
+}
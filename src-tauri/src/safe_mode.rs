@@ -0,0 +1,162 @@
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::future::Future;
+use std::sync::Arc;
+use tauri::Emitter;
+use tokio::sync::Mutex;
+
+use crate::{claude_process, claude_queue, data_watch, janitor, migration, orphans, settings, sleep, turn_recovery};
+
+// `--safe-mode` on the command line or this set to 1 forces safe mode
+const SAFE_MODE_FLAG: &str = "--safe-mode";
+const SAFE_MODE_ENV: &str = "CLAUDE_QUEST_SAFE_MODE";
+
+#[derive(Clone, Serialize)]
+pub struct InitFailure {
+    // "settings", "migration", "turn_recovery", "orphan_scan" or "startup"
+    pub step: String,
+    pub message: String,
+}
+
+// Sent as `safe-mode` when the app starts without its background work, or
+// with it but without watching the data file
+#[derive(Clone, Serialize)]
+pub struct SafeModeState {
+    pub active: bool,
+    // True when asked for at launch rather than caused by a failure
+    pub forced: bool,
+    pub failures: Vec<InitFailure>,
+    // Files that couldn't be read, moved aside so defaults load instead
+    pub quarantined_files: Vec<String>,
+    // Set when full startup ran but edits to the data file from outside
+    // the app aren't being picked up
+    pub data_watch_error: Option<String>,
+}
+
+static STATE: Lazy<Arc<Mutex<SafeModeState>>> = Lazy::new(|| Arc::new(Mutex::new(SafeModeState {
+    active: false,
+    forced: false,
+    failures: Vec::new(),
+    quarantined_files: Vec::new(),
+    data_watch_error: None,
+})));
+// Held while startup runs, so background work can't be started twice
+static INIT_LOCK: Lazy<Arc<Mutex<()>>> = Lazy::new(|| Arc::new(Mutex::new(())));
+
+fn forced_at_launch() -> bool {
+    std::env::args().any(|arg| arg == SAFE_MODE_FLAG)
+        || std::env::var(SAFE_MODE_ENV).is_ok_and(|value| value == "1")
+}
+
+// Run a startup step on its own task so a panic in it is reported instead
+// of taking the app down
+async fn run_step<F>(step: &str, future: F) -> Result<(), InitFailure>
+where
+    F: Future<Output = ()> + Send + 'static,
+{
+    tauri::async_runtime::spawn(future).await.map_err(|e| InitFailure {
+        step: step.to_string(),
+        message: format!("Panicked: {}", e),
+    })
+}
+
+// The startup work that can fail on bad data. Returns what failed and which
+// files were quarantined along the way.
+async fn check(app: &tauri::AppHandle) -> (Vec<InitFailure>, Vec<String>) {
+    let mut failures = Vec::new();
+    let mut quarantined = Vec::new();
+
+    match settings::quarantine_if_corrupt(app).await {
+        Ok(None) => {}
+        Ok(Some((problem, path))) => {
+            failures.push(InitFailure { step: "settings".to_string(), message: problem });
+            quarantined.push(path);
+        }
+        Err(e) => failures.push(InitFailure { step: "settings".to_string(), message: e }),
+    }
+
+    if let Err(failure) = run_step("migration", migration::run_migration(app.clone())).await {
+        failures.push(failure);
+    } else if let Some(report) = migration::read_report(app).await.filter(|r| !r.completed && !r.errors.is_empty()) {
+        failures.push(InitFailure { step: "migration".to_string(), message: report.errors.join("; ") });
+    }
+    if let Err(failure) = run_step("turn_recovery", turn_recovery::recover_turns(app.clone())).await {
+        failures.push(failure);
+    }
+    if let Err(failure) = run_step("orphan_scan", orphans::scan_for_orphans(app.clone())).await {
+        failures.push(failure);
+    }
+    (failures, quarantined)
+}
+
+// Background work that only runs outside safe mode. Returns why the data
+// file isn't being watched, if it isn't.
+fn start_background(app: &tauri::AppHandle) -> Option<String> {
+    tauri::async_runtime::spawn(janitor::run_janitor(app.clone()));
+    tauri::async_runtime::spawn(sleep::watch_for_wake(app.clone()));
+    tauri::async_runtime::spawn(claude_process::reap_idle_processes());
+
+    let handle = app.clone();
+    tauri::async_runtime::spawn(async move {
+        if let Ok(settings) = settings::load(&handle).await {
+            if let Some(limit) = settings.claude_concurrency {
+                claude_queue::apply_concurrency(limit).await;
+            }
+        }
+    });
+    data_watch::start(app).err()
+}
+
+async fn initialize(app: &tauri::AppHandle, forced: bool) -> SafeModeState {
+    let (mut failures, quarantined_files) = if forced { (Vec::new(), Vec::new()) } else { check(app).await };
+    if forced {
+        failures.push(InitFailure { step: "startup".to_string(), message: "Safe mode was requested at launch".to_string() });
+    }
+    let mut state = SafeModeState {
+        active: !failures.is_empty(),
+        forced,
+        failures,
+        quarantined_files,
+        data_watch_error: None,
+    };
+    if !state.active {
+        state.data_watch_error = start_background(app);
+    }
+    *STATE.lock().await = state.clone();
+    if state.active || state.data_watch_error.is_some() {
+        let _ = app.emit("safe-mode", state.clone());
+    }
+    state
+}
+
+// Called from setup; everything runs in the background so a failure can
+// never keep the window from opening
+pub(crate) fn start(app: &tauri::AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let _guard = INIT_LOCK.lock().await;
+        initialize(&app, forced_at_launch()).await;
+    });
+}
+
+// Safe mode state for a frontend that loaded after the event went out
+#[tauri::command]
+pub async fn get_safe_mode_state() -> Result<SafeModeState, String> {
+    Ok(STATE.lock().await.clone())
+}
+
+// Try full startup again once the problem is fixed. Stays in safe mode,
+// with the new failures, if it still doesn't work.
+#[tauri::command]
+pub async fn exit_safe_mode(app: tauri::AppHandle) -> Result<SafeModeState, String> {
+    let _guard = INIT_LOCK.lock().await;
+    let state = STATE.lock().await.clone();
+    if !state.active {
+        return Ok(state);
+    }
+    let state = initialize(&app, false).await;
+    if !state.active {
+        let _ = app.emit("safe-mode", state.clone());
+    }
+    Ok(state)
+}
@@ -0,0 +1,28 @@
+// Thin wrapper over the OS keychain. Callers store a reference string in their
+// own settings and only resolve the value at the moment it's needed.
+
+const KEYCHAIN_SERVICE: &str = "claude-quest";
+
+fn entry(reference: &str) -> Result<keyring::Entry, String> {
+    keyring::Entry::new(KEYCHAIN_SERVICE, reference)
+        .map_err(|e| format!("Keychain unavailable: {}", e))
+}
+
+pub(crate) fn store_secret(reference: &str, value: &str) -> Result<(), String> {
+    entry(reference)?
+        .set_password(value)
+        .map_err(|e| format!("Failed to store '{}' in keychain: {}", reference, e))
+}
+
+pub(crate) fn read_secret(reference: &str) -> Result<String, String> {
+    entry(reference)?
+        .get_password()
+        .map_err(|e| format!("Failed to read '{}' from keychain: {}", reference, e))
+}
+
+pub(crate) fn delete_secret(reference: &str) -> Result<(), String> {
+    match entry(reference)?.delete_credential() {
+        Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(e) => Err(format!("Failed to delete '{}' from keychain: {}", reference, e)),
+    }
+}
@@ -0,0 +1,262 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use crate::{orphans, service_watch};
+use crate::{spawn_service, terminate_service, ServiceDefinition, RUNNING_SERVICES, SERVICE_STOP_TIMEOUT};
+
+// How long to wait after starting a dependency that has no health check
+const DEFAULT_STARTUP_DELAY_MS: u64 = 1000;
+// How long a health check may take to start passing
+const DEFAULT_HEALTH_TIMEOUT_MS: u64 = 30_000;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct HealthCheck {
+    // Service is considered up once something accepts connections on this localhost port
+    pub port: u16,
+    pub timeout_ms: Option<u64>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct ServiceGroupFailure {
+    pub service_id: String,
+    pub error: String,
+}
+
+#[derive(Clone, Serialize)]
+pub struct ServiceGroupReport {
+    pub started: Vec<String>,
+    pub already_running: Vec<String>,
+    // Services that were never attempted because an earlier one failed
+    pub skipped: Vec<String>,
+    pub failure: Option<ServiceGroupFailure>,
+}
+
+fn get_definitions_path(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    let app_data = crate::storage::data_dir(app);
+    Ok(app_data.join("services.json"))
+}
+
+pub(crate) async fn load_definitions(app: &tauri::AppHandle) -> Result<HashMap<String, ServiceDefinition>, String> {
+    let path = get_definitions_path(app)?;
+    if !path.exists() {
+        return Ok(HashMap::new());
+    }
+    let data = tokio::fs::read_to_string(&path).await.map_err(|e| e.to_string())?;
+    serde_json::from_str(&data).map_err(|e| format!("Failed to parse service definitions: {}", e))
+}
+
+async fn store_definitions(app: &tauri::AppHandle, definitions: &HashMap<String, ServiceDefinition>) -> Result<(), String> {
+    let path = get_definitions_path(app)?;
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await.map_err(|e| e.to_string())?;
+    }
+    let data = serde_json::to_string_pretty(definitions).map_err(|e| e.to_string())?;
+    tokio::fs::write(&path, data).await.map_err(|e| e.to_string())
+}
+
+// Return the first dependency cycle found, as the list of ids walked to close it
+fn find_cycle(definitions: &HashMap<String, ServiceDefinition>) -> Option<Vec<String>> {
+    fn visit(
+        id: &str,
+        definitions: &HashMap<String, ServiceDefinition>,
+        done: &mut HashSet<String>,
+        path: &mut Vec<String>,
+    ) -> Option<Vec<String>> {
+        if let Some(pos) = path.iter().position(|p| p == id) {
+            let mut cycle = path[pos..].to_vec();
+            cycle.push(id.to_string());
+            return Some(cycle);
+        }
+        if done.contains(id) {
+            return None;
+        }
+        path.push(id.to_string());
+        if let Some(def) = definitions.get(id) {
+            for dep in &def.depends_on {
+                if let Some(cycle) = visit(dep, definitions, done, path) {
+                    return Some(cycle);
+                }
+            }
+        }
+        path.pop();
+        done.insert(id.to_string());
+        None
+    }
+
+    let mut done = HashSet::new();
+    let mut ids: Vec<&String> = definitions.keys().collect();
+    ids.sort();
+    for id in ids {
+        if let Some(cycle) = visit(id, definitions, &mut done, &mut Vec::new()) {
+            return Some(cycle);
+        }
+    }
+    None
+}
+
+// Order the requested services plus everything they depend on so that each
+// service comes after its dependencies
+fn dependency_order(
+    ids: &[String],
+    definitions: &HashMap<String, ServiceDefinition>,
+) -> Result<Vec<String>, String> {
+    fn visit(
+        id: &str,
+        definitions: &HashMap<String, ServiceDefinition>,
+        visiting: &mut HashSet<String>,
+        order: &mut Vec<String>,
+    ) -> Result<(), String> {
+        if order.iter().any(|o| o == id) {
+            return Ok(());
+        }
+        if !visiting.insert(id.to_string()) {
+            return Err(format!("Dependency cycle involving service '{}'", id));
+        }
+        let def = definitions.get(id)
+            .ok_or_else(|| format!("No saved definition for service '{}'", id))?;
+        for dep in &def.depends_on {
+            visit(dep, definitions, visiting, order)?;
+        }
+        visiting.remove(id);
+        order.push(id.to_string());
+        Ok(())
+    }
+
+    let mut order = Vec::new();
+    let mut visiting = HashSet::new();
+    for id in ids {
+        visit(id, definitions, &mut visiting, &mut order)?;
+    }
+    Ok(order)
+}
+
+async fn is_running(service_id: &str) -> bool {
+    RUNNING_SERVICES.lock().await.contains_key(service_id)
+}
+
+// Wait until a freshly started service is ready for its dependents
+async fn wait_until_ready(definition: &ServiceDefinition) -> Result<(), String> {
+    match &definition.health_check {
+        Some(check) => {
+            let deadline = Instant::now()
+                + Duration::from_millis(check.timeout_ms.unwrap_or(DEFAULT_HEALTH_TIMEOUT_MS));
+            loop {
+                if tokio::net::TcpStream::connect(("127.0.0.1", check.port)).await.is_ok() {
+                    return Ok(());
+                }
+                if !is_running(&definition.service_id).await {
+                    return Err("Service exited before its health check passed".to_string());
+                }
+                if Instant::now() >= deadline {
+                    return Err(format!("Health check on port {} did not pass in time", check.port));
+                }
+                tokio::time::sleep(Duration::from_millis(250)).await;
+            }
+        }
+        None => {
+            let delay = definition.startup_delay_ms.unwrap_or(DEFAULT_STARTUP_DELAY_MS);
+            tokio::time::sleep(Duration::from_millis(delay)).await;
+            if is_running(&definition.service_id).await {
+                Ok(())
+            } else {
+                Err("Service exited during startup".to_string())
+            }
+        }
+    }
+}
+
+#[tauri::command]
+pub async fn save_service_definition(app: tauri::AppHandle, definition: ServiceDefinition) -> Result<(), String> {
+    let mut definitions = load_definitions(&app).await?;
+    definitions.insert(definition.service_id.clone(), definition);
+
+    if let Some(cycle) = find_cycle(&definitions) {
+        return Err(format!("Dependency cycle: {}", cycle.join(" -> ")));
+    }
+
+    store_definitions(&app, &definitions).await
+}
+
+#[tauri::command]
+pub async fn list_service_definitions(app: tauri::AppHandle) -> Result<Vec<ServiceDefinition>, String> {
+    let mut definitions: Vec<ServiceDefinition> = load_definitions(&app).await?.into_values().collect();
+    definitions.sort_by(|a, b| a.service_id.cmp(&b.service_id));
+    Ok(definitions)
+}
+
+#[tauri::command]
+pub async fn delete_service_definition(app: tauri::AppHandle, service_id: String) -> Result<bool, String> {
+    let mut definitions = load_definitions(&app).await?;
+    let removed = definitions.remove(&service_id).is_some();
+    if removed {
+        store_definitions(&app, &definitions).await?;
+    }
+    Ok(removed)
+}
+
+#[tauri::command]
+pub async fn start_service_group(app: tauri::AppHandle, service_ids: Vec<String>) -> Result<ServiceGroupReport, String> {
+    let definitions = load_definitions(&app).await?;
+    let order = dependency_order(&service_ids, &definitions)?;
+
+    let mut report = ServiceGroupReport {
+        started: Vec::new(),
+        already_running: Vec::new(),
+        skipped: Vec::new(),
+        failure: None,
+    };
+
+    for (index, service_id) in order.iter().enumerate() {
+        let definition = &definitions[service_id];
+
+        let result = if is_running(service_id).await {
+            report.already_running.push(service_id.clone());
+            Ok(())
+        } else {
+            match spawn_service(app.clone(), definition.clone()).await {
+                Ok(()) => {
+                    report.started.push(service_id.clone());
+                    // Only hold up the group for services something else depends on
+                    let has_dependents = order[index + 1..].iter()
+                        .any(|later| definitions[later].depends_on.contains(service_id));
+                    match service_watch::watch_service(app.clone(), definition).await {
+                        Ok(()) if has_dependents => wait_until_ready(definition).await,
+                        other => other,
+                    }
+                }
+                Err(e) => Err(e),
+            }
+        };
+
+        if let Err(error) = result {
+            report.failure = Some(ServiceGroupFailure { service_id: service_id.clone(), error });
+            report.skipped = order[index + 1..].to_vec();
+            break;
+        }
+    }
+
+    Ok(report)
+}
+
+#[tauri::command]
+pub async fn stop_service_group(app: tauri::AppHandle, service_ids: Vec<String>) -> Result<Vec<String>, String> {
+    let definitions = load_definitions(&app).await?;
+    let order = dependency_order(&service_ids, &definitions)?;
+
+    // Dependents go down before the services they rely on. Dependencies that
+    // weren't asked for are left running since other services may use them.
+    let mut stopped = Vec::new();
+    for service_id in order.iter().rev().filter(|id| service_ids.contains(id)) {
+        service_watch::unwatch_service(service_id).await;
+        orphans::forget_service(&app, service_id).await;
+        let service = RUNNING_SERVICES.lock().await.remove(service_id);
+        if let Some(service) = service {
+            terminate_service(service, SERVICE_STOP_TIMEOUT).await?;
+            stopped.push(service_id.clone());
+        }
+    }
+
+    Ok(stopped)
+}
@@ -0,0 +1,149 @@
+use glob::Pattern;
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tauri::Emitter;
+use tokio::sync::{mpsc, Mutex};
+
+use crate::{background_tasks, focus, restart_service_instance, ServiceDefinition};
+
+const DEFAULT_DEBOUNCE_MS: u64 = 300;
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct WatchConfig {
+    // Files or directories to watch, relative to the service's working directory.
+    // Watches the working directory itself when empty.
+    #[serde(default)]
+    pub paths: Vec<String>,
+    // Only changes matching one of these patterns trigger a restart (all changes when empty)
+    #[serde(default)]
+    pub globs: Vec<String>,
+    pub debounce_ms: Option<u64>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct ServiceWatchRestart {
+    pub service_id: String,
+    pub changed_files: Vec<String>,
+    pub error: Option<String>,
+}
+
+struct ServiceWatcher {
+    // Dropping the watcher stops filesystem notifications
+    _watcher: notify::RecommendedWatcher,
+    task: tokio::task::JoinHandle<()>,
+}
+
+impl Drop for ServiceWatcher {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+static SERVICE_WATCHERS: Lazy<Arc<Mutex<HashMap<String, ServiceWatcher>>>> =
+    Lazy::new(|| Arc::new(Mutex::new(HashMap::new())));
+
+// Add the paths touched by `event` that match the service's globs to `changed`
+fn collect_changes(event: &Event, base: &Path, patterns: &[Pattern], changed: &mut BTreeSet<String>) {
+    if !matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)) {
+        return;
+    }
+    for path in &event.paths {
+        let relative = path.strip_prefix(base).unwrap_or(path);
+        if patterns.is_empty() || patterns.iter().any(|p| p.matches_path(relative)) {
+            changed.insert(relative.to_string_lossy().to_string());
+        }
+    }
+}
+
+// Start (or replace) the file watcher for a service. Does nothing beyond
+// removing an old watcher if the definition has no watch block.
+pub(crate) async fn watch_service(app: tauri::AppHandle, definition: &ServiceDefinition) -> Result<(), String> {
+    let Some(config) = definition.watch.clone() else {
+        unwatch_service(&definition.service_id).await;
+        return Ok(());
+    };
+
+    let base = match definition.working_directory {
+        Some(ref dir) => PathBuf::from(dir),
+        None => std::env::current_dir().map_err(|e| e.to_string())?,
+    };
+    let roots: Vec<PathBuf> = if config.paths.is_empty() {
+        vec![base.clone()]
+    } else {
+        config.paths.iter().map(|p| base.join(p)).collect()
+    };
+    let patterns = config.globs.iter()
+        .map(|g| Pattern::new(g).map_err(|e| format!("Invalid watch glob '{}': {}", g, e)))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let (tx, mut rx) = mpsc::unbounded_channel::<Event>();
+    let mut watcher = notify::recommended_watcher(move |res: notify::Result<Event>| {
+        if let Ok(event) = res {
+            let _ = tx.send(event);
+        }
+    }).map_err(|e| format!("Failed to create file watcher: {}", e))?;
+
+    for root in &roots {
+        watcher.watch(root, RecursiveMode::Recursive)
+            .map_err(|e| format!("Failed to watch {}: {}", root.display(), e))?;
+    }
+
+    let debounce = Duration::from_millis(config.debounce_ms.unwrap_or(DEFAULT_DEBOUNCE_MS));
+    let definition_clone = definition.clone();
+    let description = format!("service watcher ({})", definition.service_id);
+    let task = background_tasks::spawn(description, async move {
+        let definition = definition_clone;
+        let sid = definition.service_id.clone();
+        while let Some(event) = rx.recv().await {
+            let mut changed = BTreeSet::new();
+            collect_changes(&event, &base, &patterns, &mut changed);
+
+            // Keep absorbing events until the burst has been quiet for the debounce
+            // window. Changes that land while the restart below is running queue up
+            // in the channel and become at most one further restart.
+            loop {
+                match tokio::time::timeout(debounce, rx.recv()).await {
+                    Ok(Some(event)) => collect_changes(&event, &base, &patterns, &mut changed),
+                    Ok(None) => return,
+                    Err(_) => break,
+                }
+            }
+
+            if changed.is_empty() {
+                continue;
+            }
+
+            // Hold the restart during a focus session, picking up whatever
+            // changed while it waited
+            if focus::defers(focus::Deferral::ServiceRestarts) {
+                focus::defer_service_restart(&app, &sid).await;
+                while let Ok(event) = rx.try_recv() {
+                    collect_changes(&event, &base, &patterns, &mut changed);
+                }
+            }
+
+            let result = restart_service_instance(app.clone(), &definition).await;
+            let _ = app.emit(&format!("service-watch-restart-{}", sid), ServiceWatchRestart {
+                service_id: sid.clone(),
+                changed_files: changed.into_iter().collect(),
+                error: result.err(),
+            });
+        }
+    });
+
+    SERVICE_WATCHERS.lock().await.insert(definition.service_id.clone(), ServiceWatcher {
+        _watcher: watcher,
+        task,
+    });
+
+    Ok(())
+}
+
+pub(crate) async fn unwatch_service(service_id: &str) {
+    SERVICE_WATCHERS.lock().await.remove(service_id);
+}
@@ -0,0 +1,111 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tauri::Emitter;
+use tokio::io::{AsyncBufReadExt, BufReader};
+
+use crate::{settings, turn_recovery};
+
+// Entries near the start of a session file that are looked at for its cwd
+const MAX_LINES_SCANNED: usize = 50;
+
+// What send_to_claude does when asked to resume a session that was started
+// in a different directory
+#[derive(Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SessionDirCheck {
+    Off,
+    // Send `claude-session-mismatch` and carry on
+    #[default]
+    Warn,
+    // Refuse the turn
+    Error,
+}
+
+#[derive(Clone, Serialize)]
+pub struct SessionMismatch {
+    pub conversation_id: String,
+    pub session_id: String,
+    pub working_directory: String,
+    pub session_directory: String,
+}
+
+// The CLI names each project folder after its cwd with every character
+// other than a letter or digit replaced by `-`
+fn encode_project_dir(dir: &Path) -> String {
+    dir.to_string_lossy().chars().map(|c| if c.is_ascii_alphanumeric() { c } else { '-' }).collect()
+}
+
+fn same_dir(a: &Path, b: &Path) -> bool {
+    let canonical = |p: &Path| std::fs::canonicalize(p).unwrap_or_else(|_| p.to_path_buf());
+    canonical(a) == canonical(b)
+}
+
+// The directory a session was started in: the `cwd` its entries record, or
+// failing that the folder it's stored under, which can only be compared
+async fn session_directory(session_file: &Path) -> Option<Result<PathBuf, String>> {
+    if let Ok(file) = tokio::fs::File::open(session_file).await {
+        let mut lines = BufReader::new(file).lines();
+        let mut scanned = 0;
+        while let Ok(Some(line)) = lines.next_line().await {
+            let cwd = serde_json::from_str::<serde_json::Value>(&line).ok()
+                .and_then(|entry| entry.get("cwd").and_then(|c| c.as_str()).map(PathBuf::from));
+            if let Some(cwd) = cwd {
+                return Some(Ok(cwd));
+            }
+            scanned += 1;
+            if scanned >= MAX_LINES_SCANNED {
+                break;
+            }
+        }
+    }
+    let folder = session_file.parent()?.file_name()?.to_string_lossy().to_string();
+    Some(Err(folder))
+}
+
+// Check that `session_id` belongs to `working_directory` before it's resumed
+// there. Sessions the CLI has no file for are left for it to report.
+pub(crate) async fn check_session_dir(
+    app: &tauri::AppHandle,
+    conversation_id: &str,
+    session_id: &str,
+    working_directory: &str,
+) -> Result<(), String> {
+    let mode = settings::load(app).await?.session_dir_check;
+    if mode == SessionDirCheck::Off {
+        return Ok(());
+    }
+    let Some(session_file) = turn_recovery::find_session_file(session_id).await else { return Ok(()) };
+    let dir = Path::new(working_directory);
+    let session_directory = match session_directory(&session_file).await {
+        Some(Ok(cwd)) if same_dir(&cwd, dir) => return Ok(()),
+        Some(Ok(cwd)) => cwd.to_string_lossy().to_string(),
+        Some(Err(folder)) => {
+            let resolved = std::fs::canonicalize(dir).unwrap_or_else(|_| dir.to_path_buf());
+            if folder == encode_project_dir(&resolved) || folder == encode_project_dir(dir) {
+                return Ok(());
+            }
+            folder
+        }
+        None => return Ok(()),
+    };
+
+    if mode == SessionDirCheck::Error {
+        return Err(format!(
+            "Session {} belongs to {}, not {}",
+            session_id, session_directory, working_directory
+        ));
+    }
+    let _ = app.emit("claude-session-mismatch", SessionMismatch {
+        conversation_id: conversation_id.to_string(),
+        session_id: session_id.to_string(),
+        working_directory: working_directory.to_string(),
+        session_directory,
+    });
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn set_session_dir_check(app: tauri::AppHandle, mode: SessionDirCheck) -> Result<(), String> {
+    settings::update(&app, |settings| settings.session_dir_check = mode).await?;
+    Ok(())
+}
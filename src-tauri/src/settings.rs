@@ -0,0 +1,243 @@
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use crate::accessibility::AccessibilitySettings;
+use crate::endpoints::EndpointSettings;
+use crate::env_profiles::EnvProfile;
+use crate::ipc_limits::{self, IpcLimitSettings};
+use crate::postprocess::ResponseTransforms;
+use crate::session_binding::SessionDirCheck;
+use crate::shell_config::ShellSettings;
+use crate::speech::SpeechSettings;
+use crate::time_format::TimeFormatSettings;
+
+// Backend-owned settings, persisted separately from the frontend's data.json
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub env_profiles: HashMap<String, EnvProfile>,
+    // Maximum number of claude processes running at once
+    pub claude_concurrency: Option<usize>,
+    pub response_transforms: ResponseTransforms,
+    pub shell: ShellSettings,
+    // Used when a claude call, shell command or service doesn't name a directory
+    pub default_working_dir: Option<String>,
+    // Gitignore-style patterns left out of every workspace's file index
+    pub global_excludes: Vec<String>,
+    // Spending caps in USD; a turn is stopped once it goes over either
+    pub max_turn_cost_usd: Option<f64>,
+    pub max_conversation_cost_usd: Option<f64>,
+    // Claude-compatible gateways used instead of the default API
+    pub endpoints: EndpointSettings,
+    // Largest message plus attachments send_to_claude will accept
+    pub max_prompt_bytes: Option<u64>,
+    // When set, claude, shell commands and services only run in directories under these
+    pub allowed_roots: Vec<String>,
+    // Lets send_to_claude write raw stream-json to a debug_log_path
+    pub allow_debug_logs: bool,
+    // Whether resuming a session started in another directory warns, fails or is allowed
+    pub session_dir_check: SessionDirCheck,
+    // Locale, clock and time zone for the display strings next to timestamps
+    pub time_format: TimeFormatSettings,
+    // Which whisper model transcribes voice prompts
+    pub speech: SpeechSettings,
+    // Ask a small model for follow-up prompts after each turn; off unless turned on
+    pub followup_suggestions: bool,
+    // Milestone events in place of streamed text, for screen readers
+    pub accessibility: AccessibilitySettings,
+    // Largest event payloads sent to the webview, per event name
+    pub ipc_limits: IpcLimitSettings,
+}
+
+// Loaded from disk on first use
+static SETTINGS: Lazy<Arc<Mutex<Option<Settings>>>> =
+    Lazy::new(|| Arc::new(Mutex::new(None)));
+
+fn get_settings_path(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    let app_data = crate::storage::data_dir(app);
+    Ok(app_data.join("settings.json"))
+}
+
+async fn read_settings(app: &tauri::AppHandle) -> Result<Settings, String> {
+    let path = get_settings_path(app)?;
+    if !path.exists() {
+        return Ok(Settings::default());
+    }
+    let data = tokio::fs::read_to_string(&path).await.map_err(|e| e.to_string())?;
+    serde_json::from_str(&data).map_err(|e| format!("Failed to parse settings: {}", e))
+}
+
+async fn write_settings(app: &tauri::AppHandle, settings: &Settings) -> Result<(), String> {
+    let path = get_settings_path(app)?;
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await.map_err(|e| e.to_string())?;
+    }
+    let data = serde_json::to_string_pretty(settings).map_err(|e| e.to_string())?;
+    tokio::fs::write(&path, data).await.map_err(|e| e.to_string())
+}
+
+pub(crate) async fn load(app: &tauri::AppHandle) -> Result<Settings, String> {
+    let mut cached = SETTINGS.lock().await;
+    if let Some(ref settings) = *cached {
+        return Ok(settings.clone());
+    }
+    let settings = read_settings(app).await?;
+    ipc_limits::apply(&settings.ipc_limits);
+    *cached = Some(settings.clone());
+    Ok(settings)
+}
+
+// Move an unreadable settings file aside so defaults load in its place.
+// Returns where it went, or None if the file was fine.
+pub(crate) async fn quarantine_if_corrupt(app: &tauri::AppHandle) -> Result<Option<(String, String)>, String> {
+    let Err(problem) = load(app).await else { return Ok(None) };
+    let path = get_settings_path(app)?;
+    let secs = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let quarantine = crate::with_suffix(&path, &format!(".corrupt-{}", secs));
+    tokio::fs::rename(&path, &quarantine).await.map_err(|e| e.to_string())?;
+    Ok(Some((problem, quarantine.to_string_lossy().to_string())))
+}
+
+// Apply `change` to the current settings and persist the result
+pub(crate) async fn update<F>(app: &tauri::AppHandle, change: F) -> Result<Settings, String>
+where
+    F: FnOnce(&mut Settings),
+{
+    let mut cached = SETTINGS.lock().await;
+    let mut settings = match cached.take() {
+        Some(settings) => settings,
+        None => read_settings(app).await?,
+    };
+    let previous = settings.clone();
+    change(&mut settings);
+
+    // Only keep the change in memory if it made it to disk
+    match write_settings(app, &settings).await {
+        Ok(()) => {
+            ipc_limits::apply(&settings.ipc_limits);
+            *cached = Some(settings.clone());
+            Ok(settings)
+        }
+        Err(e) => {
+            *cached = Some(previous);
+            Err(e)
+        }
+    }
+}
+
+// `working_directory` if given, otherwise the saved default
+pub(crate) async fn working_dir_or_default(app: &tauri::AppHandle, working_directory: Option<String>) -> Option<String> {
+    match working_directory {
+        Some(dir) => Some(dir),
+        None => load(app).await.ok().and_then(|s| s.default_working_dir),
+    }
+}
+
+// Err unless `dir` is under one of the allowed roots. With no directory the
+// process would run wherever the app was started, so that's refused too.
+pub(crate) async fn check_allowed_dir(app: &tauri::AppHandle, dir: Option<&str>) -> Result<(), String> {
+    let roots = load(app).await?.allowed_roots;
+    if roots.is_empty() {
+        return Ok(());
+    }
+    let Some(dir) = dir else {
+        return Err("A working directory inside the allowed roots is required".to_string());
+    };
+    // Resolve symlinks and `..` so neither can step outside a root
+    let resolved = std::fs::canonicalize(dir).map_err(|e| format!("Invalid working directory {}: {}", dir, e))?;
+    if roots.iter().any(|root| resolved.starts_with(root)) {
+        Ok(())
+    } else {
+        Err(format!("Working directory {} is outside the allowed roots", dir))
+    }
+}
+
+#[derive(Clone, Serialize)]
+pub struct SettingsView {
+    #[serde(flatten)]
+    pub settings: Settings,
+    // True when running on temporary storage; nothing will be saved across restarts
+    pub storage_degraded: bool,
+}
+
+#[tauri::command]
+pub async fn get_settings(app: tauri::AppHandle) -> Result<SettingsView, String> {
+    Ok(SettingsView {
+        settings: load(&app).await?,
+        storage_degraded: crate::storage::is_degraded(),
+    })
+}
+
+#[tauri::command]
+pub async fn set_default_working_dir(app: tauri::AppHandle, path: Option<String>) -> Result<Option<String>, String> {
+    let path = match path {
+        Some(path) => {
+            let dir = std::fs::canonicalize(&path).map_err(|e| format!("Invalid directory {}: {}", path, e))?;
+            if !dir.is_dir() {
+                return Err(format!("Not a directory: {}", path));
+            }
+            Some(dir.to_string_lossy().to_string())
+        }
+        None => None,
+    };
+    update(&app, |settings| settings.default_working_dir = path.clone()).await?;
+    Ok(path)
+}
+
+// Err naming both sizes when a message and its attachments are over the limit
+pub(crate) async fn check_prompt_size(app: &tauri::AppHandle, message: &str, attachments: Option<&[String]>) -> Result<(), String> {
+    let Some(limit) = load(app).await?.max_prompt_bytes else { return Ok(()) };
+    let attached = match attachments {
+        Some(ids) => crate::attachments::total_size(app, ids).await?,
+        None => 0,
+    };
+    let size = message.len() as u64 + attached;
+    if size > limit {
+        return Err(format!("Message is too large: {} bytes (limit is {} bytes)", size, limit));
+    }
+    Ok(())
+}
+
+// Pass None to remove the limit
+#[tauri::command]
+pub async fn set_max_prompt_bytes(app: tauri::AppHandle, max_bytes: Option<u64>) -> Result<(), String> {
+    if max_bytes == Some(0) {
+        return Err("The limit must be at least one byte".to_string());
+    }
+    update(&app, |settings| settings.max_prompt_bytes = max_bytes).await?;
+    Ok(())
+}
+
+// Restrict where claude, shell commands and services may run. An empty list
+// lifts the restriction.
+#[tauri::command]
+pub async fn set_allowed_roots(app: tauri::AppHandle, roots: Vec<String>) -> Result<Vec<String>, String> {
+    let mut resolved = Vec::new();
+    for root in roots {
+        let dir = std::fs::canonicalize(&root).map_err(|e| format!("Invalid directory {}: {}", root, e))?;
+        if !dir.is_dir() {
+            return Err(format!("Not a directory: {}", root));
+        }
+        resolved.push(dir.to_string_lossy().to_string());
+    }
+    update(&app, |settings| settings.allowed_roots = resolved.clone()).await?;
+    Ok(resolved)
+}
+
+#[tauri::command]
+pub async fn set_allow_debug_logs(app: tauri::AppHandle, enabled: bool) -> Result<(), String> {
+    update(&app, |settings| settings.allow_debug_logs = enabled).await?;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_default_working_dir(app: tauri::AppHandle) -> Result<Option<String>, String> {
+    Ok(load(&app).await?.default_working_dir)
+}
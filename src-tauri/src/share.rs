@@ -0,0 +1,193 @@
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+// Exactly one of the two
+#[derive(Deserialize)]
+pub struct ShareContent {
+    pub text: Option<String>,
+    pub file_path: Option<String>,
+}
+
+// Serialized as {"kind": "...", "message": "..."}; on `unsupported` the UI
+// should fall back to copying to the clipboard
+#[derive(Clone, Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "snake_case")]
+pub enum ShareError {
+    Unsupported(String),
+    InvalidContent(String),
+    InvalidPath(String),
+    Failed(String),
+}
+
+enum ShareItem {
+    Text(String),
+    File(PathBuf),
+}
+
+// Only existing, readable regular files are handed to the OS, by their real path
+fn validate_file(path: &str) -> Result<PathBuf, ShareError> {
+    let path = PathBuf::from(path);
+    if !path.is_absolute() {
+        return Err(ShareError::InvalidPath(format!("Path must be absolute: {}", path.display())));
+    }
+    let real = std::fs::canonicalize(&path)
+        .map_err(|e| ShareError::InvalidPath(format!("Can't share {}: {}", path.display(), e)))?;
+    if !real.is_file() {
+        return Err(ShareError::InvalidPath(format!("Not a file: {}", path.display())));
+    }
+    std::fs::File::open(&real)
+        .map_err(|e| ShareError::InvalidPath(format!("Can't read {}: {}", path.display(), e)))?;
+    Ok(real)
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use objc2::rc::Retained;
+    use objc2::runtime::AnyObject;
+    use objc2::AllocAnyThread;
+    use objc2_app_kit::{NSSharingServicePicker, NSWindow};
+    use objc2_foundation::{NSArray, NSPoint, NSRect, NSRectEdge, NSSize, NSString, NSURL};
+
+    use super::{ShareError, ShareItem};
+
+    // NSSharingServicePicker, anchored to the middle of the window's content
+    pub(super) fn share(window: &tauri::Window, item: ShareItem) -> Result<(), ShareError> {
+        let ns_window = window.ns_window().map_err(|e| ShareError::Failed(e.to_string()))? as usize;
+        window.run_on_main_thread(move || {
+            // SAFETY: tauri's live NSWindow for this window, used on the main thread
+            let ns_window = unsafe { &*(ns_window as *const NSWindow) };
+            let Some(view) = ns_window.contentView() else { return };
+            let object: Retained<AnyObject> = match item {
+                ShareItem::Text(text) => NSString::from_str(&text).into(),
+                ShareItem::File(path) => NSURL::fileURLWithPath(&NSString::from_str(&path.to_string_lossy())).into(),
+            };
+            let items = NSArray::from_retained_slice(&[object]);
+            let picker = unsafe { NSSharingServicePicker::initWithItems(NSSharingServicePicker::alloc(), &items) };
+            let bounds = view.bounds();
+            let anchor = NSRect::new(
+                NSPoint::new(bounds.size.width / 2.0, bounds.size.height / 2.0),
+                NSSize::new(1.0, 1.0),
+            );
+            picker.showRelativeToRect_ofView_preferredEdge(anchor, &view, NSRectEdge::MinY);
+        }).map_err(|e| ShareError::Failed(e.to_string()))
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use once_cell::sync::Lazy;
+    use std::sync::Mutex;
+    use windows::core::{factory, Interface, HSTRING};
+    use windows::ApplicationModel::DataTransfer::{DataRequestedEventArgs, DataTransferManager};
+    use windows::Foundation::TypedEventHandler;
+    use windows::Storage::{IStorageItem, StorageFile};
+    use windows::Win32::Foundation::HWND;
+    use windows::Win32::UI::Shell::IDataTransferManagerInterop;
+    use windows_collections::IIterable;
+
+    use super::{ShareError, ShareItem};
+
+    // The DataRequested handler registered for the last share, replaced by the next
+    static HANDLER_TOKEN: Lazy<Mutex<Option<i64>>> = Lazy::new(|| Mutex::new(None));
+
+    fn show(hwnd: HWND, item: ShareItem) -> windows::core::Result<()> {
+        let interop = factory::<DataTransferManager, IDataTransferManagerInterop>()?;
+        let manager: DataTransferManager = unsafe { interop.GetForWindow(hwnd)? };
+        let mut token = HANDLER_TOKEN.lock().unwrap();
+        if let Some(previous) = token.take() {
+            let _ = manager.RemoveDataRequested(previous);
+        }
+        *token = Some(manager.DataRequested(&TypedEventHandler::new(
+            move |_, args: windows::core::Ref<DataRequestedEventArgs>| {
+                let data = args.ok()?.Request()?.Data()?;
+                match item {
+                    ShareItem::Text(ref text) => {
+                        data.Properties()?.SetTitle(&HSTRING::from("Shared text"))?;
+                        data.SetText(&HSTRING::from(text.as_str()))?;
+                    }
+                    ShareItem::File(ref path) => {
+                        let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+                        data.Properties()?.SetTitle(&HSTRING::from(name))?;
+                        let file = StorageFile::GetFileFromPathAsync(&HSTRING::from(path.as_os_str()))?.get()?;
+                        let items: IIterable<IStorageItem> = vec![Some(file.cast::<IStorageItem>()?)].into();
+                        data.SetStorageItemsReadOnly(&items)?;
+                    }
+                }
+                Ok(())
+            },
+        ))?);
+        drop(token);
+        unsafe { interop.ShowShareUIForWindow(hwnd) }
+    }
+
+    // The Windows share UI, which has to be opened from the window's UI thread
+    pub(super) fn share(window: &tauri::Window, item: ShareItem) -> Result<(), ShareError> {
+        let hwnd = window.hwnd().map_err(|e| ShareError::Failed(e.to_string()))?.0 as isize;
+        let (tx, rx) = std::sync::mpsc::channel();
+        window.run_on_main_thread(move || {
+            let _ = tx.send(show(HWND(hwnd as _), item));
+        }).map_err(|e| ShareError::Failed(e.to_string()))?;
+        match rx.recv() {
+            Ok(Ok(())) => Ok(()),
+            // Older Windows builds don't have the share UI
+            Ok(Err(e)) if e.code().0 as u32 == 0x80004002 => Err(ShareError::Unsupported(e.message().to_string())),
+            Ok(Err(e)) => Err(ShareError::Failed(e.message().to_string())),
+            Err(e) => Err(ShareError::Failed(e.to_string())),
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use std::process::{Command, Stdio};
+
+    use super::{ShareError, ShareItem};
+
+    fn percent_encode(text: &str) -> String {
+        text.bytes().map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        }).collect()
+    }
+
+    // No share sheet on Linux: files open in their default app and text in
+    // a new mail, through xdg-open
+    pub(super) fn share(_window: &tauri::Window, item: ShareItem) -> Result<(), ShareError> {
+        let target = match item {
+            ShareItem::Text(text) => format!("mailto:?body={}", percent_encode(&text)),
+            ShareItem::File(path) => path.to_string_lossy().to_string(),
+        };
+        let mut child = Command::new("xdg-open")
+            .arg(target)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| match e.kind() {
+                std::io::ErrorKind::NotFound => ShareError::Unsupported("xdg-open is not installed".to_string()),
+                _ => ShareError::Failed(format!("Failed to run xdg-open: {}", e)),
+            })?;
+        std::thread::spawn(move || child.wait());
+        Ok(())
+    }
+}
+
+#[cfg(not(any(target_os = "macos", windows, target_os = "linux")))]
+mod platform {
+    use super::{ShareError, ShareItem};
+
+    pub(super) fn share(_window: &tauri::Window, _item: ShareItem) -> Result<(), ShareError> {
+        Err(ShareError::Unsupported("Sharing isn't available on this platform".to_string()))
+    }
+}
+
+// Open the system share sheet for some text or a file, from the calling window
+#[tauri::command]
+pub async fn share_content(window: tauri::Window, content: ShareContent) -> Result<(), ShareError> {
+    let item = match (content.text, content.file_path) {
+        (Some(text), None) => ShareItem::Text(text),
+        (None, Some(path)) => ShareItem::File(validate_file(&path)?),
+        _ => return Err(ShareError::InvalidContent("Give either text or file_path".to_string())),
+    };
+    platform::share(&window, item)
+}
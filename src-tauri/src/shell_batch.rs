@@ -0,0 +1,134 @@
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tauri::Emitter;
+use tokio::sync::{watch, Mutex};
+
+use crate::{operations, run_shell_command, ShellOutput, ShellSpawnParams, KILL_SIGNALS};
+
+// Sent as `shell-batch-<batch_id>` when a command in the batch starts; its
+// output streams on `shell-output-<process_id>` as usual
+#[derive(Clone, Serialize)]
+pub struct ShellBatchStep {
+    pub batch_id: String,
+    pub index: usize,
+    pub process_id: String,
+}
+
+#[derive(Clone, Serialize)]
+pub struct ShellBatchResult {
+    pub outputs: Vec<ShellOutput>,
+    // Stopped by kill_shell_batch before every command had run
+    pub cancelled: bool,
+}
+
+struct Batch {
+    // The command running right now, so a kill can reach it
+    current_process_id: Option<String>,
+    // Filled in with everything collected once the batch stops
+    done: watch::Receiver<Option<Vec<ShellOutput>>>,
+}
+
+static BATCHES: Lazy<Arc<Mutex<HashMap<String, Batch>>>> =
+    Lazy::new(|| Arc::new(Mutex::new(HashMap::new())));
+
+// Run commands one after another, each as process `<batch_id>-<index>`.
+// With stop_on_error (the default) a non-zero exit ends the batch.
+#[tauri::command]
+pub async fn run_shell_commands(
+    app: tauri::AppHandle,
+    batch_id: String,
+    commands: Vec<ShellSpawnParams>,
+    output_mode: Option<String>,
+    stop_on_error: Option<bool>,
+) -> Result<ShellBatchResult, String> {
+    let (done_tx, done_rx) = watch::channel(None);
+    {
+        let mut batches = BATCHES.lock().await;
+        if batches.contains_key(&batch_id) {
+            return Err(format!("Batch {} is already running", batch_id));
+        }
+        batches.insert(batch_id.clone(), Batch { current_process_id: None, done: done_rx });
+    }
+    let operation = operations::register(&batch_id, "shell_batch", true);
+    operation.set_progress(Some(0.0), None);
+
+    let total = commands.len();
+    let mut outputs = Vec::new();
+    let mut result = Ok(());
+    for (index, step) in commands.into_iter().enumerate() {
+        let process_id = format!("{}-{}", batch_id, index);
+        // Checked under the same lock kill_shell_batch takes, so a kill
+        // either sees this command or stops it from starting
+        {
+            let mut batches = BATCHES.lock().await;
+            if operation.is_cancelled() {
+                break;
+            }
+            if let Some(batch) = batches.get_mut(&batch_id) {
+                batch.current_process_id = Some(process_id.clone());
+            }
+        }
+        let _ = app.emit(&format!("shell-batch-{}", batch_id), ShellBatchStep {
+            batch_id: batch_id.clone(),
+            index,
+            process_id: process_id.clone(),
+        });
+        operation.set_progress(Some(index as f64 / total as f64), Some(step.command.clone()));
+
+        let output = run_shell_command(
+            app.clone(),
+            process_id,
+            step.command,
+            step.working_directory,
+            output_mode.clone(),
+            step.profile,
+            step.env,
+            step.clean_env,
+            step.shell,
+            step.shell_args,
+            step.interactive,
+            None,
+            None,
+            None,
+        ).await;
+        match output {
+            Ok(output) => {
+                let failed = output.exit_code != 0;
+                outputs.push(output);
+                if failed && stop_on_error.unwrap_or(true) {
+                    break;
+                }
+            }
+            Err(e) => {
+                result = Err(e);
+                break;
+            }
+        }
+    }
+
+    let cancelled = operation.is_cancelled();
+    BATCHES.lock().await.remove(&batch_id);
+    let _ = done_tx.send(Some(outputs.clone()));
+    result.map(|()| ShellBatchResult { outputs, cancelled })
+}
+
+// Stop the running command and skip the rest, returning the outputs of the
+// commands that ran (the stopped one included)
+#[tauri::command]
+pub async fn kill_shell_batch(batch_id: String) -> Result<Vec<ShellOutput>, String> {
+    let mut done = {
+        let batches = BATCHES.lock().await;
+        let batch = batches.get(&batch_id).ok_or_else(|| format!("No running batch: {}", batch_id))?;
+        operations::cancel_operation(batch_id.clone()).await?;
+        if let Some(ref process_id) = batch.current_process_id {
+            KILL_SIGNALS.lock().await.insert(process_id.clone());
+        }
+        batch.done.clone()
+    };
+    let outputs = done.wait_for(|outputs| outputs.is_some()).await
+        .map_err(|_| format!("Batch {} ended without a result", batch_id))?
+        .clone();
+    Ok(outputs.unwrap_or_default())
+}
@@ -0,0 +1,265 @@
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::git;
+
+const MAX_CANDIDATES: usize = 50;
+
+const GIT_SUBCOMMANDS: &[&str] = &[
+    "add", "bisect", "blame", "branch", "checkout", "cherry-pick", "clean", "clone", "commit",
+    "config", "diff", "fetch", "grep", "init", "log", "merge", "mv", "pull", "push", "rebase",
+    "reflog", "remote", "reset", "restore", "revert", "rm", "show", "stash", "status", "switch",
+    "tag", "worktree",
+];
+
+// Subcommands whose next argument is usually a branch name
+const GIT_BRANCH_SUBCOMMANDS: &[&str] = &[
+    "branch", "checkout", "cherry-pick", "diff", "log", "merge", "push", "rebase", "reset",
+    "switch",
+];
+
+#[derive(Clone, Serialize)]
+pub struct Completion {
+    // Text to put in place of the current token, already shell-escaped
+    pub value: String,
+    pub display: String,
+    pub kind: String,
+}
+
+#[derive(Clone, Serialize)]
+pub struct CompletionResult {
+    // Character range of the input the chosen value replaces
+    pub replace_from: usize,
+    pub replace_to: usize,
+    pub candidates: Vec<Completion>,
+}
+
+// PATH value and the executable names found on it
+type PathScan = (String, Vec<String>);
+
+// Executables on PATH, rescanned whenever PATH changes
+static PATH_EXECUTABLES: Lazy<Mutex<Option<PathScan>>> =
+    Lazy::new(|| Mutex::new(None));
+
+#[cfg(unix)]
+fn is_executable(metadata: &std::fs::Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.is_file() && metadata.permissions().mode() & 0o111 != 0
+}
+
+#[cfg(not(unix))]
+fn is_executable(metadata: &std::fs::Metadata) -> bool {
+    metadata.is_file()
+}
+
+fn path_executables() -> Vec<String> {
+    let path_var = std::env::var("PATH").unwrap_or_default();
+    let mut cache = PATH_EXECUTABLES.lock().unwrap();
+    if let Some((ref cached_path, ref names)) = *cache {
+        if *cached_path == path_var {
+            return names.clone();
+        }
+    }
+
+    let mut names = BTreeSet::new();
+    for dir in std::env::split_paths(&path_var) {
+        let Ok(entries) = std::fs::read_dir(&dir) else { continue };
+        for entry in entries.flatten() {
+            if entry.metadata().map(|m| is_executable(&m)).unwrap_or(false) {
+                names.insert(entry.file_name().to_string_lossy().to_string());
+            }
+        }
+    }
+    let names: Vec<String> = names.into_iter().collect();
+    *cache = Some((path_var, names.clone()));
+    names
+}
+
+fn shell_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if c.is_whitespace() || "\\'\"$`&|;<>()*?!#[]{}".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+// Split the text before the cursor into completed tokens and the (unescaped)
+// token being typed, along with where that token starts
+fn split_tokens(before_cursor: &[char]) -> (Vec<String>, String, usize) {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut current_start = 0;
+    let mut i = 0;
+    while i < before_cursor.len() {
+        let c = before_cursor[i];
+        if c == '\\' && i + 1 < before_cursor.len() {
+            current.push(before_cursor[i + 1]);
+            i += 2;
+            continue;
+        }
+        if c.is_whitespace() {
+            if !current.is_empty() {
+                tokens.push(std::mem::take(&mut current));
+            }
+            current_start = i + 1;
+        } else {
+            current.push(c);
+        }
+        i += 1;
+    }
+    (tokens, current, current_start)
+}
+
+// Prefix matches first (exact case before case-insensitive), then substring matches
+fn rank<I>(items: I, partial: &str) -> Vec<(String, String)>
+where
+    I: IntoIterator<Item = (String, String)>,
+{
+    let lower = partial.to_lowercase();
+    let mut scored: Vec<(u8, String, String)> = items.into_iter()
+        .filter_map(|(name, kind)| {
+            let score = if name.starts_with(partial) {
+                0
+            } else if name.to_lowercase().starts_with(&lower) {
+                1
+            } else if !partial.is_empty() && name.to_lowercase().contains(&lower) {
+                2
+            } else {
+                return None;
+            };
+            Some((score, name, kind))
+        })
+        .collect();
+    scored.sort_by(|a, b| a.0.cmp(&b.0).then_with(|| a.1.len().cmp(&b.1.len())).then_with(|| a.1.cmp(&b.1)));
+    scored.into_iter().take(MAX_CANDIDATES).map(|(_, name, kind)| (name, kind)).collect()
+}
+
+fn complete_paths(working_directory: &Path, partial: &str) -> Vec<Completion> {
+    // Split "src/com" into the directory to list and the name prefix to match
+    let (dir_part, name_prefix) = match partial.rfind('/') {
+        Some(pos) => (&partial[..=pos], &partial[pos + 1..]),
+        None => ("", partial),
+    };
+    let dir = if let Some(rest) = dir_part.strip_prefix("~/") {
+        dirs::home_dir().map(|home| home.join(rest)).unwrap_or_default()
+    } else if Path::new(dir_part).is_absolute() {
+        PathBuf::from(dir_part)
+    } else {
+        working_directory.join(dir_part)
+    };
+
+    let Ok(entries) = std::fs::read_dir(&dir) else { return Vec::new() };
+    let show_hidden = name_prefix.starts_with('.');
+    let names = entries.flatten().filter_map(|entry| {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if name.starts_with('.') && !show_hidden {
+            return None;
+        }
+        let is_dir = entry.metadata().map(|m| m.is_dir()).unwrap_or(false);
+        Some((name, if is_dir { "directory" } else { "file" }.to_string()))
+    });
+
+    rank(names, name_prefix).into_iter().map(|(name, kind)| {
+        let suffix = if kind == "directory" { "/" } else { "" };
+        Completion {
+            value: format!("{}{}{}", shell_escape(dir_part), shell_escape(&name), suffix),
+            display: format!("{}{}", name, suffix),
+            kind,
+        }
+    }).collect()
+}
+
+// Local branch names, read straight from refs/heads and packed-refs
+fn git_branches(working_directory: &Path) -> Vec<String> {
+    let Some(git_dir) = git::find_repo_root(working_directory).and_then(|root| git::git_dir(&root)) else {
+        return Vec::new();
+    };
+
+    fn walk(dir: &Path, prefix: &str, out: &mut BTreeSet<String>) {
+        let Ok(entries) = std::fs::read_dir(dir) else { return };
+        for entry in entries.flatten() {
+            let name = format!("{}{}", prefix, entry.file_name().to_string_lossy());
+            if entry.path().is_dir() {
+                walk(&entry.path(), &format!("{}/", name), out);
+            } else {
+                out.insert(name);
+            }
+        }
+    }
+
+    let mut branches = BTreeSet::new();
+    walk(&git_dir.join("refs/heads"), "", &mut branches);
+    if let Ok(packed) = std::fs::read_to_string(git_dir.join("packed-refs")) {
+        for line in packed.lines() {
+            if let Some((_, name)) = line.split_once(' ') {
+                if let Some(branch) = name.strip_prefix("refs/heads/") {
+                    branches.insert(branch.to_string());
+                }
+            }
+        }
+    }
+    branches.into_iter().collect()
+}
+
+fn complete(working_directory: &Path, input: &str, cursor_pos: usize) -> CompletionResult {
+    let chars: Vec<char> = input.chars().collect();
+    let cursor = cursor_pos.min(chars.len());
+    let (tokens, partial, start) = split_tokens(&chars[..cursor]);
+
+    let simple = |items: Vec<(String, String)>| -> Vec<Completion> {
+        items.into_iter().map(|(name, kind)| Completion {
+            value: shell_escape(&name),
+            display: name,
+            kind,
+        }).collect()
+    };
+
+    let candidates = match tokens.as_slice() {
+        // First token: a command, unless it's clearly a path
+        [] if !partial.contains('/') => {
+            let commands = path_executables().into_iter().map(|name| (name, "command".to_string()));
+            simple(rank(commands, &partial))
+        }
+        [git] if git == "git" => {
+            let subcommands = GIT_SUBCOMMANDS.iter().map(|s| (s.to_string(), "git-subcommand".to_string()));
+            simple(rank(subcommands, &partial))
+        }
+        [git, sub, ..] if git == "git" && GIT_BRANCH_SUBCOMMANDS.contains(&sub.as_str()) && !partial.starts_with('-') => {
+            let branches = git_branches(working_directory).into_iter().map(|b| (b, "git-branch".to_string()));
+            let mut candidates = simple(rank(branches, &partial));
+            candidates.extend(complete_paths(working_directory, &partial));
+            candidates.truncate(MAX_CANDIDATES);
+            candidates
+        }
+        _ => complete_paths(working_directory, &partial),
+    };
+
+    CompletionResult {
+        replace_from: start,
+        replace_to: cursor,
+        candidates,
+    }
+}
+
+#[tauri::command]
+pub async fn complete_shell_input(
+    working_directory: Option<String>,
+    partial_input: String,
+    cursor_pos: Option<usize>,
+) -> Result<CompletionResult, String> {
+    let working_directory = match working_directory {
+        Some(dir) => PathBuf::from(dir),
+        None => std::env::current_dir().map_err(|e| e.to_string())?,
+    };
+    let cursor = cursor_pos.unwrap_or_else(|| partial_input.chars().count());
+
+    tokio::task::spawn_blocking(move || complete(&working_directory, &partial_input, cursor))
+        .await
+        .map_err(|e| e.to_string())
+}
@@ -0,0 +1,63 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::settings;
+
+// How run_shell_command invokes the user's command
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ShellSettings {
+    // Shell binary; `sh` when unset
+    pub program: Option<String>,
+    // Arguments placed before the command. Derived from the shell when unset.
+    pub args: Option<Vec<String>>,
+    // Load the shell's interactive config (aliases, functions from .bashrc)
+    pub interactive: bool,
+}
+
+// Arguments that make `program` run the string that follows as a command
+fn default_args(program: &str, interactive: bool) -> Vec<String> {
+    let name = Path::new(program)
+        .file_stem()
+        .map(|n| n.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let args: &[&str] = match name.as_str() {
+        // PowerShell has no interactive-config equivalent worth emulating; the
+        // profile is skipped so runs don't depend on it
+        "pwsh" | "powershell" => &["-NoProfile", "-Command"],
+        "cmd" => &["/C"],
+        // fish takes separate flags and rejects the combined `-ic`
+        "fish" if interactive => &["-i", "-c"],
+        _ if interactive => &["-ic"],
+        _ => &["-c"],
+    };
+    args.iter().map(|a| a.to_string()).collect()
+}
+
+// Resolve the shell and leading arguments for a run: per-call values win over
+// settings, which win over the `sh -c` default
+pub(crate) async fn resolve(
+    app: &tauri::AppHandle,
+    shell: Option<String>,
+    shell_args: Option<Vec<String>>,
+    interactive: Option<bool>,
+) -> (String, Vec<String>) {
+    let saved = settings::load(app).await.map(|s| s.shell).unwrap_or_default();
+    let interactive = interactive.unwrap_or(saved.interactive);
+
+    // Saved args only apply to the saved program
+    let (program, saved_args) = match shell {
+        Some(program) => (program, None),
+        None => (saved.program.unwrap_or_else(|| "sh".to_string()), saved.args),
+    };
+    let args = shell_args
+        .or(saved_args)
+        .unwrap_or_else(|| default_args(&program, interactive));
+    (program, args)
+}
+
+#[tauri::command]
+pub async fn set_shell_settings(app: tauri::AppHandle, shell: ShellSettings) -> Result<(), String> {
+    settings::update(&app, |settings| settings.shell = shell).await?;
+    Ok(())
+}
@@ -0,0 +1,368 @@
+use serde::Serialize;
+use std::path::Path;
+use std::process::ExitStatus;
+use std::time::{Duration, Instant};
+
+// All post-processing of one failure fits in this, PATH scan included
+const DIAGNOSIS_BUDGET: Duration = Duration::from_millis(50);
+// Suggestions further than this many edits from the typed name are dropped
+const MAX_SUGGESTION_DISTANCE: usize = 2;
+
+#[derive(Clone, Serialize)]
+pub struct ExitDiagnosis {
+    // "command_not_found", "permission_denied", "signal", "out_of_memory", ...
+    pub code: String,
+    pub hint: String,
+}
+
+// What's known about a finished command
+pub(crate) struct FailedCommand<'a> {
+    pub command: &'a str,
+    pub stderr: &'a str,
+    pub status: ExitStatus,
+    pub pid: Option<u32>,
+}
+
+enum Detector {
+    CommandNotFound,
+    PermissionDenied,
+    OutOfMemory,
+    Signal,
+    Fixed(&'static str),
+}
+
+// Checked in order; the first one whose exit code or stderr matches and
+// whose detector produces a hint wins
+struct Signature {
+    code: &'static str,
+    exit_codes: &'static [i32],
+    stderr: &'static [&'static str],
+    detector: Detector,
+}
+
+const SIGNATURES: &[Signature] = &[
+    Signature {
+        code: "command_not_found",
+        exit_codes: &[127],
+        stderr: &["command not found", "is not recognized as an internal or external command"],
+        detector: Detector::CommandNotFound,
+    },
+    Signature {
+        code: "permission_denied",
+        exit_codes: &[126],
+        stderr: &["Permission denied", "Operation not permitted"],
+        detector: Detector::PermissionDenied,
+    },
+    // Killed by SIGKILL, either directly or as reported by the shell
+    Signature {
+        code: "out_of_memory",
+        exit_codes: &[137],
+        stderr: &["Out of memory", "Killed"],
+        detector: Detector::OutOfMemory,
+    },
+    Signature {
+        code: "signal",
+        exit_codes: &[],
+        stderr: &[],
+        detector: Detector::Signal,
+    },
+    Signature {
+        code: "timed_out",
+        exit_codes: &[124],
+        stderr: &[],
+        detector: Detector::Fixed("Stopped by `timeout` after its time limit"),
+    },
+    Signature {
+        code: "no_space",
+        exit_codes: &[],
+        stderr: &["No space left on device"],
+        detector: Detector::Fixed("The disk is full"),
+    },
+    Signature {
+        code: "address_in_use",
+        exit_codes: &[],
+        stderr: &["EADDRINUSE", "Address already in use"],
+        detector: Detector::Fixed("The port is already taken; scan_local_ports shows by what"),
+    },
+];
+
+const SIGNAL_NAMES: &[(i32, &str)] = &[
+    (1, "SIGHUP"), (2, "SIGINT"), (3, "SIGQUIT"), (4, "SIGILL"), (5, "SIGTRAP"), (6, "SIGABRT"),
+    (7, "SIGBUS"), (8, "SIGFPE"), (9, "SIGKILL"), (10, "SIGUSR1"), (11, "SIGSEGV"), (12, "SIGUSR2"),
+    (13, "SIGPIPE"), (14, "SIGALRM"), (15, "SIGTERM"),
+];
+
+// The signal that ended the process, whether the OS reported it or the
+// shell turned it into 128 + n
+fn signal_of(status: &ExitStatus) -> Option<i32> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        if let Some(signal) = status.signal() {
+            return Some(signal);
+        }
+    }
+    status.code().filter(|code| (129..=128 + 31).contains(code)).map(|code| code - 128)
+}
+
+fn signal_name(signal: i32) -> String {
+    SIGNAL_NAMES.iter()
+        .find(|(number, _)| *number == signal)
+        .map(|(_, name)| name.to_string())
+        .unwrap_or_else(|| format!("signal {}", signal))
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut previous = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let current = row[j + 1];
+            row[j + 1] = (previous + usize::from(ca != *cb)).min(row[j] + 1).min(current + 1);
+            previous = current;
+        }
+    }
+    row[b.len()]
+}
+
+// The name the shell couldn't find, from its message or the command itself
+fn missing_command<'a>(command: &'a str, stderr: &'a str) -> Option<&'a str> {
+    for line in stderr.lines() {
+        // zsh: "zsh: command not found: foo"
+        if let Some(name) = line.split("command not found: ").nth(1) {
+            return Some(name.trim());
+        }
+        // bash/sh: "bash: foo: command not found", "sh: 1: foo: not found"
+        if line.ends_with("not found") {
+            let parts: Vec<&str> = line.split(": ").collect();
+            if parts.len() >= 3 {
+                return Some(parts[parts.len() - 2].trim());
+            }
+        }
+        // cmd.exe: "'foo' is not recognized as ..."
+        if let Some(rest) = line.strip_prefix('\'') {
+            if let Some((name, _)) = rest.split_once("' is not recognized") {
+                return Some(name);
+            }
+        }
+    }
+    command.split_whitespace().next()
+}
+
+// The closest executable name on PATH, giving up when the budget runs out
+fn suggest(name: &str, deadline: Instant) -> Option<String> {
+    let path = std::env::var_os("PATH")?;
+    let mut best: Option<(usize, String)> = None;
+    for dir in std::env::split_paths(&path) {
+        let Ok(entries) = std::fs::read_dir(&dir) else { continue };
+        for entry in entries.flatten() {
+            if Instant::now() >= deadline {
+                return best.map(|(_, candidate)| candidate);
+            }
+            let candidate = entry.file_name().to_string_lossy().to_string();
+            let candidate = candidate.strip_suffix(".exe").unwrap_or(&candidate).to_string();
+            if candidate == name || candidate.len().abs_diff(name.len()) > MAX_SUGGESTION_DISTANCE {
+                continue;
+            }
+            let distance = edit_distance(name, &candidate);
+            if distance <= MAX_SUGGESTION_DISTANCE && best.as_ref().is_none_or(|(d, _)| distance < *d) {
+                best = Some((distance, candidate));
+            }
+        }
+    }
+    best.map(|(_, candidate)| candidate)
+}
+
+// "bash: ./run.sh: Permission denied" -> "./run.sh"
+fn denied_path(stderr: &str) -> Option<&str> {
+    stderr.lines()
+        .find(|line| line.contains("Permission denied"))
+        .and_then(|line| {
+            let parts: Vec<&str> = line.split(": ").collect();
+            (parts.len() >= 2).then(|| parts[parts.len() - 2].trim())
+        })
+        .filter(|path| Path::new(path).exists())
+}
+
+#[cfg(unix)]
+fn describe_mode(path: &str) -> Option<String> {
+    use std::os::unix::fs::PermissionsExt;
+    let mode = std::fs::metadata(path).ok()?.permissions().mode() & 0o7777;
+    let executable = mode & 0o111 != 0;
+    Some(format!(
+        "{} has mode {:o}{}",
+        path,
+        mode,
+        if executable { "" } else { "; it isn't executable (chmod +x)" }
+    ))
+}
+
+#[cfg(not(unix))]
+fn describe_mode(path: &str) -> Option<String> {
+    let readonly = std::fs::metadata(path).ok()?.permissions().readonly();
+    Some(format!("{} is {}", path, if readonly { "read-only" } else { "writable" }))
+}
+
+// "Out of memory: Killed process 1234 (node)" or "oom-kill:...,pid=1234,..."
+fn log_shows_oom_kill(log: &str, pid: u32) -> bool {
+    let marker = format!("Killed process {} ", pid);
+    let field = format!("pid={},", pid);
+    log.lines().any(|line| line.contains(&marker) || (line.contains("oom-kill") && line.contains(&field)))
+}
+
+// The kernel logs OOM kills with the victim's pid. dmesg often needs
+// privileges, in which case this just can't tell.
+#[cfg(target_os = "linux")]
+fn oom_killed(pid: Option<u32>, deadline: Instant) -> bool {
+    let Some(pid) = pid else { return false };
+    let Ok(mut child) = std::process::Command::new("dmesg")
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::null())
+        .spawn()
+    else {
+        return false;
+    };
+    while Instant::now() < deadline {
+        match child.try_wait() {
+            Ok(Some(_)) => {
+                let mut log = String::new();
+                if let Some(mut stdout) = child.stdout.take() {
+                    let _ = std::io::Read::read_to_string(&mut stdout, &mut log);
+                }
+                return log_shows_oom_kill(&log, pid);
+            }
+            Ok(None) => std::thread::sleep(Duration::from_millis(2)),
+            Err(_) => break,
+        }
+    }
+    let _ = child.kill();
+    let _ = child.wait();
+    false
+}
+
+#[cfg(not(target_os = "linux"))]
+fn oom_killed(_pid: Option<u32>, _deadline: Instant) -> bool {
+    false
+}
+
+fn run_detector(detector: &Detector, failed: &FailedCommand, deadline: Instant) -> Option<String> {
+    match detector {
+        Detector::CommandNotFound => {
+            let name = missing_command(failed.command, failed.stderr)?;
+            Some(match suggest(name, deadline) {
+                Some(candidate) => format!("`{}` isn't on PATH. Did you mean `{}`?", name, candidate),
+                None => format!("`{}` isn't on PATH", name),
+            })
+        }
+        Detector::PermissionDenied => Some(
+            denied_path(failed.stderr)
+                .and_then(describe_mode)
+                .unwrap_or_else(|| "Permission denied".to_string()),
+        ),
+        Detector::OutOfMemory => {
+            let killed = signal_of(&failed.status) == Some(9);
+            (killed && oom_killed(failed.pid, deadline))
+                .then(|| "Killed by the kernel's out-of-memory killer".to_string())
+        }
+        Detector::Signal => {
+            let signal = signal_of(&failed.status)?;
+            Some(format!("Terminated by {}", signal_name(signal)))
+        }
+        Detector::Fixed(hint) => Some(hint.to_string()),
+    }
+}
+
+// Explain a non-zero exit, or None when nothing in the table fits
+pub(crate) fn diagnose(failed: &FailedCommand) -> Option<ExitDiagnosis> {
+    if failed.status.success() {
+        return None;
+    }
+    let deadline = Instant::now() + DIAGNOSIS_BUDGET;
+    let exit_code = failed.status.code();
+    for signature in SIGNATURES {
+        let code_matches = exit_code.is_some_and(|code| signature.exit_codes.contains(&code));
+        let stderr_matches = signature.stderr.iter().any(|pattern| failed.stderr.contains(pattern));
+        let unconditional = signature.exit_codes.is_empty() && signature.stderr.is_empty();
+        if !(code_matches || stderr_matches || unconditional) {
+            continue;
+        }
+        if let Some(hint) = run_detector(&signature.detector, failed, deadline) {
+            return Some(ExitDiagnosis { code: signature.code.to_string(), hint });
+        }
+    }
+    None
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+    use std::os::unix::process::ExitStatusExt;
+
+    fn exited(code: i32) -> ExitStatus {
+        ExitStatus::from_raw(code << 8)
+    }
+
+    fn signalled(signal: i32) -> ExitStatus {
+        ExitStatus::from_raw(signal)
+    }
+
+    fn run(command: &str, stderr: &str, status: ExitStatus) -> Option<ExitDiagnosis> {
+        diagnose(&FailedCommand { command, stderr, status, pid: None })
+    }
+
+    #[test]
+    fn each_signature_has_a_diagnosis() {
+        let script = std::env::temp_dir().join(format!("diagnosis-test-{}.sh", std::process::id()));
+        std::fs::write(&script, "#!/bin/sh\n").unwrap();
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o644)).unwrap();
+        let denied = format!("bash: {}: Permission denied", script.display());
+
+        // (command, stderr, status, expected code, text the hint contains)
+        let cases: &[(&str, &str, ExitStatus, &str, &str)] = &[
+            ("gti status", "bash: gti: command not found", exited(127), "command_not_found", "`gti` isn't on PATH"),
+            ("gti status", "zsh: command not found: gti", exited(127), "command_not_found", "`gti` isn't on PATH"),
+            ("./run.sh", &denied, exited(126), "permission_denied", "has mode 644; it isn't executable"),
+            ("make", "", signalled(15), "signal", "Terminated by SIGTERM"),
+            ("make", "", exited(130), "signal", "Terminated by SIGINT"),
+            // No kernel log entry for this pid, so a SIGKILL is only a signal
+            ("node big.js", "Killed", signalled(9), "signal", "Terminated by SIGKILL"),
+            ("timeout 5 make", "", exited(124), "timed_out", "time limit"),
+            ("cp a b", "cp: error writing 'b': No space left on device", exited(1), "no_space", "disk is full"),
+            ("npm start", "Error: listen EADDRINUSE: address already in use :::3000", exited(1), "address_in_use", "port is already taken"),
+        ];
+        for (command, stderr, status, code, hint) in cases {
+            let diagnosis = run(command, stderr, *status).unwrap_or_else(|| panic!("no diagnosis for {:?}", stderr));
+            assert_eq!(diagnosis.code, *code, "{:?}", stderr);
+            assert!(diagnosis.hint.contains(hint), "{:?} gave {:?}", stderr, diagnosis.hint);
+        }
+        std::fs::remove_file(&script).unwrap();
+    }
+
+    #[test]
+    fn oom_kills_are_found_in_the_kernel_log() {
+        let log = "[123.4] Out of memory: Killed process 4242 (node) total-vm:1234kB\n";
+        assert!(log_shows_oom_kill(log, 4242));
+        assert!(!log_shows_oom_kill(log, 424));
+        let log = "[123.4] oom-kill:constraint=CONSTRAINT_NONE,task=node,pid=4242,uid=1000\n";
+        assert!(log_shows_oom_kill(log, 4242));
+        assert!(!log_shows_oom_kill(log, 42));
+    }
+
+    #[test]
+    fn unrecognised_failures_have_no_diagnosis() {
+        assert!(run("cargo test", "error: test failed, to rerun pass `--lib`", exited(101)).is_none());
+        assert!(run("false", "", exited(1)).is_none());
+        assert!(run("true", "", exited(0)).is_none());
+    }
+
+    #[test]
+    fn suggestions_are_close_names() {
+        assert_eq!(edit_distance("gti", "git"), 2);
+        assert_eq!(edit_distance("pyton", "python"), 1);
+        assert_eq!(missing_command("gti status", "sh: 1: gti: not found"), Some("gti"));
+        assert_eq!(missing_command("gti status", ""), Some("gti"));
+    }
+}
@@ -0,0 +1,141 @@
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+use tauri::Emitter;
+use tokio::sync::Mutex;
+
+use crate::{orphans, service_groups, service_watch};
+use crate::{restart_service_instance, spawn_service, terminate_service, ServiceDefinition, ServiceFailure, RUNNING_SERVICES, SERVICE_STOP_TIMEOUT};
+
+const WAKE_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+// How far the wall clock may run ahead of the monotonic clock before we call it a wake
+const WAKE_THRESHOLD: Duration = Duration::from_secs(30);
+
+#[derive(Clone, Serialize)]
+pub struct SystemResumed {
+    // None when the app didn't see the sleep itself, as with resume_after_sleep
+    pub slept_ms: Option<u64>,
+    pub restarted: Vec<String>,
+    pub failed: Vec<String>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct SleepPrepared {
+    pub stopped: Vec<String>,
+    pub failed: Vec<ServiceFailure>,
+}
+
+// Services stopped by prepare_for_sleep, in their original start order
+static STOPPED_FOR_SLEEP: Lazy<Arc<Mutex<Vec<ServiceDefinition>>>> =
+    Lazy::new(|| Arc::new(Mutex::new(Vec::new())));
+
+// The flag may be on the running definition or only on the saved one
+async fn stops_before_sleep(app: &tauri::AppHandle, definition: &ServiceDefinition) -> bool {
+    if definition.stop_before_sleep {
+        return true;
+    }
+    service_groups::load_definitions(app).await
+        .ok()
+        .and_then(|saved| saved.get(&definition.service_id).map(|d| d.stop_before_sleep))
+        .unwrap_or(false)
+}
+
+// Running services that opted in, oldest first
+async fn flagged_services(app: &tauri::AppHandle) -> Vec<(u64, ServiceDefinition)> {
+    let running: Vec<(u64, ServiceDefinition)> = RUNNING_SERVICES.lock().await.values()
+        .map(|service| (service.instance, service.definition.clone()))
+        .collect();
+    let mut flagged = Vec::new();
+    for (instance, definition) in running {
+        if stops_before_sleep(app, &definition).await {
+            flagged.push((instance, definition));
+        }
+    }
+    flagged.sort_by_key(|(instance, _)| *instance);
+    flagged
+}
+
+async fn restart_stopped(app: &tauri::AppHandle) -> (Vec<String>, Vec<String>) {
+    let stopped: Vec<ServiceDefinition> = STOPPED_FOR_SLEEP.lock().await.drain(..).collect();
+    let mut restarted = Vec::new();
+    let mut failed = Vec::new();
+    for definition in stopped {
+        let service_id = definition.service_id.clone();
+        // Started again by hand in the meantime
+        if RUNNING_SERVICES.lock().await.contains_key(&service_id) {
+            continue;
+        }
+        match spawn_service(app.clone(), definition.clone()).await {
+            Ok(()) => {
+                let _ = service_watch::watch_service(app.clone(), &definition).await;
+                restarted.push(service_id);
+            }
+            Err(_) => failed.push(service_id),
+        }
+    }
+    (restarted, failed)
+}
+
+// Stop every service flagged stop_before_sleep, remembering them for resume
+#[tauri::command]
+pub async fn prepare_for_sleep(app: tauri::AppHandle) -> Result<SleepPrepared, String> {
+    let mut stopped = Vec::new();
+    let mut failed = Vec::new();
+    // Last started goes first, so dependents stop before what they depend on
+    for (_, definition) in flagged_services(&app).await.into_iter().rev() {
+        let service_id = definition.service_id.clone();
+        let Some(service) = RUNNING_SERVICES.lock().await.remove(&service_id) else { continue };
+        service_watch::unwatch_service(&service_id).await;
+        orphans::forget_service(&app, &service_id).await;
+        // Out of RUNNING_SERVICES either way, so it's still brought back on wake
+        STOPPED_FOR_SLEEP.lock().await.insert(0, definition);
+        match terminate_service(service, SERVICE_STOP_TIMEOUT).await {
+            Ok(()) => stopped.insert(0, service_id),
+            Err(error) => failed.push(ServiceFailure { service_id, error }),
+        }
+    }
+    Ok(SleepPrepared { stopped, failed })
+}
+
+// Bring back whatever prepare_for_sleep stopped
+#[tauri::command]
+pub async fn resume_after_sleep(app: tauri::AppHandle) -> Result<SystemResumed, String> {
+    let (restarted, failed) = restart_stopped(&app).await;
+    Ok(SystemResumed { slept_ms: None, restarted, failed })
+}
+
+// The monotonic clock stands still while the machine sleeps and the wall
+// clock doesn't, so a gap between them means we just woke up. Services that
+// were stopped ahead of time are started again; flagged services that slept
+// through it anyway are restarted.
+pub(crate) async fn watch_for_wake(app: tauri::AppHandle) {
+    let mut last_instant = Instant::now();
+    let mut last_wall = SystemTime::now();
+    loop {
+        tokio::time::sleep(WAKE_CHECK_INTERVAL).await;
+        let (instant, wall) = (Instant::now(), SystemTime::now());
+        let monotonic = instant.duration_since(last_instant);
+        let elapsed = wall.duration_since(last_wall).unwrap_or(monotonic);
+        (last_instant, last_wall) = (instant, wall);
+
+        if elapsed <= monotonic + WAKE_THRESHOLD {
+            continue;
+        }
+        let (mut restarted, mut failed) = restart_stopped(&app).await;
+        for (_, definition) in flagged_services(&app).await {
+            if restarted.contains(&definition.service_id) {
+                continue;
+            }
+            match restart_service_instance(app.clone(), &definition).await {
+                Ok(()) => restarted.push(definition.service_id),
+                Err(_) => failed.push(definition.service_id),
+            }
+        }
+        let _ = app.emit("system-resumed", SystemResumed {
+            slept_ms: Some((elapsed - monotonic).as_millis() as u64),
+            restarted,
+            failed,
+        });
+    }
+}
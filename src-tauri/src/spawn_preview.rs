@@ -0,0 +1,64 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+use tokio::process::Command;
+
+use crate::{build_claude_command, build_prompt, build_service_command, build_shell_command};
+use crate::{ClaudeSpawnParams, ServiceDefinition, ShellSpawnParams};
+
+// Values of variables whose names contain any of these are masked
+const SECRET_MARKERS: &[&str] = &["KEY", "TOKEN", "SECRET", "PASSWORD"];
+
+#[derive(Clone, Serialize)]
+pub struct SpawnPreview {
+    pub program: String,
+    pub args: Vec<String>,
+    // Variables set on top of the inherited environment; None means removed
+    pub env_overrides: BTreeMap<String, Option<String>>,
+    pub cwd: Option<String>,
+}
+
+fn describe(cmd: &Command) -> SpawnPreview {
+    let std_cmd = cmd.as_std();
+    let env_overrides = std_cmd.get_envs()
+        .map(|(key, value)| {
+            let key = key.to_string_lossy().to_string();
+            let secret = SECRET_MARKERS.iter().any(|marker| key.to_uppercase().contains(marker));
+            let value = value.map(|v| if secret { "********".to_string() } else { v.to_string_lossy().to_string() });
+            (key, value)
+        })
+        .collect();
+    SpawnPreview {
+        program: std_cmd.get_program().to_string_lossy().to_string(),
+        args: std_cmd.get_args().map(|a| a.to_string_lossy().to_string()).collect(),
+        env_overrides,
+        cwd: std_cmd.get_current_dir().map(|d| d.to_string_lossy().to_string()),
+    }
+}
+
+// Show exactly what a spawn would run without running it. `kind` is "claude",
+// "shell" or "service"; `params` takes the same fields as the matching command
+// (a service definition for services).
+#[tauri::command]
+pub async fn preview_spawn(app: tauri::AppHandle, kind: String, params: serde_json::Value) -> Result<SpawnPreview, String> {
+    let invalid = |e: serde_json::Error| format!("Invalid {} parameters: {}", kind, e);
+    match kind.as_str() {
+        "claude" => {
+            let params: ClaudeSpawnParams = serde_json::from_value(params).map_err(invalid)?;
+            // Pinned files are left out: including them marks them as sent
+            let prompt = build_prompt(&app, &params, "").await?;
+            // The MCP config it wrote goes when `built` is dropped
+            let built = build_claude_command(&app, &params, &prompt).await?;
+            Ok(describe(&built.cmd))
+        }
+        "shell" => {
+            let params: ShellSpawnParams = serde_json::from_value(params).map_err(invalid)?;
+            let (cmd, _, _) = build_shell_command(&app, params).await?;
+            Ok(describe(&cmd))
+        }
+        "service" => {
+            let definition: ServiceDefinition = serde_json::from_value(params).map_err(invalid)?;
+            Ok(describe(&build_service_command(&app, &definition).await?))
+        }
+        other => Err(format!("Unknown spawn kind: {}", other)),
+    }
+}
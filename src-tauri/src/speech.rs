@@ -0,0 +1,455 @@
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tauri::Emitter;
+use tokio::sync::{oneshot, Mutex};
+use whisper_rs::{FullParams, SamplingStrategy, WhisperContext, WhisperContextParameters};
+
+use crate::downloads::{self, DownloadOptions};
+use crate::{janitor, settings, storage, unix_millis};
+
+const DEFAULT_MODEL: &str = "base";
+const MODELS: &[&str] = &["tiny", "tiny.en", "base", "base.en", "small", "small.en", "medium", "medium.en"];
+const MODEL_URL: &str = "https://huggingface.co/ggerganov/whisper.cpp/resolve/main";
+// Whisper only takes 16kHz mono
+const WHISPER_SAMPLE_RATE: u32 = 16_000;
+const LEVEL_INTERVAL: Duration = Duration::from_millis(50);
+// A recording nobody stops ends by itself after this
+const MAX_CAPTURE: Duration = Duration::from_secs(10 * 60);
+const MAX_AUDIO_BYTES: u64 = 200 * 1024 * 1024;
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SpeechSettings {
+    // One of MODELS, downloaded on first use; "base" when unset
+    pub model: Option<String>,
+    // A ggml model file to use instead of downloading one
+    pub model_path: Option<String>,
+    // Expected sha256 of the download, checked instead of the server's
+    pub model_sha256: Option<String>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct TranscriptSegment {
+    pub start_ms: u64,
+    pub end_ms: u64,
+    pub text: String,
+    // Mean probability of the segment's tokens, 0 to 1
+    pub confidence: f32,
+}
+
+#[derive(Clone, Serialize)]
+pub struct Transcript {
+    pub text: String,
+    pub segments: Vec<TranscriptSegment>,
+    // Token-weighted mean over all segments
+    pub confidence: f32,
+    // Detected or hinted language code, e.g. "en"
+    pub language: Option<String>,
+    pub duration_ms: u64,
+}
+
+// Sent as `audio-level` while recording, about every 50ms
+#[derive(Clone, Serialize)]
+pub struct AudioLevel {
+    pub rms: f32,
+    pub peak: f32,
+}
+
+#[derive(Clone, Serialize)]
+pub struct AudioCapture {
+    // A 16-bit wav in the app's temp folder, ready for transcribe_audio.
+    // The janitor removes it a day later.
+    pub path: String,
+    pub duration_ms: u64,
+    pub sample_rate: u32,
+    pub channels: u16,
+}
+
+// The loaded model and the file it came from, kept between transcriptions
+struct LoadedModel {
+    path: PathBuf,
+    context: Arc<WhisperContext>,
+}
+
+static MODEL: Lazy<Arc<Mutex<Option<LoadedModel>>>> = Lazy::new(|| Arc::new(Mutex::new(None)));
+
+struct Recording {
+    stop: std::sync::mpsc::Sender<()>,
+    thread: std::thread::JoinHandle<Result<AudioCapture, String>>,
+}
+
+static RECORDING: Lazy<Arc<Mutex<Option<Recording>>>> = Lazy::new(|| Arc::new(Mutex::new(None)));
+
+fn model_name(speech: &SpeechSettings) -> Result<&str, String> {
+    let name = speech.model.as_deref().unwrap_or(DEFAULT_MODEL);
+    if MODELS.contains(&name) {
+        Ok(name)
+    } else {
+        Err(format!("Unknown model {}; expected one of {}", name, MODELS.join(", ")))
+    }
+}
+
+// Hugging Face sends the sha256 of large files as X-Linked-Etag on the
+// redirect to its CDN
+async fn published_sha256(url: &str) -> Result<String, String> {
+    let client = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .map_err(|e| e.to_string())?;
+    let response = client.head(url).send().await.map_err(|e| e.to_string())?;
+    response.headers()
+        .get("x-linked-etag")
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.trim_matches('"').to_lowercase())
+        .filter(|value| value.len() == 64 && value.chars().all(|c| c.is_ascii_hexdigit()))
+        .ok_or_else(|| "The server didn't publish a checksum for the model; set speech.model_sha256".to_string())
+}
+
+// The model file, downloaded into app_data/models the first time. Progress
+// is sent as `download-progress-whisper-model-<name>`.
+async fn model_file(app: &tauri::AppHandle, speech: &SpeechSettings) -> Result<PathBuf, String> {
+    if let Some(ref path) = speech.model_path {
+        let path = PathBuf::from(path);
+        return if path.is_file() { Ok(path) } else { Err(format!("Model file not found: {}", path.display())) };
+    }
+    let name = model_name(speech)?;
+    let dir = storage::data_dir(app).join("models");
+    let path = dir.join(format!("ggml-{}.bin", name));
+    if tokio::fs::metadata(&path).await.is_ok() {
+        return Ok(path);
+    }
+
+    tokio::fs::create_dir_all(&dir).await.map_err(|e| e.to_string())?;
+    let url = format!("{}/ggml-{}.bin", MODEL_URL, name);
+    let sha256 = match speech.model_sha256 {
+        Some(ref sha256) => sha256.clone(),
+        None => published_sha256(&url).await?,
+    };
+    downloads::download_file(
+        app.clone(),
+        url,
+        path.to_string_lossy().to_string(),
+        format!("whisper-model-{}", name),
+        Some(DownloadOptions { sha256: Some(sha256), resume: true }),
+    ).await.map_err(|e| format!("Model download failed: {}", e.message()))?;
+    Ok(path)
+}
+
+async fn load_model(app: &tauri::AppHandle) -> Result<Arc<WhisperContext>, String> {
+    let speech = settings::load(app).await?.speech;
+    // Held across the download too, so two first transcriptions don't both fetch it
+    let mut model = MODEL.lock().await;
+    let path = model_file(app, &speech).await?;
+    if let Some(ref loaded) = *model {
+        if loaded.path == path {
+            return Ok(loaded.context.clone());
+        }
+    }
+    let load_path = path.clone();
+    let context = tokio::task::spawn_blocking(move || {
+        WhisperContext::new_with_params(&load_path, WhisperContextParameters::default())
+            .map(Arc::new)
+            .map_err(|e| format!("Failed to load {}: {}", load_path.display(), e))
+    }).await.map_err(|e| e.to_string())??;
+    *model = Some(LoadedModel { path, context: context.clone() });
+    Ok(context)
+}
+
+// Any wav to 16kHz mono floats
+fn decode_wav(bytes: &[u8]) -> Result<Vec<f32>, String> {
+    let mut reader = hound::WavReader::new(std::io::Cursor::new(bytes)).map_err(|e| format!("Not a readable wav file: {}", e))?;
+    let spec = reader.spec();
+    let samples: Vec<f32> = match spec.sample_format {
+        hound::SampleFormat::Float => reader.samples::<f32>().collect::<Result<_, _>>(),
+        hound::SampleFormat::Int => {
+            let scale = 1.0 / (1i64 << (spec.bits_per_sample - 1)) as f32;
+            reader.samples::<i32>().map(|s| s.map(|s| s as f32 * scale)).collect::<Result<_, _>>()
+        }
+    }.map_err(|e| e.to_string())?;
+
+    let channels = spec.channels.max(1) as usize;
+    let mono: Vec<f32> = samples.chunks(channels)
+        .map(|frame| frame.iter().sum::<f32>() / frame.len() as f32)
+        .collect();
+    if spec.sample_rate == WHISPER_SAMPLE_RATE || mono.is_empty() {
+        return Ok(mono);
+    }
+    // Linear interpolation is plenty for speech
+    let ratio = spec.sample_rate as f64 / WHISPER_SAMPLE_RATE as f64;
+    let length = (mono.len() as f64 / ratio) as usize;
+    Ok((0..length).map(|i| {
+        let position = i as f64 * ratio;
+        let index = position as usize;
+        let next = mono.get(index + 1).copied().unwrap_or(mono[index]);
+        let fraction = (position - index as f64) as f32;
+        mono[index] + (next - mono[index]) * fraction
+    }).collect())
+}
+
+fn run_whisper(context: &WhisperContext, audio: &[f32], language: Option<&str>) -> Result<Transcript, String> {
+    let mut state = context.create_state().map_err(|e| e.to_string())?;
+    let mut params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
+    // "auto" has whisper detect the language
+    params.set_language(Some(language.unwrap_or("auto")));
+    params.set_n_threads(std::thread::available_parallelism().map(|n| n.get().min(8) as i32).unwrap_or(4));
+    params.set_print_progress(false);
+    params.set_print_realtime(false);
+    params.set_print_special(false);
+    params.set_print_timestamps(false);
+    state.full(params, audio).map_err(|e| format!("Transcription failed: {}", e))?;
+
+    // Ids from end-of-text up are timestamps and other special tokens
+    let special = context.token_eot();
+    let mut segments = Vec::new();
+    let mut probability_sum = 0.0;
+    let mut token_count = 0;
+    for segment in state.as_iter() {
+        let probabilities: Vec<f32> = (0..segment.n_tokens())
+            .filter_map(|i| segment.get_token(i))
+            .filter(|token| token.token_id() < special)
+            .map(|token| token.token_probability())
+            .collect();
+        probability_sum += probabilities.iter().sum::<f32>();
+        token_count += probabilities.len();
+        segments.push(TranscriptSegment {
+            // Whisper counts in centiseconds
+            start_ms: segment.start_timestamp().max(0) as u64 * 10,
+            end_ms: segment.end_timestamp().max(0) as u64 * 10,
+            text: segment.to_str_lossy().map_err(|e| e.to_string())?.trim().to_string(),
+            confidence: if probabilities.is_empty() { 0.0 } else { probabilities.iter().sum::<f32>() / probabilities.len() as f32 },
+        });
+    }
+
+    Ok(Transcript {
+        text: segments.iter().map(|s| s.text.as_str()).filter(|t| !t.is_empty()).collect::<Vec<_>>().join(" "),
+        segments,
+        confidence: if token_count == 0 { 0.0 } else { probability_sum / token_count as f32 },
+        language: whisper_rs::get_lang_str(state.full_lang_id_from_state()).map(String::from),
+        duration_ms: audio.len() as u64 * 1000 / WHISPER_SAMPLE_RATE as u64,
+    })
+}
+
+// Transcribe a wav file or wav bytes with the local whisper model. Nothing
+// leaves the machine once the model is downloaded.
+#[tauri::command]
+pub async fn transcribe_audio(
+    app: tauri::AppHandle,
+    path: Option<String>,
+    bytes: Option<Vec<u8>>,
+    language_hint: Option<String>,
+) -> Result<Transcript, String> {
+    let audio = match (path, bytes) {
+        (Some(path), None) => {
+            let size = tokio::fs::metadata(&path).await.map_err(|e| format!("{}: {}", path, e))?.len();
+            if size > MAX_AUDIO_BYTES {
+                return Err(format!("Audio file is too large ({} bytes)", size));
+            }
+            tokio::fs::read(&path).await.map_err(|e| format!("{}: {}", path, e))?
+        }
+        (None, Some(bytes)) if bytes.len() as u64 > MAX_AUDIO_BYTES => {
+            return Err(format!("Audio is too large ({} bytes)", bytes.len()));
+        }
+        (None, Some(bytes)) => bytes,
+        _ => return Err("Provide exactly one of path or bytes".to_string()),
+    };
+    let language = language_hint.map(|l| l.trim().to_lowercase()).filter(|l| !l.is_empty() && l != "auto");
+    if let Some(ref language) = language {
+        if whisper_rs::get_lang_id(language).is_none() {
+            return Err(format!("Unknown language: {}", language));
+        }
+    }
+
+    let context = load_model(&app).await?;
+    tokio::task::spawn_blocking(move || {
+        let samples = decode_wav(&audio)?;
+        run_whisper(&context, &samples, language.as_deref())
+    }).await.map_err(|e| e.to_string())?
+}
+
+// Writes what the microphone hears and reports how loud it is
+struct Sink {
+    app: tauri::AppHandle,
+    writer: Option<hound::WavWriter<std::io::BufWriter<std::fs::File>>>,
+    samples: u64,
+    error: Option<String>,
+    level_sum: f32,
+    level_count: usize,
+    peak: f32,
+    last_level: Instant,
+}
+
+impl Sink {
+    fn push(&mut self, samples: &[f32]) {
+        let Some(writer) = self.writer.as_mut() else { return };
+        for &sample in samples {
+            let sample = sample.clamp(-1.0, 1.0);
+            if let Err(e) = writer.write_sample((sample * i16::MAX as f32) as i16) {
+                self.error.get_or_insert(e.to_string());
+                return;
+            }
+            self.level_sum += sample * sample;
+            self.peak = self.peak.max(sample.abs());
+        }
+        self.samples += samples.len() as u64;
+        self.level_count += samples.len();
+        if self.last_level.elapsed() >= LEVEL_INTERVAL && self.level_count > 0 {
+            let _ = self.app.emit("audio-level", AudioLevel {
+                rms: (self.level_sum / self.level_count as f32).sqrt(),
+                peak: self.peak,
+            });
+            self.level_sum = 0.0;
+            self.level_count = 0;
+            self.peak = 0.0;
+            self.last_level = Instant::now();
+        }
+    }
+}
+
+fn input_stream<T>(
+    device: &cpal::Device,
+    config: &cpal::StreamConfig,
+    sink: Arc<std::sync::Mutex<Sink>>,
+) -> Result<cpal::Stream, String>
+where
+    T: cpal::SizedSample,
+    f32: cpal::FromSample<T>,
+{
+    let errors = sink.clone();
+    device.build_input_stream(
+        config,
+        move |data: &[T], _: &cpal::InputCallbackInfo| {
+            let samples: Vec<f32> = data.iter().map(|s| cpal::Sample::to_sample::<f32>(*s)).collect();
+            if let Ok(mut sink) = sink.lock() {
+                sink.push(&samples);
+            }
+        },
+        move |e| {
+            if let Ok(mut sink) = errors.lock() {
+                sink.error.get_or_insert(e.to_string());
+            }
+        },
+        None,
+    ).map_err(|e| format!("Failed to open the microphone: {}", e))
+}
+
+// Runs on its own thread, since audio streams can't move between threads on
+// every platform. Reports on `ready` once recording has started.
+fn record(
+    app: tauri::AppHandle,
+    path: PathBuf,
+    stop: std::sync::mpsc::Receiver<()>,
+    ready: oneshot::Sender<Result<(), String>>,
+) -> Result<AudioCapture, String> {
+    let started = (|| {
+        let device = cpal::default_host().default_input_device().ok_or("No microphone found")?;
+        let supported = device.default_input_config().map_err(|e| e.to_string())?;
+        let config = supported.config();
+        let spec = hound::WavSpec {
+            channels: config.channels,
+            sample_rate: config.sample_rate,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let writer = hound::WavWriter::create(&path, spec).map_err(|e| e.to_string())?;
+        let sink = Arc::new(std::sync::Mutex::new(Sink {
+            app,
+            writer: Some(writer),
+            samples: 0,
+            error: None,
+            level_sum: 0.0,
+            level_count: 0,
+            peak: 0.0,
+            last_level: Instant::now(),
+        }));
+        let stream = match supported.sample_format() {
+            cpal::SampleFormat::F32 => input_stream::<f32>(&device, &config, sink.clone()),
+            cpal::SampleFormat::I16 => input_stream::<i16>(&device, &config, sink.clone()),
+            cpal::SampleFormat::U16 => input_stream::<u16>(&device, &config, sink.clone()),
+            cpal::SampleFormat::I32 => input_stream::<i32>(&device, &config, sink.clone()),
+            format => Err(format!("Unsupported sample format: {}", format)),
+        }?;
+        stream.play().map_err(|e| e.to_string())?;
+        Ok::<_, String>((stream, sink, config))
+    })();
+    let (stream, sink, config) = match started {
+        Ok(started) => {
+            let _ = ready.send(Ok(()));
+            started
+        }
+        Err(e) => {
+            let _ = std::fs::remove_file(&path);
+            let _ = ready.send(Err(e.clone()));
+            return Err(e);
+        }
+    };
+
+    // Either stop_audio_capture or the time limit
+    let _ = stop.recv_timeout(MAX_CAPTURE);
+    drop(stream);
+
+    let mut sink = sink.lock().map_err(|_| "Recording state was poisoned".to_string())?;
+    if let Some(writer) = sink.writer.take() {
+        writer.finalize().map_err(|e| e.to_string())?;
+    }
+    if let Some(ref error) = sink.error {
+        if sink.samples == 0 {
+            return Err(error.clone());
+        }
+    }
+    let frames = sink.samples / config.channels.max(1) as u64;
+    Ok(AudioCapture {
+        path: path.to_string_lossy().to_string(),
+        duration_ms: frames * 1000 / config.sample_rate.max(1) as u64,
+        sample_rate: config.sample_rate,
+        channels: config.channels,
+    })
+}
+
+// Start recording the default microphone. Levels arrive as `audio-level`
+// until stop_audio_capture, which returns the recording.
+#[tauri::command]
+pub async fn start_audio_capture(app: tauri::AppHandle) -> Result<String, String> {
+    let mut recording = RECORDING.lock().await;
+    if recording.is_some() {
+        return Err("Already recording".to_string());
+    }
+    let path = storage::temp_dir(&app)?.join(format!("capture-{}.wav", unix_millis()));
+    janitor::register_temp_file(&app, &path).await;
+    let (stop_tx, stop_rx) = std::sync::mpsc::channel();
+    let (ready_tx, ready_rx) = oneshot::channel();
+    let thread_path = path.clone();
+    let thread = std::thread::Builder::new()
+        .name("audio capture".to_string())
+        .spawn(move || record(app, thread_path, stop_rx, ready_tx))
+        .map_err(|e| e.to_string())?;
+    ready_rx.await.map_err(|_| "Recording thread exited".to_string())??;
+    *recording = Some(Recording { stop: stop_tx, thread });
+    Ok(path.to_string_lossy().to_string())
+}
+
+// Also collects a recording that already ended at the time limit
+#[tauri::command]
+pub async fn stop_audio_capture() -> Result<AudioCapture, String> {
+    let recording = RECORDING.lock().await.take().ok_or("Not recording")?;
+    let _ = recording.stop.send(());
+    tokio::task::spawn_blocking(move || recording.thread.join())
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|_| "Recording thread panicked".to_string())?
+}
+
+#[tauri::command]
+pub async fn set_speech_settings(app: tauri::AppHandle, speech: SpeechSettings) -> Result<(), String> {
+    model_name(&speech)?;
+    if let Some(ref path) = speech.model_path {
+        if !Path::new(path).is_file() {
+            return Err(format!("Model file not found: {}", path));
+        }
+    }
+    settings::update(&app, |settings| settings.speech = speech.clone()).await?;
+    Ok(())
+}
@@ -0,0 +1,75 @@
+use once_cell::sync::OnceCell;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use tauri::{Emitter, Manager};
+
+// Where everything the backend persists lives. Resolved once; when the real
+// app data directory can't be used we fall back to a per-run temp directory
+// and nothing survives a restart.
+struct StorageRoot {
+    dir: PathBuf,
+    degraded: bool,
+    reason: Option<String>,
+}
+
+static STORAGE: OnceCell<StorageRoot> = OnceCell::new();
+
+#[derive(Clone, Serialize)]
+pub struct StorageDegraded {
+    pub reason: String,
+    pub fallback_dir: String,
+}
+
+fn check_writable(dir: &Path) -> Result<(), String> {
+    std::fs::create_dir_all(dir).map_err(|e| format!("Cannot create {}: {}", dir.display(), e))?;
+    let probe = dir.join(".write-probe");
+    std::fs::write(&probe, b"ok").map_err(|e| format!("{} is not writable: {}", dir.display(), e))?;
+    let _ = std::fs::remove_file(&probe);
+    Ok(())
+}
+
+fn probe(app: &tauri::AppHandle) -> StorageRoot {
+    let persistent = app.path().app_data_dir()
+        .map_err(|e| format!("Cannot resolve app data directory: {}", e))
+        .and_then(|dir| check_writable(&dir).map(|_| dir));
+
+    match persistent {
+        Ok(dir) => StorageRoot { dir, degraded: false, reason: None },
+        Err(reason) => {
+            let dir = std::env::temp_dir().join(format!("claude-quest-ephemeral-{}", std::process::id()));
+            let _ = std::fs::create_dir_all(&dir);
+            StorageRoot { dir, degraded: true, reason: Some(reason) }
+        }
+    }
+}
+
+fn root(app: &tauri::AppHandle) -> &'static StorageRoot {
+    STORAGE.get_or_init(|| probe(app))
+}
+
+// Directory for all persisted backend and frontend data
+pub(crate) fn data_dir(app: &tauri::AppHandle) -> PathBuf {
+    root(app).dir.clone()
+}
+
+// Scratch space for short-lived files like generated MCP configs
+pub(crate) fn temp_dir(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    let dir = data_dir(app).join("tmp");
+    std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    Ok(dir)
+}
+
+pub(crate) fn is_degraded() -> bool {
+    STORAGE.get().is_some_and(|root| root.degraded)
+}
+
+// Run at startup so the fallback is decided before anything is written
+pub(crate) fn probe_at_startup(app: &tauri::AppHandle) {
+    let root = root(app);
+    if let (true, Some(reason)) = (root.degraded, &root.reason) {
+        let _ = app.emit("storage-degraded", StorageDegraded {
+            reason: reason.clone(),
+            fallback_dir: root.dir.to_string_lossy().to_string(),
+        });
+    }
+}
@@ -0,0 +1,123 @@
+use serde::Serialize;
+use std::future::Future;
+use tauri::Emitter;
+use tokio::task::{AbortHandle, JoinHandle};
+
+use crate::{background_tasks, RUNNING_SERVICES};
+
+// Sent as `stream-error` whenever a task feeding one of the output streams
+// stops for a reason other than its stream ending
+#[derive(Clone, Serialize)]
+pub struct StreamError {
+    // The id in the stream's own event names: service id, shell process id, tail id or conversation id
+    pub channel_id: String,
+    // "service", "shell", "file_tail" or "claude"
+    pub subsystem: String,
+    // "read_failed", "task_panicked" or "task_cancelled"
+    pub code: String,
+    pub message: String,
+    // True when no more output will arrive on the channel
+    pub is_fatal: bool,
+    // Whether the process behind the stream was still running when this was
+    // sent; None when there's no process to check
+    pub process_alive: Option<bool>,
+}
+
+// How to tell whether the process behind a stream is still running
+#[derive(Clone)]
+pub(crate) enum Liveness {
+    Service(String),
+    Pid(u32),
+    Unknown,
+}
+
+impl Liveness {
+    async fn check(&self) -> Option<bool> {
+        match self {
+            Liveness::Service(service_id) => {
+                let mut services = RUNNING_SERVICES.lock().await;
+                Some(services.get_mut(service_id).is_some_and(|s| matches!(s.child.try_wait(), Ok(None))))
+            }
+            #[cfg(unix)]
+            Liveness::Pid(pid) => Some(unsafe { libc::kill(*pid as i32, 0) } == 0),
+            #[cfg(not(unix))]
+            Liveness::Pid(_) => None,
+            Liveness::Unknown => None,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct StreamSource {
+    pub channel_id: String,
+    pub subsystem: &'static str,
+    pub liveness: Liveness,
+}
+
+impl StreamSource {
+    pub(crate) fn new(channel_id: &str, subsystem: &'static str, liveness: Liveness) -> Self {
+        StreamSource { channel_id: channel_id.to_string(), subsystem, liveness }
+    }
+
+    pub(crate) async fn report(&self, app: &tauri::AppHandle, code: &str, message: String, is_fatal: bool) {
+        let _ = app.emit("stream-error", StreamError {
+            channel_id: self.channel_id.clone(),
+            subsystem: self.subsystem.to_string(),
+            code: code.to_string(),
+            message,
+            is_fatal,
+            process_alive: self.liveness.check().await,
+        });
+    }
+}
+
+// Aborts the wrapped task when the supervisor itself is aborted or dropped
+struct AbortOnDrop(AbortHandle);
+
+impl Drop for AbortOnDrop {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+fn panic_message(error: tokio::task::JoinError) -> String {
+    let Ok(payload) = error.try_into_panic() else { return "Task panicked".to_string() };
+    payload.downcast_ref::<&str>().map(|s| s.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "Task panicked".to_string())
+}
+
+// Run a reader as a registered background task and report through
+// `stream-error` if it fails. The future hands back its value along with how
+// reading went; a read error still returns what was gathered, a panic gives None.
+pub(crate) fn spawn_reader<T, F>(
+    app: tauri::AppHandle,
+    source: StreamSource,
+    description: String,
+    future: F,
+) -> JoinHandle<Option<T>>
+where
+    T: Send + 'static,
+    F: Future<Output = (T, Result<(), String>)> + Send + 'static,
+{
+    background_tasks::spawn(description, async move {
+        // A separate task so a panic in the reader is caught here
+        let inner = tokio::spawn(future);
+        let _guard = AbortOnDrop(inner.abort_handle());
+        match inner.await {
+            Ok((value, Ok(()))) => Some(value),
+            Ok((value, Err(message))) => {
+                source.report(&app, "read_failed", message, true).await;
+                Some(value)
+            }
+            Err(e) if e.is_panic() => {
+                source.report(&app, "task_panicked", panic_message(e), true).await;
+                None
+            }
+            Err(_) => {
+                source.report(&app, "task_cancelled", "Reader task was cancelled".to_string(), true).await;
+                None
+            }
+        }
+    })
+}
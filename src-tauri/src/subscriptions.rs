@@ -0,0 +1,87 @@
+use glob::Pattern;
+use once_cell::sync::Lazy;
+use std::sync::Arc;
+use tokio::sync::{watch, Mutex};
+
+struct Subscription {
+    window_label: String,
+    channel_pattern: String,
+    pattern: Pattern,
+}
+
+#[derive(Default)]
+struct Subscriptions {
+    // Frontends that never call subscribe keep every producer running
+    tracking: bool,
+    entries: Vec<Subscription>,
+}
+
+static SUBSCRIPTIONS: Lazy<Arc<Mutex<Subscriptions>>> =
+    Lazy::new(|| Arc::new(Mutex::new(Subscriptions::default())));
+
+// Bumped on every change so paused producers can look again
+static CHANGES: Lazy<watch::Sender<u64>> = Lazy::new(|| watch::channel(0).0);
+
+fn changed() {
+    CHANGES.send_modify(|version| *version += 1);
+}
+
+// Whether anyone is listening on `channel`. Only producers doing work nobody
+// would see should ask; completion and exit events are emitted and recorded
+// whether or not this is true.
+pub(crate) async fn has_subscribers(channel: &str) -> bool {
+    let subscriptions = SUBSCRIPTIONS.lock().await;
+    !subscriptions.tracking || subscriptions.entries.iter().any(|s| s.pattern.matches(channel))
+}
+
+// Returns straight away if `channel` has a subscriber, otherwise once one subscribes
+pub(crate) async fn wait_for_subscribers(channel: &str) {
+    let mut changes = CHANGES.subscribe();
+    while !has_subscribers(channel).await {
+        if changes.changed().await.is_err() {
+            return;
+        }
+    }
+}
+
+// Drop everything a window subscribed to once it has gone away
+pub(crate) async fn remove_window(window_label: &str) {
+    let mut subscriptions = SUBSCRIPTIONS.lock().await;
+    let before = subscriptions.entries.len();
+    subscriptions.entries.retain(|s| s.window_label != window_label);
+    if subscriptions.entries.len() != before {
+        changed();
+    }
+}
+
+// `channel_pattern` is a glob over event names, e.g. `file-tail-*`. The
+// subscription belongs to `window_label`, or the calling window if not given.
+#[tauri::command]
+pub async fn subscribe(window: tauri::Window, channel_pattern: String, window_label: Option<String>) -> Result<(), String> {
+    let pattern = Pattern::new(&channel_pattern)
+        .map_err(|e| format!("Invalid channel pattern '{}': {}", channel_pattern, e))?;
+    let window_label = window_label.unwrap_or_else(|| window.label().to_string());
+    let mut subscriptions = SUBSCRIPTIONS.lock().await;
+    subscriptions.tracking = true;
+    let exists = subscriptions.entries.iter()
+        .any(|s| s.window_label == window_label && s.channel_pattern == channel_pattern);
+    if !exists {
+        subscriptions.entries.push(Subscription { window_label, channel_pattern, pattern });
+    }
+    changed();
+    Ok(())
+}
+
+// Returns whether the subscription existed
+#[tauri::command]
+pub async fn unsubscribe(window: tauri::Window, channel_pattern: String, window_label: Option<String>) -> Result<bool, String> {
+    let window_label = window_label.unwrap_or_else(|| window.label().to_string());
+    let mut subscriptions = SUBSCRIPTIONS.lock().await;
+    let before = subscriptions.entries.len();
+    subscriptions.entries.retain(|s| !(s.window_label == window_label && s.channel_pattern == channel_pattern));
+    let removed = subscriptions.entries.len() != before;
+    if removed {
+        changed();
+    }
+    Ok(removed)
+}
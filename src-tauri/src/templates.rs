@@ -0,0 +1,257 @@
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use crate::attachments::hex_digest;
+use crate::{conversation_integrations, settings, storage, transcripts, unix_millis, write_atomic, IntegrationConfig};
+
+const PERMISSION_MODES: &[&str] = &["default", "acceptEdits", "plan", "bypassPermissions"];
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ConversationTemplate {
+    pub template_id: String,
+    pub name: String,
+    pub system_prompt: Option<String>,
+    pub model: Option<String>,
+    // A claude permission mode; None keeps the usual bypassPermissions
+    pub permission_mode: Option<String>,
+    // Environment profile the conversation's turns run with
+    pub profile: Option<String>,
+    pub integration_ids: Vec<String>,
+    pub default_working_directory: Option<String>,
+    pub first_message: Option<String>,
+    pub created_at: u64,
+}
+
+// What save_conversation_template takes; an existing template_id updates that template
+#[derive(Clone, Deserialize)]
+pub struct TemplateInput {
+    pub template_id: Option<String>,
+    pub name: String,
+    pub system_prompt: Option<String>,
+    pub model: Option<String>,
+    pub permission_mode: Option<String>,
+    pub profile: Option<String>,
+    #[serde(default)]
+    pub integration_ids: Vec<String>,
+    pub default_working_directory: Option<String>,
+    pub first_message: Option<String>,
+}
+
+// Per-instantiation changes; anything left out comes from the template
+#[derive(Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct TemplateOverrides {
+    pub title: Option<String>,
+    pub system_prompt: Option<String>,
+    pub model: Option<String>,
+    pub working_directory: Option<String>,
+    pub first_message: Option<String>,
+    // Send the first message straight away (default true when there is one)
+    pub send_first_message: Option<bool>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct InstantiatedConversation {
+    pub conversation_id: String,
+    pub session_id: Option<String>,
+    // The first turn's result, when it was sent
+    pub first_response: Option<crate::ClaudeResult>,
+}
+
+static TEMPLATES_LOCK: Lazy<Arc<Mutex<()>>> = Lazy::new(|| Arc::new(Mutex::new(())));
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+fn get_templates_path(app: &tauri::AppHandle) -> PathBuf {
+    storage::data_dir(app).join("conversation-templates.json")
+}
+
+async fn read_templates(app: &tauri::AppHandle) -> Vec<ConversationTemplate> {
+    match tokio::fs::read_to_string(get_templates_path(app)).await {
+        Ok(data) => serde_json::from_str(&data).unwrap_or_default(),
+        Err(_) => Vec::new(),
+    }
+}
+
+async fn write_templates(app: &tauri::AppHandle, templates: &[ConversationTemplate]) -> Result<(), String> {
+    let data = serde_json::to_vec_pretty(templates).map_err(|e| e.to_string())?;
+    write_atomic(&get_templates_path(app), &data).await
+}
+
+// Random-looking UUID-shaped id, the same shape the frontend gives conversations
+pub(crate) fn new_id() -> String {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or(0);
+    let seed = format!("{}-{}-{}", nanos, std::process::id(), NEXT_ID.fetch_add(1, Ordering::Relaxed));
+    let hex = hex_digest(&Sha256::digest(seed.as_bytes()));
+    format!("{}-{}-{}-{}-{}", &hex[0..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..32])
+}
+
+// Integrations the frontend has configured, from the store the migration writes
+async fn stored_integrations(app: &tauri::AppHandle) -> Vec<IntegrationConfig> {
+    let path = storage::data_dir(app).join("integrations.json");
+    let Ok(data) = tokio::fs::read_to_string(&path).await else { return Vec::new() };
+    let stored: Vec<serde_json::Value> = serde_json::from_str(&data).unwrap_or_default();
+    stored.into_iter().filter_map(|int| serde_json::from_value(int).ok()).collect()
+}
+
+#[tauri::command]
+pub async fn save_conversation_template(app: tauri::AppHandle, template: TemplateInput) -> Result<ConversationTemplate, String> {
+    if template.name.trim().is_empty() {
+        return Err("Template name is required".to_string());
+    }
+    if let Some(ref mode) = template.permission_mode {
+        if !PERMISSION_MODES.contains(&mode.as_str()) {
+            return Err(format!("Unknown permission mode: {}", mode));
+        }
+    }
+
+    let _guard = TEMPLATES_LOCK.lock().await;
+    let mut templates = read_templates(&app).await;
+    let existing = template.template_id.as_ref()
+        .and_then(|id| templates.iter().position(|t| &t.template_id == id));
+    let saved = ConversationTemplate {
+        template_id: template.template_id.clone().unwrap_or_else(new_id),
+        name: template.name,
+        system_prompt: template.system_prompt,
+        model: template.model,
+        permission_mode: template.permission_mode,
+        profile: template.profile,
+        integration_ids: template.integration_ids,
+        default_working_directory: template.default_working_directory,
+        first_message: template.first_message,
+        created_at: existing.map(|i| templates[i].created_at).unwrap_or_else(unix_millis),
+    };
+    match existing {
+        Some(index) => templates[index] = saved.clone(),
+        None => templates.push(saved.clone()),
+    }
+    write_templates(&app, &templates).await?;
+    Ok(saved)
+}
+
+#[tauri::command]
+pub async fn list_conversation_templates(app: tauri::AppHandle) -> Result<Vec<ConversationTemplate>, String> {
+    Ok(read_templates(&app).await)
+}
+
+#[tauri::command]
+pub async fn delete_conversation_template(app: tauri::AppHandle, template_id: String) -> Result<bool, String> {
+    let _guard = TEMPLATES_LOCK.lock().await;
+    let mut templates = read_templates(&app).await;
+    let before = templates.len();
+    templates.retain(|t| t.template_id != template_id);
+    if templates.len() == before {
+        return Ok(false);
+    }
+    write_templates(&app, &templates).await?;
+    Ok(true)
+}
+
+// Create a conversation from a template and, if it has a first message,
+// send it. Everything the template refers to is checked before anything is
+// written, so a stale template fails with the list of what's missing instead
+// of leaving a half-set-up conversation.
+#[tauri::command]
+pub async fn instantiate_template(
+    app: tauri::AppHandle,
+    template_id: String,
+    overrides: Option<TemplateOverrides>,
+) -> Result<InstantiatedConversation, String> {
+    let overrides = overrides.unwrap_or_default();
+    let template = read_templates(&app).await.into_iter()
+        .find(|t| t.template_id == template_id)
+        .ok_or_else(|| format!("Unknown template: {}", template_id))?;
+
+    let mut problems = Vec::new();
+    let configured = stored_integrations(&app).await;
+    let mut integrations = Vec::new();
+    for id in &template.integration_ids {
+        match configured.iter().find(|int| &int.id == id) {
+            Some(int) => integrations.push(int.clone()),
+            None => problems.push(format!("integration {} no longer exists", id)),
+        }
+    }
+    let working_directory = overrides.working_directory.or(template.default_working_directory.clone());
+    if let Some(ref dir) = working_directory {
+        if !std::path::Path::new(dir).is_dir() {
+            problems.push(format!("working directory {} does not exist", dir));
+        }
+    }
+    if let Some(ref profile) = template.profile {
+        if !settings::load(&app).await?.env_profiles.contains_key(profile) {
+            problems.push(format!("environment profile {} no longer exists", profile));
+        }
+    }
+    if !problems.is_empty() {
+        return Err(format!("Template {} can't be used: {}", template.name, problems.join("; ")));
+    }
+
+    let conversation_id = new_id();
+    let now = unix_millis();
+    let system_prompt = overrides.system_prompt.or(template.system_prompt.clone());
+    let model = overrides.model.or(template.model.clone());
+    // Shaped like the frontend's own conversations; the extra fields are kept as-is
+    let record = serde_json::json!({
+        "id": conversation_id,
+        "title": overrides.title.unwrap_or_else(|| template.name.clone()),
+        "messages": [],
+        "status": "idle",
+        "createdAt": now,
+        "updatedAt": now,
+        "equippedSkills": [],
+        "equippedIntegrations": template.integration_ids,
+        "workingDirectory": working_directory.clone().unwrap_or_default(),
+        "lastSeenMessageCount": 0,
+        "tokensUsed": 0,
+        "templateId": template.template_id,
+        "systemPrompt": system_prompt,
+        "model": model,
+        "permissionMode": template.permission_mode,
+        "profile": template.profile,
+    });
+    transcripts::write_transcript(&app, &conversation_id, &record).await?;
+    conversation_integrations::set_conversation_integrations(
+        app.clone(),
+        conversation_id.clone(),
+        Some(template.integration_ids.clone()),
+    ).await?;
+
+    let first_message = overrides.first_message.or(template.first_message.clone());
+    let first_response = match first_message {
+        Some(message) if overrides.send_first_message.unwrap_or(true) => {
+            Some(crate::send_to_claude(
+                app.clone(),
+                conversation_id.clone(),
+                message,
+                system_prompt,
+                working_directory,
+                Some(integrations),
+                None,
+                template.profile.clone(),
+                None,
+                None,
+                None,
+                None,
+                model,
+                template.permission_mode.clone(),
+                None,
+                None,
+                None,
+            ).await.map_err(|e| format!("Conversation {} was created, but its first message failed: {}", conversation_id, e))?)
+        }
+        _ => None,
+    };
+
+    Ok(InstantiatedConversation {
+        conversation_id,
+        session_id: first_response.as_ref().and_then(|r| r.session_id.clone()),
+        first_response,
+    })
+}
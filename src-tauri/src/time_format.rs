@@ -0,0 +1,168 @@
+use serde::{Deserialize, Serialize};
+use tauri::Emitter;
+
+use crate::{settings, unix_millis};
+
+const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+
+// How timestamps are shown. Unset fields follow the system.
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TimeFormatSettings {
+    // BCP 47 tag such as "en-US" or "de-DE"; decides the date order and clock
+    pub locale: Option<String>,
+    // Overrides the locale's 12/24-hour clock
+    pub hour12: Option<bool>,
+    // Fixed offset from UTC instead of the system time zone, e.g. 540 for +09:00
+    pub utc_offset_minutes: Option<i32>,
+}
+
+// Sits next to a raw timestamp, which stays in the response for sorting
+#[derive(Clone, Serialize, Deserialize)]
+pub struct FormattedTime {
+    // "Mar 4, 2026, 3:07 PM", "4 Mar 2026, 15:07", "2026-03-04 15:07"
+    pub display: String,
+    // "just now", "3 min ago", "in 2 hr", "yesterday"
+    pub relative: String,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum DateOrder {
+    MonthDayYear,
+    DayMonthYear,
+    YearMonthDay,
+}
+
+// Formats any number of timestamps against one reading of the clock
+pub(crate) struct TimeFormatter {
+    order: DateOrder,
+    hour12: bool,
+    utc_offset_minutes: Option<i32>,
+    now: u64,
+}
+
+// The locale from LANG and friends, e.g. "en_US.UTF-8" -> "en-US"
+fn system_locale() -> Option<String> {
+    ["LC_ALL", "LC_TIME", "LANG"].iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|value| !value.is_empty() && value != "C" && value != "POSIX")
+        .map(|value| value.split(['.', '@']).next().unwrap_or("").replace('_', "-"))
+}
+
+fn locale_defaults(locale: &str) -> (DateOrder, bool) {
+    let mut parts = locale.split('-');
+    let language = parts.next().unwrap_or("").to_lowercase();
+    let region = parts.next().unwrap_or("").to_uppercase();
+    match (language.as_str(), region.as_str()) {
+        ("en", "US" | "CA" | "PH" | "") => (DateOrder::MonthDayYear, true),
+        ("en", "AU" | "IN" | "NZ") => (DateOrder::DayMonthYear, true),
+        ("ja" | "zh" | "ko" | "hu" | "lt" | "sv" | "mn", _) => (DateOrder::YearMonthDay, language == "ko"),
+        _ => (DateOrder::DayMonthYear, false),
+    }
+}
+
+// Offset of the system time zone at that moment, so daylight saving is right
+#[cfg(unix)]
+fn system_offset_seconds(millis: u64) -> i64 {
+    let time = (millis / 1000) as libc::time_t;
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    if unsafe { libc::localtime_r(&time, &mut tm) }.is_null() {
+        return 0;
+    }
+    tm.tm_gmtoff as i64
+}
+
+// Without a time zone lookup here, times are shown in UTC unless overridden
+#[cfg(not(unix))]
+fn system_offset_seconds(_millis: u64) -> i64 {
+    0
+}
+
+// Days since 1970-01-01 to (year, month 1-12, day)
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+fn relative(now: u64, millis: u64) -> String {
+    let future = millis > now;
+    let seconds = now.abs_diff(millis) / 1000;
+    let amount = match seconds {
+        0..=44 => return "just now".to_string(),
+        45..=5399 => format!("{} min", (seconds + 30) / 60),
+        5400..=86_399 => format!("{} hr", (seconds + 1800) / 3600),
+        86_400..=172_799 => return if future { "tomorrow" } else { "yesterday" }.to_string(),
+        _ if seconds < 60 * 86_400 => format!("{} days", seconds / 86_400),
+        _ if seconds < 365 * 86_400 => format!("{} mo", seconds / (30 * 86_400)),
+        _ => format!("{} yr", seconds / (365 * 86_400)),
+    };
+    if future { format!("in {}", amount) } else { format!("{} ago", amount) }
+}
+
+impl TimeFormatter {
+    pub(crate) async fn load(app: &tauri::AppHandle) -> Self {
+        let settings = settings::load(app).await.map(|s| s.time_format).unwrap_or_default();
+        Self::new(&settings)
+    }
+
+    fn new(settings: &TimeFormatSettings) -> Self {
+        let locale = settings.locale.clone().or_else(system_locale).unwrap_or_else(|| "en-US".to_string());
+        let (order, hour12) = locale_defaults(&locale);
+        TimeFormatter {
+            order,
+            hour12: settings.hour12.unwrap_or(hour12),
+            utc_offset_minutes: settings.utc_offset_minutes,
+            now: unix_millis(),
+        }
+    }
+
+    pub(crate) fn format(&self, millis: u64) -> FormattedTime {
+        let offset = match self.utc_offset_minutes {
+            Some(minutes) => minutes as i64 * 60,
+            None => system_offset_seconds(millis),
+        };
+        let local = (millis / 1000) as i64 + offset;
+        let (year, month, day) = civil_from_days(local.div_euclid(86_400));
+        let seconds_of_day = local.rem_euclid(86_400);
+        let (hour, minute) = (seconds_of_day / 3600, seconds_of_day % 3600 / 60);
+
+        let month_name = MONTHS[month as usize - 1];
+        let date = match self.order {
+            DateOrder::MonthDayYear => format!("{} {}, {}", month_name, day, year),
+            DateOrder::DayMonthYear => format!("{} {} {}", day, month_name, year),
+            DateOrder::YearMonthDay => format!("{}-{:02}-{:02}", year, month, day),
+        };
+        let time = if self.hour12 {
+            let suffix = if hour < 12 { "AM" } else { "PM" };
+            let hour = match hour % 12 { 0 => 12, h => h };
+            format!("{}:{:02} {}", hour, minute, suffix)
+        } else {
+            format!("{:02}:{:02}", hour, minute)
+        };
+        let separator = if self.order == DateOrder::YearMonthDay { " " } else { ", " };
+        FormattedTime {
+            display: format!("{}{}{}", date, separator, time),
+            relative: relative(self.now, millis),
+        }
+    }
+}
+
+// Views showing formatted times should ask for them again after
+// `time-format-changed`
+#[tauri::command]
+pub async fn set_time_format_settings(app: tauri::AppHandle, format: TimeFormatSettings) -> Result<(), String> {
+    if format.utc_offset_minutes.is_some_and(|minutes| minutes.abs() > 14 * 60) {
+        return Err("UTC offset must be within 14 hours".to_string());
+    }
+    settings::update(&app, |settings| settings.time_format = format.clone()).await?;
+    let _ = app.emit("time-format-changed", format);
+    Ok(())
+}
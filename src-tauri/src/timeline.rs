@@ -0,0 +1,90 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Instant;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct TimelineEntry {
+    // "text", "tool_use" or "tool_result"
+    pub kind: String,
+    // Milliseconds since the turn started, as seen when the line arrived
+    pub at_ms: u64,
+    pub tool_use_id: Option<String>,
+    pub tool_name: Option<String>,
+    // Tool arguments, for tool_use entries
+    pub input: Option<serde_json::Value>,
+    pub is_error: Option<bool>,
+    // From the tool_use to its tool_result, for tool_result entries
+    pub duration_ms: Option<u64>,
+}
+
+// Builds the ordered record of what claude did during one turn. The stream
+// carries no timestamps of its own, so times are when each line was read.
+pub(crate) struct TimelineRecorder {
+    started: Instant,
+    entries: Vec<TimelineEntry>,
+    // A run of text blocks is one entry, marking where the text started
+    in_text: bool,
+    tool_starts: HashMap<String, (u64, String)>,
+}
+
+impl TimelineRecorder {
+    pub(crate) fn new() -> Self {
+        TimelineRecorder {
+            started: Instant::now(),
+            entries: Vec::new(),
+            in_text: false,
+            tool_starts: HashMap::new(),
+        }
+    }
+
+    fn now_ms(&self) -> u64 {
+        self.started.elapsed().as_millis() as u64
+    }
+
+    fn push(&mut self, kind: &str, tool_use_id: Option<String>, tool_name: Option<String>) -> &mut TimelineEntry {
+        self.entries.push(TimelineEntry {
+            kind: kind.to_string(),
+            at_ms: self.now_ms(),
+            tool_use_id,
+            tool_name,
+            input: None,
+            is_error: None,
+            duration_ms: None,
+        });
+        self.entries.last_mut().expect("just pushed")
+    }
+
+    pub(crate) fn on_text(&mut self) {
+        if !self.in_text {
+            self.push("text", None, None);
+            self.in_text = true;
+        }
+    }
+
+    pub(crate) fn on_tool_use(&mut self, item: &serde_json::Value) {
+        self.in_text = false;
+        let id = item.get("id").and_then(|i| i.as_str()).map(String::from);
+        let name = item.get("name").and_then(|n| n.as_str()).unwrap_or("tool").to_string();
+        let at_ms = self.now_ms();
+        if let Some(ref id) = id {
+            self.tool_starts.insert(id.clone(), (at_ms, name.clone()));
+        }
+        let entry = self.push("tool_use", id, Some(name));
+        entry.input = item.get("input").cloned();
+    }
+
+    pub(crate) fn on_tool_result(&mut self, item: &serde_json::Value) {
+        self.in_text = false;
+        let id = item.get("tool_use_id").and_then(|i| i.as_str()).map(String::from);
+        let start = id.as_ref().and_then(|id| self.tool_starts.remove(id));
+        let now = self.now_ms();
+        let is_error = item.get("is_error").and_then(|e| e.as_bool()).unwrap_or(false);
+        let entry = self.push("tool_result", id, start.as_ref().map(|(_, name)| name.clone()));
+        entry.is_error = Some(is_error);
+        entry.duration_ms = start.map(|(at, _)| now.saturating_sub(at));
+    }
+
+    pub(crate) fn finish(self) -> Vec<TimelineEntry> {
+        self.entries
+    }
+}
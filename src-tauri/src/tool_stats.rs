@@ -0,0 +1,322 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Instant;
+use tokio::io::AsyncWriteExt;
+
+use crate::unix_millis;
+
+const DEFAULT_TOP_COMMANDS: usize = 10;
+// Integrations with no calls in this window are flagged as unused
+const UNUSED_AFTER_MS: u64 = 30 * 24 * 60 * 60 * 1000;
+
+// One tool call made by claude during a turn
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ToolInvocation {
+    pub conversation_id: String,
+    // When the send_to_claude call this happened in started (unix ms)
+    pub turn_started_at: u64,
+    pub tool_use_id: String,
+    pub tool_name: String,
+    pub started_at: u64,
+    // None if no tool_result arrived before the turn ended
+    pub duration_ms: Option<u64>,
+    pub is_error: bool,
+    // First word of the command, for Bash calls
+    pub command_prefix: Option<String>,
+    // Integration id for MCP tools, otherwise "builtin" or "unknown".
+    // Missing from entries written before attribution existed.
+    #[serde(default)]
+    pub integration: Option<String>,
+    // The file an Edit, MultiEdit, Write or NotebookEdit call changed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file_path: Option<String>,
+}
+
+// Pairs tool_use blocks with their tool_result blocks as the stream is read
+pub(crate) struct ToolTracker {
+    conversation_id: String,
+    // Ids of the MCP integrations active this turn
+    mcp_integrations: Vec<String>,
+    turn_started_at: u64,
+    pending: HashMap<String, (ToolInvocation, Instant)>,
+    finished: Vec<ToolInvocation>,
+}
+
+// First real word of a shell command, skipping leading VAR=value assignments
+fn command_prefix(command: &str) -> Option<String> {
+    command.split_whitespace()
+        .find(|word| !word.contains('=') || word.starts_with('='))
+        .map(String::from)
+}
+
+// MCP tools are named `mcp__<server>__<tool>`, where the server is the
+// integration id it was configured under
+fn classify(tool_name: &str, mcp_integrations: &[String]) -> String {
+    let Some(rest) = tool_name.strip_prefix("mcp__") else {
+        return "builtin".to_string();
+    };
+    let server = rest.split("__").next().unwrap_or(rest);
+    if mcp_integrations.iter().any(|id| id == server) {
+        server.to_string()
+    } else {
+        "unknown".to_string()
+    }
+}
+
+impl ToolTracker {
+    pub(crate) fn new(conversation_id: &str, mcp_integrations: Vec<String>) -> Self {
+        ToolTracker {
+            conversation_id: conversation_id.to_string(),
+            mcp_integrations,
+            turn_started_at: unix_millis(),
+            pending: HashMap::new(),
+            finished: Vec::new(),
+        }
+    }
+
+    // Handle a `tool_use` content block from an assistant message
+    pub(crate) fn on_tool_use(&mut self, item: &serde_json::Value) {
+        let Some(id) = item.get("id").and_then(|i| i.as_str()) else { return };
+        let tool_name = item.get("name").and_then(|n| n.as_str()).unwrap_or("tool").to_string();
+        let command_prefix = if tool_name == "Bash" {
+            item.get("input")
+                .and_then(|input| input.get("command"))
+                .and_then(|c| c.as_str())
+                .and_then(command_prefix)
+        } else {
+            None
+        };
+        let path_field = match tool_name.as_str() {
+            "Edit" | "MultiEdit" | "Write" => Some("file_path"),
+            "NotebookEdit" => Some("notebook_path"),
+            _ => None,
+        };
+        let file_path = path_field
+            .and_then(|field| item.get("input")?.get(field)?.as_str())
+            .map(String::from);
+        let invocation = ToolInvocation {
+            conversation_id: self.conversation_id.clone(),
+            turn_started_at: self.turn_started_at,
+            tool_use_id: id.to_string(),
+            integration: Some(classify(&tool_name, &self.mcp_integrations)),
+            tool_name,
+            started_at: unix_millis(),
+            duration_ms: None,
+            is_error: false,
+            command_prefix,
+            file_path,
+        };
+        self.pending.insert(id.to_string(), (invocation, Instant::now()));
+    }
+
+    // Handle a `tool_result` content block from a user message, returning the
+    // completed invocation it belongs to
+    pub(crate) fn on_tool_result(&mut self, item: &serde_json::Value) -> Option<&ToolInvocation> {
+        let id = item.get("tool_use_id").and_then(|i| i.as_str())?;
+        let (mut invocation, started) = self.pending.remove(id)?;
+        invocation.duration_ms = Some(started.elapsed().as_millis() as u64);
+        invocation.is_error = item.get("is_error").and_then(|e| e.as_bool()).unwrap_or(false);
+        self.finished.push(invocation);
+        self.finished.last()
+    }
+
+    // Everything seen this turn, including calls that never got a result
+    pub(crate) fn finish(mut self) -> Vec<ToolInvocation> {
+        let mut unfinished: Vec<ToolInvocation> = self.pending.drain().map(|(_, (inv, _))| inv).collect();
+        unfinished.sort_by_key(|inv| inv.started_at);
+        self.finished.extend(unfinished);
+        self.finished
+    }
+}
+
+fn get_ledger_path(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    let app_data = crate::storage::data_dir(app);
+    Ok(app_data.join("tool-usage.jsonl"))
+}
+
+// Append a turn's invocations to the on-disk ledger
+pub(crate) async fn record(app: &tauri::AppHandle, invocations: &[ToolInvocation]) -> Result<(), String> {
+    if invocations.is_empty() {
+        return Ok(());
+    }
+    let path = get_ledger_path(app)?;
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await.map_err(|e| e.to_string())?;
+    }
+    let mut lines = String::new();
+    for invocation in invocations {
+        lines.push_str(&serde_json::to_string(invocation).map_err(|e| e.to_string())?);
+        lines.push('\n');
+    }
+    let mut file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .await
+        .map_err(|e| e.to_string())?;
+    file.write_all(lines.as_bytes()).await.map_err(|e| e.to_string())
+}
+
+// Read every recorded invocation, skipping lines that don't parse
+pub(crate) async fn load_all(app: &tauri::AppHandle) -> Result<Vec<ToolInvocation>, String> {
+    let path = get_ledger_path(app)?;
+    let data = match tokio::fs::read_to_string(&path).await {
+        Ok(data) => data,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.to_string()),
+    };
+    Ok(data.lines().filter_map(|line| serde_json::from_str(line).ok()).collect())
+}
+
+#[derive(Clone, Serialize)]
+pub struct ToolStat {
+    pub tool_name: String,
+    pub count: u64,
+    pub failures: u64,
+    pub total_duration_ms: u64,
+}
+
+#[derive(Clone, Serialize)]
+pub struct CommandCount {
+    pub prefix: String,
+    pub count: u64,
+}
+
+#[derive(Clone, Serialize)]
+pub struct ToolStats {
+    pub turns: u64,
+    pub total_invocations: u64,
+    pub by_tool: Vec<ToolStat>,
+    pub top_bash_commands: Vec<CommandCount>,
+}
+
+#[tauri::command]
+pub async fn get_tool_stats(
+    app: tauri::AppHandle,
+    conversation_id: Option<String>,
+    from: Option<u64>,
+    to: Option<u64>,
+    top_commands: Option<usize>,
+) -> Result<ToolStats, String> {
+    let invocations: Vec<ToolInvocation> = load_all(&app).await?
+        .into_iter()
+        .filter(|inv| conversation_id.as_ref().is_none_or(|id| *id == inv.conversation_id))
+        .filter(|inv| from.is_none_or(|from| inv.started_at >= from))
+        .filter(|inv| to.is_none_or(|to| inv.started_at <= to))
+        .collect();
+
+    let mut by_tool: HashMap<String, ToolStat> = HashMap::new();
+    let mut commands: HashMap<String, u64> = HashMap::new();
+    let mut turns = std::collections::HashSet::new();
+
+    for inv in &invocations {
+        turns.insert((inv.conversation_id.clone(), inv.turn_started_at));
+        let stat = by_tool.entry(inv.tool_name.clone()).or_insert_with(|| ToolStat {
+            tool_name: inv.tool_name.clone(),
+            count: 0,
+            failures: 0,
+            total_duration_ms: 0,
+        });
+        stat.count += 1;
+        stat.failures += inv.is_error as u64;
+        stat.total_duration_ms += inv.duration_ms.unwrap_or(0);
+        if let Some(ref prefix) = inv.command_prefix {
+            *commands.entry(prefix.clone()).or_insert(0) += 1;
+        }
+    }
+
+    let mut by_tool: Vec<ToolStat> = by_tool.into_values().collect();
+    by_tool.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.tool_name.cmp(&b.tool_name)));
+
+    let mut top_bash_commands: Vec<CommandCount> = commands.into_iter()
+        .map(|(prefix, count)| CommandCount { prefix, count })
+        .collect();
+    top_bash_commands.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.prefix.cmp(&b.prefix)));
+    top_bash_commands.truncate(top_commands.unwrap_or(DEFAULT_TOP_COMMANDS));
+
+    Ok(ToolStats {
+        turns: turns.len() as u64,
+        total_invocations: invocations.len() as u64,
+        by_tool,
+        top_bash_commands,
+    })
+}
+
+#[derive(Clone, Serialize)]
+pub struct IntegrationUsage {
+    // Integration id, or "builtin" / "unknown"
+    pub integration: String,
+    pub invocations: u64,
+    pub failures: u64,
+    pub total_duration_ms: u64,
+    pub last_used: Option<u64>,
+    // No calls at all in the last 30 days
+    pub unused_30_days: bool,
+}
+
+fn usage_entry<'a>(usage: &'a mut HashMap<String, IntegrationUsage>, id: &str) -> &'a mut IntegrationUsage {
+    usage.entry(id.to_string()).or_insert_with(|| IntegrationUsage {
+        integration: id.to_string(),
+        invocations: 0,
+        failures: 0,
+        total_duration_ms: 0,
+        last_used: None,
+        unused_30_days: true,
+    })
+}
+
+// Ids of configured MCP integrations, from the store the migration writes
+async fn stored_mcp_integrations(app: &tauri::AppHandle) -> Vec<String> {
+    let path = crate::storage::data_dir(app).join("integrations.json");
+    let Ok(data) = tokio::fs::read_to_string(&path).await else { return Vec::new() };
+    let stored: Vec<serde_json::Value> = serde_json::from_str(&data).unwrap_or_default();
+    stored.iter()
+        .filter(|int| int.get("type").and_then(|t| t.as_str()) == Some("mcp"))
+        .filter_map(|int| int.get("id").and_then(|i| i.as_str()).map(String::from))
+        .collect()
+}
+
+// Per-integration call stats between `from` and `to`. Pass the configured
+// integrations so ones that were never called show up too; without them the
+// stored integrations are used.
+#[tauri::command]
+pub async fn get_integration_usage(
+    app: tauri::AppHandle,
+    from: Option<u64>,
+    to: Option<u64>,
+    integrations: Option<Vec<crate::IntegrationConfig>>,
+) -> Result<Vec<IntegrationUsage>, String> {
+    let configured: Vec<String> = match integrations {
+        Some(ints) => ints.into_iter().filter(|int| int.integration_type == "mcp").map(|int| int.id).collect(),
+        None => stored_mcp_integrations(&app).await,
+    };
+
+    let mut usage: HashMap<String, IntegrationUsage> = HashMap::new();
+    for id in &configured {
+        usage_entry(&mut usage, id);
+    }
+
+    let cutoff = unix_millis().saturating_sub(UNUSED_AFTER_MS);
+    for inv in load_all(&app).await? {
+        let integration = inv.integration.clone()
+            .unwrap_or_else(|| classify(&inv.tool_name, &configured));
+        let stat = usage_entry(&mut usage, &integration);
+
+        // Recency looks at the whole ledger, whatever the requested range
+        stat.last_used = stat.last_used.max(Some(inv.started_at));
+        if inv.started_at >= cutoff {
+            stat.unused_30_days = false;
+        }
+        if from.is_some_and(|from| inv.started_at < from) || to.is_some_and(|to| inv.started_at > to) {
+            continue;
+        }
+        stat.invocations += 1;
+        stat.failures += inv.is_error as u64;
+        stat.total_duration_ms += inv.duration_ms.unwrap_or(0);
+    }
+
+    let mut usage: Vec<IntegrationUsage> = usage.into_values().collect();
+    usage.sort_by(|a, b| b.invocations.cmp(&a.invocations).then_with(|| a.integration.cmp(&b.integration)));
+    Ok(usage)
+}
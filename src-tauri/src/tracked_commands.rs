@@ -0,0 +1,241 @@
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::process::Stdio;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tauri::Emitter;
+use tokio::process::{Child, Command};
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+
+use crate::ipc_limits::{self, EventPart};
+use crate::{background_tasks, read_output, settings, shell_config, OutputMode};
+
+// Output chunks kept per command for reattaching
+const MAX_BUFFERED_CHUNKS: usize = 5000;
+// How long an exited command's remaining output may take to arrive
+const OUTPUT_DRAIN_TIMEOUT: Duration = Duration::from_secs(1);
+
+#[derive(Clone, Serialize)]
+pub struct TrackedOutputChunk {
+    pub handle_id: String,
+    // Increases by one per chunk, so a reattaching UI can ask for what it missed
+    pub seq: u64,
+    pub output: String,
+    pub is_stderr: bool,
+    // Set on live events for an oversized chunk; buffered chunks are whole
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub part: Option<EventPart>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct TrackedExit {
+    pub handle_id: String,
+    pub exit_code: Option<i32>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct TrackedOutput {
+    pub chunks: Vec<TrackedOutputChunk>,
+    pub running: bool,
+    pub exit_code: Option<i32>,
+    // True if older output than requested was dropped from the buffer
+    pub truncated: bool,
+}
+
+// A command that keeps running whether or not anyone is listening
+struct TrackedCommand {
+    // Which spawn of the handle this is, so readers left over from an
+    // earlier run can't write into a new one's buffer
+    generation: u64,
+    // None once the process has exited
+    child: Option<Child>,
+    buffer: VecDeque<TrackedOutputChunk>,
+    next_seq: u64,
+    exit_code: Option<i32>,
+}
+
+static TRACKED_COMMANDS: Lazy<Arc<Mutex<HashMap<String, TrackedCommand>>>> =
+    Lazy::new(|| Arc::new(Mutex::new(HashMap::new())));
+
+static NEXT_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+async fn push_chunk(app: &tauri::AppHandle, handle_id: &str, generation: u64, output: String, is_stderr: bool) {
+    let chunk = {
+        let mut commands = TRACKED_COMMANDS.lock().await;
+        let Some(tracked) = commands.get_mut(handle_id).filter(|t| t.generation == generation) else { return };
+        let chunk = TrackedOutputChunk {
+            handle_id: handle_id.to_string(),
+            seq: tracked.next_seq,
+            output,
+            is_stderr,
+            part: None,
+        };
+        tracked.next_seq += 1;
+        if tracked.buffer.len() == MAX_BUFFERED_CHUNKS {
+            tracked.buffer.pop_front();
+        }
+        tracked.buffer.push_back(chunk.clone());
+        chunk
+    };
+    let event = format!("tracked-output-{}", handle_id);
+    ipc_limits::emit_text(app, &event, chunk.output.clone(), |output, part| TrackedOutputChunk {
+        output,
+        part,
+        ..chunk.clone()
+    });
+}
+
+fn spawn_reader<R>(app: tauri::AppHandle, handle_id: String, generation: u64, reader: Option<R>, is_stderr: bool) -> Option<JoinHandle<()>>
+where
+    R: tokio::io::AsyncRead + Unpin + Send + 'static,
+{
+    let reader = reader?;
+    let stream = if is_stderr { "stderr" } else { "stdout" };
+    Some(background_tasks::spawn(format!("tracked {} reader ({})", stream, handle_id), async move {
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let forward = {
+            let app = app.clone();
+            let handle_id = handle_id.clone();
+            background_tasks::spawn(format!("tracked {} forwarder ({})", stream, handle_id), async move {
+                while let Some(line) = rx.recv().await {
+                    push_chunk(&app, &handle_id, generation, line, is_stderr).await;
+                }
+            })
+        };
+        let _ = read_output(reader, OutputMode::Line, |line| {
+            let _ = tx.send(line);
+        }).await;
+        drop(tx);
+        let _ = forward.await;
+    }))
+}
+
+#[tauri::command]
+pub async fn spawn_tracked_command(
+    app: tauri::AppHandle,
+    handle_id: String,
+    command: String,
+    working_directory: Option<String>,
+) -> Result<Option<u32>, String> {
+    let mut commands = TRACKED_COMMANDS.lock().await;
+    if commands.get(&handle_id).is_some_and(|tracked| tracked.child.is_some()) {
+        return Err(format!("Command {} is already running", handle_id));
+    }
+
+    let (shell, shell_args) = shell_config::resolve(&app, None, None, None).await;
+    let mut cmd = Command::new(&shell);
+    cmd.args(&shell_args).arg(&command);
+    let dir = settings::working_dir_or_default(&app, working_directory).await;
+    settings::check_allowed_dir(&app, dir.as_deref()).await?;
+    if let Some(dir) = dir {
+        cmd.current_dir(dir);
+    }
+
+    // Own process group so the whole tree can be stopped
+    #[cfg(unix)]
+    unsafe {
+        cmd.pre_exec(|| {
+            libc::setpgid(0, 0);
+            Ok(())
+        });
+    }
+
+    cmd.stdin(Stdio::null()).stdout(Stdio::piped()).stderr(Stdio::piped());
+    let mut child = cmd.spawn().map_err(|e| format!("Failed to spawn {}: {}", shell, e))?;
+    let pid = child.id();
+    let stdout = child.stdout.take();
+    let stderr = child.stderr.take();
+
+    // Re-spawning a finished handle replaces its old output
+    let generation = NEXT_GENERATION.fetch_add(1, Ordering::Relaxed);
+    commands.insert(handle_id.clone(), TrackedCommand {
+        generation,
+        child: Some(child),
+        buffer: VecDeque::new(),
+        next_seq: 0,
+        exit_code: None,
+    });
+    drop(commands);
+
+    let readers: Vec<JoinHandle<()>> = [
+        spawn_reader(app.clone(), handle_id.clone(), generation, stdout, false),
+        spawn_reader(app.clone(), handle_id.clone(), generation, stderr, true),
+    ].into_iter().flatten().collect();
+
+    background_tasks::spawn(format!("tracked command monitor ({})", handle_id), async move {
+        let exit_code = loop {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            let mut commands = TRACKED_COMMANDS.lock().await;
+            let Some(tracked) = commands.get_mut(&handle_id) else { return };
+            let Some(ref mut child) = tracked.child else { return };
+            match child.try_wait() {
+                Ok(Some(status)) => break status.code(),
+                Ok(None) => continue,
+                Err(_) => break None,
+            }
+        };
+
+        // Let the readers drain so the exit event comes after the last
+        // output. Children that inherited the pipes can keep them open, so
+        // don't wait on them forever.
+        for reader in readers {
+            let _ = tokio::time::timeout(OUTPUT_DRAIN_TIMEOUT, reader).await;
+        }
+        {
+            let mut commands = TRACKED_COMMANDS.lock().await;
+            let Some(tracked) = commands.get_mut(&handle_id).filter(|t| t.generation == generation) else { return };
+            tracked.child = None;
+            tracked.exit_code = exit_code;
+        }
+        let _ = app.emit(&format!("tracked-exit-{}", handle_id), TrackedExit {
+            handle_id: handle_id.clone(),
+            exit_code,
+        });
+    });
+
+    Ok(pid)
+}
+
+// Buffered output with a sequence number of at least `since_seq`
+#[tauri::command]
+pub async fn get_tracked_output(handle_id: String, since_seq: Option<u64>) -> Result<TrackedOutput, String> {
+    let commands = TRACKED_COMMANDS.lock().await;
+    let tracked = commands.get(&handle_id).ok_or_else(|| format!("Unknown command: {}", handle_id))?;
+    let since = since_seq.unwrap_or(0);
+    let oldest = tracked.buffer.front().map(|c| c.seq).unwrap_or(tracked.next_seq);
+    Ok(TrackedOutput {
+        chunks: tracked.buffer.iter().filter(|c| c.seq >= since).cloned().collect(),
+        running: tracked.child.is_some(),
+        exit_code: tracked.exit_code,
+        truncated: oldest > since,
+    })
+}
+
+#[tauri::command]
+pub async fn kill_tracked_command(handle_id: String) -> Result<bool, String> {
+    let mut commands = TRACKED_COMMANDS.lock().await;
+    let Some(child) = commands.get_mut(&handle_id).and_then(|tracked| tracked.child.as_mut()) else {
+        return Ok(false);
+    };
+    #[cfg(unix)]
+    if let Some(pid) = child.id() {
+        unsafe {
+            libc::killpg(pid as i32, libc::SIGTERM);
+        }
+    }
+    child.start_kill().map_err(|e| format!("Failed to stop command: {}", e))?;
+    Ok(true)
+}
+
+// Drop a finished command's buffered output
+#[tauri::command]
+pub async fn discard_tracked_command(handle_id: String) -> Result<bool, String> {
+    let mut commands = TRACKED_COMMANDS.lock().await;
+    if commands.get(&handle_id).is_some_and(|tracked| tracked.child.is_some()) {
+        return Err(format!("Command {} is still running", handle_id));
+    }
+    Ok(commands.remove(&handle_id).is_some())
+}
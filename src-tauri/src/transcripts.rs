@@ -0,0 +1,35 @@
+use std::path::PathBuf;
+
+use crate::storage;
+
+// One file per conversation under transcripts/, holding the conversation as
+// the frontend shaped it (unknown fields are kept as-is)
+fn transcript_path(app: &tauri::AppHandle, conversation_id: &str) -> Result<PathBuf, String> {
+    if conversation_id.is_empty() || conversation_id.contains(['/', '\\']) || conversation_id.starts_with('.') {
+        return Err(format!("Invalid conversation id: {}", conversation_id));
+    }
+    Ok(storage::data_dir(app).join("transcripts").join(format!("{}.json", conversation_id)))
+}
+
+// None when the conversation has no transcript file
+pub(crate) async fn read_transcript(app: &tauri::AppHandle, conversation_id: &str) -> Result<Option<serde_json::Value>, String> {
+    let path = transcript_path(app, conversation_id)?;
+    match tokio::fs::read_to_string(&path).await {
+        Ok(data) => serde_json::from_str(&data).map(Some).map_err(|e| format!("Failed to parse {}: {}", path.display(), e)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+pub(crate) async fn write_transcript(
+    app: &tauri::AppHandle,
+    conversation_id: &str,
+    transcript: &serde_json::Value,
+) -> Result<(), String> {
+    let path = transcript_path(app, conversation_id)?;
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await.map_err(|e| e.to_string())?;
+    }
+    let data = serde_json::to_vec_pretty(transcript).map_err(|e| e.to_string())?;
+    crate::write_atomic(&path, &data).await
+}
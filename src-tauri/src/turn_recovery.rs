@@ -0,0 +1,187 @@
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use crate::{storage, write_atomic};
+
+// Written before claude is spawned and removed once the turn finishes, so a
+// record still on disk at startup means the app went away mid-turn
+#[derive(Clone, Serialize, Deserialize)]
+pub struct InFlightTurn {
+    pub conversation_id: String,
+    pub session_id: Option<String>,
+    pub message: String,
+    pub started_at: u64,
+}
+
+#[derive(Clone, Serialize)]
+pub struct RecoveredTurn {
+    #[serde(flatten)]
+    pub turn: InFlightTurn,
+    // "recovered" when the CLI finished the turn, "interrupted" otherwise
+    pub status: String,
+    pub response: Option<String>,
+}
+
+// Guards the in-flight file; the value is unused
+static IN_FLIGHT_LOCK: Lazy<Arc<Mutex<()>>> = Lazy::new(|| Arc::new(Mutex::new(())));
+
+static RECOVERED_TURNS: Lazy<Arc<Mutex<Vec<RecoveredTurn>>>> =
+    Lazy::new(|| Arc::new(Mutex::new(Vec::new())));
+
+fn get_in_flight_path(app: &tauri::AppHandle) -> PathBuf {
+    storage::data_dir(app).join("in-flight-turns.json")
+}
+
+async fn read_in_flight(app: &tauri::AppHandle) -> HashMap<String, InFlightTurn> {
+    match tokio::fs::read_to_string(get_in_flight_path(app)).await {
+        Ok(data) => serde_json::from_str(&data).unwrap_or_default(),
+        Err(_) => HashMap::new(),
+    }
+}
+
+async fn write_in_flight(app: &tauri::AppHandle, turns: &HashMap<String, InFlightTurn>) -> Result<(), String> {
+    let data = serde_json::to_vec_pretty(turns).map_err(|e| e.to_string())?;
+    write_atomic(&get_in_flight_path(app), &data).await
+}
+
+async fn modify_in_flight<F>(app: &tauri::AppHandle, change: F)
+where
+    F: FnOnce(&mut HashMap<String, InFlightTurn>),
+{
+    let _guard = IN_FLIGHT_LOCK.lock().await;
+    let mut turns = read_in_flight(app).await;
+    change(&mut turns);
+    let _ = write_in_flight(app, &turns).await;
+}
+
+pub(crate) async fn begin_turn(app: &tauri::AppHandle, turn: InFlightTurn) {
+    modify_in_flight(app, |turns| {
+        turns.insert(turn.conversation_id.clone(), turn);
+    }).await;
+}
+
+// New sessions only get their id once the CLI reports it
+pub(crate) async fn set_turn_session(app: &tauri::AppHandle, conversation_id: &str, session_id: &str) {
+    modify_in_flight(app, |turns| {
+        if let Some(turn) = turns.get_mut(conversation_id) {
+            turn.session_id = Some(session_id.to_string());
+        }
+    }).await;
+}
+
+pub(crate) async fn finish_turn(app: &tauri::AppHandle, conversation_id: &str) {
+    modify_in_flight(app, |turns| {
+        turns.remove(conversation_id);
+    }).await;
+}
+
+// "2026-01-02T03:04:05.678Z" -> unix milliseconds
+pub(crate) fn parse_timestamp(text: &str) -> Option<u64> {
+    let (date, time) = text.trim_end_matches('Z').split_once('T')?;
+    let mut date_parts = date.split('-').map(|p| p.parse::<i64>());
+    let (year, month, day) = (date_parts.next()?.ok()?, date_parts.next()?.ok()?, date_parts.next()?.ok()?);
+    let (hms, millis) = time.split_once('.').unwrap_or((time, "0"));
+    let mut time_parts = hms.split(':').map(|p| p.parse::<i64>());
+    let (hour, minute, second) = (time_parts.next()?.ok()?, time_parts.next()?.ok()?, time_parts.next()?.ok()?);
+    let millis: i64 = format!("{:0<3}", &millis[..millis.len().min(3)]).parse().ok()?;
+
+    // Days since the epoch for a proleptic Gregorian date
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146_097 + doe - 719_468;
+
+    let ms = ((days * 24 + hour) * 60 + minute) * 60_000 + second * 1000 + millis;
+    u64::try_from(ms).ok()
+}
+
+// The CLI keeps each session as ~/.claude/projects/<encoded cwd>/<session id>.jsonl
+pub(crate) async fn find_session_file(session_id: &str) -> Option<PathBuf> {
+    let projects = dirs::home_dir()?.join(".claude").join("projects");
+    let mut dirs = tokio::fs::read_dir(&projects).await.ok()?;
+    let file_name = format!("{}.jsonl", session_id);
+    while let Ok(Some(entry)) = dirs.next_entry().await {
+        let candidate = entry.path().join(&file_name);
+        if tokio::fs::metadata(&candidate).await.is_ok() {
+            return Some(candidate);
+        }
+    }
+    None
+}
+
+// Assistant text written to the session after `started_at`, if the turn ran
+// to completion
+async fn completed_response(session_id: &str, started_at: u64) -> Option<String> {
+    let path = find_session_file(session_id).await?;
+    let data = tokio::fs::read_to_string(&path).await.ok()?;
+
+    let mut response = String::new();
+    let mut completed = false;
+    for line in data.lines() {
+        let Ok(entry) = serde_json::from_str::<serde_json::Value>(line) else { continue };
+        if entry.get("type").and_then(|t| t.as_str()) != Some("assistant") {
+            continue;
+        }
+        let newer = entry.get("timestamp")
+            .and_then(|t| t.as_str())
+            .and_then(parse_timestamp)
+            .is_some_and(|ts| ts >= started_at);
+        if !newer {
+            continue;
+        }
+        let Some(message) = entry.get("message") else { continue };
+        if let Some(content) = message.get("content").and_then(|c| c.as_array()) {
+            for item in content {
+                if item.get("type").and_then(|t| t.as_str()) == Some("text") {
+                    if let Some(text) = item.get("text").and_then(|t| t.as_str()) {
+                        response.push_str(text);
+                    }
+                }
+            }
+        }
+        completed = message.get("stop_reason").and_then(|s| s.as_str()) == Some("end_turn");
+    }
+
+    (completed && !response.trim().is_empty()).then(|| response.trim().to_string())
+}
+
+// Run at startup: nothing is in flight yet, so every record left on disk is
+// from a turn the previous run never finished
+pub(crate) async fn recover_turns(app: tauri::AppHandle) {
+    let stale = {
+        let _guard = IN_FLIGHT_LOCK.lock().await;
+        let turns = read_in_flight(&app).await;
+        if turns.is_empty() {
+            return;
+        }
+        let _ = write_in_flight(&app, &HashMap::new()).await;
+        turns
+    };
+
+    let mut recovered = Vec::new();
+    for turn in stale.into_values() {
+        let response = match turn.session_id {
+            Some(ref session_id) => completed_response(session_id, turn.started_at).await,
+            None => None,
+        };
+        recovered.push(RecoveredTurn {
+            status: if response.is_some() { "recovered" } else { "interrupted" }.to_string(),
+            response,
+            turn,
+        });
+    }
+    recovered.sort_by_key(|r| r.turn.started_at);
+    RECOVERED_TURNS.lock().await.extend(recovered);
+}
+
+#[tauri::command]
+pub async fn get_recovered_turns() -> Result<Vec<RecoveredTurn>, String> {
+    Ok(RECOVERED_TURNS.lock().await.clone())
+}
@@ -0,0 +1,270 @@
+use base64::Engine;
+use futures_util::{SinkExt, StreamExt};
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tauri::Emitter;
+use tokio::net::TcpStream;
+use tokio::sync::{mpsc, Mutex};
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::http::{HeaderName, HeaderValue};
+use tokio_tungstenite::tungstenite::protocol::{CloseFrame, WebSocketConfig};
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+use crate::{background_tasks, ipc_limits};
+
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(15);
+// Frames (or reassembled messages) larger than this end the connection
+const MAX_MESSAGE_BYTES: usize = 64 * 1024 * 1024;
+// Binary payloads are cut to this before base64 encoding for the event
+const MAX_BINARY_EVENT_BYTES: usize = 256 * 1024;
+const PING_INTERVAL: Duration = Duration::from_secs(30);
+// How long to wait for the server to answer our close frame
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Everything sent on `ws-message-<connection_id>`, told apart by `kind`
+#[derive(Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum WsEvent {
+    Open,
+    Text { data: String },
+    Binary { data_base64: String, size: usize, truncated: bool },
+    // The server pinged us; a pong went back automatically
+    Ping { size: usize },
+    // Reply to our keepalive ping, with the round trip when it was ours
+    Pong { rtt_ms: Option<u64> },
+    // by_server is false when we started the close
+    Closed { code: Option<u16>, reason: String, by_server: bool },
+    Error { message: String },
+}
+
+#[derive(Clone, Serialize)]
+pub struct WsMessage {
+    pub connection_id: String,
+    #[serde(flatten)]
+    pub event: WsEvent,
+}
+
+enum Outgoing {
+    Text(String),
+    Binary(Vec<u8>),
+    Close(u16, String),
+}
+
+struct Connection {
+    window_label: String,
+    outgoing: mpsc::UnboundedSender<Outgoing>,
+    task: tokio::task::JoinHandle<()>,
+}
+
+static CONNECTIONS: Lazy<Arc<Mutex<HashMap<String, Connection>>>> =
+    Lazy::new(|| Arc::new(Mutex::new(HashMap::new())));
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+fn emit(app: &tauri::AppHandle, connection_id: &str, event: WsEvent) {
+    let _ = app.emit(&format!("ws-message-{}", connection_id), WsMessage {
+        connection_id: connection_id.to_string(),
+        event,
+    });
+}
+
+// Connect and upgrade, sending the caller's extra headers with the handshake
+async fn open(url: &str, headers: &BTreeMap<String, String>) -> Result<Socket, String> {
+    let parsed = reqwest::Url::parse(url).map_err(|e| format!("Invalid URL {}: {}", url, e))?;
+    if !matches!(parsed.scheme(), "ws" | "wss") {
+        return Err(format!("Unsupported scheme {}: use ws or wss", parsed.scheme()));
+    }
+    let mut request = url.into_client_request().map_err(|e| format!("Invalid URL {}: {}", url, e))?;
+    for (name, value) in headers {
+        let header = HeaderName::from_bytes(name.as_bytes()).ok().zip(HeaderValue::from_str(value).ok());
+        let (name, value) = header.ok_or_else(|| format!("Invalid header: {}", name))?;
+        request.headers_mut().insert(name, value);
+    }
+    let config = WebSocketConfig::default()
+        .max_message_size(Some(MAX_MESSAGE_BYTES))
+        .max_frame_size(Some(MAX_MESSAGE_BYTES));
+    let (socket, _) = tokio_tungstenite::connect_async_with_config(request, Some(config), true).await
+        .map_err(|e| format!("Failed to connect to {}: {}", url, e))?;
+    Ok(socket)
+}
+
+async fn run_connection(
+    app: tauri::AppHandle,
+    connection_id: String,
+    mut socket: Socket,
+    mut outgoing: mpsc::UnboundedReceiver<Outgoing>,
+) {
+    let mut keepalive = tokio::time::interval(PING_INTERVAL);
+    keepalive.tick().await;
+    let mut ping_sent: Option<Instant> = None;
+    // Set once we've sent a close frame and are waiting for the server's
+    let mut closing: Option<(u16, String, Instant)> = None;
+
+    let closed = loop {
+        let close_deadline = closing.as_ref().map(|(_, _, at)| *at + CLOSE_TIMEOUT);
+        tokio::select! {
+            message = socket.next() => {
+                let message = match message {
+                    Some(Ok(message)) => message,
+                    // The server may just drop the connection after our close
+                    Some(Err(_)) | None if closing.is_some() => {
+                        let (code, reason, _) = closing.take().unwrap();
+                        break WsEvent::Closed { code: Some(code), reason, by_server: false };
+                    }
+                    Some(Err(e)) => break WsEvent::Error { message: e.to_string() },
+                    None => break WsEvent::Error { message: "Connection closed without a close frame".to_string() },
+                };
+                match message {
+                    Message::Text(text) => {
+                        let event = format!("ws-message-{}", connection_id);
+                        ipc_limits::emit_text(&app, &event, text.to_string(), |data, _| WsMessage {
+                            connection_id: connection_id.clone(),
+                            event: WsEvent::Text { data },
+                        });
+                    }
+                    Message::Binary(bytes) => {
+                        let size = bytes.len();
+                        let shown = &bytes[..size.min(MAX_BINARY_EVENT_BYTES)];
+                        emit(&app, &connection_id, WsEvent::Binary {
+                            data_base64: base64::engine::general_purpose::STANDARD.encode(shown),
+                            size,
+                            truncated: size > MAX_BINARY_EVENT_BYTES,
+                        });
+                    }
+                    // tungstenite queues the pong itself
+                    Message::Ping(payload) => emit(&app, &connection_id, WsEvent::Ping { size: payload.len() }),
+                    Message::Pong(_) => {
+                        let rtt_ms = ping_sent.take().map(|at| at.elapsed().as_millis() as u64);
+                        emit(&app, &connection_id, WsEvent::Pong { rtt_ms });
+                    }
+                    Message::Close(frame) => {
+                        break match closing {
+                            Some((our_code, our_reason, _)) => WsEvent::Closed { code: Some(our_code), reason: our_reason, by_server: false },
+                            None => {
+                                // The echo is queued on receipt; make sure it goes out
+                                let _ = socket.flush().await;
+                                WsEvent::Closed {
+                                    code: frame.as_ref().map(|f| f.code.into()),
+                                    reason: frame.map(|f| f.reason.to_string()).unwrap_or_default(),
+                                    by_server: true,
+                                }
+                            }
+                        };
+                    }
+                    // Raw frames only come up when writing
+                    Message::Frame(_) => {}
+                }
+            }
+            message = outgoing.recv(), if closing.is_none() => {
+                let sent = match message {
+                    Some(Outgoing::Text(text)) => socket.send(Message::Text(text.into())).await,
+                    Some(Outgoing::Binary(bytes)) => socket.send(Message::Binary(bytes.into())).await,
+                    Some(Outgoing::Close(code, reason)) => {
+                        let frame = CloseFrame { code: code.into(), reason: reason.clone().into() };
+                        closing = Some((code, reason, Instant::now()));
+                        socket.send(Message::Close(Some(frame))).await
+                    }
+                    None => break WsEvent::Closed { code: None, reason: "Connection dropped".to_string(), by_server: false },
+                };
+                if let Err(e) = sent {
+                    break WsEvent::Error { message: format!("Failed to send: {}", e) };
+                }
+            }
+            _ = keepalive.tick(), if closing.is_none() => {
+                if socket.send(Message::Ping(b"keepalive".to_vec().into())).await.is_err() {
+                    break WsEvent::Error { message: "Failed to send keepalive ping".to_string() };
+                }
+                ping_sent = Some(Instant::now());
+            }
+            _ = async { tokio::time::sleep_until(close_deadline.unwrap().into()).await }, if close_deadline.is_some() => {
+                let (code, reason, _) = closing.take().unwrap();
+                break WsEvent::Closed { code: Some(code), reason, by_server: false };
+            }
+        }
+    };
+
+    drop(socket);
+    CONNECTIONS.lock().await.remove(&connection_id);
+    emit(&app, &connection_id, closed);
+}
+
+#[tauri::command]
+pub async fn ws_connect(
+    app: tauri::AppHandle,
+    window: tauri::Window,
+    connection_id: String,
+    url: String,
+    headers: Option<BTreeMap<String, String>>,
+) -> Result<(), String> {
+    if CONNECTIONS.lock().await.contains_key(&connection_id) {
+        return Err(format!("Connection {} is already open", connection_id));
+    }
+    let socket = tokio::time::timeout(HANDSHAKE_TIMEOUT, open(&url, &headers.unwrap_or_default())).await
+        .map_err(|_| format!("Timed out connecting to {}", url))??;
+
+    let (outgoing, outgoing_rx) = mpsc::unbounded_channel();
+    let mut connections = CONNECTIONS.lock().await;
+    if connections.contains_key(&connection_id) {
+        return Err(format!("Connection {} is already open", connection_id));
+    }
+    emit(&app, &connection_id, WsEvent::Open);
+    let description = format!("websocket ({})", connection_id);
+    let task = background_tasks::spawn(description, run_connection(app, connection_id.clone(), socket, outgoing_rx));
+    connections.insert(connection_id, Connection {
+        window_label: window.label().to_string(),
+        outgoing,
+        task,
+    });
+    Ok(())
+}
+
+// Send a text message, or binary when `binary` is true and payload is base64
+#[tauri::command]
+pub async fn ws_send(connection_id: String, payload: String, binary: Option<bool>) -> Result<(), String> {
+    let message = if binary.unwrap_or(false) {
+        let bytes = base64::engine::general_purpose::STANDARD.decode(payload.as_bytes())
+            .map_err(|e| format!("Invalid base64 payload: {}", e))?;
+        Outgoing::Binary(bytes)
+    } else {
+        Outgoing::Text(payload)
+    };
+    let connections = CONNECTIONS.lock().await;
+    let connection = connections.get(&connection_id).ok_or_else(|| format!("No open connection: {}", connection_id))?;
+    connection.outgoing.send(message).map_err(|_| format!("Connection {} is closing", connection_id))
+}
+
+// Start a clean close; the closed event follows once the server answers.
+// Returns false if there was no such connection.
+#[tauri::command]
+pub async fn ws_close(connection_id: String, code: Option<u16>, reason: Option<String>) -> Result<bool, String> {
+    let connections = CONNECTIONS.lock().await;
+    let Some(connection) = connections.get(&connection_id) else { return Ok(false) };
+    let _ = connection.outgoing.send(Outgoing::Close(code.unwrap_or(1000), reason.unwrap_or_default()));
+    Ok(true)
+}
+
+// Close every connection opened from a window that has gone away
+pub(crate) async fn close_for_window(window_label: &str) {
+    let connections = CONNECTIONS.lock().await;
+    for connection in connections.values().filter(|c| c.window_label == window_label) {
+        let _ = connection.outgoing.send(Outgoing::Close(1001, "Window closed".to_string()));
+    }
+}
+
+// On exit: say goodbye to every server, but don't hold up quitting for long
+pub(crate) async fn close_all() {
+    let connections: Vec<Connection> = CONNECTIONS.lock().await.drain().map(|(_, c)| c).collect();
+    for connection in &connections {
+        let _ = connection.outgoing.send(Outgoing::Close(1001, "Application exiting".to_string()));
+    }
+    for connection in connections {
+        let abort = connection.task.abort_handle();
+        if tokio::time::timeout(Duration::from_secs(1), connection.task).await.is_err() {
+            abort.abort();
+        }
+    }
+}
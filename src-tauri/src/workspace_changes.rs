@@ -0,0 +1,337 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use tokio::process::Command;
+
+use crate::git::find_repo_root;
+use crate::time_format::TimeFormatter;
+use crate::tool_stats;
+
+// What `git diff` compares against when nothing was committed before `since`
+const EMPTY_TREE: &str = "4b825dc642cb6eb9a060e54bf8d69288fbee4904";
+// Untracked files larger than this aren't read to count lines
+const MAX_LINE_COUNT_BYTES: u64 = 1024 * 1024;
+// A NUL in this much of the start of a file marks it as binary, as git does
+const BINARY_SNIFF_BYTES: usize = 8000;
+
+// Unix millis, or a commit (hash, tag or branch) to compare against
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum ChangesSince {
+    Time(u64),
+    Checkpoint(String),
+}
+
+#[derive(Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    Added,
+    Modified,
+    Deleted,
+    Renamed,
+}
+
+impl ChangeKind {
+    fn label(self) -> &'static str {
+        match self {
+            ChangeKind::Added => "added",
+            ChangeKind::Modified => "modified",
+            ChangeKind::Deleted => "deleted",
+            ChangeKind::Renamed => "renamed",
+        }
+    }
+}
+
+#[derive(Clone, Serialize)]
+pub struct ToolTouch {
+    pub conversation_id: String,
+    // Identifies the turn: when its send_to_claude call started (unix ms)
+    pub turn_started_at: u64,
+    pub tool_name: String,
+}
+
+#[derive(Serialize)]
+pub struct WorkspaceChange {
+    // Relative to the workspace root
+    pub path: String,
+    pub previous_path: Option<String>,
+    pub kind: ChangeKind,
+    // False for files git doesn't track, or outside a repository
+    pub tracked: bool,
+    // None for binary files, which are reported by size only
+    pub lines_added: Option<u64>,
+    pub lines_removed: Option<u64>,
+    pub binary: bool,
+    // Current size; None once deleted
+    pub size_bytes: Option<u64>,
+    pub touched_by: Vec<ToolTouch>,
+}
+
+#[derive(Serialize)]
+pub struct WorkspaceChanges {
+    pub workspace_root: String,
+    pub since_ms: u64,
+    // The commit tracked files were compared against, when in a repository
+    pub base_commit: Option<String>,
+    pub changes: Vec<WorkspaceChange>,
+    // The same report as markdown, e.g. for a PR description
+    pub markdown: String,
+}
+
+async fn git(dir: &Path, args: &[&str]) -> Result<Vec<u8>, String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .await
+        .map_err(|e| format!("Failed to run git: {}", e))?;
+    if !output.status.success() {
+        return Err(format!("git {} failed: {}", args.join(" "), String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    Ok(output.stdout)
+}
+
+async fn git_line(dir: &Path, args: &[&str]) -> Result<String, String> {
+    Ok(String::from_utf8_lossy(&git(dir, args).await?).trim().to_string())
+}
+
+// The commit to diff from and the time it stands for
+async fn resolve_since(root: &Path, since: &ChangesSince) -> Result<(String, u64), String> {
+    match since {
+        ChangesSince::Checkpoint(id) => {
+            let commit = git_line(root, &["rev-parse", "--verify", "--quiet", &format!("{}^{{commit}}", id)]).await
+                .map_err(|_| format!("Unknown checkpoint: {}", id))?;
+            let seconds: u64 = git_line(root, &["show", "-s", "--format=%ct", &commit]).await?
+                .parse()
+                .map_err(|_| format!("Couldn't read the time of {}", id))?;
+            Ok((commit, seconds * 1000))
+        }
+        ChangesSince::Time(ms) => {
+            let before = format!("--before=@{}", ms / 1000);
+            let commit = git_line(root, &["rev-list", "-1", &before, "HEAD"]).await.unwrap_or_default();
+            Ok((if commit.is_empty() { EMPTY_TREE.to_string() } else { commit }, *ms))
+        }
+    }
+}
+
+fn modified_ms(metadata: &std::fs::Metadata) -> u64 {
+    metadata.modified().ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+// (lines, binary) for a file that has no diff to count from
+fn count_lines(path: &Path, size: u64) -> (Option<u64>, bool) {
+    let Ok(mut file) = std::fs::File::open(path) else { return (None, false) };
+    let mut head = vec![0u8; BINARY_SNIFF_BYTES];
+    let read = file.read(&mut head).unwrap_or(0);
+    if head[..read].contains(&0) {
+        return (None, true);
+    }
+    if size > MAX_LINE_COUNT_BYTES {
+        return (None, false);
+    }
+    let mut rest = Vec::new();
+    let _ = file.read_to_end(&mut rest);
+    let newlines = head[..read].iter().chain(rest.iter()).filter(|&&b| b == b'\n').count() as u64;
+    let unterminated = read > 0 && head[..read].iter().chain(rest.iter()).last() != Some(&b'\n');
+    (Some(newlines + unterminated as u64), false)
+}
+
+fn file_size(path: &Path) -> Option<u64> {
+    std::fs::metadata(path).ok().filter(|m| m.is_file()).map(|m| m.len())
+}
+
+fn untracked_change(root: &Path, relative: String) -> Option<WorkspaceChange> {
+    let size = file_size(&root.join(&relative))?;
+    let (lines, binary) = count_lines(&root.join(&relative), size);
+    Some(WorkspaceChange {
+        path: relative,
+        previous_path: None,
+        kind: ChangeKind::Added,
+        tracked: false,
+        lines_added: lines,
+        lines_removed: lines.map(|_| 0),
+        binary,
+        size_bytes: Some(size),
+        touched_by: Vec::new(),
+    })
+}
+
+// Tracked changes between `base` and the working tree, plus untracked files
+// written since `since_ms`. Paths are relative to `root`, which may be a
+// folder inside the repository.
+async fn git_changes(root: &Path, base: &str, since_ms: u64) -> Result<Vec<WorkspaceChange>, String> {
+    let name_status = git(root, &["diff", "--relative", "-M", "-z", "--name-status", base]).await?;
+    let numstat = git(root, &["diff", "--relative", "-M", "-z", "--numstat", base]).await?;
+
+    // numstat -z: "added\tremoved\tpath\0", or "added\tremoved\t\0old\0new\0" for renames
+    let mut counts: HashMap<String, (Option<u64>, Option<u64>)> = HashMap::new();
+    let mut fields = numstat.split(|&b| b == 0).map(|f| String::from_utf8_lossy(f).to_string());
+    while let Some(field) = fields.next() {
+        let mut parts = field.splitn(3, '\t');
+        let (Some(added), Some(removed), Some(path)) = (parts.next(), parts.next(), parts.next()) else { continue };
+        let path = if path.is_empty() {
+            let _old = fields.next();
+            fields.next().unwrap_or_default()
+        } else {
+            path.to_string()
+        };
+        counts.insert(path, (added.parse().ok(), removed.parse().ok()));
+    }
+
+    // name-status -z: "M\0path\0", or "R100\0old\0new\0"
+    let mut changes = Vec::new();
+    let mut fields = name_status.split(|&b| b == 0).map(|f| String::from_utf8_lossy(f).to_string());
+    while let Some(status) = fields.next() {
+        let Some(code) = status.chars().next() else { continue };
+        let (kind, previous_path) = match code {
+            'A' => (ChangeKind::Added, None),
+            'D' => (ChangeKind::Deleted, None),
+            'R' | 'C' => (ChangeKind::Renamed, fields.next()),
+            _ => (ChangeKind::Modified, None),
+        };
+        let Some(path) = fields.next() else { break };
+        let (lines_added, lines_removed) = counts.get(&path).copied().unwrap_or((None, None));
+        let binary = counts.contains_key(&path) && lines_added.is_none();
+        changes.push(WorkspaceChange {
+            size_bytes: file_size(&root.join(&path)),
+            path,
+            previous_path: if kind == ChangeKind::Renamed { previous_path } else { None },
+            kind,
+            tracked: true,
+            lines_added,
+            lines_removed,
+            binary,
+            touched_by: Vec::new(),
+        });
+    }
+
+    let untracked = git(root, &["ls-files", "--others", "--exclude-standard", "-z"]).await?;
+    for relative in untracked.split(|&b| b == 0).filter(|f| !f.is_empty()) {
+        let relative = String::from_utf8_lossy(relative).to_string();
+        let written = std::fs::metadata(root.join(&relative)).map(|m| modified_ms(&m)).unwrap_or(0);
+        if written >= since_ms {
+            changes.extend(untracked_change(root, relative));
+        }
+    }
+    Ok(changes)
+}
+
+// Outside a repository only mtimes are known, so everything is "modified"
+fn mtime_changes(root: &Path, since_ms: u64) -> Vec<WorkspaceChange> {
+    let mut changes = Vec::new();
+    for entry in ignore::WalkBuilder::new(root).build().flatten() {
+        let Ok(metadata) = entry.metadata() else { continue };
+        if !metadata.is_file() || modified_ms(&metadata) < since_ms {
+            continue;
+        }
+        let relative = entry.path().strip_prefix(root).unwrap_or(entry.path()).to_string_lossy().to_string();
+        if let Some(mut change) = untracked_change(root, relative) {
+            change.kind = ChangeKind::Modified;
+            change.lines_added = None;
+            change.lines_removed = None;
+            changes.push(change);
+        }
+    }
+    changes
+}
+
+fn markdown(report: &WorkspaceChanges, formatter: &TimeFormatter) -> String {
+    let mut out = format!("## Changes since {}\n\n", formatter.format(report.since_ms).display);
+    if report.changes.is_empty() {
+        out.push_str("No changes.\n");
+        return out;
+    }
+    let added: u64 = report.changes.iter().filter_map(|c| c.lines_added).sum();
+    let removed: u64 = report.changes.iter().filter_map(|c| c.lines_removed).sum();
+    out.push_str(&format!(
+        "{} file{} changed, +{} −{}\n\n| File | Change | Lines | Touched in |\n|---|---|---|---|\n",
+        report.changes.len(),
+        if report.changes.len() == 1 { "" } else { "s" },
+        added,
+        removed,
+    ));
+    for change in &report.changes {
+        let file = match change.previous_path {
+            Some(ref previous) => format!("`{}` → `{}`", previous, change.path),
+            None => format!("`{}`", change.path),
+        };
+        let lines = match (change.lines_added, change.lines_removed, change.size_bytes) {
+            (Some(added), Some(removed), _) => format!("+{} −{}", added, removed),
+            (_, _, Some(size)) if change.binary => format!("binary, {} bytes", size),
+            _ => String::new(),
+        };
+        let mut turns: Vec<String> = change.touched_by.iter()
+            .map(|t| format!("{} ({})", t.conversation_id, formatter.format(t.turn_started_at).display))
+            .collect();
+        turns.dedup();
+        out.push_str(&format!("| {} | {} | {} | {} |\n", file, change.kind.label(), lines, turns.join(", ")));
+    }
+    out
+}
+
+// Everything that changed in a workspace since a time or a commit: git's
+// view of tracked files, untracked files by mtime, and which conversation
+// turns edited each file through claude's file tools
+#[tauri::command]
+pub async fn get_workspace_changes(
+    app: tauri::AppHandle,
+    workspace_root: String,
+    since: ChangesSince,
+) -> Result<WorkspaceChanges, String> {
+    let root = std::fs::canonicalize(&workspace_root)
+        .map_err(|e| format!("Invalid workspace {}: {}", workspace_root, e))?;
+    if !root.is_dir() {
+        return Err(format!("Not a directory: {}", workspace_root));
+    }
+
+    let (base_commit, since_ms, mut changes) = if find_repo_root(&root).is_some() {
+        let (base, since_ms) = resolve_since(&root, &since).await?;
+        let changes = git_changes(&root, &base, since_ms).await?;
+        (Some(base).filter(|b| b != EMPTY_TREE), since_ms, changes)
+    } else {
+        let ChangesSince::Time(since_ms) = since else {
+            return Err("Checkpoints need the workspace to be a git repository".to_string());
+        };
+        let walk_root = root.clone();
+        let changes = tokio::task::spawn_blocking(move || mtime_changes(&walk_root, since_ms))
+            .await
+            .map_err(|e| e.to_string())?;
+        (None, since_ms, changes)
+    };
+
+    let mut touches: HashMap<PathBuf, Vec<ToolTouch>> = HashMap::new();
+    for invocation in tool_stats::load_all(&app).await? {
+        let Some(ref file_path) = invocation.file_path else { continue };
+        if invocation.turn_started_at < since_ms || invocation.is_error {
+            continue;
+        }
+        let path = PathBuf::from(file_path);
+        let Ok(relative) = path.strip_prefix(&root).or_else(|_| path.strip_prefix(&workspace_root)) else { continue };
+        touches.entry(relative.to_path_buf()).or_default().push(ToolTouch {
+            conversation_id: invocation.conversation_id.clone(),
+            turn_started_at: invocation.turn_started_at,
+            tool_name: invocation.tool_name.clone(),
+        });
+    }
+    for change in &mut changes {
+        if let Some(touched) = touches.remove(Path::new(&change.path)) {
+            change.touched_by = touched;
+        }
+    }
+    changes.sort_by(|a, b| a.path.cmp(&b.path));
+
+    let mut report = WorkspaceChanges {
+        workspace_root: root.to_string_lossy().to_string(),
+        since_ms,
+        base_commit,
+        changes,
+        markdown: String::new(),
+    };
+    report.markdown = markdown(&report, &TimeFormatter::load(&app).await);
+    Ok(report)
+}